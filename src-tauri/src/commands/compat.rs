@@ -0,0 +1,42 @@
+//! 供应商字段兼容性命令
+//!
+//! 查看和重置代理自动识别的上游不支持的请求字段
+
+use crate::proxy::compat::ProviderIncompatibility;
+use crate::store::AppState;
+
+/// 列出已识别的字段不兼容记录（app_type 为空时列出全部应用）
+#[tauri::command]
+pub async fn list_provider_incompatibilities(
+    state: tauri::State<'_, AppState>,
+    app_type: Option<String>,
+) -> Result<Vec<ProviderIncompatibility>, String> {
+    state
+        .db
+        .list_incompatibilities(app_type.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+/// 重置字段不兼容记录，返回删除的条数
+///
+/// provider_id / field 为空时重置该应用（或该供应商）的全部记录
+#[tauri::command]
+pub async fn reset_provider_incompatibilities(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+    provider_id: Option<String>,
+    field: Option<String>,
+) -> Result<usize, String> {
+    let removed = state
+        .db
+        .reset_incompatibilities(&app_type, provider_id.as_deref(), field.as_deref())
+        .map_err(|e| e.to_string())?;
+
+    log::info!(
+        "[Compat] Reset {removed} incompatibilities for {app_type} ({} / {})",
+        provider_id.as_deref().unwrap_or("all providers"),
+        field.as_deref().unwrap_or("all fields")
+    );
+
+    Ok(removed)
+}
@@ -1,5 +1,6 @@
 #![allow(non_snake_case)]
 
+mod compat;
 mod config;
 mod deeplink;
 mod env;
@@ -11,11 +12,15 @@ mod plugin;
 mod prompt;
 mod provider;
 mod proxy;
+mod response_cache;
+mod routing_rules;
+mod schedule;
 mod settings;
 pub mod skill;
 mod stream_check;
 mod usage;
 
+pub use compat::*;
 pub use config::*;
 pub use deeplink::*;
 pub use env::*;
@@ -27,6 +32,9 @@ pub use plugin::*;
 pub use prompt::*;
 pub use provider::*;
 pub use proxy::*;
+pub use response_cache::*;
+pub use routing_rules::*;
+pub use schedule::*;
 pub use settings::*;
 pub use skill::*;
 pub use stream_check::*;
@@ -3,7 +3,8 @@ use tauri::State;
 
 use crate::app_config::AppType;
 use crate::error::AppError;
-use crate::provider::Provider;
+use crate::provider::{BodyPatch, Provider};
+use crate::proxy::body_patch::{self, BodyPatchPreview};
 use crate::services::{EndpointLatency, ProviderService, ProviderSortUpdate, SpeedtestService};
 use crate::store::AppState;
 use std::str::FromStr;
@@ -158,12 +159,28 @@ pub fn read_live_provider_settings(app: String) -> Result<serde_json::Value, Str
 }
 
 /// 测试第三方/自定义供应商端点的网络延迟
+///
+/// 传入 `app` 与 `providerId` 时使用该供应商的出站设置（上游代理、根证书）
 #[tauri::command]
 pub async fn test_api_endpoints(
+    state: State<'_, AppState>,
     urls: Vec<String>,
     #[allow(non_snake_case)] timeoutSecs: Option<u64>,
+    app: Option<String>,
+    #[allow(non_snake_case)] providerId: Option<String>,
 ) -> Result<Vec<EndpointLatency>, String> {
-    SpeedtestService::test_endpoints(urls, timeoutSecs)
+    let outbound = match (app, providerId) {
+        (Some(app), Some(provider_id)) => {
+            let app_type = AppType::from_str(&app).map_err(|e| e.to_string())?;
+            state
+                .db
+                .get_provider_by_id(&provider_id, app_type.as_str())
+                .map_err(|e| e.to_string())?
+                .and_then(|provider| provider.outbound().cloned())
+        }
+        _ => None,
+    };
+    SpeedtestService::test_endpoints(urls, timeoutSecs, outbound.as_ref())
         .await
         .map_err(|e| e.to_string())
 }
@@ -230,6 +247,15 @@ pub fn update_providers_sort_order(
     ProviderService::update_sort_order(state.inner(), app_type, updates).map_err(|e| e.to_string())
 }
 
+/// 预览请求体改写规则对示例请求的效果（规则无效时返回错误）
+#[tauri::command]
+pub fn preview_body_patches(
+    patches: Vec<BodyPatch>,
+    body: serde_json::Value,
+) -> Result<BodyPatchPreview, String> {
+    body_patch::preview_body_patches(&patches, body)
+}
+
 // ============================================================================
 // 统一供应商（Universal Provider）命令
 // ============================================================================
@@ -2,6 +2,8 @@
 //!
 //! 提供前端调用的 API 接口
 
+use crate::proxy::circuit_breaker::CircuitTransitionRecord;
+use crate::proxy::key_pool::PoolKeyStatus;
 use crate::proxy::types::*;
 use crate::proxy::{CircuitBreakerConfig, CircuitBreakerStats};
 use crate::store::AppState;
@@ -79,16 +81,31 @@ pub async fn get_global_proxy_config(
 
 /// 更新全局代理配置
 ///
-/// 更新统一的全局配置字段，会同时更新三行（claude/codex/gemini）
+/// 更新统一的全局配置字段，会同时更新三行（claude/codex/gemini）；
+/// 开启入站认证时自动生成本地访问令牌并同步到已接管的 Live 配置
 #[tauri::command]
 pub async fn update_global_proxy_config(
     state: tauri::State<'_, AppState>,
     config: GlobalProxyConfig,
 ) -> Result<(), String> {
-    let db = &state.db;
-    db.update_global_proxy_config(config)
-        .await
-        .map_err(|e| e.to_string())
+    state.proxy_service.update_global_config(config).await
+}
+
+/// 重新生成代理本地访问令牌
+#[tauri::command]
+pub async fn regenerate_proxy_access_token(
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    state.proxy_service.regenerate_access_token().await
+}
+
+/// 获取自动生成的代理 HTTPS 根证书路径（尚未生成时返回 None）
+///
+/// 客户端需要信任该证书才能连接 `tls` 监听方式下的代理。
+#[tauri::command]
+pub async fn get_proxy_tls_ca_path() -> Result<Option<String>, String> {
+    let path = crate::proxy::tls::ca_cert_path();
+    Ok(path.exists().then(|| path.to_string_lossy().to_string()))
 }
 
 /// 获取指定应用的代理配置
@@ -176,6 +193,8 @@ pub async fn reset_circuit_breaker(
     db.update_provider_health(&provider_id, &app_type, true, None)
         .await
         .map_err(|e| e.to_string())?;
+    db.clear_circuit_state(&app_type, &provider_id)
+        .map_err(|e| e.to_string())?;
 
     // 2. 如果代理正在运行，重置内存中的熔断器状态
     state
@@ -281,6 +300,33 @@ pub async fn update_circuit_breaker_config(
     Ok(())
 }
 
+/// 获取供应商 Key 池状态（Key 已遮蔽）
+#[tauri::command]
+pub async fn get_key_pool_status(
+    state: tauri::State<'_, AppState>,
+    provider_id: String,
+    app_type: String,
+) -> Result<Vec<PoolKeyStatus>, String> {
+    state
+        .proxy_service
+        .get_key_pool_status(&provider_id, &app_type)
+        .await
+}
+
+/// 重新启用供应商 Key 池中被停用的 Key
+#[tauri::command]
+pub async fn reset_key_pool(
+    state: tauri::State<'_, AppState>,
+    provider_id: String,
+    app_type: String,
+) -> Result<(), String> {
+    state
+        .proxy_service
+        .reset_key_pool(&provider_id, &app_type)
+        .await;
+    Ok(())
+}
+
 /// 获取熔断器统计信息（仅当代理服务器运行时）
 #[tauri::command]
 pub async fn get_circuit_breaker_stats(
@@ -288,8 +334,26 @@ pub async fn get_circuit_breaker_stats(
     provider_id: String,
     app_type: String,
 ) -> Result<Option<CircuitBreakerStats>, String> {
-    // 这个功能需要访问运行中的代理服务器的内存状态
-    // 目前先返回 None，后续可以通过 ProxyService 暴露接口来实现
-    let _ = (state, provider_id, app_type);
-    Ok(None)
+    Ok(state
+        .proxy_service
+        .get_circuit_breaker_stats(&provider_id, &app_type)
+        .await)
+}
+
+/// 获取熔断器状态转换历史（按时间倒序，默认最近 100 条）
+#[tauri::command]
+pub async fn get_circuit_breaker_transitions(
+    state: tauri::State<'_, AppState>,
+    app_type: Option<String>,
+    provider_id: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<CircuitTransitionRecord>, String> {
+    state
+        .db
+        .list_circuit_transitions(
+            app_type.as_deref(),
+            provider_id.as_deref(),
+            limit.unwrap_or(100),
+        )
+        .map_err(|e| e.to_string())
 }
@@ -0,0 +1,50 @@
+//! 本地响应缓存命令
+//!
+//! 查看和清理代理模式下确定性请求的响应缓存
+
+use crate::proxy::response_cache::{ResponseCacheEntry, ResponseCacheStats};
+use crate::store::AppState;
+
+/// 获取响应缓存统计（app_type 为空时统计全部应用）
+#[tauri::command]
+pub async fn get_response_cache_stats(
+    state: tauri::State<'_, AppState>,
+    app_type: Option<String>,
+) -> Result<ResponseCacheStats, String> {
+    state
+        .db
+        .get_response_cache_stats(app_type.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+/// 列出响应缓存条目（默认最多 100 条）
+#[tauri::command]
+pub async fn list_response_cache_entries(
+    state: tauri::State<'_, AppState>,
+    app_type: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<ResponseCacheEntry>, String> {
+    state
+        .db
+        .list_response_cache_entries(app_type.as_deref(), limit.unwrap_or(100))
+        .map_err(|e| e.to_string())
+}
+
+/// 清空响应缓存，返回删除的条目数
+#[tauri::command]
+pub async fn clear_response_cache(
+    state: tauri::State<'_, AppState>,
+    app_type: Option<String>,
+) -> Result<usize, String> {
+    let removed = state
+        .db
+        .clear_response_cache(app_type.as_deref())
+        .map_err(|e| e.to_string())?;
+
+    log::info!(
+        "[ResponseCache] Cleared {removed} entries ({})",
+        app_type.as_deref().unwrap_or("all")
+    );
+
+    Ok(removed)
+}
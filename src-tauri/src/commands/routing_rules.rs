@@ -0,0 +1,75 @@
+//! 模型路由规则命令
+//!
+//! 管理代理模式下按模型名称路由到指定供应商链的规则
+
+use crate::app_config::AppType;
+use crate::proxy::routing_rules::RoutingRule;
+use crate::store::AppState;
+use std::str::FromStr;
+
+/// 获取指定应用的路由规则
+#[tauri::command]
+pub async fn get_routing_rules(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+) -> Result<Vec<RoutingRule>, String> {
+    state
+        .db
+        .get_routing_rules(&app_type)
+        .map_err(|e| e.to_string())
+}
+
+/// 新增或更新路由规则
+///
+/// id 为空时视为新增并生成 ID，返回保存后的规则
+#[tauri::command]
+pub async fn save_routing_rule(
+    state: tauri::State<'_, AppState>,
+    mut rule: RoutingRule,
+) -> Result<RoutingRule, String> {
+    rule.validate()?;
+
+    let app_type = AppType::from_str(&rule.app_type).map_err(|e| e.to_string())?;
+    rule.app_type = app_type.as_str().to_string();
+    for provider_id in &rule.provider_ids {
+        let exists = state
+            .db
+            .get_provider_by_id(provider_id, app_type.as_str())
+            .map_err(|e| e.to_string())?
+            .is_some();
+        if !exists {
+            return Err(format!("供应商不存在: {provider_id}"));
+        }
+    }
+
+    if rule.id.trim().is_empty() {
+        rule.id = uuid::Uuid::new_v4().to_string();
+    }
+    if rule.created_at == 0 {
+        rule.created_at = chrono::Utc::now().timestamp();
+    }
+
+    state
+        .db
+        .save_routing_rule(&rule)
+        .map_err(|e| e.to_string())?;
+
+    log::info!(
+        "[RoutingRule] Saved rule '{}' ({}) for {}: {:?}",
+        rule.name,
+        rule.model_pattern,
+        rule.app_type,
+        rule.provider_ids
+    );
+
+    Ok(rule)
+}
+
+/// 删除路由规则
+#[tauri::command]
+pub async fn delete_routing_rule(
+    state: tauri::State<'_, AppState>,
+    id: String,
+) -> Result<(), String> {
+    state.db.delete_routing_rule(&id).map_err(|e| e.to_string())
+}
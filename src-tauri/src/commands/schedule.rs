@@ -0,0 +1,103 @@
+//! 供应商定时切换命令
+//!
+//! 管理按 cron 规则定时切换供应商 / 重排故障转移队列的规则（代理接管时生效）
+
+use crate::app_config::AppType;
+use crate::proxy::schedule::{preview_schedules, ProviderSchedule, SchedulePreview};
+use crate::store::AppState;
+use chrono::{Local, TimeZone};
+use std::str::FromStr;
+
+/// 获取指定应用的定时规则
+#[tauri::command]
+pub async fn get_provider_schedules(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+) -> Result<Vec<ProviderSchedule>, String> {
+    state
+        .db
+        .get_provider_schedules(&app_type)
+        .map_err(|e| e.to_string())
+}
+
+/// 新增或更新定时规则
+///
+/// id 为空时视为新增并生成 ID，返回保存后的规则
+#[tauri::command]
+pub async fn save_provider_schedule(
+    state: tauri::State<'_, AppState>,
+    mut schedule: ProviderSchedule,
+) -> Result<ProviderSchedule, String> {
+    schedule.validate()?;
+
+    let app_type = AppType::from_str(&schedule.app_type).map_err(|e| e.to_string())?;
+    schedule.app_type = app_type.as_str().to_string();
+    for provider_id in &schedule.provider_ids {
+        let exists = state
+            .db
+            .get_provider_by_id(provider_id, app_type.as_str())
+            .map_err(|e| e.to_string())?
+            .is_some();
+        if !exists {
+            return Err(format!("供应商不存在: {provider_id}"));
+        }
+    }
+
+    if schedule.id.trim().is_empty() {
+        schedule.id = uuid::Uuid::new_v4().to_string();
+    }
+    if schedule.created_at == 0 {
+        schedule.created_at = chrono::Utc::now().timestamp();
+    }
+
+    state
+        .db
+        .save_provider_schedule(&schedule)
+        .map_err(|e| e.to_string())?;
+
+    log::info!(
+        "[Schedule] Saved schedule '{}' ({}) for {}: {} {:?}",
+        schedule.name,
+        schedule.cron,
+        schedule.app_type,
+        schedule.action.as_str(),
+        schedule.provider_ids
+    );
+
+    Ok(schedule)
+}
+
+/// 删除定时规则
+#[tauri::command]
+pub async fn delete_provider_schedule(
+    state: tauri::State<'_, AppState>,
+    id: String,
+) -> Result<(), String> {
+    state
+        .db
+        .delete_provider_schedule(&id)
+        .map_err(|e| e.to_string())
+}
+
+/// 预览某一时刻（Unix 秒，默认当前时间）生效的定时规则
+#[tauri::command]
+pub async fn preview_provider_schedule(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+    at: Option<i64>,
+) -> Result<SchedulePreview, String> {
+    let schedules = state
+        .db
+        .get_provider_schedules(&app_type)
+        .map_err(|e| e.to_string())?;
+
+    let at = match at {
+        Some(ts) => Local
+            .timestamp_opt(ts, 0)
+            .single()
+            .ok_or_else(|| format!("无效的时间戳: {ts}"))?,
+        None => Local::now(),
+    };
+
+    Ok(preview_schedules(&schedules, &at))
+}
@@ -1,6 +1,8 @@
 //! 使用统计相关命令
 
 use crate::error::AppError;
+use crate::proxy::payload_capture::{RequestPayload, SessionTurn};
+use crate::services::replay::{ReplayResult, ReplayService};
 use crate::services::usage_stats::*;
 use crate::store::AppState;
 use tauri::State;
@@ -30,6 +32,16 @@ pub fn get_provider_stats(state: State<'_, AppState>) -> Result<Vec<ProviderStat
     state.db.get_provider_stats()
 }
 
+/// 获取指定 Provider 按 API Key 归属的统计
+#[tauri::command]
+pub fn get_api_key_stats(
+    state: State<'_, AppState>,
+    provider_id: String,
+    app_type: String,
+) -> Result<Vec<ApiKeyStats>, AppError> {
+    state.db.get_api_key_stats(&provider_id, &app_type)
+}
+
 /// 获取模型统计
 #[tauri::command]
 pub fn get_model_stats(state: State<'_, AppState>) -> Result<Vec<ModelStats>, AppError> {
@@ -56,6 +68,34 @@ pub fn get_request_detail(
     state.db.get_request_detail(&request_id)
 }
 
+/// 获取单个请求捕获的请求/响应载荷（需在代理配置中启用载荷捕获）
+#[tauri::command]
+pub fn get_request_payload(
+    state: State<'_, AppState>,
+    request_id: String,
+) -> Result<Option<RequestPayload>, AppError> {
+    state.db.get_request_payload(&request_id)
+}
+
+/// 按时间顺序列出会话中已捕获载荷的各轮请求
+#[tauri::command]
+pub fn list_session_turns(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<Vec<SessionTurn>, AppError> {
+    state.db.list_session_turns(&session_id)
+}
+
+/// 将已捕获的请求重放到指定供应商，返回原始响应与重放响应用于对比
+#[tauri::command]
+pub async fn replay_request(
+    state: State<'_, AppState>,
+    request_id: String,
+    provider_id: String,
+) -> Result<ReplayResult, AppError> {
+    ReplayService::replay(&state.db, &request_id, &provider_id).await
+}
+
 /// 获取模型定价列表
 #[tauri::command]
 pub fn get_model_pricing(state: State<'_, AppState>) -> Result<Vec<ModelPricingInfo>, AppError> {
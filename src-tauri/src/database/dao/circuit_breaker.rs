@@ -0,0 +1,218 @@
+//! 熔断器状态持久化 DAO
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::proxy::circuit_breaker::{CircuitState, CircuitTransitionRecord};
+
+/// 转换历史保留天数
+const TRANSITION_RETENTION_DAYS: i64 = 30;
+
+impl Database {
+    /// 记录熔断器状态转换，并把最新状态写入 provider_health（重启后恢复）
+    pub fn record_circuit_transition(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+        from: CircuitState,
+        to: CircuitState,
+        reason: &str,
+    ) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+        let now = chrono::Utc::now();
+        let ts = now.timestamp();
+
+        conn.execute(
+            "INSERT INTO circuit_breaker_transitions
+             (app_type, provider_id, from_state, to_state, reason, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                app_type,
+                provider_id,
+                from.to_string(),
+                to.to_string(),
+                reason,
+                ts
+            ],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "DELETE FROM circuit_breaker_transitions WHERE created_at < ?1",
+            [ts - TRANSITION_RETENTION_DAYS * 24 * 3600],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 打开时记录打开时间；半开沿用打开时间；关闭时清空
+        conn.execute(
+            "INSERT INTO provider_health
+             (provider_id, app_type, is_healthy, consecutive_failures, updated_at,
+              circuit_state, circuit_opened_at)
+             VALUES (?1, ?2, 1, 0, ?3, ?4, CASE WHEN ?4 = 'closed' THEN NULL ELSE ?5 END)
+             ON CONFLICT(provider_id, app_type) DO UPDATE SET
+                circuit_state = excluded.circuit_state,
+                circuit_opened_at = CASE
+                    WHEN excluded.circuit_state = 'closed' THEN NULL
+                    WHEN excluded.circuit_state = 'open' THEN excluded.circuit_opened_at
+                    ELSE COALESCE(circuit_opened_at, excluded.circuit_opened_at)
+                END,
+                updated_at = excluded.updated_at",
+            rusqlite::params![provider_id, app_type, now.to_rfc3339(), to.to_string(), ts],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 清除持久化的熔断器状态（手动重置时使用，代理未运行时也能生效）
+    pub fn clear_circuit_state(&self, app_type: &str, provider_id: &str) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "UPDATE provider_health SET circuit_state = 'closed', circuit_opened_at = NULL
+             WHERE provider_id = ?1 AND app_type = ?2",
+            rusqlite::params![provider_id, app_type],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    /// 获取持久化的未关闭熔断器状态：(provider_id, 状态, 打开时间秒级时间戳)
+    pub fn get_persisted_circuit_states(
+        &self,
+        app_type: &str,
+    ) -> Result<Vec<(String, CircuitState, Option<i64>)>, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT provider_id, circuit_state, circuit_opened_at FROM provider_health
+                 WHERE app_type = ?1 AND circuit_state != 'closed'",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let rows = stmt
+            .query_map([app_type], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<i64>>(2)?,
+                ))
+            })
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(provider_id, state, opened_at)| {
+                let state = state.parse().ok()?;
+                Some((provider_id, state, opened_at))
+            })
+            .collect())
+    }
+
+    /// 列出熔断器状态转换历史（按时间倒序）
+    pub fn list_circuit_transitions(
+        &self,
+        app_type: Option<&str>,
+        provider_id: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<CircuitTransitionRecord>, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT t.id, t.app_type, t.provider_id, p.name, t.from_state, t.to_state,
+                        t.reason, t.created_at
+                 FROM circuit_breaker_transitions t
+                 LEFT JOIN providers p ON p.id = t.provider_id AND p.app_type = t.app_type
+                 WHERE (?1 IS NULL OR t.app_type = ?1) AND (?2 IS NULL OR t.provider_id = ?2)
+                 ORDER BY t.created_at DESC, t.id DESC
+                 LIMIT ?3",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let items = stmt
+            .query_map(rusqlite::params![app_type, provider_id, limit], |row| {
+                Ok(CircuitTransitionRecord {
+                    id: row.get(0)?,
+                    app_type: row.get(1)?,
+                    provider_id: row.get(2)?,
+                    provider_name: row.get(3)?,
+                    from_state: row.get(4)?,
+                    to_state: row.get(5)?,
+                    reason: row.get(6)?,
+                    created_at: row.get(7)?,
+                })
+            })
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_restore_circuit_transitions() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        {
+            let conn = lock_conn!(db.conn);
+            conn.execute(
+                "INSERT INTO providers (id, app_type, name, settings_config)
+                 VALUES ('p1', 'claude', 'P1', '{}')",
+                [],
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        }
+
+        db.record_circuit_transition(
+            "claude",
+            "p1",
+            CircuitState::Closed,
+            CircuitState::Open,
+            "连续失败 5 次",
+        )?;
+        let states = db.get_persisted_circuit_states("claude")?;
+        assert_eq!(states.len(), 1);
+        assert_eq!(states[0].1, CircuitState::Open);
+        assert!(states[0].2.is_some());
+
+        // 健康状态更新不应覆盖熔断器状态
+        futures::executor::block_on(db.update_provider_health("p1", "claude", true, None))?;
+        assert_eq!(db.get_persisted_circuit_states("claude")?.len(), 1);
+
+        db.record_circuit_transition(
+            "claude",
+            "p1",
+            CircuitState::Open,
+            CircuitState::Closed,
+            "手动重置",
+        )?;
+        assert!(db.get_persisted_circuit_states("claude")?.is_empty());
+
+        // 代理未运行时手动重置也要清除持久化状态
+        db.record_circuit_transition(
+            "claude",
+            "p1",
+            CircuitState::Closed,
+            CircuitState::Open,
+            "连续失败 5 次",
+        )?;
+        db.clear_circuit_state("claude", "p1")?;
+        assert!(db.get_persisted_circuit_states("claude")?.is_empty());
+
+        let history = db.list_circuit_transitions(Some("claude"), None, 10)?;
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].to_state, "open");
+        assert_eq!(history[1].to_state, "closed");
+        assert_eq!(history[0].provider_name.as_deref(), Some("P1"));
+        assert!(db
+            .list_circuit_transitions(Some("codex"), None, 10)?
+            .is_empty());
+        Ok(())
+    }
+}
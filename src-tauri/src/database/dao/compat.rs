@@ -0,0 +1,154 @@
+//! 供应商字段兼容性 DAO
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::proxy::compat::ProviderIncompatibility;
+
+impl Database {
+    /// 获取供应商已知不支持的请求字段
+    pub fn get_incompatible_fields(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+    ) -> Result<Vec<String>, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT field FROM provider_incompatibilities
+                 WHERE app_type = ?1 AND provider_id = ?2
+                 ORDER BY learned_at ASC",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let fields = stmt
+            .query_map([app_type, provider_id], |row| row.get(0))
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<Vec<String>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(fields)
+    }
+
+    /// 记录供应商不支持的字段（已存在时更新错误信息与命中次数）
+    pub fn record_incompatibility(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+        field: &str,
+        error_message: Option<&str>,
+    ) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+        let now = chrono::Utc::now().timestamp();
+
+        conn.execute(
+            "INSERT INTO provider_incompatibilities
+             (app_type, provider_id, field, error_message, hit_count, learned_at, last_seen_at)
+             VALUES (?1, ?2, ?3, ?4, 1, ?5, ?5)
+             ON CONFLICT(app_type, provider_id, field) DO UPDATE SET
+                error_message = excluded.error_message,
+                hit_count = hit_count + 1,
+                last_seen_at = excluded.last_seen_at",
+            rusqlite::params![app_type, provider_id, field, error_message, now],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 列出已识别的字段不兼容记录（app_type 为 None 时列出全部）
+    pub fn list_incompatibilities(
+        &self,
+        app_type: Option<&str>,
+    ) -> Result<Vec<ProviderIncompatibility>, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT i.app_type, i.provider_id, p.name, i.field, i.error_message,
+                        i.hit_count, i.learned_at, i.last_seen_at
+                 FROM provider_incompatibilities i
+                 LEFT JOIN providers p ON p.id = i.provider_id AND p.app_type = i.app_type
+                 WHERE ?1 IS NULL OR i.app_type = ?1
+                 ORDER BY i.last_seen_at DESC",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let items = stmt
+            .query_map([app_type], |row| {
+                Ok(ProviderIncompatibility {
+                    app_type: row.get(0)?,
+                    provider_id: row.get(1)?,
+                    provider_name: row.get(2)?,
+                    field: row.get(3)?,
+                    error_message: row.get(4)?,
+                    hit_count: row.get::<_, i64>(5)? as u64,
+                    learned_at: row.get(6)?,
+                    last_seen_at: row.get(7)?,
+                })
+            })
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(items)
+    }
+
+    /// 清除字段不兼容记录，返回删除的条数
+    ///
+    /// provider_id / field 为 None 时不作为过滤条件（例如只传 app_type 即清除该应用的全部记录）。
+    pub fn reset_incompatibilities(
+        &self,
+        app_type: &str,
+        provider_id: Option<&str>,
+        field: Option<&str>,
+    ) -> Result<usize, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        conn.execute(
+            "DELETE FROM provider_incompatibilities
+             WHERE app_type = ?1
+               AND (?2 IS NULL OR provider_id = ?2)
+               AND (?3 IS NULL OR field = ?3)",
+            rusqlite::params![app_type, provider_id, field],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_list_and_reset_incompatibilities() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        db.record_incompatibility("claude", "p1", "metadata", Some("Unknown parameter"))?;
+        db.record_incompatibility("claude", "p1", "metadata", Some("Unknown parameter"))?;
+        db.record_incompatibility("claude", "p1", "betas", None)?;
+        db.record_incompatibility("codex", "p1", "store", None)?;
+
+        assert_eq!(
+            db.get_incompatible_fields("claude", "p1")?.len(),
+            2,
+            "fields are unique per provider"
+        );
+        let claude = db.list_incompatibilities(Some("claude"))?;
+        let metadata = claude.iter().find(|i| i.field == "metadata").unwrap();
+        assert_eq!(metadata.hit_count, 2);
+        assert_eq!(db.list_incompatibilities(None)?.len(), 3);
+
+        assert_eq!(
+            db.reset_incompatibilities("claude", None, Some("betas"))?,
+            1
+        );
+        assert_eq!(
+            db.get_incompatible_fields("claude", "p1")?,
+            vec!["metadata"]
+        );
+        assert_eq!(db.reset_incompatibilities("claude", Some("p1"), None)?, 1);
+        assert!(db.get_incompatible_fields("claude", "p1")?.is_empty());
+        assert_eq!(db.get_incompatible_fields("codex", "p1")?, vec!["store"]);
+        Ok(())
+    }
+}
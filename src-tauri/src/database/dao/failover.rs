@@ -97,6 +97,59 @@ impl Database {
         Ok(())
     }
 
+    /// 把给定供应商按顺序排到故障转移队列最前面（其余保持原顺序），返回顺序是否变化
+    ///
+    /// 只在队列成员原有的 sort_index 之间重新分配，不影响队列外供应商的排序位置。
+    pub fn reorder_failover_queue(
+        &self,
+        app_type: &str,
+        provider_ids: &[String],
+    ) -> Result<bool, AppError> {
+        let queue = self.get_failover_queue(app_type)?;
+
+        let mut ordered: Vec<&FailoverQueueItem> = Vec::with_capacity(queue.len());
+        for item in provider_ids
+            .iter()
+            .filter_map(|id| queue.iter().find(|item| &item.provider_id == id))
+            .chain(queue.iter())
+        {
+            if !ordered.iter().any(|o| o.provider_id == item.provider_id) {
+                ordered.push(item);
+            }
+        }
+        if ordered
+            .iter()
+            .map(|item| &item.provider_id)
+            .eq(queue.iter().map(|item| &item.provider_id))
+        {
+            return Ok(false);
+        }
+
+        // 队列原有位置（保证严格递增，缺少 sort_index 的排在最后）
+        let mut slots = Vec::with_capacity(queue.len());
+        let mut next = 0;
+        for item in &queue {
+            let slot = item.sort_index.unwrap_or(next).max(next);
+            slots.push(slot);
+            next = slot + 1;
+        }
+
+        let mut conn = lock_conn!(self.conn);
+        let tx = conn
+            .transaction()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        for (item, slot) in ordered.iter().zip(slots) {
+            tx.execute(
+                "UPDATE providers SET sort_index = ?1 WHERE id = ?2 AND app_type = ?3",
+                rusqlite::params![slot as i64, item.provider_id, app_type],
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        }
+        tx.commit().map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(true)
+    }
+
     /// 清空故障转移队列
     pub fn clear_failover_queue(&self, app_type: &str) -> Result<(), AppError> {
         let conn = lock_conn!(self.conn);
@@ -144,3 +197,41 @@ impl Database {
         Ok(available)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reorder_failover_queue_keeps_non_queue_positions() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        {
+            let conn = lock_conn!(db.conn);
+            for (id, sort_index, in_queue) in [("a", 0, 1), ("x", 1, 0), ("b", 2, 1), ("c", 3, 1)] {
+                conn.execute(
+                    "INSERT INTO providers (id, app_type, name, settings_config, sort_index, in_failover_queue)
+                     VALUES (?1, 'claude', ?1, '{}', ?2, ?3)",
+                    rusqlite::params![id, sort_index, in_queue],
+                )
+                .map_err(|e| AppError::Database(e.to_string()))?;
+            }
+        }
+
+        let ids = |db: &Database| -> Result<Vec<String>, AppError> {
+            Ok(db
+                .get_failover_queue("claude")?
+                .into_iter()
+                .map(|item| item.provider_id)
+                .collect())
+        };
+
+        assert!(db.reorder_failover_queue("claude", &["c".to_string(), "missing".to_string()])?);
+        assert_eq!(ids(&db)?, vec!["c", "a", "b"]);
+        let x = db.get_provider_by_id("x", "claude")?.unwrap();
+        assert_eq!(x.sort_index, Some(1));
+
+        // 顺序未变化时不写入
+        assert!(!db.reorder_failover_queue("claude", &["c".to_string()])?);
+        Ok(())
+    }
+}
@@ -2,11 +2,17 @@
 //!
 //! Database access operations for each domain
 
+pub mod circuit_breaker;
+pub mod compat;
 pub mod failover;
 pub mod mcp;
 pub mod prompts;
+pub mod provider_schedules;
 pub mod providers;
 pub mod proxy;
+pub mod request_payloads;
+pub mod response_cache;
+pub mod routing_rules;
 pub mod settings;
 pub mod skills;
 pub mod stream_check;
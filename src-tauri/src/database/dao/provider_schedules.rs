@@ -0,0 +1,104 @@
+//! 供应商定时切换规则 DAO
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::proxy::schedule::ProviderSchedule;
+
+impl Database {
+    /// 获取指定应用的定时规则（按 sort_index 排序）
+    pub fn get_provider_schedules(
+        &self,
+        app_type: &str,
+    ) -> Result<Vec<ProviderSchedule>, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, app_type, name, cron, action, provider_ids, enabled, sort_index, created_at
+                 FROM provider_schedules
+                 WHERE app_type = ?1
+                 ORDER BY sort_index ASC, created_at ASC",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let schedules = stmt
+            .query_map([app_type], |row| {
+                let action: String = row.get(4)?;
+                let provider_ids: String = row.get(5)?;
+                Ok(ProviderSchedule {
+                    id: row.get(0)?,
+                    app_type: row.get(1)?,
+                    name: row.get(2)?,
+                    cron: row.get(3)?,
+                    action: action.parse().unwrap_or_default(),
+                    provider_ids: serde_json::from_str(&provider_ids).unwrap_or_default(),
+                    enabled: row.get::<_, i64>(6)? != 0,
+                    sort_index: row.get(7)?,
+                    created_at: row.get(8)?,
+                })
+            })
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(schedules)
+    }
+
+    /// 保存定时规则（存在则覆盖）
+    pub fn save_provider_schedule(&self, schedule: &ProviderSchedule) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let provider_ids = serde_json::to_string(&schedule.provider_ids)
+            .map_err(|e| AppError::Database(format!("序列化供应商列表失败: {e}")))?;
+
+        conn.execute(
+            "INSERT OR REPLACE INTO provider_schedules
+             (id, app_type, name, cron, action, provider_ids, enabled, sort_index, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            rusqlite::params![
+                schedule.id,
+                schedule.app_type,
+                schedule.name,
+                schedule.cron,
+                schedule.action.as_str(),
+                provider_ids,
+                if schedule.enabled { 1 } else { 0 },
+                schedule.sort_index,
+                schedule.created_at,
+            ],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 删除定时规则
+    pub fn delete_provider_schedule(&self, id: &str) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+
+        conn.execute("DELETE FROM provider_schedules WHERE id = ?1", [id])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 获取指定应用最近一次已处理的触发（规则 ID, 触发时间）
+    pub fn get_schedule_last_applied(
+        &self,
+        app_type: &str,
+    ) -> Result<Option<(String, i64)>, AppError> {
+        Ok(self
+            .get_setting(&format!("schedule_last_applied_{app_type}"))?
+            .and_then(|value| serde_json::from_str(&value).ok()))
+    }
+
+    /// 记录指定应用最近一次已处理的触发，重启或重新接管后不再重复执行
+    pub fn set_schedule_last_applied(
+        &self,
+        app_type: &str,
+        mark: &(String, i64),
+    ) -> Result<(), AppError> {
+        let value = serde_json::to_string(mark).map_err(|e| AppError::Message(e.to_string()))?;
+        self.set_setting(&format!("schedule_last_applied_{app_type}"), &value)
+    }
+}
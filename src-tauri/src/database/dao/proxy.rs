@@ -18,7 +18,10 @@ impl Database {
         let result = {
             let conn = lock_conn!(self.conn);
             conn.query_row(
-                "SELECT proxy_enabled, listen_address, listen_port, enable_logging
+                "SELECT proxy_enabled, listen_address, listen_port, enable_logging,
+                        global_spend_limit_daily_usd, global_spend_limit_monthly_usd,
+                        auth_enabled, access_token, allowed_cidrs,
+                        listen_mode, tls_cert_path, tls_key_path, unix_socket_path
                  FROM proxy_config WHERE app_type = 'claude'",
                 [],
                 |row| {
@@ -27,6 +30,21 @@ impl Database {
                         listen_address: row.get(1)?,
                         listen_port: row.get::<_, i32>(2)? as u16,
                         enable_logging: row.get::<_, i32>(3)? != 0,
+                        spend_limit_daily_usd: row.get(4)?,
+                        spend_limit_monthly_usd: row.get(5)?,
+                        auth_enabled: row.get::<_, i32>(6)? != 0,
+                        access_token: row.get(7)?,
+                        allowed_cidrs: row
+                            .get::<_, Option<String>>(8)?
+                            .and_then(|s| serde_json::from_str(&s).ok())
+                            .unwrap_or_default(),
+                        listen_mode: row
+                            .get::<_, Option<String>>(9)?
+                            .and_then(|s| s.parse().ok())
+                            .unwrap_or_default(),
+                        tls_cert_path: row.get(10)?,
+                        tls_key_path: row.get(11)?,
+                        unix_socket_path: row.get(12)?,
                     })
                 },
             )
@@ -43,6 +61,15 @@ impl Database {
                     listen_address: "127.0.0.1".to_string(),
                     listen_port: 5000,
                     enable_logging: true,
+                    spend_limit_daily_usd: None,
+                    spend_limit_monthly_usd: None,
+                    auth_enabled: false,
+                    access_token: None,
+                    allowed_cidrs: Vec::new(),
+                    listen_mode: ListenMode::default(),
+                    tls_cert_path: None,
+                    tls_key_path: None,
+                    unix_socket_path: None,
                 })
             }
             Err(e) => Err(AppError::Database(e.to_string())),
@@ -62,12 +89,30 @@ impl Database {
                 listen_address = ?2,
                 listen_port = ?3,
                 enable_logging = ?4,
+                global_spend_limit_daily_usd = ?5,
+                global_spend_limit_monthly_usd = ?6,
+                auth_enabled = ?7,
+                access_token = ?8,
+                allowed_cidrs = ?9,
+                listen_mode = ?10,
+                tls_cert_path = ?11,
+                tls_key_path = ?12,
+                unix_socket_path = ?13,
                 updated_at = datetime('now')",
             rusqlite::params![
                 if config.proxy_enabled { 1 } else { 0 },
                 config.listen_address,
                 config.listen_port as i32,
                 if config.enable_logging { 1 } else { 0 },
+                config.spend_limit_daily_usd,
+                config.spend_limit_monthly_usd,
+                if config.auth_enabled { 1 } else { 0 },
+                config.access_token,
+                serde_json::to_string(&config.allowed_cidrs).unwrap_or_else(|_| "[]".to_string()),
+                config.listen_mode.as_str(),
+                config.tls_cert_path,
+                config.tls_key_path,
+                config.unix_socket_path,
             ],
         )
         .map_err(|e| AppError::Database(e.to_string()))?;
@@ -88,7 +133,14 @@ impl Database {
                 "SELECT app_type, enabled, auto_failover_enabled,
                         max_retries, streaming_first_byte_timeout, streaming_idle_timeout, non_streaming_timeout,
                         circuit_failure_threshold, circuit_success_threshold, circuit_timeout_seconds,
-                        circuit_error_rate_threshold, circuit_min_requests
+                        circuit_error_rate_threshold, circuit_min_requests, load_balance_strategy,
+                        hedge_enabled, hedge_delay_ms,
+                        response_cache_enabled, response_cache_ttl_seconds, response_cache_max_mb,
+                        payload_capture_enabled, payload_retention_days, payload_max_body_kb,
+                        spend_limit_daily_usd, spend_limit_monthly_usd,
+                        stream_recovery_enabled, stream_recovery_max_attempts,
+                        health_probe_enabled, health_probe_interval_secs,
+                        health_probe_daily_limit, health_probe_model, health_probe_daily_cost_usd
                  FROM proxy_config WHERE app_type = ?1",
                 [app_type],
                 |row| {
@@ -105,6 +157,27 @@ impl Database {
                         circuit_timeout_seconds: row.get::<_, i32>(9)? as u32,
                         circuit_error_rate_threshold: row.get(10)?,
                         circuit_min_requests: row.get::<_, i32>(11)? as u32,
+                        load_balance_strategy: row
+                            .get::<_, String>(12)?
+                            .parse()
+                            .unwrap_or_default(),
+                        hedge_enabled: row.get::<_, i32>(13)? != 0,
+                        hedge_delay_ms: row.get::<_, i32>(14)? as u32,
+                        response_cache_enabled: row.get::<_, i32>(15)? != 0,
+                        response_cache_ttl_seconds: row.get::<_, i32>(16)? as u32,
+                        response_cache_max_mb: row.get::<_, i32>(17)? as u32,
+                        payload_capture_enabled: row.get::<_, i32>(18)? != 0,
+                        payload_retention_days: row.get::<_, i32>(19)? as u32,
+                        payload_max_body_kb: row.get::<_, i32>(20)? as u32,
+                        spend_limit_daily_usd: row.get(21)?,
+                        spend_limit_monthly_usd: row.get(22)?,
+                        stream_recovery_enabled: row.get::<_, i32>(23)? != 0,
+                        stream_recovery_max_attempts: row.get::<_, i32>(24)? as u32,
+                        health_probe_enabled: row.get::<_, i32>(25)? != 0,
+                        health_probe_interval_secs: row.get::<_, i32>(26)? as u32,
+                        health_probe_daily_limit: row.get::<_, i32>(27)? as u32,
+                        health_probe_model: row.get(28)?,
+                        health_probe_daily_cost_usd: row.get(29)?,
                     })
                 },
             )
@@ -129,6 +202,24 @@ impl Database {
                     circuit_timeout_seconds: 60,
                     circuit_error_rate_threshold: 0.5,
                     circuit_min_requests: 10,
+                    load_balance_strategy: LoadBalanceStrategy::default(),
+                    hedge_enabled: false,
+                    hedge_delay_ms: 3000,
+                    response_cache_enabled: false,
+                    response_cache_ttl_seconds: 3600,
+                    response_cache_max_mb: 50,
+                    payload_capture_enabled: false,
+                    payload_retention_days: 7,
+                    payload_max_body_kb: 1024,
+                    spend_limit_daily_usd: None,
+                    spend_limit_monthly_usd: None,
+                    stream_recovery_enabled: false,
+                    stream_recovery_max_attempts: 1,
+                    health_probe_enabled: false,
+                    health_probe_interval_secs: 300,
+                    health_probe_daily_limit: 200,
+                    health_probe_model: None,
+                    health_probe_daily_cost_usd: None,
                 })
             }
             Err(e) => Err(AppError::Database(e.to_string())),
@@ -155,6 +246,24 @@ impl Database {
                 circuit_timeout_seconds = ?10,
                 circuit_error_rate_threshold = ?11,
                 circuit_min_requests = ?12,
+                load_balance_strategy = ?13,
+                hedge_enabled = ?14,
+                hedge_delay_ms = ?15,
+                response_cache_enabled = ?16,
+                response_cache_ttl_seconds = ?17,
+                response_cache_max_mb = ?18,
+                payload_capture_enabled = ?19,
+                payload_retention_days = ?20,
+                payload_max_body_kb = ?21,
+                spend_limit_daily_usd = ?22,
+                spend_limit_monthly_usd = ?23,
+                stream_recovery_enabled = ?24,
+                stream_recovery_max_attempts = ?25,
+                health_probe_enabled = ?26,
+                health_probe_interval_secs = ?27,
+                health_probe_daily_limit = ?28,
+                health_probe_model = ?29,
+                health_probe_daily_cost_usd = ?30,
                 updated_at = datetime('now')
              WHERE app_type = ?1",
             rusqlite::params![
@@ -170,6 +279,24 @@ impl Database {
                 config.circuit_timeout_seconds as i32,
                 config.circuit_error_rate_threshold,
                 config.circuit_min_requests as i32,
+                config.load_balance_strategy.as_str(),
+                if config.hedge_enabled { 1 } else { 0 },
+                config.hedge_delay_ms as i32,
+                if config.response_cache_enabled { 1 } else { 0 },
+                config.response_cache_ttl_seconds as i32,
+                config.response_cache_max_mb as i32,
+                if config.payload_capture_enabled { 1 } else { 0 },
+                config.payload_retention_days as i32,
+                config.payload_max_body_kb as i32,
+                config.spend_limit_daily_usd,
+                config.spend_limit_monthly_usd,
+                if config.stream_recovery_enabled { 1 } else { 0 },
+                config.stream_recovery_max_attempts as i32,
+                if config.health_probe_enabled { 1 } else { 0 },
+                config.health_probe_interval_secs as i32,
+                config.health_probe_daily_limit as i32,
+                config.health_probe_model,
+                config.health_probe_daily_cost_usd,
             ],
         )
         .map_err(|e| AppError::Database(e.to_string()))?;
@@ -202,7 +329,8 @@ impl Database {
             conn.query_row(
                 "SELECT listen_address, listen_port, max_retries,
                         enable_logging,
-                        streaming_first_byte_timeout, streaming_idle_timeout, non_streaming_timeout
+                        streaming_first_byte_timeout, streaming_idle_timeout, non_streaming_timeout,
+                        listen_mode, tls_cert_path, tls_key_path, unix_socket_path
                  FROM proxy_config WHERE app_type = 'claude'",
                 [],
                 |row| {
@@ -216,6 +344,13 @@ impl Database {
                         streaming_first_byte_timeout: row.get::<_, i32>(4).unwrap_or(30) as u64,
                         streaming_idle_timeout: row.get::<_, i32>(5).unwrap_or(60) as u64,
                         non_streaming_timeout: row.get::<_, i32>(6).unwrap_or(300) as u64,
+                        listen_mode: row
+                            .get::<_, Option<String>>(7)?
+                            .and_then(|s| s.parse().ok())
+                            .unwrap_or_default(),
+                        tls_cert_path: row.get(8)?,
+                        tls_key_path: row.get(9)?,
+                        unix_socket_path: row.get(10)?,
                     })
                 },
             )
@@ -390,17 +525,19 @@ impl Database {
             (None, Some(now.clone()))
         };
 
-        // UPSERT
+        // UPSERT（保留熔断器状态列）
         conn.execute(
-            "INSERT OR REPLACE INTO provider_health
+            "INSERT INTO provider_health
              (provider_id, app_type, is_healthy, consecutive_failures,
               last_success_at, last_failure_at, last_error, updated_at)
-             VALUES (?1, ?2, ?3, ?4,
-                     COALESCE(?5, (SELECT last_success_at FROM provider_health
-                                   WHERE provider_id = ?1 AND app_type = ?2)),
-                     COALESCE(?6, (SELECT last_failure_at FROM provider_health
-                                   WHERE provider_id = ?1 AND app_type = ?2)),
-                     ?7, ?8)",
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT(provider_id, app_type) DO UPDATE SET
+                is_healthy = excluded.is_healthy,
+                consecutive_failures = excluded.consecutive_failures,
+                last_success_at = COALESCE(excluded.last_success_at, last_success_at),
+                last_failure_at = COALESCE(excluded.last_failure_at, last_failure_at),
+                last_error = excluded.last_error,
+                updated_at = excluded.updated_at",
             rusqlite::params![
                 provider_id,
                 app_type,
@@ -461,6 +598,45 @@ impl Database {
         Ok(())
     }
 
+    // ==================== Provider Latency ====================
+
+    /// 获取指定应用下各 Provider 最近一段时间的平均延迟（毫秒）
+    ///
+    /// 优先使用首字时间（first_token_ms），流式请求更能反映真实体感；
+    /// 仅统计成功请求，失败请求的耗时不具参考价值。
+    pub async fn get_recent_provider_latencies(
+        &self,
+        app_type: &str,
+        window_secs: i64,
+    ) -> Result<std::collections::HashMap<String, f64>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let since = chrono::Utc::now().timestamp() - window_secs;
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT provider_id, AVG(COALESCE(first_token_ms, latency_ms))
+                 FROM proxy_request_logs
+                 WHERE app_type = ?1 AND created_at >= ?2
+                   AND status_code >= 200 AND status_code < 300
+                 GROUP BY provider_id",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let rows = stmt
+            .query_map(rusqlite::params![app_type, since], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+            })
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let mut latencies = std::collections::HashMap::new();
+        for row in rows {
+            let (provider_id, avg) = row.map_err(|e| AppError::Database(e.to_string()))?;
+            latencies.insert(provider_id, avg);
+        }
+
+        Ok(latencies)
+    }
+
     // ==================== Circuit Breaker Config (Legacy Compatibility) ====================
 
     /// 获取熔断器配置（兼容旧接口，从 claude 行读取）
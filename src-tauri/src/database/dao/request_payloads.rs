@@ -0,0 +1,206 @@
+//! 请求/响应载荷 DAO
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::proxy::payload_capture::{RequestPayload, SessionTurn};
+use rusqlite::OptionalExtension;
+
+impl Database {
+    /// 保存请求载荷，并按保留天数清理同一应用的旧载荷（retention_days 为 0 时不清理）
+    pub fn save_request_payload(
+        &self,
+        payload: &RequestPayload,
+        retention_days: u32,
+    ) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+
+        conn.execute(
+            "INSERT OR REPLACE INTO proxy_request_payloads (
+                request_id, session_id, app_type, provider_id, model, endpoint,
+                request_headers, request_body, status_code, response_headers, response_body,
+                is_streaming, truncated, created_at, upstream_headers, header_rewrites
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+            rusqlite::params![
+                payload.request_id,
+                payload.session_id,
+                payload.app_type,
+                payload.provider_id,
+                payload.model,
+                payload.endpoint,
+                payload.request_headers.to_string(),
+                payload.request_body,
+                payload.status_code as i64,
+                payload.response_headers.as_ref().map(|h| h.to_string()),
+                payload.response_body,
+                payload.is_streaming as i64,
+                payload.truncated as i64,
+                payload.created_at,
+                payload.upstream_headers.as_ref().map(|h| h.to_string()),
+                serde_json::to_string(&payload.header_rewrites).unwrap_or_default(),
+            ],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        if retention_days > 0 {
+            let cutoff = payload.created_at - retention_days as i64 * 86400;
+            conn.execute(
+                "DELETE FROM proxy_request_payloads WHERE app_type = ?1 AND created_at < ?2",
+                rusqlite::params![payload.app_type, cutoff],
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    /// 获取单个请求的载荷
+    pub fn get_request_payload(
+        &self,
+        request_id: &str,
+    ) -> Result<Option<RequestPayload>, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        conn.query_row(
+            "SELECT request_id, session_id, app_type, provider_id, model, endpoint,
+                    request_headers, request_body, status_code, response_headers, response_body,
+                    is_streaming, truncated, created_at, upstream_headers, header_rewrites
+             FROM proxy_request_payloads WHERE request_id = ?1",
+            [request_id],
+            |row| {
+                let request_headers: String = row.get(6)?;
+                let response_headers: Option<String> = row.get(9)?;
+                let upstream_headers: Option<String> = row.get(14)?;
+                let header_rewrites: Option<String> = row.get(15)?;
+                Ok(RequestPayload {
+                    request_id: row.get(0)?,
+                    session_id: row.get(1)?,
+                    app_type: row.get(2)?,
+                    provider_id: row.get(3)?,
+                    model: row.get(4)?,
+                    endpoint: row.get(5)?,
+                    request_headers: serde_json::from_str(&request_headers).unwrap_or_default(),
+                    request_body: row.get(7)?,
+                    status_code: row.get::<_, i64>(8)? as u16,
+                    response_headers: response_headers.and_then(|h| serde_json::from_str(&h).ok()),
+                    response_body: row.get(10)?,
+                    is_streaming: row.get::<_, i64>(11)? != 0,
+                    truncated: row.get::<_, i64>(12)? != 0,
+                    created_at: row.get(13)?,
+                    upstream_headers: upstream_headers.and_then(|h| serde_json::from_str(&h).ok()),
+                    header_rewrites: header_rewrites
+                        .and_then(|r| serde_json::from_str(&r).ok())
+                        .unwrap_or_default(),
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// 按时间顺序列出会话中的各轮请求（关联请求日志中的用量与耗时）
+    pub fn list_session_turns(&self, session_id: &str) -> Result<Vec<SessionTurn>, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT r.request_id, r.app_type, r.provider_id, p.name, r.model, r.endpoint,
+                        r.status_code, r.is_streaming, LENGTH(r.request_body),
+                        COALESCE(LENGTH(r.response_body), 0),
+                        l.input_tokens, l.output_tokens, l.total_cost_usd, l.latency_ms,
+                        r.created_at
+                 FROM proxy_request_payloads r
+                 LEFT JOIN proxy_request_logs l ON l.request_id = r.request_id
+                 LEFT JOIN providers p ON r.provider_id = p.id AND r.app_type = p.app_type
+                 WHERE r.session_id = ?1
+                 ORDER BY r.created_at ASC, r.rowid ASC",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let turns = stmt
+            .query_map([session_id], |row| {
+                Ok(SessionTurn {
+                    request_id: row.get(0)?,
+                    app_type: row.get(1)?,
+                    provider_id: row.get(2)?,
+                    provider_name: row.get(3)?,
+                    model: row.get(4)?,
+                    endpoint: row.get(5)?,
+                    status_code: row.get::<_, i64>(6)? as u16,
+                    is_streaming: row.get::<_, i64>(7)? != 0,
+                    request_bytes: row.get::<_, i64>(8)? as u64,
+                    response_bytes: row.get::<_, i64>(9)? as u64,
+                    input_tokens: row.get::<_, Option<i64>>(10)?.map(|v| v as u32),
+                    output_tokens: row.get::<_, Option<i64>>(11)?.map(|v| v as u32),
+                    total_cost_usd: row.get(12)?,
+                    latency_ms: row.get::<_, Option<i64>>(13)?.map(|v| v as u64),
+                    created_at: row.get(14)?,
+                })
+            })
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(turns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn payload(request_id: &str, session_id: &str, created_at: i64) -> RequestPayload {
+        RequestPayload {
+            request_id: request_id.to_string(),
+            session_id: Some(session_id.to_string()),
+            app_type: "claude".to_string(),
+            provider_id: "p1".to_string(),
+            model: "claude-sonnet-4".to_string(),
+            endpoint: "/v1/messages".to_string(),
+            request_headers: json!({"x-api-key": "sk-a...1234"}),
+            request_body: r#"{"messages":[]}"#.to_string(),
+            status_code: 200,
+            response_headers: None,
+            response_body: Some(r#"{"content":[]}"#.to_string()),
+            is_streaming: false,
+            truncated: false,
+            created_at,
+            upstream_headers: Some(json!({"x-api-key": "sk-a...1234", "x-title": "cc-switch"})),
+            header_rewrites: vec!["set x-title".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_save_and_get_payload() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        let now = chrono::Utc::now().timestamp();
+        db.save_request_payload(&payload("r1", "s1", now), 7)?;
+
+        let saved = db.get_request_payload("r1")?.expect("payload saved");
+        assert_eq!(saved.session_id.as_deref(), Some("s1"));
+        assert_eq!(saved.request_headers["x-api-key"], "sk-a...1234");
+        assert_eq!(saved.response_body.as_deref(), Some(r#"{"content":[]}"#));
+        assert_eq!(saved.upstream_headers.unwrap()["x-title"], "cc-switch");
+        assert_eq!(saved.header_rewrites, vec!["set x-title"]);
+        assert!(db.get_request_payload("missing")?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_session_turns_are_ordered_and_retention_applies() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        let now = chrono::Utc::now().timestamp();
+        db.save_request_payload(&payload("old", "s1", now - 10 * 86400), 0)?;
+        db.save_request_payload(&payload("r1", "s1", now - 60), 7)?;
+        db.save_request_payload(&payload("r2", "s1", now), 7)?;
+        db.save_request_payload(&payload("other", "s2", now), 7)?;
+
+        let turns: Vec<String> = db
+            .list_session_turns("s1")?
+            .into_iter()
+            .map(|t| t.request_id)
+            .collect();
+        assert_eq!(turns, vec!["r1".to_string(), "r2".to_string()]);
+        Ok(())
+    }
+}
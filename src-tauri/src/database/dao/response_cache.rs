@@ -0,0 +1,275 @@
+//! 本地响应缓存 DAO
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::proxy::response_cache::{CachedResponse, ResponseCacheEntry, ResponseCacheStats};
+use rusqlite::OptionalExtension;
+
+impl Database {
+    /// 读取未过期的缓存条目，命中时累加命中次数
+    pub fn get_cached_response(&self, cache_key: &str) -> Result<Option<CachedResponse>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let now = chrono::Utc::now().timestamp();
+
+        let cached = conn
+            .query_row(
+                "SELECT provider_id, status_code, content_type, response_body, is_streaming
+                 FROM proxy_response_cache
+                 WHERE cache_key = ?1 AND expires_at > ?2",
+                rusqlite::params![cache_key, now],
+                |row| {
+                    Ok(CachedResponse {
+                        provider_id: row.get(0)?,
+                        status_code: row.get::<_, i64>(1)? as u16,
+                        content_type: row.get(2)?,
+                        body: row.get(3)?,
+                        is_streaming: row.get::<_, i64>(4)? != 0,
+                    })
+                },
+            )
+            .optional()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        if cached.is_some() {
+            conn.execute(
+                "UPDATE proxy_response_cache
+                 SET hit_count = hit_count + 1, last_hit_at = ?2
+                 WHERE cache_key = ?1",
+                rusqlite::params![cache_key, now],
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        }
+
+        Ok(cached)
+    }
+
+    /// 写入缓存条目，并淘汰过期条目和超出容量上限的最久未使用条目
+    pub fn put_cached_response(
+        &self,
+        cache_key: &str,
+        app_type: &str,
+        model: &str,
+        response: &CachedResponse,
+        ttl_seconds: u64,
+        max_bytes: u64,
+    ) -> Result<(), AppError> {
+        let size_bytes = response.body.len() as u64;
+        if size_bytes > max_bytes {
+            log::debug!("[ResponseCache] 响应大小 {size_bytes} 超过缓存上限，跳过缓存");
+            return Ok(());
+        }
+
+        let conn = lock_conn!(self.conn);
+        let now = chrono::Utc::now().timestamp();
+
+        conn.execute(
+            "INSERT OR REPLACE INTO proxy_response_cache (
+                cache_key, app_type, provider_id, model, is_streaming, status_code,
+                content_type, response_body, size_bytes, hit_count, created_at, expires_at, last_hit_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, 0, ?10, ?11, NULL)",
+            rusqlite::params![
+                cache_key,
+                app_type,
+                response.provider_id,
+                model,
+                response.is_streaming as i64,
+                response.status_code as i64,
+                response.content_type,
+                response.body,
+                size_bytes as i64,
+                now,
+                now + ttl_seconds as i64,
+            ],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "DELETE FROM proxy_response_cache WHERE expires_at <= ?1",
+            [now],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 按最近使用时间从新到旧累加，超出容量的部分全部淘汰
+        let mut stmt = conn
+            .prepare(
+                "SELECT cache_key, size_bytes FROM proxy_response_cache
+                 WHERE app_type = ?1
+                 ORDER BY COALESCE(last_hit_at, created_at) DESC, hit_count DESC, rowid DESC",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        let entries = stmt
+            .query_map([app_type], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+            })
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let mut total = 0u64;
+        for (key, size) in entries {
+            total += size;
+            if total > max_bytes {
+                conn.execute(
+                    "DELETE FROM proxy_response_cache WHERE cache_key = ?1",
+                    [&key],
+                )
+                .map_err(|e| AppError::Database(e.to_string()))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 列出缓存条目（最近创建的在前）
+    pub fn list_response_cache_entries(
+        &self,
+        app_type: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<ResponseCacheEntry>, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT cache_key, app_type, provider_id, model, is_streaming, status_code,
+                        size_bytes, hit_count, created_at, expires_at, last_hit_at
+                 FROM proxy_response_cache
+                 WHERE ?1 IS NULL OR app_type = ?1
+                 ORDER BY created_at DESC
+                 LIMIT ?2",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let entries = stmt
+            .query_map(rusqlite::params![app_type, limit as i64], |row| {
+                Ok(ResponseCacheEntry {
+                    cache_key: row.get(0)?,
+                    app_type: row.get(1)?,
+                    provider_id: row.get(2)?,
+                    model: row.get(3)?,
+                    is_streaming: row.get::<_, i64>(4)? != 0,
+                    status_code: row.get::<_, i64>(5)? as u16,
+                    size_bytes: row.get::<_, i64>(6)? as u64,
+                    hit_count: row.get::<_, i64>(7)? as u64,
+                    created_at: row.get(8)?,
+                    expires_at: row.get(9)?,
+                    last_hit_at: row.get(10)?,
+                })
+            })
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(entries)
+    }
+
+    /// 获取缓存统计
+    pub fn get_response_cache_stats(
+        &self,
+        app_type: Option<&str>,
+    ) -> Result<ResponseCacheStats, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(size_bytes), 0), COALESCE(SUM(hit_count), 0)
+             FROM proxy_response_cache
+             WHERE ?1 IS NULL OR app_type = ?1",
+            [app_type],
+            |row| {
+                Ok(ResponseCacheStats {
+                    entries: row.get::<_, i64>(0)? as u64,
+                    total_bytes: row.get::<_, i64>(1)? as u64,
+                    total_hits: row.get::<_, i64>(2)? as u64,
+                })
+            },
+        )
+        .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// 清空缓存（app_type 为 None 时清空全部），返回删除的条目数
+    pub fn clear_response_cache(&self, app_type: Option<&str>) -> Result<usize, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        conn.execute(
+            "DELETE FROM proxy_response_cache WHERE ?1 IS NULL OR app_type = ?1",
+            [app_type],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(provider_id: &str, size: usize) -> CachedResponse {
+        CachedResponse {
+            provider_id: provider_id.to_string(),
+            status_code: 200,
+            content_type: "application/json".to_string(),
+            body: vec![b'x'; size],
+            is_streaming: false,
+        }
+    }
+
+    #[test]
+    fn test_put_and_get_counts_hits() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        db.put_cached_response("k1", "claude", "m", &response("p1", 10), 60, 1024)?;
+
+        let cached = db.get_cached_response("k1")?.expect("cache hit");
+        assert_eq!(cached.provider_id, "p1");
+        assert_eq!(cached.body.len(), 10);
+        assert!(db.get_cached_response("missing")?.is_none());
+
+        let stats = db.get_response_cache_stats(Some("claude"))?;
+        assert_eq!(stats.entries, 1);
+        assert_eq!(stats.total_bytes, 10);
+        assert_eq!(stats.total_hits, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_expired_entries_are_not_returned() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        db.put_cached_response("k1", "claude", "m", &response("p1", 10), 0, 1024)?;
+        assert!(db.get_cached_response("k1")?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_size_cap_evicts_oldest_entries() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        db.put_cached_response("k1", "claude", "m", &response("p1", 40), 60, 100)?;
+        db.put_cached_response("k2", "claude", "m", &response("p1", 40), 60, 100)?;
+        // 命中后 k1 成为最近使用的条目
+        db.get_cached_response("k1")?;
+        db.put_cached_response("k3", "claude", "m", &response("p1", 40), 60, 100)?;
+
+        let keys: Vec<String> = db
+            .list_response_cache_entries(Some("claude"), 10)?
+            .into_iter()
+            .map(|e| e.cache_key)
+            .collect();
+        assert_eq!(keys.len(), 2);
+        assert!(keys.contains(&"k1".to_string()));
+        assert!(keys.contains(&"k3".to_string()));
+
+        // 超过上限的单个响应不缓存
+        db.put_cached_response("big", "claude", "m", &response("p1", 200), 60, 100)?;
+        assert!(db.get_cached_response("big")?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_clear_by_app_type() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        db.put_cached_response("k1", "claude", "m", &response("p1", 10), 60, 1024)?;
+        db.put_cached_response("k2", "codex", "m", &response("p2", 10), 60, 1024)?;
+
+        assert_eq!(db.clear_response_cache(Some("claude"))?, 1);
+        assert_eq!(db.get_response_cache_stats(None)?.entries, 1);
+        assert_eq!(db.clear_response_cache(None)?, 1);
+        assert_eq!(db.get_response_cache_stats(None)?.entries, 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+//! 模型路由规则 DAO
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::proxy::routing_rules::RoutingRule;
+
+impl Database {
+    /// 获取指定应用的路由规则（按 sort_index 排序）
+    pub fn get_routing_rules(&self, app_type: &str) -> Result<Vec<RoutingRule>, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, app_type, name, model_pattern, match_type, thinking,
+                        provider_ids, enabled, sort_index, created_at
+                 FROM proxy_routing_rules
+                 WHERE app_type = ?1
+                 ORDER BY sort_index ASC, created_at ASC",
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let rules = stmt
+            .query_map([app_type], |row| {
+                let match_type: String = row.get(4)?;
+                let provider_ids: String = row.get(6)?;
+                Ok(RoutingRule {
+                    id: row.get(0)?,
+                    app_type: row.get(1)?,
+                    name: row.get(2)?,
+                    model_pattern: row.get(3)?,
+                    match_type: match_type.parse().unwrap_or_default(),
+                    thinking: row.get::<_, Option<i64>>(5)?.map(|v| v != 0),
+                    provider_ids: serde_json::from_str(&provider_ids).unwrap_or_default(),
+                    enabled: row.get::<_, i64>(7)? != 0,
+                    sort_index: row.get(8)?,
+                    created_at: row.get(9)?,
+                })
+            })
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(rules)
+    }
+
+    /// 保存路由规则（存在则覆盖）
+    pub fn save_routing_rule(&self, rule: &RoutingRule) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let provider_ids = serde_json::to_string(&rule.provider_ids)
+            .map_err(|e| AppError::Database(format!("序列化供应商列表失败: {e}")))?;
+
+        conn.execute(
+            "INSERT OR REPLACE INTO proxy_routing_rules
+             (id, app_type, name, model_pattern, match_type, thinking,
+              provider_ids, enabled, sort_index, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            rusqlite::params![
+                rule.id,
+                rule.app_type,
+                rule.name,
+                rule.model_pattern,
+                rule.match_type.as_str(),
+                rule.thinking.map(|v| if v { 1 } else { 0 }),
+                provider_ids,
+                if rule.enabled { 1 } else { 0 },
+                rule.sort_index,
+                rule.created_at,
+            ],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 删除路由规则
+    pub fn delete_routing_rule(&self, id: &str) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+
+        conn.execute("DELETE FROM proxy_routing_rules WHERE id = ?1", [id])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+}
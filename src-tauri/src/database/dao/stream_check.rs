@@ -12,14 +12,53 @@ impl Database {
         provider_name: &str,
         app_type: &str,
         result: &StreamCheckResult,
+    ) -> Result<i64, AppError> {
+        self.insert_stream_check_log(provider_id, provider_name, app_type, result, false, None)
+    }
+
+    /// 保存后台健康探测日志（附带估算费用，用于每日探测费用上限）
+    pub fn save_health_probe_log(
+        &self,
+        provider_id: &str,
+        provider_name: &str,
+        app_type: &str,
+        result: &StreamCheckResult,
+        cost_usd: Option<&str>,
+    ) -> Result<i64, AppError> {
+        self.insert_stream_check_log(provider_id, provider_name, app_type, result, true, cost_usd)
+    }
+
+    /// 统计指定时间（秒级时间戳）之后后台健康探测的累计费用（USD）
+    pub fn get_health_probe_cost_since(&self, app_type: &str, since: i64) -> Result<f64, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        conn.query_row(
+            "SELECT COALESCE(SUM(CAST(cost_usd AS REAL)), 0)
+             FROM stream_check_logs
+             WHERE is_probe = 1 AND app_type = ?1 AND tested_at >= ?2",
+            rusqlite::params![app_type, since],
+            |row| row.get(0),
+        )
+        .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    fn insert_stream_check_log(
+        &self,
+        provider_id: &str,
+        provider_name: &str,
+        app_type: &str,
+        result: &StreamCheckResult,
+        is_probe: bool,
+        cost_usd: Option<&str>,
     ) -> Result<i64, AppError> {
         let conn = lock_conn!(self.conn);
 
         conn.execute(
             "INSERT INTO stream_check_logs 
              (provider_id, provider_name, app_type, status, success, message, 
-              response_time_ms, http_status, model_used, retry_count, tested_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+              response_time_ms, http_status, model_used, retry_count, tested_at,
+              is_probe, cost_usd)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
             rusqlite::params![
                 provider_id,
                 provider_name,
@@ -32,6 +71,8 @@ impl Database {
                 result.model_used,
                 result.retry_count as i64,
                 result.tested_at,
+                is_probe,
+                cost_usd,
             ],
         )
         .map_err(|e| AppError::Database(e.to_string()))?;
@@ -55,3 +96,38 @@ impl Database {
         self.set_setting("stream_check_config", &json)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::stream_check::HealthStatus;
+
+    fn check_result(tested_at: i64) -> StreamCheckResult {
+        StreamCheckResult {
+            status: HealthStatus::Operational,
+            success: true,
+            message: "ok".to_string(),
+            response_time_ms: Some(100),
+            http_status: Some(200),
+            model_used: "claude-haiku".to_string(),
+            tested_at,
+            retry_count: 0,
+        }
+    }
+
+    #[test]
+    fn test_health_probe_cost_only_counts_probe_logs() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        db.save_health_probe_log("p1", "P1", "claude", &check_result(100), Some("0.25"))?;
+        db.save_health_probe_log("p2", "P2", "claude", &check_result(200), Some("0.5"))?;
+        // 早于统计起点、其他应用、未计费、手动检查的日志都不计入
+        db.save_health_probe_log("p1", "P1", "claude", &check_result(50), Some("9"))?;
+        db.save_health_probe_log("p1", "P1", "codex", &check_result(200), Some("9"))?;
+        db.save_health_probe_log("p1", "P1", "claude", &check_result(200), None)?;
+        db.save_stream_check_log("p1", "P1", "claude", &check_result(200))?;
+
+        let cost = db.get_health_probe_cost_since("claude", 100)?;
+        assert!((cost - 0.75).abs() < 1e-9);
+        Ok(())
+    }
+}
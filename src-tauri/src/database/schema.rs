@@ -109,6 +109,20 @@ impl Database {
             circuit_failure_threshold INTEGER NOT NULL DEFAULT 5, circuit_success_threshold INTEGER NOT NULL DEFAULT 2,
             circuit_timeout_seconds INTEGER NOT NULL DEFAULT 60, circuit_error_rate_threshold REAL NOT NULL DEFAULT 0.5,
             circuit_min_requests INTEGER NOT NULL DEFAULT 10,
+            load_balance_strategy TEXT NOT NULL DEFAULT 'priority',
+            hedge_enabled INTEGER NOT NULL DEFAULT 0, hedge_delay_ms INTEGER NOT NULL DEFAULT 3000,
+            response_cache_enabled INTEGER NOT NULL DEFAULT 0, response_cache_ttl_seconds INTEGER NOT NULL DEFAULT 3600,
+            response_cache_max_mb INTEGER NOT NULL DEFAULT 50,
+            payload_capture_enabled INTEGER NOT NULL DEFAULT 0, payload_retention_days INTEGER NOT NULL DEFAULT 7,
+            payload_max_body_kb INTEGER NOT NULL DEFAULT 1024,
+            spend_limit_daily_usd TEXT, spend_limit_monthly_usd TEXT,
+            global_spend_limit_daily_usd TEXT, global_spend_limit_monthly_usd TEXT,
+            auth_enabled INTEGER NOT NULL DEFAULT 0, access_token TEXT, allowed_cidrs TEXT,
+            listen_mode TEXT NOT NULL DEFAULT 'tcp', tls_cert_path TEXT, tls_key_path TEXT, unix_socket_path TEXT,
+            stream_recovery_enabled INTEGER NOT NULL DEFAULT 0, stream_recovery_max_attempts INTEGER NOT NULL DEFAULT 1,
+            health_probe_enabled INTEGER NOT NULL DEFAULT 0, health_probe_interval_secs INTEGER NOT NULL DEFAULT 300,
+            health_probe_daily_limit INTEGER NOT NULL DEFAULT 200, health_probe_model TEXT,
+            health_probe_daily_cost_usd TEXT,
             created_at TEXT NOT NULL DEFAULT (datetime('now')), updated_at TEXT NOT NULL DEFAULT (datetime('now'))
         )", []).map_err(|e| AppError::Database(e.to_string()))?;
 
@@ -152,6 +166,7 @@ impl Database {
             provider_id TEXT NOT NULL, app_type TEXT NOT NULL, is_healthy INTEGER NOT NULL DEFAULT 1,
             consecutive_failures INTEGER NOT NULL DEFAULT 0, last_success_at TEXT, last_failure_at TEXT,
             last_error TEXT, updated_at TEXT NOT NULL,
+            circuit_state TEXT NOT NULL DEFAULT 'closed', circuit_opened_at INTEGER,
             PRIMARY KEY (provider_id, app_type),
             FOREIGN KEY (provider_id, app_type) REFERENCES providers(id, app_type) ON DELETE CASCADE
         )", []).map_err(|e| AppError::Database(e.to_string()))?;
@@ -166,7 +181,9 @@ impl Database {
             total_cost_usd TEXT NOT NULL DEFAULT '0', latency_ms INTEGER NOT NULL, first_token_ms INTEGER,
             duration_ms INTEGER, status_code INTEGER NOT NULL, error_message TEXT, session_id TEXT,
             provider_type TEXT, is_streaming INTEGER NOT NULL DEFAULT 0,
-            cost_multiplier TEXT NOT NULL DEFAULT '1.0', created_at INTEGER NOT NULL
+            cost_multiplier TEXT NOT NULL DEFAULT '1.0', created_at INTEGER NOT NULL,
+            cache_hit INTEGER NOT NULL DEFAULT 0, api_key TEXT,
+            stream_recoveries INTEGER NOT NULL DEFAULT 0
         )", []).map_err(|e| AppError::Database(e.to_string()))?;
 
         conn.execute("CREATE INDEX IF NOT EXISTS idx_request_logs_provider ON proxy_request_logs(provider_id, app_type)", [])
@@ -206,7 +223,8 @@ impl Database {
             id INTEGER PRIMARY KEY AUTOINCREMENT, provider_id TEXT NOT NULL, provider_name TEXT NOT NULL,
             app_type TEXT NOT NULL, status TEXT NOT NULL, success INTEGER NOT NULL, message TEXT NOT NULL,
             response_time_ms INTEGER, http_status INTEGER, model_used TEXT,
-            retry_count INTEGER DEFAULT 0, tested_at INTEGER NOT NULL
+            retry_count INTEGER DEFAULT 0, tested_at INTEGER NOT NULL,
+            is_probe INTEGER NOT NULL DEFAULT 0, cost_usd TEXT
         )", []).map_err(|e| AppError::Database(e.to_string()))?;
 
         conn.execute(
@@ -227,6 +245,122 @@ impl Database {
         )
         .map_err(|e| AppError::Database(e.to_string()))?;
 
+        // 17. Proxy Routing Rules 表（按模型路由到指定供应商链）
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS proxy_routing_rules (
+            id TEXT PRIMARY KEY, app_type TEXT NOT NULL, name TEXT NOT NULL DEFAULT '',
+            model_pattern TEXT NOT NULL, match_type TEXT NOT NULL DEFAULT 'glob', thinking INTEGER,
+            provider_ids TEXT NOT NULL DEFAULT '[]', enabled INTEGER NOT NULL DEFAULT 1,
+            sort_index INTEGER NOT NULL DEFAULT 0, created_at INTEGER NOT NULL
+        )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_routing_rules_app
+             ON proxy_routing_rules(app_type, sort_index)",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 18. Proxy Response Cache 表（temperature 为 0 的确定性请求的本地响应缓存）
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS proxy_response_cache (
+            cache_key TEXT PRIMARY KEY, app_type TEXT NOT NULL, provider_id TEXT NOT NULL,
+            model TEXT NOT NULL, is_streaming INTEGER NOT NULL DEFAULT 0, status_code INTEGER NOT NULL,
+            content_type TEXT NOT NULL DEFAULT '', response_body BLOB NOT NULL,
+            size_bytes INTEGER NOT NULL, hit_count INTEGER NOT NULL DEFAULT 0,
+            created_at INTEGER NOT NULL, expires_at INTEGER NOT NULL, last_hit_at INTEGER
+        )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_response_cache_app
+             ON proxy_response_cache(app_type, expires_at)",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 19. Proxy Request Payloads 表（可选的请求/响应载荷捕获，按 request_id 关联请求日志）
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS proxy_request_payloads (
+            request_id TEXT PRIMARY KEY, session_id TEXT, app_type TEXT NOT NULL,
+            provider_id TEXT NOT NULL DEFAULT '', model TEXT NOT NULL DEFAULT '',
+            endpoint TEXT NOT NULL DEFAULT '', request_headers TEXT NOT NULL DEFAULT '{}',
+            request_body TEXT NOT NULL DEFAULT '', status_code INTEGER NOT NULL DEFAULT 0,
+            response_headers TEXT, response_body TEXT, is_streaming INTEGER NOT NULL DEFAULT 0,
+            truncated INTEGER NOT NULL DEFAULT 0, created_at INTEGER NOT NULL,
+            upstream_headers TEXT, header_rewrites TEXT
+        )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_request_payloads_session
+             ON proxy_request_payloads(session_id, created_at)",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_request_payloads_app_created
+             ON proxy_request_payloads(app_type, created_at)",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 20. Provider Incompatibilities 表（代理自动识别的上游不支持的请求字段）
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS provider_incompatibilities (
+            app_type TEXT NOT NULL, provider_id TEXT NOT NULL, field TEXT NOT NULL,
+            error_message TEXT, hit_count INTEGER NOT NULL DEFAULT 1,
+            learned_at INTEGER NOT NULL, last_seen_at INTEGER NOT NULL,
+            PRIMARY KEY (app_type, provider_id, field)
+        )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 21. Circuit Breaker Transitions 表（熔断器状态转换历史）
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS circuit_breaker_transitions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT, app_type TEXT NOT NULL, provider_id TEXT NOT NULL,
+            from_state TEXT NOT NULL, to_state TEXT NOT NULL, reason TEXT,
+            created_at INTEGER NOT NULL
+        )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_circuit_transitions_provider
+             ON circuit_breaker_transitions(app_type, provider_id, created_at)",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 22. Provider Schedules 表（按 cron 规则定时切换供应商 / 重排故障转移队列）
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS provider_schedules (
+            id TEXT PRIMARY KEY, app_type TEXT NOT NULL, name TEXT NOT NULL DEFAULT '',
+            cron TEXT NOT NULL, action TEXT NOT NULL DEFAULT 'switch',
+            provider_ids TEXT NOT NULL DEFAULT '[]', enabled INTEGER NOT NULL DEFAULT 1,
+            sort_index INTEGER NOT NULL DEFAULT 0, created_at INTEGER NOT NULL
+        )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_provider_schedules_app
+             ON provider_schedules(app_type, sort_index)",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
         // 尝试添加 live_takeover_active 列到 proxy_config 表
         let _ = conn.execute(
             "ALTER TABLE proxy_config ADD COLUMN live_takeover_active INTEGER NOT NULL DEFAULT 0",
@@ -305,6 +439,71 @@ impl Database {
                         Self::migrate_v1_to_v2(conn)?;
                         Self::set_user_version(conn, 2)?;
                     }
+                    2 => {
+                        log::info!("迁移数据库从 v2 到 v3（添加负载均衡策略配置）");
+                        Self::migrate_v2_to_v3(conn)?;
+                        Self::set_user_version(conn, 3)?;
+                    }
+                    3 => {
+                        log::info!("迁移数据库从 v3 到 v4（添加对冲请求配置）");
+                        Self::migrate_v3_to_v4(conn)?;
+                        Self::set_user_version(conn, 4)?;
+                    }
+                    4 => {
+                        log::info!("迁移数据库从 v4 到 v5（添加本地响应缓存配置）");
+                        Self::migrate_v4_to_v5(conn)?;
+                        Self::set_user_version(conn, 5)?;
+                    }
+                    5 => {
+                        log::info!("迁移数据库从 v5 到 v6（添加载荷捕获配置）");
+                        Self::migrate_v5_to_v6(conn)?;
+                        Self::set_user_version(conn, 6)?;
+                    }
+                    6 => {
+                        log::info!("迁移数据库从 v6 到 v7（添加消费上限配置）");
+                        Self::migrate_v6_to_v7(conn)?;
+                        Self::set_user_version(conn, 7)?;
+                    }
+                    7 => {
+                        log::info!("迁移数据库从 v7 到 v8（添加代理入站认证配置）");
+                        Self::migrate_v7_to_v8(conn)?;
+                        Self::set_user_version(conn, 8)?;
+                    }
+                    8 => {
+                        log::info!("迁移数据库从 v8 到 v9（添加代理监听方式配置）");
+                        Self::migrate_v8_to_v9(conn)?;
+                        Self::set_user_version(conn, 9)?;
+                    }
+                    9 => {
+                        log::info!("迁移数据库从 v9 到 v10（记录上游请求头与改写规则）");
+                        Self::migrate_v9_to_v10(conn)?;
+                        Self::set_user_version(conn, 10)?;
+                    }
+                    10 => {
+                        log::info!("迁移数据库从 v10 到 v11（记录请求使用的 API Key）");
+                        Self::migrate_v10_to_v11(conn)?;
+                        Self::set_user_version(conn, 11)?;
+                    }
+                    11 => {
+                        log::info!("迁移数据库从 v11 到 v12（添加流式中断恢复配置）");
+                        Self::migrate_v11_to_v12(conn)?;
+                        Self::set_user_version(conn, 12)?;
+                    }
+                    12 => {
+                        log::info!("迁移数据库从 v12 到 v13（持久化熔断器状态）");
+                        Self::migrate_v12_to_v13(conn)?;
+                        Self::set_user_version(conn, 13)?;
+                    }
+                    13 => {
+                        log::info!("迁移数据库从 v13 到 v14（添加后台健康探测配置）");
+                        Self::migrate_v13_to_v14(conn)?;
+                        Self::set_user_version(conn, 14)?;
+                    }
+                    14 => {
+                        log::info!("迁移数据库从 v14 到 v15（添加健康探测费用上限）");
+                        Self::migrate_v14_to_v15(conn)?;
+                        Self::set_user_version(conn, 15)?;
+                    }
                     _ => {
                         return Err(AppError::Database(format!(
                             "未知的数据库版本 {version}，无法迁移到 {SCHEMA_VERSION}"
@@ -500,6 +699,266 @@ impl Database {
         Ok(())
     }
 
+    /// v2 -> v3 迁移：添加负载均衡策略列
+    fn migrate_v2_to_v3(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_config")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "load_balance_strategy",
+                "TEXT NOT NULL DEFAULT 'priority'",
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// v3 -> v4 迁移：添加对冲请求配置列
+    fn migrate_v3_to_v4(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_config")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "hedge_enabled",
+                "INTEGER NOT NULL DEFAULT 0",
+            )?;
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "hedge_delay_ms",
+                "INTEGER NOT NULL DEFAULT 3000",
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// v4 -> v5 迁移：添加本地响应缓存配置列和请求日志的缓存命中标记
+    fn migrate_v4_to_v5(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_config")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "response_cache_enabled",
+                "INTEGER NOT NULL DEFAULT 0",
+            )?;
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "response_cache_ttl_seconds",
+                "INTEGER NOT NULL DEFAULT 3600",
+            )?;
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "response_cache_max_mb",
+                "INTEGER NOT NULL DEFAULT 50",
+            )?;
+        }
+        if Self::table_exists(conn, "proxy_request_logs")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_request_logs",
+                "cache_hit",
+                "INTEGER NOT NULL DEFAULT 0",
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// v5 -> v6 迁移：添加请求/响应载荷捕获配置列
+    fn migrate_v5_to_v6(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_config")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "payload_capture_enabled",
+                "INTEGER NOT NULL DEFAULT 0",
+            )?;
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "payload_retention_days",
+                "INTEGER NOT NULL DEFAULT 7",
+            )?;
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "payload_max_body_kb",
+                "INTEGER NOT NULL DEFAULT 1024",
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// v6 -> v7 迁移：添加应用级与全局消费上限（USD，NULL 表示不限）
+    fn migrate_v6_to_v7(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_config")? {
+            for column in [
+                "spend_limit_daily_usd",
+                "spend_limit_monthly_usd",
+                "global_spend_limit_daily_usd",
+                "global_spend_limit_monthly_usd",
+            ] {
+                Self::add_column_if_missing(conn, "proxy_config", column, "TEXT")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// v7 -> v8 迁移：添加代理入站认证（本地访问令牌与来源 CIDR 允许列表）
+    fn migrate_v7_to_v8(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_config")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "auth_enabled",
+                "INTEGER NOT NULL DEFAULT 0",
+            )?;
+            Self::add_column_if_missing(conn, "proxy_config", "access_token", "TEXT")?;
+            Self::add_column_if_missing(conn, "proxy_config", "allowed_cidrs", "TEXT")?;
+        }
+
+        Ok(())
+    }
+
+    /// v8 -> v9 迁移：添加代理监听方式（TCP / TLS / Unix 域套接字）及证书、套接字路径
+    fn migrate_v8_to_v9(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_config")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "listen_mode",
+                "TEXT NOT NULL DEFAULT 'tcp'",
+            )?;
+            Self::add_column_if_missing(conn, "proxy_config", "tls_cert_path", "TEXT")?;
+            Self::add_column_if_missing(conn, "proxy_config", "tls_key_path", "TEXT")?;
+            Self::add_column_if_missing(conn, "proxy_config", "unix_socket_path", "TEXT")?;
+        }
+
+        Ok(())
+    }
+
+    /// v9 -> v10 迁移：请求载荷记录实际发往上游的请求头与生效的请求头改写规则
+    fn migrate_v9_to_v10(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_request_payloads")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_request_payloads",
+                "upstream_headers",
+                "TEXT",
+            )?;
+            Self::add_column_if_missing(conn, "proxy_request_payloads", "header_rewrites", "TEXT")?;
+        }
+
+        Ok(())
+    }
+
+    /// v10 -> v11 迁移：请求日志记录使用的 API Key（已遮蔽，用于 Key 池用量归属）
+    fn migrate_v10_to_v11(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_request_logs")? {
+            Self::add_column_if_missing(conn, "proxy_request_logs", "api_key", "TEXT")?;
+        }
+
+        Ok(())
+    }
+
+    /// v11 -> v12 迁移：添加流式中断恢复配置列和请求日志的续写次数
+    fn migrate_v11_to_v12(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_config")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "stream_recovery_enabled",
+                "INTEGER NOT NULL DEFAULT 0",
+            )?;
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "stream_recovery_max_attempts",
+                "INTEGER NOT NULL DEFAULT 1",
+            )?;
+        }
+        if Self::table_exists(conn, "proxy_request_logs")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_request_logs",
+                "stream_recoveries",
+                "INTEGER NOT NULL DEFAULT 0",
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// v12 -> v13 迁移：provider_health 记录熔断器状态，重启后恢复
+    fn migrate_v12_to_v13(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "provider_health")? {
+            Self::add_column_if_missing(
+                conn,
+                "provider_health",
+                "circuit_state",
+                "TEXT NOT NULL DEFAULT 'closed'",
+            )?;
+            Self::add_column_if_missing(conn, "provider_health", "circuit_opened_at", "INTEGER")?;
+        }
+
+        Ok(())
+    }
+
+    /// v13 -> v14 迁移：添加后台健康探测配置列
+    fn migrate_v13_to_v14(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_config")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "health_probe_enabled",
+                "INTEGER NOT NULL DEFAULT 0",
+            )?;
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "health_probe_interval_secs",
+                "INTEGER NOT NULL DEFAULT 300",
+            )?;
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "health_probe_daily_limit",
+                "INTEGER NOT NULL DEFAULT 200",
+            )?;
+            Self::add_column_if_missing(conn, "proxy_config", "health_probe_model", "TEXT")?;
+        }
+
+        Ok(())
+    }
+
+    /// v14 -> v15 迁移：健康探测每日费用上限，探测日志记录估算费用
+    fn migrate_v14_to_v15(conn: &Connection) -> Result<(), AppError> {
+        if Self::table_exists(conn, "proxy_config")? {
+            Self::add_column_if_missing(
+                conn,
+                "proxy_config",
+                "health_probe_daily_cost_usd",
+                "TEXT",
+            )?;
+        }
+        if Self::table_exists(conn, "stream_check_logs")? {
+            Self::add_column_if_missing(
+                conn,
+                "stream_check_logs",
+                "is_probe",
+                "INTEGER NOT NULL DEFAULT 0",
+            )?;
+            Self::add_column_if_missing(conn, "stream_check_logs", "cost_usd", "TEXT")?;
+        }
+
+        Ok(())
+    }
+
     /// 将 proxy_config 迁移为三行结构（每应用独立配置）
     fn migrate_proxy_config_to_per_app(conn: &Connection) -> Result<(), AppError> {
         // 检查是否已经是新表结构（幂等性）
@@ -332,3 +332,475 @@ fn model_pricing_is_seeded_on_init() {
         gemini_count
     );
 }
+
+#[test]
+fn migration_v2_to_v3_adds_load_balance_strategy() {
+    let conn = Connection::open_in_memory().expect("open memory db");
+
+    // 模拟 v2 的 proxy_config 表（缺少 load_balance_strategy 列）
+    conn.execute_batch(
+        "CREATE TABLE proxy_config (
+            app_type TEXT PRIMARY KEY,
+            auto_failover_enabled INTEGER NOT NULL DEFAULT 0
+        );
+        INSERT INTO proxy_config (app_type) VALUES ('claude');",
+    )
+    .expect("seed v2 proxy_config");
+    Database::set_user_version(&conn, 2).expect("set v2");
+
+    Database::apply_schema_migrations_on_conn(&conn).expect("apply migrations");
+
+    assert!(Database::has_column(&conn, "proxy_config", "load_balance_strategy").unwrap());
+    let strategy: String = conn
+        .query_row(
+            "SELECT load_balance_strategy FROM proxy_config WHERE app_type = 'claude'",
+            [],
+            |row| row.get(0),
+        )
+        .expect("read strategy");
+    assert_eq!(strategy, "priority");
+    assert_eq!(
+        Database::get_user_version(&conn).expect("version after migration"),
+        SCHEMA_VERSION
+    );
+}
+
+#[test]
+fn migration_v3_to_v4_adds_hedge_columns() {
+    let conn = Connection::open_in_memory().expect("open memory db");
+
+    // 模拟 v3 的 proxy_config 表（缺少对冲请求配置列）
+    conn.execute_batch(
+        "CREATE TABLE proxy_config (
+            app_type TEXT PRIMARY KEY,
+            load_balance_strategy TEXT NOT NULL DEFAULT 'priority'
+        );
+        INSERT INTO proxy_config (app_type) VALUES ('codex');",
+    )
+    .expect("seed v3 proxy_config");
+    Database::set_user_version(&conn, 3).expect("set v3");
+
+    Database::apply_schema_migrations_on_conn(&conn).expect("apply migrations");
+
+    let (enabled, delay): (i64, i64) = conn
+        .query_row(
+            "SELECT hedge_enabled, hedge_delay_ms FROM proxy_config WHERE app_type = 'codex'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .expect("read hedge config");
+    assert_eq!(enabled, 0);
+    assert_eq!(delay, 3000);
+    assert_eq!(
+        Database::get_user_version(&conn).expect("version after migration"),
+        SCHEMA_VERSION
+    );
+}
+
+#[test]
+fn migration_v4_to_v5_adds_response_cache_columns() {
+    let conn = Connection::open_in_memory().expect("open memory db");
+
+    // 模拟 v4 的 proxy_config / proxy_request_logs 表（缺少响应缓存相关列）
+    conn.execute_batch(
+        "CREATE TABLE proxy_config (
+            app_type TEXT PRIMARY KEY,
+            hedge_enabled INTEGER NOT NULL DEFAULT 0
+        );
+        INSERT INTO proxy_config (app_type) VALUES ('claude');
+        CREATE TABLE proxy_request_logs (
+            request_id TEXT PRIMARY KEY,
+            status_code INTEGER NOT NULL
+        );
+        INSERT INTO proxy_request_logs (request_id, status_code) VALUES ('r1', 200);",
+    )
+    .expect("seed v4 tables");
+    Database::set_user_version(&conn, 4).expect("set v4");
+
+    Database::apply_schema_migrations_on_conn(&conn).expect("apply migrations");
+
+    let (enabled, ttl, max_mb): (i64, i64, i64) = conn
+        .query_row(
+            "SELECT response_cache_enabled, response_cache_ttl_seconds, response_cache_max_mb
+             FROM proxy_config WHERE app_type = 'claude'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .expect("read response cache config");
+    assert_eq!((enabled, ttl, max_mb), (0, 3600, 50));
+
+    let cache_hit: i64 = conn
+        .query_row(
+            "SELECT cache_hit FROM proxy_request_logs WHERE request_id = 'r1'",
+            [],
+            |row| row.get(0),
+        )
+        .expect("read cache_hit");
+    assert_eq!(cache_hit, 0);
+    assert_eq!(
+        Database::get_user_version(&conn).expect("version after migration"),
+        SCHEMA_VERSION
+    );
+}
+
+#[test]
+fn migration_v5_to_v6_adds_payload_capture_columns() {
+    let conn = Connection::open_in_memory().expect("open memory db");
+
+    // 模拟 v5 的 proxy_config 表（缺少载荷捕获相关列）
+    conn.execute_batch(
+        "CREATE TABLE proxy_config (
+            app_type TEXT PRIMARY KEY,
+            response_cache_enabled INTEGER NOT NULL DEFAULT 0
+        );
+        INSERT INTO proxy_config (app_type) VALUES ('codex');",
+    )
+    .expect("seed v5 table");
+    Database::set_user_version(&conn, 5).expect("set v5");
+
+    Database::apply_schema_migrations_on_conn(&conn).expect("apply migrations");
+
+    let (enabled, retention, max_kb): (i64, i64, i64) = conn
+        .query_row(
+            "SELECT payload_capture_enabled, payload_retention_days, payload_max_body_kb
+             FROM proxy_config WHERE app_type = 'codex'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .expect("read payload capture config");
+    assert_eq!((enabled, retention, max_kb), (0, 7, 1024));
+    assert_eq!(
+        Database::get_user_version(&conn).expect("version after migration"),
+        SCHEMA_VERSION
+    );
+}
+
+#[test]
+fn migration_v6_to_v7_adds_spend_limit_columns() {
+    let conn = Connection::open_in_memory().expect("open memory db");
+
+    // 模拟 v6 的 proxy_config 表（缺少消费上限列）
+    conn.execute_batch(
+        "CREATE TABLE proxy_config (
+            app_type TEXT PRIMARY KEY,
+            payload_capture_enabled INTEGER NOT NULL DEFAULT 0
+        );
+        INSERT INTO proxy_config (app_type) VALUES ('claude');",
+    )
+    .expect("seed v6 table");
+    Database::set_user_version(&conn, 6).expect("set v6");
+
+    Database::apply_schema_migrations_on_conn(&conn).expect("apply migrations");
+
+    let (daily, global_monthly): (Option<String>, Option<String>) = conn
+        .query_row(
+            "SELECT spend_limit_daily_usd, global_spend_limit_monthly_usd
+             FROM proxy_config WHERE app_type = 'claude'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .expect("read spend limit config");
+    assert_eq!((daily, global_monthly), (None, None));
+    assert_eq!(
+        Database::get_user_version(&conn).expect("version after migration"),
+        SCHEMA_VERSION
+    );
+}
+
+#[test]
+fn migration_v7_to_v8_adds_inbound_auth_columns() {
+    let conn = Connection::open_in_memory().expect("open memory db");
+
+    // 模拟 v7 的 proxy_config 表（缺少入站认证列）
+    conn.execute_batch(
+        "CREATE TABLE proxy_config (
+            app_type TEXT PRIMARY KEY,
+            global_spend_limit_daily_usd TEXT
+        );
+        INSERT INTO proxy_config (app_type) VALUES ('claude');",
+    )
+    .expect("seed v7 table");
+    Database::set_user_version(&conn, 7).expect("set v7");
+
+    Database::apply_schema_migrations_on_conn(&conn).expect("apply migrations");
+
+    let (auth_enabled, token, cidrs): (i64, Option<String>, Option<String>) = conn
+        .query_row(
+            "SELECT auth_enabled, access_token, allowed_cidrs
+             FROM proxy_config WHERE app_type = 'claude'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .expect("read inbound auth config");
+    assert_eq!((auth_enabled, token, cidrs), (0, None, None));
+    assert_eq!(
+        Database::get_user_version(&conn).expect("version after migration"),
+        SCHEMA_VERSION
+    );
+}
+
+#[test]
+fn migration_v8_to_v9_adds_listen_mode_columns() {
+    let conn = Connection::open_in_memory().expect("open memory db");
+
+    // 模拟 v8 的 proxy_config 表（缺少监听方式列）
+    conn.execute_batch(
+        "CREATE TABLE proxy_config (
+            app_type TEXT PRIMARY KEY,
+            auth_enabled INTEGER NOT NULL DEFAULT 0
+        );
+        INSERT INTO proxy_config (app_type) VALUES ('claude');",
+    )
+    .expect("seed v8 table");
+    Database::set_user_version(&conn, 8).expect("set v8");
+
+    Database::apply_schema_migrations_on_conn(&conn).expect("apply migrations");
+
+    let (mode, cert, key, socket): (String, Option<String>, Option<String>, Option<String>) = conn
+        .query_row(
+            "SELECT listen_mode, tls_cert_path, tls_key_path, unix_socket_path
+             FROM proxy_config WHERE app_type = 'claude'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .expect("read listen mode config");
+    assert_eq!(mode, "tcp");
+    assert_eq!((cert, key, socket), (None, None, None));
+    assert_eq!(
+        Database::get_user_version(&conn).expect("version after migration"),
+        SCHEMA_VERSION
+    );
+}
+
+#[test]
+fn migration_v9_to_v10_adds_upstream_header_columns() {
+    let conn = Connection::open_in_memory().expect("open memory db");
+
+    // 模拟 v9 的 proxy_request_payloads 表（缺少上游请求头列）
+    conn.execute_batch(
+        "CREATE TABLE proxy_request_payloads (
+            request_id TEXT PRIMARY KEY,
+            app_type TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        );
+        INSERT INTO proxy_request_payloads (request_id, app_type, created_at)
+        VALUES ('r1', 'claude', 0);",
+    )
+    .expect("seed v9 table");
+    Database::set_user_version(&conn, 9).expect("set v9");
+
+    Database::apply_schema_migrations_on_conn(&conn).expect("apply migrations");
+
+    let (headers, rewrites): (Option<String>, Option<String>) = conn
+        .query_row(
+            "SELECT upstream_headers, header_rewrites
+             FROM proxy_request_payloads WHERE request_id = 'r1'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .expect("read upstream header columns");
+    assert_eq!((headers, rewrites), (None, None));
+    assert_eq!(
+        Database::get_user_version(&conn).expect("version after migration"),
+        SCHEMA_VERSION
+    );
+}
+
+#[test]
+fn migration_v10_to_v11_adds_request_log_api_key_column() {
+    let conn = Connection::open_in_memory().expect("open memory db");
+
+    // 模拟 v10 的 proxy_request_logs 表（缺少 api_key 列）
+    conn.execute_batch(
+        "CREATE TABLE proxy_request_logs (
+            request_id TEXT PRIMARY KEY,
+            provider_id TEXT NOT NULL,
+            app_type TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        );
+        INSERT INTO proxy_request_logs (request_id, provider_id, app_type, created_at)
+        VALUES ('r1', 'p1', 'claude', 0);",
+    )
+    .expect("seed v10 table");
+    Database::set_user_version(&conn, 10).expect("set v10");
+
+    Database::apply_schema_migrations_on_conn(&conn).expect("apply migrations");
+
+    let api_key: Option<String> = conn
+        .query_row(
+            "SELECT api_key FROM proxy_request_logs WHERE request_id = 'r1'",
+            [],
+            |row| row.get(0),
+        )
+        .expect("read api_key column");
+    assert_eq!(api_key, None);
+    assert_eq!(
+        Database::get_user_version(&conn).expect("version after migration"),
+        SCHEMA_VERSION
+    );
+}
+
+#[test]
+fn migration_v11_to_v12_adds_stream_recovery_columns() {
+    let conn = Connection::open_in_memory().expect("open memory db");
+
+    // 模拟 v11 的 proxy_config 与 proxy_request_logs 表（缺少流式中断恢复相关列）
+    conn.execute_batch(
+        "CREATE TABLE proxy_config (
+            app_type TEXT PRIMARY KEY,
+            payload_capture_enabled INTEGER NOT NULL DEFAULT 0
+        );
+        INSERT INTO proxy_config (app_type) VALUES ('claude');
+        CREATE TABLE proxy_request_logs (
+            request_id TEXT PRIMARY KEY,
+            provider_id TEXT NOT NULL,
+            app_type TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            api_key TEXT
+        );
+        INSERT INTO proxy_request_logs (request_id, provider_id, app_type, created_at)
+        VALUES ('r1', 'p1', 'claude', 0);",
+    )
+    .expect("seed v11 tables");
+    Database::set_user_version(&conn, 11).expect("set v11");
+
+    Database::apply_schema_migrations_on_conn(&conn).expect("apply migrations");
+
+    let (enabled, max_attempts): (i64, i64) = conn
+        .query_row(
+            "SELECT stream_recovery_enabled, stream_recovery_max_attempts
+             FROM proxy_config WHERE app_type = 'claude'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .expect("read stream recovery config");
+    assert_eq!((enabled, max_attempts), (0, 1));
+
+    let recoveries: i64 = conn
+        .query_row(
+            "SELECT stream_recoveries FROM proxy_request_logs WHERE request_id = 'r1'",
+            [],
+            |row| row.get(0),
+        )
+        .expect("read stream_recoveries column");
+    assert_eq!(recoveries, 0);
+    assert_eq!(
+        Database::get_user_version(&conn).expect("version after migration"),
+        SCHEMA_VERSION
+    );
+}
+
+#[test]
+fn migration_v12_to_v13_adds_circuit_state_columns() {
+    let conn = Connection::open_in_memory().expect("open memory db");
+
+    // 模拟 v12 的 provider_health 表（缺少熔断器状态列）
+    conn.execute_batch(
+        "CREATE TABLE provider_health (
+            provider_id TEXT NOT NULL,
+            app_type TEXT NOT NULL,
+            is_healthy INTEGER NOT NULL DEFAULT 1,
+            consecutive_failures INTEGER NOT NULL DEFAULT 0,
+            updated_at TEXT NOT NULL,
+            PRIMARY KEY (provider_id, app_type)
+        );
+        INSERT INTO provider_health (provider_id, app_type, updated_at)
+        VALUES ('p1', 'claude', '2024-01-01T00:00:00Z');",
+    )
+    .expect("seed v12 provider_health");
+    Database::set_user_version(&conn, 12).expect("set v12");
+
+    Database::apply_schema_migrations_on_conn(&conn).expect("apply migrations");
+
+    let (state, opened_at): (String, Option<i64>) = conn
+        .query_row(
+            "SELECT circuit_state, circuit_opened_at FROM provider_health
+             WHERE provider_id = 'p1' AND app_type = 'claude'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .expect("read circuit state columns");
+    assert_eq!((state.as_str(), opened_at), ("closed", None));
+    assert_eq!(
+        Database::get_user_version(&conn).expect("version after migration"),
+        SCHEMA_VERSION
+    );
+}
+
+#[test]
+fn migration_v13_to_v14_adds_health_probe_columns() {
+    let conn = Connection::open_in_memory().expect("open memory db");
+
+    // 模拟 v13 的 proxy_config 表（缺少健康探测列）
+    conn.execute_batch(
+        "CREATE TABLE proxy_config (
+            app_type TEXT PRIMARY KEY,
+            stream_recovery_enabled INTEGER NOT NULL DEFAULT 0
+        );
+        INSERT INTO proxy_config (app_type) VALUES ('claude');",
+    )
+    .expect("seed v13 proxy_config");
+    Database::set_user_version(&conn, 13).expect("set v13");
+
+    Database::apply_schema_migrations_on_conn(&conn).expect("apply migrations");
+
+    let (enabled, interval, limit, model): (i64, i64, i64, Option<String>) = conn
+        .query_row(
+            "SELECT health_probe_enabled, health_probe_interval_secs,
+                    health_probe_daily_limit, health_probe_model
+             FROM proxy_config WHERE app_type = 'claude'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .expect("read health probe config");
+    assert_eq!((enabled, interval, limit, model), (0, 300, 200, None));
+    assert_eq!(
+        Database::get_user_version(&conn).expect("version after migration"),
+        SCHEMA_VERSION
+    );
+}
+
+#[test]
+fn migration_v14_to_v15_adds_health_probe_cost_columns() {
+    let conn = Connection::open_in_memory().expect("open memory db");
+
+    // 模拟 v14 的 proxy_config / stream_check_logs 表（缺少探测费用列）
+    conn.execute_batch(
+        "CREATE TABLE proxy_config (
+            app_type TEXT PRIMARY KEY,
+            health_probe_daily_limit INTEGER NOT NULL DEFAULT 200
+        );
+        INSERT INTO proxy_config (app_type) VALUES ('claude');
+        CREATE TABLE stream_check_logs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT, provider_id TEXT NOT NULL,
+            tested_at INTEGER NOT NULL
+        );
+        INSERT INTO stream_check_logs (provider_id, tested_at) VALUES ('p1', 1);",
+    )
+    .expect("seed v14 tables");
+    Database::set_user_version(&conn, 14).expect("set v14");
+
+    Database::apply_schema_migrations_on_conn(&conn).expect("apply migrations");
+
+    let cost_cap: Option<String> = conn
+        .query_row(
+            "SELECT health_probe_daily_cost_usd FROM proxy_config WHERE app_type = 'claude'",
+            [],
+            |row| row.get(0),
+        )
+        .expect("read health probe cost cap");
+    assert_eq!(cost_cap, None);
+
+    let (is_probe, cost): (i64, Option<String>) = conn
+        .query_row(
+            "SELECT is_probe, cost_usd FROM stream_check_logs WHERE provider_id = 'p1'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .expect("read stream check log");
+    assert_eq!((is_probe, cost), (0, None));
+    assert_eq!(
+        Database::get_user_version(&conn).expect("version after migration"),
+        SCHEMA_VERSION
+    );
+}
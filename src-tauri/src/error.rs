@@ -52,6 +52,8 @@ pub enum AppError {
     },
     #[error("数据库错误: {0}")]
     Database(String),
+    #[error("消费已达上限: {0}")]
+    SpendLimitExceeded(String),
 }
 
 impl AppError {
@@ -619,6 +619,7 @@ pub fn run() {
             commands::set_app_config_dir_override,
             // provider sort order management
             commands::update_providers_sort_order,
+            commands::preview_body_patches,
             // theirs: config import/export and dialogs
             commands::export_config_to_file,
             commands::import_config_from_file,
@@ -659,6 +660,8 @@ pub fn run() {
             // Global & Per-App Config
             commands::get_global_proxy_config,
             commands::update_global_proxy_config,
+            commands::regenerate_proxy_access_token,
+            commands::get_proxy_tls_ca_path,
             commands::get_proxy_config_for_app,
             commands::update_proxy_config_for_app,
             commands::is_proxy_running,
@@ -670,6 +673,9 @@ pub fn run() {
             commands::get_circuit_breaker_config,
             commands::update_circuit_breaker_config,
             commands::get_circuit_breaker_stats,
+            commands::get_circuit_breaker_transitions,
+            commands::get_key_pool_status,
+            commands::reset_key_pool,
             // Failover queue management
             commands::get_failover_queue,
             commands::get_available_providers_for_failover,
@@ -677,13 +683,32 @@ pub fn run() {
             commands::remove_from_failover_queue,
             commands::get_auto_failover_enabled,
             commands::set_auto_failover_enabled,
+            // Model routing rules
+            commands::get_routing_rules,
+            commands::save_routing_rule,
+            commands::delete_routing_rule,
+            // Provider schedules
+            commands::get_provider_schedules,
+            commands::save_provider_schedule,
+            commands::delete_provider_schedule,
+            commands::preview_provider_schedule,
+            // Response cache
+            commands::get_response_cache_stats,
+            commands::list_response_cache_entries,
+            commands::clear_response_cache,
+            commands::list_provider_incompatibilities,
+            commands::reset_provider_incompatibilities,
             // Usage statistics
             commands::get_usage_summary,
             commands::get_usage_trends,
             commands::get_provider_stats,
+            commands::get_api_key_stats,
             commands::get_model_stats,
             commands::get_request_logs,
             commands::get_request_detail,
+            commands::get_request_payload,
+            commands::list_session_turns,
+            commands::replay_request,
             commands::get_model_pricing,
             commands::update_model_pricing,
             commands::delete_model_pricing,
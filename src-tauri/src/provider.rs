@@ -65,6 +65,39 @@ impl Provider {
             in_failover_queue: false,
         }
     }
+
+    /// 出站网络设置
+    pub fn outbound(&self) -> Option<&OutboundConfig> {
+        self.meta.as_ref().and_then(|meta| meta.outbound.as_ref())
+    }
+
+    /// 请求头改写规则
+    pub fn header_rules(&self) -> &[HeaderRule] {
+        self.meta
+            .as_ref()
+            .map(|meta| meta.header_rules.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// API Key 池（未配置或没有启用的 Key 时返回 None）
+    pub fn key_pool(&self) -> Option<&KeyPool> {
+        self.meta
+            .as_ref()
+            .and_then(|meta| meta.key_pool.as_ref())
+            .filter(|pool| {
+                pool.keys
+                    .iter()
+                    .any(|k| k.enabled && !k.key.trim().is_empty())
+            })
+    }
+
+    /// 请求体改写规则
+    pub fn body_patches(&self) -> &[BodyPatch] {
+        self.meta
+            .as_ref()
+            .map(|meta| meta.body_patches.as_slice())
+            .unwrap_or_default()
+    }
 }
 
 /// 供应商管理器
@@ -165,6 +198,209 @@ pub struct ProviderMeta {
     /// 每月消费限额（USD）
     #[serde(rename = "limitMonthlyUsd", skip_serializing_if = "Option::is_none")]
     pub limit_monthly_usd: Option<String>,
+    /// 消费限额执行方式（缺省为 hard：超出后代理跳过该供应商；soft：仅告警）
+    #[serde(rename = "limitMode", skip_serializing_if = "Option::is_none")]
+    pub limit_mode: Option<SpendLimitMode>,
+    /// 负载均衡权重（weighted_random 策略使用，缺省为 1，0 表示仅作兜底）
+    #[serde(rename = "loadBalanceWeight", skip_serializing_if = "Option::is_none")]
+    pub load_balance_weight: Option<u32>,
+    /// 每分钟请求数上限（代理限流，0 或缺省表示不限）
+    #[serde(rename = "rateLimitRpm", skip_serializing_if = "Option::is_none")]
+    pub rate_limit_rpm: Option<u32>,
+    /// 每分钟输入 Token 数上限（按请求体估算）
+    #[serde(rename = "rateLimitTpm", skip_serializing_if = "Option::is_none")]
+    pub rate_limit_tpm: Option<u32>,
+    /// 最大并发请求数（流式请求在流结束时释放）
+    #[serde(rename = "maxConcurrency", skip_serializing_if = "Option::is_none")]
+    pub max_concurrency: Option<u32>,
+    /// 限流等待队列长度（缺省为 32）
+    #[serde(rename = "rateLimitQueueSize", skip_serializing_if = "Option::is_none")]
+    pub rate_limit_queue_size: Option<u32>,
+    /// 限流最长等待时间（毫秒，缺省为 10000），超过后溢出到下一个故障转移供应商
+    #[serde(rename = "rateLimitMaxWaitMs", skip_serializing_if = "Option::is_none")]
+    pub rate_limit_max_wait_ms: Option<u64>,
+    /// 出站网络设置（上游代理、自定义根证书等），缺省时沿用系统代理
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outbound: Option<OutboundConfig>,
+    /// 请求头改写规则（在适配器设置认证头之后按顺序执行）
+    #[serde(rename = "headerRules", default, skip_serializing_if = "Vec::is_empty")]
+    pub header_rules: Vec<HeaderRule>,
+    /// 请求体改写规则（转发前对最终请求体按顺序执行）
+    #[serde(rename = "bodyPatches", default, skip_serializing_if = "Vec::is_empty")]
+    pub body_patches: Vec<BodyPatch>,
+    /// API Key 池（与供应商自身的 API Key 一起轮换使用）
+    #[serde(rename = "keyPool", skip_serializing_if = "Option::is_none")]
+    pub key_pool: Option<KeyPool>,
+    /// 后台健康探测使用的模型（缺省按供应商自身配置的模型解析）
+    #[serde(rename = "healthProbeModel", skip_serializing_if = "Option::is_none")]
+    pub health_probe_model: Option<String>,
+}
+
+/// API Key 池
+///
+/// 供应商自身配置的 API Key 之外的其他 Key；代理转发时按策略从中选择，
+/// 认证方式沿用供应商自身 Key 的认证方式。
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyPool {
+    #[serde(default)]
+    pub strategy: KeyPoolStrategy,
+    #[serde(default)]
+    pub keys: Vec<PoolKey>,
+}
+
+/// Key 选择策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyPoolStrategy {
+    /// 轮询
+    #[default]
+    RoundRobin,
+    /// 选择使用次数最少的 Key
+    LeastUsed,
+    /// 同一会话固定使用同一个 Key（利于上游提示缓存命中）
+    Sticky,
+}
+
+/// Key 池中的单个 Key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolKey {
+    pub key: String,
+    /// 备注（如使用者）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// 是否启用（缺省启用）
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+/// 请求头改写规则
+///
+/// `value` 支持模板变量 `{session_id}`、`{provider_id}`、`{provider_name}`、`{model}`、`{timestamp}`。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeaderRule {
+    pub action: HeaderRuleAction,
+    /// 请求头名称（不区分大小写）
+    pub name: String,
+    /// set / append：写入的值；replace：替换内容（可引用 `$1` 等捕获组）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// replace：匹配现有值的正则表达式
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// 是否启用（缺省启用）
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// 请求头改写动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HeaderRuleAction {
+    /// 设置（覆盖已有值）
+    Set,
+    /// 追加一个值（保留已有值）
+    Append,
+    /// 删除
+    Remove,
+    /// 按正则替换已有值
+    Replace,
+}
+
+/// 请求体改写规则
+///
+/// `path` 为点分路径（如 `metadata`、`thinking.budget_tokens`），数组用下标访问，
+/// `*` 匹配数组的每个元素或对象的每个键（如 `messages.*.content.*.cache_control`）。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BodyPatch {
+    pub action: BodyPatchAction,
+    pub path: String,
+    /// set：写入的值；clamp：允许的最大值
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<serde_json::Value>,
+    /// rename：新的键名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+    /// 仅对匹配的模型生效（通配符，不区分大小写；为空时对所有模型生效）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// 是否启用（缺省启用）
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+/// 请求体改写动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BodyPatchAction {
+    /// 删除字段
+    Remove,
+    /// 设置字段（不存在时创建，路径不能包含 `*`）
+    Set,
+    /// 重命名字段
+    Rename,
+    /// 数值超过上限时截断（如 `max_tokens`）
+    Clamp,
+}
+
+/// 供应商出站网络设置
+///
+/// 作用于代理转发、流式检查、端点测速和用量脚本，不同设置使用各自缓存的 HTTP 客户端。
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboundConfig {
+    /// 上游代理模式
+    #[serde(default)]
+    pub proxy_mode: OutboundProxyMode,
+    /// 自定义代理地址（`http://`、`https://`、`socks5://`、`socks5h://`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_url: Option<String>,
+    /// 代理认证用户名（也可直接写在代理地址中）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_username: Option<String>,
+    /// 代理认证密码
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_password: Option<String>,
+    /// 不走代理的主机（域名、`*.example.com`、IP 或 CIDR）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub no_proxy: Vec<String>,
+    /// 额外信任的根证书（PEM 文件，可包含多个证书）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_cert_path: Option<String>,
+    /// 跳过证书校验（仅用于内网自签名网关）
+    #[serde(default)]
+    pub accept_invalid_certs: bool,
+}
+
+/// 上游代理模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutboundProxyMode {
+    /// 跟随系统代理（`HTTP_PROXY` / `HTTPS_PROXY` 等环境变量）
+    #[default]
+    System,
+    /// 直连，忽略系统代理
+    Direct,
+    /// 使用 `proxy_url` 指定的代理
+    Custom,
+}
+
+/// 供应商消费限额的执行方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpendLimitMode {
+    /// 超出限额后代理不再选择该供应商
+    #[default]
+    Hard,
+    /// 仅告警，不影响路由
+    Soft,
 }
 
 impl ProviderManager {
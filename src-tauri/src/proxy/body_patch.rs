@@ -0,0 +1,400 @@
+//! 请求体改写
+//!
+//! 中转服务经常因为 `cache_control`、`thinking`、`metadata`、`betas` 或未知的工具类型返回 400。
+//! 供应商 meta 中的 `bodyPatches` 在转发前对最终请求体（模型映射与格式转换之后）按顺序执行，
+//! 可以删除、设置、重命名字段或截断数值，并可限定只对匹配的模型生效。
+
+use super::routing_rules::{compile_glob, compile_pattern, RuleMatchType};
+use crate::provider::{BodyPatch, BodyPatchAction};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// 改写预览结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BodyPatchPreview {
+    /// 改写后的请求体
+    pub body: Value,
+    /// 实际生效的规则说明
+    pub applied: Vec<String>,
+}
+
+/// 校验单条规则
+pub fn validate_body_patch(patch: &BodyPatch) -> Result<(), String> {
+    let segments = split_path(&patch.path);
+    let Some(last) = segments.last() else {
+        return Err("字段路径不能为空".to_string());
+    };
+    if segments.iter().any(|s| s.is_empty()) {
+        return Err(format!("无效的字段路径: {}", patch.path));
+    }
+
+    match patch.action {
+        BodyPatchAction::Remove => {}
+        BodyPatchAction::Set => {
+            if patch.value.is_none() {
+                return Err("set 规则需要提供 value".to_string());
+            }
+            if segments.contains(&"*") {
+                return Err("set 规则的路径不能包含 *".to_string());
+            }
+        }
+        BodyPatchAction::Rename => {
+            if patch
+                .to
+                .as_deref()
+                .map(str::trim)
+                .unwrap_or_default()
+                .is_empty()
+            {
+                return Err("rename 规则需要提供新的键名".to_string());
+            }
+            if *last == "*" {
+                return Err("rename 规则的路径不能以 * 结尾".to_string());
+            }
+        }
+        BodyPatchAction::Clamp => {
+            if !patch.value.as_ref().is_some_and(Value::is_number) {
+                return Err("clamp 规则的 value 必须是数字".to_string());
+            }
+        }
+    }
+
+    if let Some(pattern) = model_pattern(patch) {
+        compile_glob(pattern)?;
+    }
+    Ok(())
+}
+
+/// 按顺序执行改写规则，返回实际生效的规则说明
+///
+/// 无效的规则会被跳过并记录警告；路径不存在时规则不生效。
+pub fn apply_body_patches(body: &mut Value, patches: &[BodyPatch], model: &str) -> Vec<String> {
+    let mut applied = Vec::new();
+
+    for patch in patches.iter().filter(|patch| patch.enabled) {
+        if let Err(e) = validate_body_patch(patch) {
+            log::warn!("[BodyPatch] 跳过无效的规则 {}: {e}", patch.path);
+            continue;
+        }
+        if let Some(pattern) = model_pattern(patch) {
+            if !compile_pattern(RuleMatchType::Glob, pattern).is_ok_and(|re| re.is_match(model)) {
+                continue;
+            }
+        }
+        if apply_patch(body, patch) > 0 {
+            applied.push(describe(patch));
+        }
+    }
+
+    if !applied.is_empty() {
+        log::info!("[BodyPatch] 已改写请求体: {}", applied.join(", "));
+    }
+    applied
+}
+
+/// 预览改写结果（用于在保存前验证规则）
+///
+/// 任一规则无效时返回错误；模型取自请求体中的 `model` 字段。
+pub fn preview_body_patches(
+    patches: &[BodyPatch],
+    mut body: Value,
+) -> Result<BodyPatchPreview, String> {
+    for (index, patch) in patches.iter().enumerate() {
+        validate_body_patch(patch).map_err(|e| format!("第 {} 条规则: {e}", index + 1))?;
+    }
+    let model = body
+        .get("model")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    let applied = apply_body_patches(&mut body, patches, &model);
+    Ok(BodyPatchPreview { body, applied })
+}
+
+fn model_pattern(patch: &BodyPatch) -> Option<&str> {
+    patch
+        .model
+        .as_deref()
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+}
+
+fn split_path(path: &str) -> Vec<&str> {
+    let path = path.trim();
+    if path.is_empty() {
+        return Vec::new();
+    }
+    path.split('.').map(str::trim).collect()
+}
+
+fn describe(patch: &BodyPatch) -> String {
+    let path = patch.path.trim();
+    match patch.action {
+        BodyPatchAction::Remove => format!("remove {path}"),
+        BodyPatchAction::Set => format!("set {path}"),
+        BodyPatchAction::Rename => format!(
+            "rename {path} -> {}",
+            patch.to.as_deref().unwrap_or_default().trim()
+        ),
+        BodyPatchAction::Clamp => format!(
+            "clamp {path} <= {}",
+            patch
+                .value
+                .as_ref()
+                .map(Value::to_string)
+                .unwrap_or_default()
+        ),
+    }
+}
+
+/// 执行单条规则，返回修改的字段数
+fn apply_patch(body: &mut Value, patch: &BodyPatch) -> usize {
+    let segments = split_path(&patch.path);
+    let Some((last, parents)) = segments.split_last() else {
+        return 0;
+    };
+
+    if patch.action == BodyPatchAction::Set {
+        let value = patch.value.clone().unwrap_or_default();
+        return usize::from(set_path(body, &segments, value));
+    }
+
+    let mut changed = 0;
+    visit_parents(body, parents, &mut |parent| {
+        changed += match patch.action {
+            BodyPatchAction::Remove => remove_child(parent, last),
+            BodyPatchAction::Rename => {
+                let to = patch.to.as_deref().unwrap_or_default().trim();
+                match parent {
+                    Value::Object(map) if *last != to => match map.remove(*last) {
+                        Some(value) => {
+                            map.insert(to.to_string(), value);
+                            1
+                        }
+                        None => 0,
+                    },
+                    _ => 0,
+                }
+            }
+            BodyPatchAction::Clamp => {
+                let max = patch.value.as_ref().and_then(Value::as_f64);
+                let mut clamped = 0;
+                for_each_child(parent, last, &mut |value| {
+                    if let (Some(current), Some(max)) = (value.as_f64(), max) {
+                        if current > max {
+                            *value = patch.value.clone().unwrap_or_default();
+                            clamped += 1;
+                        }
+                    }
+                });
+                clamped
+            }
+            BodyPatchAction::Set => 0,
+        };
+    });
+    changed
+}
+
+/// 对路径匹配到的每个父节点调用 `f`
+fn visit_parents(value: &mut Value, segments: &[&str], f: &mut dyn FnMut(&mut Value)) {
+    match segments.split_first() {
+        None => f(value),
+        Some((&"*", rest)) => match value {
+            Value::Array(items) => items.iter_mut().for_each(|v| visit_parents(v, rest, f)),
+            Value::Object(map) => map.values_mut().for_each(|v| visit_parents(v, rest, f)),
+            _ => {}
+        },
+        Some((segment, rest)) => {
+            if let Some(child) = child_mut(value, segment) {
+                visit_parents(child, rest, f);
+            }
+        }
+    }
+}
+
+fn for_each_child(parent: &mut Value, segment: &str, f: &mut dyn FnMut(&mut Value)) {
+    if segment == "*" {
+        match parent {
+            Value::Array(items) => items.iter_mut().for_each(f),
+            Value::Object(map) => map.values_mut().for_each(f),
+            _ => {}
+        }
+    } else if let Some(child) = child_mut(parent, segment) {
+        f(child);
+    }
+}
+
+fn child_mut<'a>(value: &'a mut Value, segment: &str) -> Option<&'a mut Value> {
+    match value {
+        Value::Object(map) => map.get_mut(segment),
+        Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get_mut(i)),
+        _ => None,
+    }
+}
+
+fn remove_child(parent: &mut Value, segment: &str) -> usize {
+    match (parent, segment) {
+        (Value::Object(map), "*") => std::mem::take(map).len(),
+        (Value::Array(items), "*") => std::mem::take(items).len(),
+        (Value::Object(map), key) => usize::from(map.remove(key).is_some()),
+        (Value::Array(items), index) => match index.parse::<usize>() {
+            Ok(i) if i < items.len() => {
+                items.remove(i);
+                1
+            }
+            _ => 0,
+        },
+        _ => 0,
+    }
+}
+
+/// 设置字段，缺失的中间对象会被创建
+fn set_path(body: &mut Value, segments: &[&str], value: Value) -> bool {
+    let Some((last, parents)) = segments.split_last() else {
+        return false;
+    };
+
+    let mut current = body;
+    for segment in parents {
+        if current.is_null() {
+            *current = Value::Object(Map::new());
+        }
+        current = match current {
+            Value::Object(map) => map.entry(segment.to_string()).or_insert(Value::Null),
+            Value::Array(items) => {
+                match segment.parse::<usize>().ok().and_then(|i| items.get_mut(i)) {
+                    Some(item) => item,
+                    None => return false,
+                }
+            }
+            _ => return false,
+        };
+    }
+
+    if current.is_null() {
+        *current = Value::Object(Map::new());
+    }
+    match current {
+        Value::Object(map) => {
+            map.insert(last.to_string(), value);
+            true
+        }
+        Value::Array(items) => match last.parse::<usize>().ok().and_then(|i| items.get_mut(i)) {
+            Some(item) => {
+                *item = value;
+                true
+            }
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn patch(action: BodyPatchAction, path: &str, value: Option<Value>) -> BodyPatch {
+        BodyPatch {
+            action,
+            path: path.to_string(),
+            value,
+            to: None,
+            model: None,
+            enabled: true,
+        }
+    }
+
+    fn sample() -> Value {
+        json!({
+            "model": "claude-sonnet-4",
+            "max_tokens": 64000,
+            "metadata": {"user_id": "u"},
+            "betas": ["a"],
+            "messages": [
+                {"role": "user", "content": [
+                    {"type": "text", "text": "hi", "cache_control": {"type": "ephemeral"}},
+                    {"type": "text", "text": "there"}
+                ]}
+            ]
+        })
+    }
+
+    #[test]
+    fn test_apply_body_patches() {
+        let mut rename = patch(BodyPatchAction::Rename, "betas", None);
+        rename.to = Some("anthropic_beta".to_string());
+        let patches = vec![
+            patch(BodyPatchAction::Remove, "metadata", None),
+            patch(
+                BodyPatchAction::Remove,
+                "messages.*.content.*.cache_control",
+                None,
+            ),
+            patch(BodyPatchAction::Clamp, "max_tokens", Some(json!(8192))),
+            patch(
+                BodyPatchAction::Set,
+                "thinking.type",
+                Some(json!("disabled")),
+            ),
+            rename,
+            patch(BodyPatchAction::Remove, "missing", None),
+        ];
+
+        let mut body = sample();
+        let applied = apply_body_patches(&mut body, &patches, "claude-sonnet-4");
+        assert!(body.get("metadata").is_none());
+        assert!(body["messages"][0]["content"][0]
+            .get("cache_control")
+            .is_none());
+        assert_eq!(body["max_tokens"], 8192);
+        assert_eq!(body["thinking"], json!({"type": "disabled"}));
+        assert_eq!(body["anthropic_beta"], json!(["a"]));
+        assert!(body.get("betas").is_none());
+        assert_eq!(
+            applied,
+            vec![
+                "remove metadata",
+                "remove messages.*.content.*.cache_control",
+                "clamp max_tokens <= 8192",
+                "set thinking.type",
+                "rename betas -> anthropic_beta"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_model_filter_and_disabled_patches() {
+        let mut only_haiku = patch(BodyPatchAction::Remove, "metadata", None);
+        only_haiku.model = Some("*haiku*".to_string());
+        let mut disabled = patch(BodyPatchAction::Remove, "betas", None);
+        disabled.enabled = false;
+        let patches = vec![only_haiku, disabled];
+
+        let mut body = sample();
+        assert!(apply_body_patches(&mut body, &patches, "claude-sonnet-4").is_empty());
+        assert_eq!(body, sample());
+
+        let applied = apply_body_patches(&mut body, &patches, "Claude-Haiku-4");
+        assert_eq!(applied, vec!["remove metadata"]);
+    }
+
+    #[test]
+    fn test_preview_rejects_invalid_patches() {
+        let patches = vec![
+            patch(BodyPatchAction::Clamp, "max_tokens", Some(json!(1024))),
+            patch(BodyPatchAction::Set, "messages.*.role", Some(json!("user"))),
+        ];
+        let err = preview_body_patches(&patches, sample()).unwrap_err();
+        assert!(err.starts_with("第 2 条规则"));
+
+        let preview = preview_body_patches(&patches[..1], sample()).unwrap();
+        assert_eq!(preview.body["max_tokens"], 1024);
+        assert_eq!(preview.applied, vec!["clamp max_tokens <= 1024"]);
+
+        assert!(validate_body_patch(&patch(BodyPatchAction::Remove, "", None)).is_err());
+        assert!(validate_body_patch(&patch(BodyPatchAction::Rename, "betas", None)).is_err());
+    }
+}
@@ -1,13 +1,18 @@
 //! 熔断器模块
 //!
-//! 实现熔断器模式，用于防止向不健康的供应商发送请求
+//! 实现熔断器模式，用于防止向不健康的供应商发送请求。
+//! 错误率按最近 `ERROR_RATE_WINDOW` 内的请求计算；状态转换通过监听器通知 `ProviderRouter` 持久化。
 
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::sync::atomic::{AtomicU32, Ordering};
-use std::sync::Arc;
-use std::time::Instant;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
+/// 错误率统计的滑动窗口长度
+pub const ERROR_RATE_WINDOW: Duration = Duration::from_secs(60);
+
 /// 熔断器状态
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -20,6 +25,19 @@ pub enum CircuitState {
     HalfOpen,
 }
 
+impl std::str::FromStr for CircuitState {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "closed" => Ok(CircuitState::Closed),
+            "open" => Ok(CircuitState::Open),
+            "half_open" => Ok(CircuitState::HalfOpen),
+            other => Err(format!("未知的熔断器状态: {other}")),
+        }
+    }
+}
+
 impl std::fmt::Display for CircuitState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -58,6 +76,18 @@ impl Default for CircuitBreakerConfig {
     }
 }
 
+/// 熔断器状态转换
+#[derive(Debug, Clone)]
+pub struct CircuitTransition {
+    pub from: CircuitState,
+    pub to: CircuitState,
+    /// 转换原因（用于审计）
+    pub reason: String,
+}
+
+/// 状态转换监听器
+pub type TransitionListener = Arc<dyn Fn(&CircuitTransition) + Send + Sync>;
+
 /// 熔断器实例
 pub struct CircuitBreaker {
     /// 当前状态
@@ -66,16 +96,16 @@ pub struct CircuitBreaker {
     consecutive_failures: Arc<AtomicU32>,
     /// 连续成功计数（半开状态）
     consecutive_successes: Arc<AtomicU32>,
-    /// 总请求计数
-    total_requests: Arc<AtomicU32>,
-    /// 失败请求计数
-    failed_requests: Arc<AtomicU32>,
+    /// 滑动窗口内的请求结果（完成时间, 是否失败）
+    window: Mutex<VecDeque<(Instant, bool)>>,
     /// 上次打开时间
     last_opened_at: Arc<RwLock<Option<Instant>>>,
     /// 配置（支持热更新）
     config: Arc<RwLock<CircuitBreakerConfig>>,
     /// 半开状态已放行的请求数（用于限流）
     half_open_requests: Arc<AtomicU32>,
+    /// 状态转换监听器
+    listener: Option<TransitionListener>,
 }
 
 /// 熔断器放行结果
@@ -95,14 +125,36 @@ impl CircuitBreaker {
             state: Arc::new(RwLock::new(CircuitState::Closed)),
             consecutive_failures: Arc::new(AtomicU32::new(0)),
             consecutive_successes: Arc::new(AtomicU32::new(0)),
-            total_requests: Arc::new(AtomicU32::new(0)),
-            failed_requests: Arc::new(AtomicU32::new(0)),
+            window: Mutex::new(VecDeque::new()),
             last_opened_at: Arc::new(RwLock::new(None)),
             config: Arc::new(RwLock::new(config)),
             half_open_requests: Arc::new(AtomicU32::new(0)),
+            listener: None,
         }
     }
 
+    /// 设置状态转换监听器（用于持久化状态与转换历史）
+    pub fn with_listener(mut self, listener: TransitionListener) -> Self {
+        self.listener = Some(listener);
+        self
+    }
+
+    /// 恢复持久化的状态（不触发监听器）
+    ///
+    /// `open_for` 为熔断器已打开的时长，恢复后仍按配置的超时时间进入半开。
+    pub async fn restore(&self, state: CircuitState, open_for: Option<Duration>) {
+        *self.state.write().await = state;
+        *self.last_opened_at.write().await = match state {
+            CircuitState::Closed => None,
+            _ => Some(
+                open_for
+                    .and_then(|elapsed| Instant::now().checked_sub(elapsed))
+                    .unwrap_or_else(Instant::now),
+            ),
+        };
+        self.half_open_requests.store(0, Ordering::SeqCst);
+    }
+
     /// 更新熔断器配置（热更新，不重置状态）
     pub async fn update_config(&self, new_config: CircuitBreakerConfig) {
         *self.config.write().await = new_config;
@@ -130,7 +182,7 @@ impl CircuitBreaker {
                         log::info!(
                             "Circuit breaker transitioning from Open to HalfOpen (timeout reached)"
                         );
-                        self.transition_to_half_open().await;
+                        self.transition_to_half_open("熔断超时，进入半开探测").await;
                         return true;
                     }
                 }
@@ -157,7 +209,7 @@ impl CircuitBreaker {
                         log::info!(
                             "Circuit breaker transitioning from Open to HalfOpen (timeout reached)"
                         );
-                        self.transition_to_half_open().await;
+                        self.transition_to_half_open("熔断超时，进入半开探测").await;
 
                         // 转换后按当前状态决定是否需要获取 HalfOpen 探测名额
                         let current_state = *self.state.read().await;
@@ -184,6 +236,13 @@ impl CircuitBreaker {
         }
     }
 
+    /// 释放 HalfOpen 探测名额，不计入成功或失败（用于上游限流等与供应商健康无关的结果）
+    pub fn release_permit(&self, used_half_open_permit: bool) {
+        if used_half_open_permit {
+            self.release_half_open_permit();
+        }
+    }
+
     /// 记录成功
     pub async fn record_success(&self, used_half_open_permit: bool) {
         let state = *self.state.read().await;
@@ -195,7 +254,7 @@ impl CircuitBreaker {
 
         // 重置失败计数
         self.consecutive_failures.store(0, Ordering::SeqCst);
-        self.total_requests.fetch_add(1, Ordering::SeqCst);
+        self.record_window(false);
 
         match state {
             CircuitState::HalfOpen => {
@@ -209,7 +268,8 @@ impl CircuitBreaker {
                 if successes >= config.success_threshold {
                     drop(config); // 释放读锁再转换状态
                     log::info!("Circuit breaker transitioning from HalfOpen to Closed (success threshold reached)");
-                    self.transition_to_closed().await;
+                    self.transition_to_closed(&format!("半开探测连续成功 {successes} 次"))
+                        .await;
                 }
             }
             CircuitState::Closed => {
@@ -230,8 +290,7 @@ impl CircuitBreaker {
 
         // 更新计数器
         let failures = self.consecutive_failures.fetch_add(1, Ordering::SeqCst) + 1;
-        self.total_requests.fetch_add(1, Ordering::SeqCst);
-        self.failed_requests.fetch_add(1, Ordering::SeqCst);
+        self.record_window(true);
 
         // 重置成功计数
         self.consecutive_successes.store(0, Ordering::SeqCst);
@@ -249,7 +308,7 @@ impl CircuitBreaker {
                 // HalfOpen 状态下失败，立即转为 Open
                 log::warn!("Circuit breaker HalfOpen probe failed, transitioning to Open");
                 drop(config);
-                self.transition_to_open().await;
+                self.transition_to_open("半开探测失败").await;
             }
             CircuitState::Closed => {
                 // 检查连续失败次数
@@ -260,11 +319,11 @@ impl CircuitBreaker {
                         config.failure_threshold
                     );
                     drop(config); // 释放读锁再转换状态
-                    self.transition_to_open().await;
+                    self.transition_to_open(&format!("连续失败 {failures} 次"))
+                        .await;
                 } else {
-                    // 检查错误率
-                    let total = self.total_requests.load(Ordering::SeqCst);
-                    let failed = self.failed_requests.load(Ordering::SeqCst);
+                    // 检查滑动窗口内的错误率
+                    let (total, failed) = self.window_counts();
 
                     if total >= config.min_requests {
                         let error_rate = failed as f64 / total as f64;
@@ -281,8 +340,13 @@ impl CircuitBreaker {
                                 error_rate * 100.0,
                                 config.error_rate_threshold * 100.0
                             );
+                            let reason = format!(
+                                "错误率 {:.0}%（最近 {}s 内 {failed}/{total}）",
+                                error_rate * 100.0,
+                                ERROR_RATE_WINDOW.as_secs()
+                            );
                             drop(config); // 释放读锁再转换状态
-                            self.transition_to_open().await;
+                            self.transition_to_open(&reason).await;
                         }
                     }
                 }
@@ -300,12 +364,14 @@ impl CircuitBreaker {
     /// 获取统计信息
     #[allow(dead_code)]
     pub async fn get_stats(&self) -> CircuitBreakerStats {
+        let (total_requests, failed_requests) = self.window_counts();
         CircuitBreakerStats {
             state: *self.state.read().await,
             consecutive_failures: self.consecutive_failures.load(Ordering::SeqCst),
             consecutive_successes: self.consecutive_successes.load(Ordering::SeqCst),
-            total_requests: self.total_requests.load(Ordering::SeqCst),
-            failed_requests: self.failed_requests.load(Ordering::SeqCst),
+            total_requests,
+            failed_requests,
+            cooldown_remaining_secs: None,
         }
     }
 
@@ -313,7 +379,45 @@ impl CircuitBreaker {
     #[allow(dead_code)]
     pub async fn reset(&self) {
         log::info!("Circuit breaker manually reset to Closed state");
-        self.transition_to_closed().await;
+        self.transition_to_closed("手动重置").await;
+    }
+
+    /// 记录一次请求结果到滑动窗口
+    fn record_window(&self, failed: bool) {
+        let now = Instant::now();
+        let mut window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        window.push_back((now, failed));
+        Self::prune_window(&mut window, now);
+    }
+
+    /// 滑动窗口内的（总请求数, 失败数）
+    fn window_counts(&self) -> (u32, u32) {
+        let mut window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        Self::prune_window(&mut window, Instant::now());
+        let failed = window.iter().filter(|(_, failed)| *failed).count();
+        (window.len() as u32, failed as u32)
+    }
+
+    fn prune_window(window: &mut VecDeque<(Instant, bool)>, now: Instant) {
+        while window
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) > ERROR_RATE_WINDOW)
+        {
+            window.pop_front();
+        }
+    }
+
+    fn notify(&self, from: CircuitState, to: CircuitState, reason: &str) {
+        if from == to {
+            return;
+        }
+        if let Some(listener) = &self.listener {
+            listener(&CircuitTransition {
+                from,
+                to,
+                reason: reason.to_string(),
+            });
+        }
     }
 
     fn allow_half_open_probe(&self) -> AllowResult {
@@ -367,15 +471,16 @@ impl CircuitBreaker {
     }
 
     /// 转换到打开状态
-    async fn transition_to_open(&self) {
-        *self.state.write().await = CircuitState::Open;
+    async fn transition_to_open(&self, reason: &str) {
+        let from = std::mem::replace(&mut *self.state.write().await, CircuitState::Open);
         *self.last_opened_at.write().await = Some(Instant::now());
         self.consecutive_failures.store(0, Ordering::SeqCst);
         self.consecutive_successes.store(0, Ordering::SeqCst);
+        self.notify(from, CircuitState::Open, reason);
     }
 
     /// 转换到半开状态
-    async fn transition_to_half_open(&self) {
+    async fn transition_to_half_open(&self, reason: &str) {
         let mut state = self.state.write().await;
         if *state != CircuitState::Open {
             return;
@@ -385,16 +490,21 @@ impl CircuitBreaker {
         self.consecutive_successes.store(0, Ordering::SeqCst);
         // 重置半开状态的请求限流计数
         self.half_open_requests.store(0, Ordering::SeqCst);
+        drop(state);
+        self.notify(CircuitState::Open, CircuitState::HalfOpen, reason);
     }
 
     /// 转换到关闭状态
-    async fn transition_to_closed(&self) {
-        *self.state.write().await = CircuitState::Closed;
+    async fn transition_to_closed(&self, reason: &str) {
+        let from = std::mem::replace(&mut *self.state.write().await, CircuitState::Closed);
         self.consecutive_failures.store(0, Ordering::SeqCst);
         self.consecutive_successes.store(0, Ordering::SeqCst);
-        // 重置计数器
-        self.total_requests.store(0, Ordering::SeqCst);
-        self.failed_requests.store(0, Ordering::SeqCst);
+        // 清空错误率窗口
+        self.window
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+        self.notify(from, CircuitState::Closed, reason);
     }
 }
 
@@ -405,8 +515,27 @@ pub struct CircuitBreakerStats {
     pub state: CircuitState,
     pub consecutive_failures: u32,
     pub consecutive_successes: u32,
+    /// 滑动窗口（`ERROR_RATE_WINDOW`）内的请求数
     pub total_requests: u32,
+    /// 滑动窗口内的失败请求数
     pub failed_requests: u32,
+    /// 上游限流冷却剩余秒数（由 `ProviderRouter` 填充，未冷却时为 None）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cooldown_remaining_secs: Option<u64>,
+}
+
+/// 持久化的熔断器状态转换记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CircuitTransitionRecord {
+    pub id: i64,
+    pub app_type: String,
+    pub provider_id: String,
+    pub provider_name: Option<String>,
+    pub from_state: String,
+    pub to_state: String,
+    pub reason: Option<String>,
+    pub created_at: i64,
 }
 
 #[cfg(test)]
@@ -450,7 +579,7 @@ mod tests {
         assert_eq!(breaker.get_state().await, CircuitState::Open);
 
         // 手动转换到半开状态
-        breaker.transition_to_half_open().await;
+        breaker.transition_to_half_open("test").await;
         assert_eq!(breaker.get_state().await, CircuitState::HalfOpen);
 
         // 记录 2 次成功
@@ -470,14 +599,14 @@ mod tests {
         let breaker = CircuitBreaker::new(config);
 
         // 进入 Open，然后由于 timeout_seconds=0，allow_request 会立即切换到 HalfOpen 并占用探测名额
-        breaker.transition_to_open().await;
+        breaker.transition_to_open("test").await;
         let first = breaker.allow_request().await;
         assert!(first.allowed);
         assert!(first.used_half_open_permit);
         assert_eq!(breaker.get_state().await, CircuitState::HalfOpen);
 
         // 模拟并发下的“重复 HalfOpen 转换调用”，不应重置 in-flight 计数
-        breaker.transition_to_half_open().await;
+        breaker.transition_to_half_open("test").await;
 
         // 由于名额仍被占用，第二次请求应被拒绝
         let second = breaker.allow_request().await;
@@ -503,4 +632,68 @@ mod tests {
         assert_eq!(breaker.get_state().await, CircuitState::Closed);
         assert!(breaker.allow_request().await.allowed);
     }
+
+    #[tokio::test]
+    async fn test_error_rate_uses_sliding_window() {
+        let config = CircuitBreakerConfig {
+            failure_threshold: 100,
+            error_rate_threshold: 0.5,
+            min_requests: 4,
+            ..Default::default()
+        };
+        let transitions = Arc::new(Mutex::new(Vec::new()));
+        let sink = transitions.clone();
+        let breaker = CircuitBreaker::new(config).with_listener(Arc::new(move |t| {
+            sink.lock().unwrap().push((t.from, t.to));
+        }));
+
+        // 窗口外的旧失败不计入错误率
+        {
+            let stale = Instant::now() - ERROR_RATE_WINDOW - Duration::from_secs(1);
+            let mut window = breaker.window.lock().unwrap();
+            for _ in 0..10 {
+                window.push_back((stale, true));
+            }
+        }
+        breaker.record_failure(false).await;
+        breaker.record_success(false).await;
+        let stats = breaker.get_stats().await;
+        assert_eq!((stats.total_requests, stats.failed_requests), (2, 1));
+        assert_eq!(breaker.get_state().await, CircuitState::Closed);
+
+        // 未达到 min_requests 前不按错误率熔断
+        breaker.record_failure(false).await;
+        assert_eq!(breaker.get_state().await, CircuitState::Closed);
+
+        // 窗口内 3/4 失败，超过阈值
+        breaker.record_failure(false).await;
+        assert_eq!(breaker.get_state().await, CircuitState::Open);
+        assert_eq!(
+            *transitions.lock().unwrap(),
+            vec![(CircuitState::Closed, CircuitState::Open)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_restore_does_not_notify() {
+        let notified = Arc::new(AtomicU32::new(0));
+        let counter = notified.clone();
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            timeout_seconds: 60,
+            ..Default::default()
+        })
+        .with_listener(Arc::new(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }));
+
+        breaker
+            .restore(CircuitState::Open, Some(Duration::from_secs(10)))
+            .await;
+        assert_eq!(breaker.get_state().await, CircuitState::Open);
+        assert!(!breaker.allow_request().await.allowed);
+        assert_eq!(notified.load(Ordering::SeqCst), 0);
+
+        breaker.reset().await;
+        assert_eq!(notified.load(Ordering::SeqCst), 1);
+    }
 }
@@ -0,0 +1,42 @@
+//! 自适应字段兼容
+//!
+//! 上游以 400/422 明确指出某个请求字段不受支持时（识别逻辑见 [`ProxyError::rejected_field`]），
+//! 转发器去掉该字段后对同一供应商重试一次，重试不再因该字段被拒绝时记住这一不兼容，
+//! 之后发往该供应商的请求都会自动去掉该字段。记录可在界面中查看和重置。
+//!
+//! [`ProxyError::rejected_field`]: super::ProxyError::rejected_field
+
+use crate::provider::{BodyPatch, BodyPatchAction};
+use serde::{Deserialize, Serialize};
+
+/// 已识别的字段不兼容记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderIncompatibility {
+    pub app_type: String,
+    pub provider_id: String,
+    pub provider_name: Option<String>,
+    /// 字段路径（与请求体改写规则的路径格式相同）
+    pub field: String,
+    /// 最近一次上游返回的错误信息
+    pub error_message: Option<String>,
+    /// 被上游拒绝的次数
+    pub hit_count: u64,
+    pub learned_at: i64,
+    pub last_seen_at: i64,
+}
+
+/// 把需要去掉的字段转换为请求体删除规则
+pub fn strip_patches(fields: &[String]) -> Vec<BodyPatch> {
+    fields
+        .iter()
+        .map(|field| BodyPatch {
+            action: BodyPatchAction::Remove,
+            path: field.clone(),
+            value: None,
+            to: None,
+            model: None,
+            enabled: true,
+        })
+        .collect()
+}
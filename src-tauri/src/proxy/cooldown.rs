@@ -0,0 +1,306 @@
+//! 上游限流冷却
+//!
+//! 上游返回 429/529（或携带 Retry-After 的 503）时，按响应头给出的重置时间让供应商进入冷却期，
+//! 冷却期内转发器跳过该供应商。冷却与熔断器相互独立：上游限流不计入熔断器的失败统计。
+
+use super::ProxyError;
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// 限流响应未携带重置时间时的默认冷却时长（秒）
+pub const DEFAULT_COOLDOWN_SECS: u64 = 10;
+
+/// 冷却时长上限（秒），避免异常响应头让供应商长时间不可用
+pub const MAX_COOLDOWN_SECS: u64 = 600;
+
+/// 冷却中的供应商（用于代理状态展示）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderCooldown {
+    pub app_type: String,
+    pub provider_id: String,
+    pub provider_name: String,
+    /// 触发冷却的上游状态码
+    pub status_code: u16,
+    pub remaining_secs: u64,
+}
+
+/// 是否为上游限流/过载状态码（429 Too Many Requests、529 Overloaded）
+pub fn is_throttle_status(status: u16) -> bool {
+    status == 429 || status == 529
+}
+
+/// 根据上游错误计算冷却时长；非限流错误返回 None
+pub fn cooldown_for(error: &ProxyError) -> Option<Duration> {
+    let ProxyError::UpstreamError {
+        status,
+        retry_after,
+        ..
+    } = error
+    else {
+        return None;
+    };
+
+    if is_throttle_status(*status) {
+        Some(retry_after.unwrap_or(Duration::from_secs(DEFAULT_COOLDOWN_SECS)))
+    } else if *status == 503 {
+        *retry_after
+    } else {
+        None
+    }
+}
+
+/// 从限流响应头解析冷却时长
+///
+/// 优先使用 `retry-after-ms` / `retry-after`（秒数或 HTTP 日期），其次是
+/// `anthropic-ratelimit-*-reset`（RFC 3339 时间）和 `x-ratelimit-reset-*`（如 `6m0s`）。
+/// 多个额度同时给出重置时间时，取已耗尽额度（remaining 为 0）中最晚的一个；
+/// 没有耗尽的额度时取最早的一个。结果不超过 `MAX_COOLDOWN_SECS`。
+pub fn parse_cooldown(headers: &HeaderMap, now: DateTime<Utc>) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+    let retry_after = header("retry-after-ms")
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .and_then(|ms| secs_to_duration(ms / 1000.0))
+        .or_else(|| header("retry-after").and_then(|v| parse_retry_after(v, now)));
+
+    let cooldown = retry_after.or_else(|| {
+        // (重置时长, 是否已耗尽)
+        let mut resets: Vec<(Duration, bool)> = Vec::new();
+        for (name, value) in headers {
+            let name = name.as_str();
+            let Ok(value) = value.to_str() else {
+                continue;
+            };
+            let (reset, remaining_header) = if let Some(resource) = name
+                .strip_prefix("anthropic-ratelimit-")
+                .and_then(|n| n.strip_suffix("-reset"))
+            {
+                (
+                    parse_timestamp(value, now),
+                    format!("anthropic-ratelimit-{resource}-remaining"),
+                )
+            } else if let Some(resource) = name.strip_prefix("x-ratelimit-reset-") {
+                (
+                    parse_go_duration(value).or_else(|| parse_seconds_or_epoch(value, now)),
+                    format!("x-ratelimit-remaining-{resource}"),
+                )
+            } else {
+                continue;
+            };
+            if let Some(reset) = reset {
+                let exhausted = header(&remaining_header).is_some_and(|r| r.trim() == "0");
+                resets.push((reset, exhausted));
+            }
+        }
+
+        if resets.iter().any(|(_, exhausted)| *exhausted) {
+            resets
+                .iter()
+                .filter(|(_, exhausted)| *exhausted)
+                .map(|(reset, _)| *reset)
+                .max()
+        } else {
+            resets.iter().map(|(reset, _)| *reset).min()
+        }
+    })?;
+
+    (!cooldown.is_zero()).then(|| cooldown.min(Duration::from_secs(MAX_COOLDOWN_SECS)))
+}
+
+/// `retry-after`：秒数或 HTTP 日期
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<f64>() {
+        return secs_to_duration(secs);
+    }
+    DateTime::parse_from_rfc2822(value)
+        .ok()
+        .map(|at| until(at.with_timezone(&Utc), now))
+}
+
+/// RFC 3339 时间戳
+fn parse_timestamp(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    DateTime::parse_from_rfc3339(value.trim())
+        .ok()
+        .map(|at| until(at.with_timezone(&Utc), now))
+}
+
+/// 纯数字：秒数，或（足够大时）Unix 时间戳
+fn parse_seconds_or_epoch(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let secs = value.trim().parse::<f64>().ok()?;
+    if secs > 1_000_000_000.0 {
+        DateTime::from_timestamp(secs as i64, 0).map(|at| until(at, now))
+    } else {
+        secs_to_duration(secs)
+    }
+}
+
+/// Go 风格的时长字符串（OpenAI 使用），如 `1s`、`6m0s`、`20ms`、`1h2m3.5s`
+fn parse_go_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if value.is_empty() || value.parse::<f64>().is_ok() {
+        return None;
+    }
+
+    let mut total = 0.0f64;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let number_end = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let number: f64 = rest[..number_end].parse().ok()?;
+        rest = &rest[number_end..];
+
+        let unit_end = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        let factor = match &rest[..unit_end] {
+            "h" => 3600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 0.001,
+            "us" | "µs" => 0.000_001,
+            "ns" => 0.000_000_001,
+            _ => return None,
+        };
+        rest = &rest[unit_end..];
+        total += number * factor;
+    }
+
+    // 溢出时返回 None，不在这里钳制（由 parse_cooldown 统一限制上限）
+    Duration::try_from_secs_f64(total).ok()
+}
+
+/// 秒数 → Duration：拒绝 NaN / 无穷大，并在转换前钳制到 `0..=MAX_COOLDOWN_SECS`
+/// （`Duration::from_secs_f64` 遇到非有限值或溢出会 panic，上游响应头不可信）
+fn secs_to_duration(secs: f64) -> Option<Duration> {
+    secs.is_finite()
+        .then(|| Duration::from_secs_f64(secs.clamp(0.0, MAX_COOLDOWN_SECS as f64)))
+}
+
+fn until(at: DateTime<Utc>, now: DateTime<Utc>) -> Duration {
+    (at - now).to_std().unwrap_or(Duration::ZERO)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2025-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_retry_after_seconds_and_http_date() {
+        let h = headers(&[("retry-after", "30")]);
+        assert_eq!(parse_cooldown(&h, now()), Some(Duration::from_secs(30)));
+
+        let h = headers(&[("retry-after", "Wed, 01 Jan 2025 00:01:00 GMT")]);
+        assert_eq!(parse_cooldown(&h, now()), Some(Duration::from_secs(60)));
+
+        let h = headers(&[("retry-after-ms", "1500"), ("retry-after", "30")]);
+        assert_eq!(parse_cooldown(&h, now()), Some(Duration::from_millis(1500)));
+    }
+
+    #[test]
+    fn test_anthropic_reset_prefers_exhausted_limit() {
+        let h = headers(&[
+            ("anthropic-ratelimit-requests-remaining", "10"),
+            ("anthropic-ratelimit-requests-reset", "2025-01-01T00:00:05Z"),
+            ("anthropic-ratelimit-tokens-remaining", "0"),
+            ("anthropic-ratelimit-tokens-reset", "2025-01-01T00:00:40Z"),
+        ]);
+        assert_eq!(parse_cooldown(&h, now()), Some(Duration::from_secs(40)));
+    }
+
+    #[test]
+    fn test_openai_reset_durations() {
+        let h = headers(&[
+            ("x-ratelimit-reset-requests", "6m0s"),
+            ("x-ratelimit-reset-tokens", "1.5s"),
+        ]);
+        assert_eq!(parse_cooldown(&h, now()), Some(Duration::from_millis(1500)));
+
+        assert_eq!(parse_go_duration("1h2m3s"), Some(Duration::from_secs(3723)));
+        assert_eq!(parse_go_duration("20ms"), Some(Duration::from_millis(20)));
+        assert_eq!(parse_go_duration("abc"), None);
+    }
+
+    #[test]
+    fn test_cooldown_is_capped_and_ignores_past_resets() {
+        let h = headers(&[("retry-after", "86400")]);
+        assert_eq!(
+            parse_cooldown(&h, now()),
+            Some(Duration::from_secs(MAX_COOLDOWN_SECS))
+        );
+
+        let h = headers(&[("anthropic-ratelimit-requests-reset", "2024-12-31T23:59:00Z")]);
+        assert_eq!(parse_cooldown(&h, now()), None);
+    }
+
+    #[test]
+    fn test_non_finite_and_huge_values_do_not_panic() {
+        for value in ["inf", "-inf", "NaN", "infinity"] {
+            let h = headers(&[("retry-after", value)]);
+            assert_eq!(parse_cooldown(&h, now()), None, "retry-after: {value}");
+            let h = headers(&[("retry-after-ms", value)]);
+            assert_eq!(parse_cooldown(&h, now()), None, "retry-after-ms: {value}");
+            let h = headers(&[("x-ratelimit-reset-requests", value)]);
+            assert_eq!(
+                parse_cooldown(&h, now()),
+                None,
+                "x-ratelimit-reset: {value}"
+            );
+        }
+
+        let max = Some(Duration::from_secs(MAX_COOLDOWN_SECS));
+        let h = headers(&[("retry-after", "1e20")]);
+        assert_eq!(parse_cooldown(&h, now()), max);
+        let h = headers(&[("retry-after-ms", "1e300")]);
+        assert_eq!(parse_cooldown(&h, now()), max);
+        assert_eq!(parse_go_duration("99999999999999999999999h"), None);
+        // 超大数字按 Unix 时间戳处理，超出可表示范围时忽略
+        let h = headers(&[("x-ratelimit-reset-tokens", "1e20")]);
+        assert_eq!(parse_cooldown(&h, now()), None);
+    }
+
+    #[test]
+    fn test_cooldown_for_errors() {
+        let throttled = ProxyError::UpstreamError {
+            status: 429,
+            body: None,
+            retry_after: None,
+        };
+        assert_eq!(
+            cooldown_for(&throttled),
+            Some(Duration::from_secs(DEFAULT_COOLDOWN_SECS))
+        );
+
+        let unavailable = ProxyError::UpstreamError {
+            status: 503,
+            body: None,
+            retry_after: Some(Duration::from_secs(5)),
+        };
+        assert_eq!(cooldown_for(&unavailable), Some(Duration::from_secs(5)));
+
+        let server_error = ProxyError::UpstreamError {
+            status: 500,
+            body: None,
+            retry_after: Some(Duration::from_secs(5)),
+        };
+        assert_eq!(cooldown_for(&server_error), None);
+    }
+}
@@ -3,7 +3,9 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
-use serde_json::json;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::sync::LazyLock;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -27,8 +29,13 @@ pub enum ProxyError {
     #[error("Provider不健康: {0}")]
     ProviderUnhealthy(String),
 
+    /// `retry_after` 为限流响应头（Retry-After 等）给出的冷却时长
     #[error("上游错误 (状态码 {status}): {body:?}")]
-    UpstreamError { status: u16, body: Option<String> },
+    UpstreamError {
+        status: u16,
+        body: Option<String>,
+        retry_after: Option<std::time::Duration>,
+    },
 
     #[error("超过最大重试次数")]
     MaxRetriesExceeded,
@@ -50,16 +57,27 @@ pub enum ProxyError {
     #[error("超时: {0}")]
     Timeout(String),
 
+    /// 本地限流：所有候选供应商均已达到限流上限
+    #[error("供应商限流: {0}")]
+    RateLimited(String),
+
+    /// 消费已达上限（应用级/全局上限，或所有供应商均超出预算）
+    #[error("消费已达上限: {0}")]
+    SpendLimitExceeded(String),
+
     /// 流式响应空闲超时
     #[allow(dead_code)]
     #[error("流式响应空闲超时: {0}秒无数据")]
     StreamIdleTimeout(u64),
 
     /// 认证错误
-    #[allow(dead_code)]
     #[error("认证失败: {0}")]
     AuthError(String),
 
+    /// 来源地址不在允许列表中
+    #[error("禁止访问: {0}")]
+    Forbidden(String),
+
     #[allow(dead_code)]
     #[error("内部错误: {0}")]
     Internal(String),
@@ -71,6 +89,7 @@ impl IntoResponse for ProxyError {
             ProxyError::UpstreamError {
                 status: upstream_status,
                 body: upstream_body,
+                ..
             } => {
                 let http_status =
                     StatusCode::from_u16(*upstream_status).unwrap_or(StatusCode::BAD_GATEWAY);
@@ -126,10 +145,15 @@ impl IntoResponse for ProxyError {
                     }
                     ProxyError::InvalidRequest(_) => (StatusCode::BAD_REQUEST, self.to_string()),
                     ProxyError::Timeout(_) => (StatusCode::GATEWAY_TIMEOUT, self.to_string()),
+                    ProxyError::RateLimited(_) => (StatusCode::TOO_MANY_REQUESTS, self.to_string()),
+                    ProxyError::SpendLimitExceeded(_) => {
+                        (StatusCode::PAYMENT_REQUIRED, self.to_string())
+                    }
                     ProxyError::StreamIdleTimeout(_) => {
                         (StatusCode::GATEWAY_TIMEOUT, self.to_string())
                     }
                     ProxyError::AuthError(_) => (StatusCode::UNAUTHORIZED, self.to_string()),
+                    ProxyError::Forbidden(_) => (StatusCode::FORBIDDEN, self.to_string()),
                     ProxyError::Internal(_) => {
                         (StatusCode::INTERNAL_SERVER_ERROR, self.to_string())
                     }
@@ -170,7 +194,8 @@ pub fn categorize_error(error: &reqwest::Error) -> ErrorCategory {
     }
 
     if let Some(status) = error.status() {
-        if status.is_server_error() {
+        // 429 限流：换一个供应商即可，不代表请求本身有误
+        if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
             ErrorCategory::Retryable
         } else if status.is_client_error() {
             ErrorCategory::NonRetryable
@@ -181,3 +206,180 @@ pub fn categorize_error(error: &reqwest::Error) -> ErrorCategory {
         ErrorCategory::Retryable
     }
 }
+
+/// 请求的核心字段：即使上游报告不支持也不会被自动去掉
+const ESSENTIAL_FIELDS: &[&str] = &["model", "messages", "input", "contents", "prompt"];
+
+/// 常见的"不支持的字段"错误信息（OpenAI、Anthropic/pydantic、Gemini、JSON Schema 校验）
+static REJECTED_FIELD_PATTERNS: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    [
+        r#"(?i)(?:unknown|unrecognized|unsupported|unexpected)\s+(?:request\s+)?(?:parameter|field|argument|property|key)s?(?:\s+supplied)?\s*[:=]?\s*['"`]?([A-Za-z_][\w.\[\]-]*)"#,
+        r"([A-Za-z_][\w.]*)\s*:\s*Extra inputs are not permitted",
+        r#"Unknown name "(\w+)"(?: at '([\w.\[\]]+)')?"#,
+        r#"(?i)property\s+['"`]?(\w+)['"`]?\s+is not allowed"#,
+    ]
+    .iter()
+    .map(|pattern| Regex::new(pattern).expect("invalid rejected field pattern"))
+    .collect()
+});
+
+impl ProxyError {
+    /// 上游因请求包含不支持的字段而拒绝（400/422）时，返回该字段的路径
+    ///
+    /// 路径为点分形式，数组下标归一化为 `*`（如 `messages.*.content.*.cache_control`）。
+    pub fn rejected_field(&self) -> Option<String> {
+        match self {
+            ProxyError::UpstreamError {
+                status: 400 | 422,
+                body: Some(body),
+                ..
+            } => parse_rejected_field(body),
+            _ => None,
+        }
+    }
+}
+
+/// 从上游错误响应中识别被拒绝的字段
+pub fn parse_rejected_field(body: &str) -> Option<String> {
+    let message = serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|json| {
+            ["/error/message", "/message", "/detail", "/error"]
+                .iter()
+                .find_map(|pointer| json.pointer(pointer)?.as_str().map(str::to_string))
+        })
+        .unwrap_or_else(|| body.to_string());
+
+    let captures = REJECTED_FIELD_PATTERNS
+        .iter()
+        .find_map(|re| re.captures(&message))?;
+    // Gemini 的错误信息中字段名与父路径分开给出，父路径为 proto 的 snake_case 名称，
+    // 而发出的请求体使用 camelCase（如 generation_config -> generationConfig）
+    let path = match (captures.get(1), captures.get(2)) {
+        (Some(name), Some(parent)) => {
+            format!("{}.{}", snake_to_camel(parent.as_str()), name.as_str())
+        }
+        (Some(name), None) => name.as_str().to_string(),
+        _ => return None,
+    };
+
+    let segments: Vec<String> = path
+        .replace('[', ".")
+        .replace(']', "")
+        .split('.')
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            if s.chars().all(|c| c.is_ascii_digit()) {
+                "*".to_string()
+            } else {
+                s.to_string()
+            }
+        })
+        .collect();
+    // 不去掉必需字段及其子字段，也不整体去掉数组元素（否则会把对话内容清空）
+    match (segments.first(), segments.last()) {
+        (Some(first), Some(last)) if !ESSENTIAL_FIELDS.contains(&first.as_str()) && last != "*" => {
+            Some(segments.join("."))
+        }
+        _ => None,
+    }
+}
+
+fn snake_to_camel(path: &str) -> String {
+    let mut result = String::with_capacity(path.len());
+    let mut upper = false;
+    for c in path.chars() {
+        match c {
+            '_' => upper = true,
+            '.' | '[' | ']' => {
+                upper = false;
+                result.push(c);
+            }
+            _ if upper => {
+                result.extend(c.to_uppercase());
+                upper = false;
+            }
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rejected_field() {
+        let cases = [
+            (
+                r#"{"error":{"message":"Unrecognized request argument supplied: reasoning_effort"}}"#,
+                Some("reasoning_effort"),
+            ),
+            (
+                r#"{"type":"error","error":{"type":"invalid_request_error","message":"metadata.user_id: Extra inputs are not permitted"}}"#,
+                Some("metadata.user_id"),
+            ),
+            (
+                r#"{"error":{"message":"Invalid JSON payload received. Unknown name \"thinkingConfig\" at 'generation_config': Cannot find field."}}"#,
+                Some("generationConfig.thinkingConfig"),
+            ),
+            // 必需字段的子字段、数组元素不会被去掉
+            (
+                r#"{"type":"error","error":{"type":"invalid_request_error","message":"messages.0.content.1.cache_control: Extra inputs are not permitted"}}"#,
+                None,
+            ),
+            ("Unknown parameter: 'messages.0'", None),
+            ("Unknown parameter: 'messages.0.content'", None),
+            ("Unknown parameter: 'tools[0]'", None),
+            (
+                r#"{"error":{"message":"Invalid JSON payload received. Unknown name \"parts\" at 'contents[0]': Cannot find field."}}"#,
+                None,
+            ),
+            ("Unknown parameter: 'metadata'.", Some("metadata")),
+            (
+                r#"{"detail":"property 'betas' is not allowed"}"#,
+                Some("betas"),
+            ),
+            (
+                r#"{"error":{"message":"Unknown parameter: 'model'"}}"#,
+                None,
+            ),
+            (r#"{"error":{"message":"max_tokens is too large"}}"#, None),
+        ];
+        for (body, expected) in cases {
+            assert_eq!(parse_rejected_field(body).as_deref(), expected, "{body}");
+        }
+    }
+
+    #[test]
+    fn test_gemini_rejected_field_is_stripped_from_body() {
+        use crate::proxy::body_patch::apply_body_patches;
+        use crate::proxy::compat::strip_patches;
+
+        let field = parse_rejected_field(
+            r#"{"error":{"message":"Invalid JSON payload received. Unknown name \"thinkingConfig\" at 'generation_config': Cannot find field."}}"#,
+        )
+        .unwrap();
+        let mut body = serde_json::json!({
+            "contents": [{"role": "user", "parts": [{"text": "hi"}]}],
+            "generationConfig": {"temperature": 0.5, "thinkingConfig": {"thinkingBudget": 1024}}
+        });
+        apply_body_patches(&mut body, &strip_patches(&[field]), "gemini-2.5-pro");
+
+        assert!(body["generationConfig"].get("thinkingConfig").is_none());
+        assert_eq!(body["generationConfig"]["temperature"], 0.5);
+        assert_eq!(body["contents"][0]["parts"][0]["text"], "hi");
+    }
+
+    #[test]
+    fn test_rejected_field_only_for_client_errors() {
+        let error = |status| ProxyError::UpstreamError {
+            status,
+            body: Some("Unknown parameter: 'metadata'".to_string()),
+            retry_after: None,
+        };
+        assert_eq!(error(400).rejected_field().as_deref(), Some("metadata"));
+        assert!(error(500).rejected_field().is_none());
+    }
+}
@@ -24,6 +24,16 @@ pub fn map_proxy_error_to_status(error: &ProxyError) -> u16 {
         // 转发失败/连接失败：502 Bad Gateway
         ProxyError::ForwardFailed(_) => 502,
 
+        // 本地限流：429 Too Many Requests
+        ProxyError::RateLimited(_) => 429,
+
+        // 消费已达上限：402 Payment Required（客户端不会自动重试）
+        ProxyError::SpendLimitExceeded(_) => 402,
+
+        // 入站认证失败 / 来源地址被拒绝
+        ProxyError::AuthError(_) => 401,
+        ProxyError::Forbidden(_) => 403,
+
         // 无可用 Provider：503 Service Unavailable
         ProxyError::NoAvailableProvider => 503,
 
@@ -47,7 +57,7 @@ pub fn map_proxy_error_to_status(error: &ProxyError) -> u16 {
 /// 将 ProxyError 转换为用户友好的错误消息
 pub fn get_error_message(error: &ProxyError) -> String {
     match error {
-        ProxyError::UpstreamError { status, body } => {
+        ProxyError::UpstreamError { status, body, .. } => {
             if let Some(body) = body {
                 format!("上游错误 ({status}): {body}")
             } else {
@@ -57,6 +67,8 @@ pub fn get_error_message(error: &ProxyError) -> String {
         ProxyError::Timeout(msg) => format!("请求超时: {msg}"),
         ProxyError::ForwardFailed(msg) => format!("转发失败: {msg}"),
         ProxyError::NoAvailableProvider => "无可用 Provider".to_string(),
+        ProxyError::RateLimited(msg) => format!("供应商限流: {msg}"),
+        ProxyError::SpendLimitExceeded(msg) => format!("消费已达上限: {msg}"),
         ProxyError::MaxRetriesExceeded => "所有 Provider 都失败，重试耗尽".to_string(),
         ProxyError::ProviderUnhealthy(msg) => format!("Provider 不健康: {msg}"),
         ProxyError::DatabaseError(msg) => format!("数据库错误: {msg}"),
@@ -74,6 +86,7 @@ mod tests {
         let error = ProxyError::UpstreamError {
             status: 401,
             body: Some("Unauthorized".to_string()),
+            retry_after: None,
         };
         assert_eq!(map_proxy_error_to_status(&error), 401);
     }
@@ -101,6 +114,7 @@ mod tests {
         let error = ProxyError::UpstreamError {
             status: 500,
             body: Some("Internal Server Error".to_string()),
+            retry_after: None,
         };
         let msg = get_error_message(&error);
         assert!(msg.contains("上游错误"));
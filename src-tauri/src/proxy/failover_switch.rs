@@ -6,6 +6,8 @@
 //! - 托盘菜单更新
 //! - 前端事件发射
 //! - Live 备份更新
+//!
+//! 定时切换（`schedule` 模块）同样通过这里切换，事件中的 `source` 区分来源。
 
 use crate::database::Database;
 use crate::error::AppError;
@@ -47,6 +49,19 @@ impl FailoverSwitchManager {
         app_type: &str,
         provider_id: &str,
         provider_name: &str,
+    ) -> Result<bool, AppError> {
+        self.try_switch_from(app_handle, app_type, provider_id, provider_name, "failover")
+            .await
+    }
+
+    /// 尝试执行供应商切换，`source` 写入 `provider-switched` 事件（如 failover / schedule）
+    pub async fn try_switch_from(
+        &self,
+        app_handle: Option<&tauri::AppHandle>,
+        app_type: &str,
+        provider_id: &str,
+        provider_name: &str,
+        source: &str,
     ) -> Result<bool, AppError> {
         let switch_key = format!("{app_type}:{provider_id}");
 
@@ -62,7 +77,7 @@ impl FailoverSwitchManager {
 
         // 执行切换（确保最后清理 pending 标记）
         let result = self
-            .do_switch(app_handle, app_type, provider_id, provider_name)
+            .do_switch(app_handle, app_type, provider_id, provider_name, source)
             .await;
 
         // 清理 pending 标记
@@ -80,6 +95,7 @@ impl FailoverSwitchManager {
         app_type: &str,
         provider_id: &str,
         provider_name: &str,
+        source: &str,
     ) -> Result<bool, AppError> {
         log::info!("[Failover] 开始切换供应商: {app_type} -> {provider_name} ({provider_id})");
 
@@ -91,11 +107,9 @@ impl FailoverSwitchManager {
             .map_err(|_| AppError::Message(format!("无效的应用类型: {app_type}")))?;
         crate::settings::set_current_provider(&app_type_enum, Some(provider_id))?;
 
-        // 3. 更新托盘菜单和发射事件
+        // 3. 更新 Live 备份（确保代理停止时恢复正确配置）
         if let Some(app) = app_handle {
-            // 更新托盘菜单
             if let Some(app_state) = app.try_state::<crate::store::AppState>() {
-                // 更新 Live 备份（确保代理停止时恢复正确配置）
                 if let Ok(Some(provider)) = self.db.get_provider_by_id(provider_id, app_type) {
                     if let Err(e) = app_state
                         .proxy_service
@@ -105,30 +119,63 @@ impl FailoverSwitchManager {
                         log::warn!("[Failover] 更新 Live 备份失败: {e}");
                     }
                 }
-
-                // 重建托盘菜单
-                if let Ok(new_menu) = crate::tray::create_tray_menu(app, app_state.inner()) {
-                    if let Some(tray) = app.tray_by_id("main") {
-                        if let Err(e) = tray.set_menu(Some(new_menu)) {
-                            log::error!("[Failover] 更新托盘菜单失败: {e}");
-                        }
-                    }
-                }
-            }
-
-            // 发射事件到前端
-            let event_data = serde_json::json!({
-                "appType": app_type,
-                "providerId": provider_id,
-                "source": "failover"  // 标识来源是故障转移
-            });
-            if let Err(e) = app.emit("provider-switched", event_data) {
-                log::error!("[Failover] 发射供应商切换事件失败: {e}");
             }
         }
 
+        // 4. 更新托盘菜单和发射事件
+        self.emit_switched(app_handle, app_type, provider_id, source);
+
         log::info!("[Failover] 供应商切换完成: {app_type} -> {provider_name} ({provider_id})");
 
         Ok(true)
     }
+
+    /// 当前供应商未变但列表顺序变化（如定时重排故障转移队列）时，刷新托盘菜单并通知前端
+    pub async fn notify_providers_changed(
+        &self,
+        app_handle: Option<&tauri::AppHandle>,
+        app_type: &str,
+        source: &str,
+    ) {
+        let current = self
+            .db
+            .get_current_provider(app_type)
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        self.emit_switched(app_handle, app_type, &current, source);
+    }
+
+    fn emit_switched(
+        &self,
+        app_handle: Option<&tauri::AppHandle>,
+        app_type: &str,
+        provider_id: &str,
+        source: &str,
+    ) {
+        let Some(app) = app_handle else {
+            return;
+        };
+
+        // 重建托盘菜单
+        if let Some(app_state) = app.try_state::<crate::store::AppState>() {
+            if let Ok(new_menu) = crate::tray::create_tray_menu(app, app_state.inner()) {
+                if let Some(tray) = app.tray_by_id("main") {
+                    if let Err(e) = tray.set_menu(Some(new_menu)) {
+                        log::error!("[Failover] 更新托盘菜单失败: {e}");
+                    }
+                }
+            }
+        }
+
+        // 发射事件到前端（source 标识切换来源）
+        let event_data = serde_json::json!({
+            "appType": app_type,
+            "providerId": provider_id,
+            "source": source
+        });
+        if let Err(e) = app.emit("provider-switched", event_data) {
+            log::error!("[Failover] 发射供应商切换事件失败: {e}");
+        }
+    }
 }
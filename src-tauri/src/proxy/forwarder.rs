@@ -3,16 +3,29 @@
 //! 负责将请求转发到上游Provider，支持重试和故障转移
 
 use super::{
+    body_patch::apply_body_patches,
+    compat::strip_patches,
+    cooldown::{cooldown_for, parse_cooldown},
     error::*,
+    error_mapper::{get_error_message, map_proxy_error_to_status},
     failover_switch::FailoverSwitchManager,
+    header_rules::{apply_header_rules, TemplateVars, UpstreamRequestInfo},
+    http_client::{self, ClientOptions},
+    metrics::ProxyMetrics,
     provider_router::ProviderRouter,
-    providers::{get_adapter, ProviderAdapter},
+    providers::{get_adapter, AuthInfo, ProviderAdapter, ProviderType},
+    rate_limiter::{estimate_input_tokens, RateLimitPermit, RateLimits},
+    session::request_session_id,
     types::ProxyStatus,
+    usage::logger::UsageLogger,
     ProxyError,
 };
-use crate::{app_config::AppType, provider::Provider};
+use crate::{app_config::AppType, database::Database, provider::Provider};
+use futures::future::{self, Either};
+use futures::StreamExt;
 use reqwest::{Client, Response};
 use serde_json::Value;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
@@ -27,8 +40,25 @@ pub struct ForwardError {
     pub provider: Option<Provider>,
 }
 
+/// 对冲请求配置
+///
+/// 流式请求在首个供应商超过 `delay` 仍未返回首字节时，并发请求队列中的下一个供应商，
+/// 先返回首字节者胜出，落败者被取消并记入请求日志（被取消不代表供应商异常，不计入熔断器）。
+pub struct HedgeConfig {
+    pub delay: Duration,
+    pub db: Arc<Database>,
+    pub metrics: Arc<ProxyMetrics>,
+    /// 请求 ID（落败方的请求日志以此为前缀，便于与胜出方关联）
+    pub request_id: String,
+    /// 会话 ID（落败方的请求日志归属到同一会话）
+    pub session_id: Option<String>,
+    /// 请求模型（用于记录落败方的请求日志）
+    pub request_model: String,
+}
+
 pub struct RequestForwarder {
-    client: Client,
+    /// 上游客户端参数（与供应商出站设置一起决定使用哪个缓存客户端）
+    client_options: ClientOptions,
     /// 共享的 ProviderRouter（持有熔断器状态）
     router: Arc<ProviderRouter>,
     /// 单个 Provider 内的最大重试次数
@@ -41,6 +71,8 @@ pub struct RequestForwarder {
     app_handle: Option<tauri::AppHandle>,
     /// 请求开始时的"当前供应商 ID"（用于判断是否需要同步 UI/托盘）
     current_provider_id_at_start: String,
+    /// 对冲请求配置（None 表示未启用）
+    hedge: Option<HedgeConfig>,
 }
 
 impl RequestForwarder {
@@ -61,21 +93,16 @@ impl RequestForwarder {
         // 参考 Claude Code Hub 的 undici 全局超时设计
         const GLOBAL_TIMEOUT_SECS: u64 = 1800;
 
-        let mut client_builder = Client::builder();
-        if non_streaming_timeout > 0 {
+        let client_options = if non_streaming_timeout > 0 {
             // 使用配置的非流式超时
-            client_builder = client_builder.timeout(Duration::from_secs(non_streaming_timeout));
+            ClientOptions::with_timeout(Duration::from_secs(non_streaming_timeout))
         } else {
             // 禁用超时时使用全局超时作为保底
-            client_builder = client_builder.timeout(Duration::from_secs(GLOBAL_TIMEOUT_SECS));
-        }
-
-        let client = client_builder
-            .build()
-            .expect("Failed to create HTTP client");
+            ClientOptions::with_timeout(Duration::from_secs(GLOBAL_TIMEOUT_SECS))
+        };
 
         Self {
-            client,
+            client_options,
             router,
             max_retries,
             status,
@@ -83,15 +110,23 @@ impl RequestForwarder {
             failover_manager,
             app_handle,
             current_provider_id_at_start,
+            hedge: None,
         }
     }
 
+    /// 启用对冲请求
+    pub fn with_hedging(mut self, hedge: HedgeConfig) -> Self {
+        self.hedge = Some(hedge);
+        self
+    }
+
     /// 对单个 Provider 执行请求（带重试）
     ///
     /// 在同一个 Provider 上最多重试 max_retries 次，使用指数退避
     async fn forward_with_provider_retry(
         &self,
         provider: &Provider,
+        app_type_str: &str,
         endpoint: &str,
         body: &Value,
         headers: &axum::http::HeaderMap,
@@ -114,7 +149,7 @@ impl RequestForwarder {
             }
 
             match self
-                .forward(provider, endpoint, body, headers, adapter)
+                .forward(provider, app_type_str, endpoint, body, headers, adapter)
                 .await
             {
                 Ok(response) => return Ok(response),
@@ -175,32 +210,95 @@ impl RequestForwarder {
         let mut last_error = None;
         let mut last_provider = None;
         let mut attempted_providers = 0usize;
+        // 因本地限流或上游限流冷却被跳过的供应商（全部被跳过时返回 429）
+        let mut rate_limited: Vec<String> = Vec::new();
+
+        // 仅在有供应商配置了限流时估算输入 Token
+        let estimated_tokens = if providers
+            .iter()
+            .any(|p| RateLimits::from_provider(p).is_some())
+        {
+            estimate_input_tokens(&body)
+        } else {
+            0
+        };
 
         // 单 Provider 场景下跳过熔断器检查（故障转移关闭时）
         let bypass_circuit_breaker = providers.len() == 1;
 
-        // 依次尝试每个供应商
-        for provider in providers.iter() {
-            // 发起请求前先获取熔断器放行许可（HalfOpen 会占用探测名额）
-            // 单 Provider 场景下跳过此检查，避免熔断器阻塞所有请求
-            let (allowed, used_half_open_permit) = if bypass_circuit_breaker {
-                (true, false)
-            } else {
-                let permit = self
-                    .router
-                    .allow_provider_request(&provider.id, app_type_str)
-                    .await;
-                (permit.allowed, permit.used_half_open_permit)
-            };
+        // 对冲仅用于流式请求：非流式响应的首字节即完整结果，提前对冲只会成倍放大上游开销
+        let is_streaming = body
+            .get("stream")
+            .and_then(|s| s.as_bool())
+            .unwrap_or(false)
+            || endpoint.contains("streamGenerateContent");
+        let hedge_delay = self
+            .hedge
+            .as_ref()
+            .filter(|_| is_streaming && providers.len() > 1)
+            .map(|hedge| hedge.delay);
+
+        // 依次尝试每个供应商（对冲请求会提前消耗队列中的下一个供应商）
+        let mut next_index = 0usize;
+        while next_index < providers.len() {
+            let provider = &providers[next_index];
+            next_index += 1;
+
+            // 上游限流冷却中的供应商直接跳过（冷却与熔断器独立，单 Provider 场景同样生效）
+            if let Some(remaining) = self
+                .router
+                .cooldown_remaining(&provider.id, app_type_str)
+                .await
+            {
+                log::info!(
+                    "[{}] Provider {} 上游限流冷却中（剩余 {}s），尝试下一个供应商",
+                    app_type_str,
+                    provider.name,
+                    remaining.as_secs() + 1
+                );
+                rate_limited.push(format!(
+                    "{} 冷却中（剩余 {}s）",
+                    provider.name,
+                    remaining.as_secs() + 1
+                ));
+                continue;
+            }
 
-            if !allowed {
+            // 先获取熔断器放行许可（HalfOpen 会占用探测名额），熔断中的供应商不必排队等待限流额度
+            // 单 Provider 场景下跳过此检查，避免熔断器阻塞所有请求
+            let Some(used_half_open_permit) = self
+                .acquire_permit(provider, app_type_str, bypass_circuit_breaker)
+                .await
+            else {
                 log::debug!(
                     "[{}] Provider {} 熔断器拒绝本次请求，跳过",
                     app_type_str,
                     provider.name
                 );
                 continue;
-            }
+            };
+
+            // 再获取限流许可：额度不足时排队等待，等待超过截止时间则归还熔断器名额并溢出到下一个供应商
+            let rate_limit_permit = match self
+                .router
+                .acquire_rate_limit(provider, app_type_str, estimated_tokens, true)
+                .await
+            {
+                Ok(permit) => permit,
+                Err(rejection) => {
+                    self.router
+                        .release_permit(&provider.id, app_type_str, used_half_open_permit)
+                        .await;
+                    log::info!(
+                        "[{}] Provider {} 已达到限流上限（{}），尝试下一个供应商",
+                        app_type_str,
+                        provider.name,
+                        rejection
+                    );
+                    rate_limited.push(format!("{} {}", provider.name, rejection));
+                    continue;
+                }
+            };
 
             attempted_providers += 1;
 
@@ -224,11 +322,44 @@ impl RequestForwarder {
 
             let start = Instant::now();
 
-            // 转发请求（带单 Provider 内重试）
-            match self
-                .forward_with_provider_retry(provider, endpoint, &body, &headers, adapter.as_ref())
-                .await
-            {
+            // 转发请求（带单 Provider 内重试）；对冲时结果可能来自队列中的下一个供应商
+            let (provider, used_half_open_permit, result) = match hedge_delay {
+                Some(delay) if next_index < providers.len() => {
+                    self.forward_hedged(
+                        app_type_str,
+                        provider,
+                        used_half_open_permit,
+                        rate_limit_permit,
+                        &providers,
+                        &mut next_index,
+                        delay,
+                        estimated_tokens,
+                        endpoint,
+                        &body,
+                        &headers,
+                        adapter.as_ref(),
+                        bypass_circuit_breaker,
+                    )
+                    .await
+                }
+                _ => {
+                    let result = self
+                        .attempt(
+                            provider,
+                            app_type_str,
+                            endpoint,
+                            &body,
+                            &headers,
+                            adapter.as_ref(),
+                            rate_limit_permit,
+                            false,
+                        )
+                        .await;
+                    (provider, used_half_open_permit, result)
+                }
+            };
+
+            match result {
                 Ok(response) => {
                     let latency = start.elapsed().as_millis() as u64;
 
@@ -309,20 +440,9 @@ impl RequestForwarder {
                 Err(e) => {
                     let latency = start.elapsed().as_millis() as u64;
 
-                    // 失败：记录失败并更新熔断器
-                    if let Err(record_err) = self
-                        .router
-                        .record_result(
-                            &provider.id,
-                            app_type_str,
-                            used_half_open_permit,
-                            false,
-                            Some(e.to_string()),
-                        )
-                        .await
-                    {
-                        log::warn!("Failed to record failure: {record_err}");
-                    }
+                    // 失败：记录失败并更新熔断器（上游限流改为进入冷却）
+                    self.record_failure(provider, app_type_str, used_half_open_permit, &e)
+                        .await;
 
                     // 分类错误
                     let category = self.categorize_proxy_error(&e);
@@ -377,6 +497,24 @@ impl RequestForwarder {
             }
         }
 
+        if attempted_providers == 0 && !rate_limited.is_empty() {
+            // 没有发出任何请求，且至少一个供应商因限流被跳过
+            let message = rate_limited.join("; ");
+            {
+                let mut status = self.status.write().await;
+                status.failed_requests += 1;
+                status.last_error = Some(format!("所有供应商暂时不可用（限流）: {message}"));
+                if status.total_requests > 0 {
+                    status.success_rate =
+                        (status.success_requests as f32 / status.total_requests as f32) * 100.0;
+                }
+            }
+            return Err(ForwardError {
+                error: ProxyError::RateLimited(message),
+                provider: None,
+            });
+        }
+
         if attempted_providers == 0 {
             // providers 列表非空，但全部被熔断器拒绝（典型：HalfOpen 探测名额被占用）
             {
@@ -417,150 +555,402 @@ impl RequestForwarder {
         })
     }
 
-    /// 转发单个请求（使用适配器）
-    async fn forward(
+    /// 获取熔断器放行许可
+    ///
+    /// 返回是否占用了 HalfOpen 探测名额；被熔断器拒绝时返回 None
+    async fn acquire_permit(
         &self,
         provider: &Provider,
+        app_type_str: &str,
+        bypass_circuit_breaker: bool,
+    ) -> Option<bool> {
+        if bypass_circuit_breaker {
+            return Some(false);
+        }
+        let permit = self
+            .router
+            .allow_provider_request(&provider.id, app_type_str)
+            .await;
+        permit.allowed.then_some(permit.used_half_open_permit)
+    }
+
+    /// 对单个 Provider 发起请求（带重试），期间计入在途请求数
+    ///
+    /// 在途计数与限流许可随响应体一起释放（流式响应在流结束时释放），请求失败时立即释放。
+    /// `wait_first_chunk` 为 true 时等到响应体首个数据块到达才返回，用于对冲请求判定胜负
+    #[allow(clippy::too_many_arguments)]
+    async fn attempt(
+        &self,
+        provider: &Provider,
+        app_type_str: &str,
         endpoint: &str,
         body: &Value,
         headers: &axum::http::HeaderMap,
         adapter: &dyn ProviderAdapter,
+        rate_limit_permit: Option<RateLimitPermit>,
+        wait_first_chunk: bool,
     ) -> Result<Response, ProxyError> {
-        // 使用适配器提取 base_url
-        let base_url = adapter.extract_base_url(provider)?;
-        log::info!("[{}] base_url: {}", adapter.name(), base_url);
-
-        // 检查是否需要格式转换
-        let needs_transform = adapter.needs_transform(provider);
-
-        let effective_endpoint =
-            if needs_transform && adapter.name() == "Claude" && endpoint == "/v1/messages" {
-                "/v1/chat/completions"
-            } else {
-                endpoint
-            };
+        let in_flight = self.router.begin_request(&provider.id, app_type_str).await;
+        let response = self
+            .forward_with_provider_retry(provider, app_type_str, endpoint, body, headers, adapter)
+            .await?;
+        let response = hold_until_body_end(response, (in_flight, rate_limit_permit));
 
-        // 使用适配器构建 URL
-        let url = adapter.build_url(&base_url, effective_endpoint);
+        if wait_first_chunk {
+            await_first_chunk(response).await
+        } else {
+            Ok(response)
+        }
+    }
 
-        // 记录原始请求 JSON
-        log::info!(
-            "[{}] ====== 请求开始 ======\n>>> 原始请求 JSON:\n{}",
-            adapter.name(),
-            serde_json::to_string_pretty(body).unwrap_or_else(|_| body.to_string())
+    /// 对冲转发
+    ///
+    /// 首个供应商超过 `delay` 仍未返回首字节时，并发请求队列中下一个熔断器放行的供应商。
+    /// 先成功返回首字节者胜出，另一方被取消（drop 未完成的请求即断开上游连接）；
+    /// 先失败的一方记为失败，继续等待另一方的结果。
+    /// 返回本轮最终结果所属的供应商、其是否占用 HalfOpen 许可以及请求结果。
+    #[allow(clippy::too_many_arguments)]
+    async fn forward_hedged<'p>(
+        &self,
+        app_type_str: &str,
+        primary: &'p Provider,
+        primary_permit: bool,
+        primary_rate_limit: Option<RateLimitPermit>,
+        providers: &'p [Provider],
+        next_index: &mut usize,
+        delay: Duration,
+        estimated_tokens: u32,
+        endpoint: &str,
+        body: &Value,
+        headers: &axum::http::HeaderMap,
+        adapter: &dyn ProviderAdapter,
+        bypass_circuit_breaker: bool,
+    ) -> (&'p Provider, bool, Result<Response, ProxyError>) {
+        let primary_start = Instant::now();
+        let primary_fut = self.attempt(
+            primary,
+            app_type_str,
+            endpoint,
+            body,
+            headers,
+            adapter,
+            primary_rate_limit,
+            true,
         );
+        tokio::pin!(primary_fut);
 
-        // 应用模型映射（独立于格式转换）
-        let (mapped_body, _original_model, mapped_model) =
-            super::model_mapper::apply_model_mapping(body.clone(), provider);
-
-        if let Some(ref mapped) = mapped_model {
-            log::info!(
-                "[{}] >>> 模型映射后的请求 JSON:\n{}",
-                adapter.name(),
-                serde_json::to_string_pretty(&mapped_body).unwrap_or_default()
-            );
-            log::info!("[{}] 模型已映射到: {}", adapter.name(), mapped);
+        if let Ok(result) = tokio::time::timeout(delay, &mut primary_fut).await {
+            return (primary, primary_permit, result);
         }
 
-        // 转换请求体（如果需要）
-        let request_body = if needs_transform {
-            log::info!("[{}] 转换请求格式 (Anthropic → OpenAI)", adapter.name());
-            let transformed = adapter.transform_request(mapped_body, provider)?;
-            log::info!(
-                "[{}] >>> 转换后的请求 JSON:\n{}",
-                adapter.name(),
-                serde_json::to_string_pretty(&transformed).unwrap_or_default()
-            );
-            transformed
-        } else {
-            mapped_body
+        // 在剩余队列中寻找限流与熔断器均放行的对冲目标（对冲不排队等待限流额度）
+        let mut secondary = None;
+        while *next_index < providers.len() {
+            let candidate = &providers[*next_index];
+            *next_index += 1;
+            if self
+                .router
+                .cooldown_remaining(&candidate.id, app_type_str)
+                .await
+                .is_some()
+            {
+                continue;
+            }
+            let Some(permit) = self
+                .acquire_permit(candidate, app_type_str, bypass_circuit_breaker)
+                .await
+            else {
+                continue;
+            };
+            match self
+                .router
+                .acquire_rate_limit(candidate, app_type_str, estimated_tokens, false)
+                .await
+            {
+                Ok(rate_limit) => {
+                    secondary = Some((candidate, permit, rate_limit));
+                    break;
+                }
+                Err(_) => {
+                    self.router
+                        .release_permit(&candidate.id, app_type_str, permit)
+                        .await;
+                }
+            }
+        }
+        let Some((secondary, secondary_permit, secondary_rate_limit)) = secondary else {
+            return (primary, primary_permit, primary_fut.await);
         };
 
         log::info!(
-            "[{}] 转发请求: {} -> {}",
-            adapter.name(),
-            provider.name,
-            url
+            "[{}] Provider {} 超过 {}ms 未返回首字节，对冲请求 Provider {}",
+            app_type_str,
+            primary.name,
+            delay.as_millis(),
+            secondary.name
         );
 
-        // 构建请求
-        let mut request = self.client.post(&url);
-
-        // 只透传必要的 Headers（白名单模式）
-        let allowed_headers = [
-            "accept",
-            "user-agent",
-            "x-request-id",
-            "x-stainless-arch",
-            "x-stainless-lang",
-            "x-stainless-os",
-            "x-stainless-package-version",
-            "x-stainless-runtime",
-            "x-stainless-runtime-version",
-        ];
+        let secondary_start = Instant::now();
+        let secondary_fut = self.attempt(
+            secondary,
+            app_type_str,
+            endpoint,
+            body,
+            headers,
+            adapter,
+            secondary_rate_limit,
+            true,
+        );
+        tokio::pin!(secondary_fut);
+
+        let primary_side = (primary, primary_permit, primary_start);
+        let secondary_side = (secondary, secondary_permit, secondary_start);
+        let (finished, pending, result, pending_fut) =
+            match future::select(primary_fut, secondary_fut).await {
+                Either::Left((result, pending_fut)) => {
+                    (primary_side, secondary_side, result, pending_fut)
+                }
+                Either::Right((result, pending_fut)) => {
+                    (secondary_side, primary_side, result, pending_fut)
+                }
+            };
 
-        for (key, value) in headers {
-            let key_str = key.as_str().to_lowercase();
-            if allowed_headers.contains(&key_str.as_str()) {
-                request = request.header(key, value);
+        match result {
+            // 未完成的一方在函数返回时随栈上的 future 一起被 drop，上游连接随之断开
+            Ok(response) => {
+                log::info!(
+                    "[{}] 对冲请求由 Provider {} 胜出，已取消 Provider {}",
+                    app_type_str,
+                    finished.0.name,
+                    pending.0.name
+                );
+                self.record_hedge_loser(app_type_str, pending, None).await;
+                (finished.0, finished.1, Ok(response))
+            }
+            Err(e) => {
+                log::warn!(
+                    "[{}] 对冲请求中 Provider {} 失败: {}，继续等待 Provider {}",
+                    app_type_str,
+                    finished.0.name,
+                    e,
+                    pending.0.name
+                );
+                self.record_hedge_loser(app_type_str, finished, Some(&e))
+                    .await;
+                (pending.0, pending.1, pending_fut.await)
             }
         }
+    }
 
-        // 确保 Content-Type 是 json
-        request = request.header("Content-Type", "application/json");
+    /// 记录对冲落败方：先失败的一方计入熔断器，被取消的一方只归还 HalfOpen 名额；两者都写入请求日志
+    async fn record_hedge_loser(
+        &self,
+        app_type_str: &str,
+        (provider, used_half_open_permit, started): (&Provider, bool, Instant),
+        error: Option<&ProxyError>,
+    ) {
+        let reason = match error {
+            Some(e) => {
+                self.record_failure(provider, app_type_str, used_half_open_permit, e)
+                    .await;
+                e.to_string()
+            }
+            None => {
+                // 被取消的一方只是慢于对手，不计入熔断器，仅归还 HalfOpen 名额
+                self.router
+                    .release_permit(&provider.id, app_type_str, used_half_open_permit)
+                    .await;
+                "对冲请求落败，已取消".to_string()
+            }
+        };
 
-        // 使用适配器添加认证头
-        if let Some(auth) = adapter.extract_auth(provider) {
-            log::debug!(
-                "[{}] 使用认证: {:?} (key: {})",
-                adapter.name(),
-                auth.strategy,
-                auth.masked_key()
-            );
-            request = adapter.add_auth_headers(request, &auth);
-        } else {
-            log::error!(
-                "[{}] 未找到 API Key！Provider: {}",
-                adapter.name(),
-                provider.name
-            );
+        let Some(hedge) = &self.hedge else {
+            return;
+        };
+        // 499：请求被代理主动取消
+        let status_code = error.map(map_proxy_error_to_status).unwrap_or(499);
+        let error_message = error.map(get_error_message).unwrap_or(reason);
+        // request_id 是请求日志主键，胜出方使用原 ID，落败方加后缀
+        let request_id = format!("{}-hedge-{}", hedge.request_id, provider.id);
+        let provider_type = AppType::from_str(app_type_str).ok().map(|app_type| {
+            ProviderType::from_app_type_and_config(&app_type, provider)
+                .as_str()
+                .to_string()
+        });
+
+        if let Err(e) = UsageLogger::new(&hedge.db)
+            .with_metrics(&hedge.metrics)
+            .log_error_with_context(
+                request_id,
+                provider.id.clone(),
+                app_type_str.to_string(),
+                hedge.request_model.clone(),
+                status_code,
+                error_message,
+                started.elapsed().as_millis() as u64,
+                true,
+                hedge.session_id.clone(),
+                provider_type,
+            )
+        {
+            log::warn!("记录对冲请求日志失败: {e}");
+        }
+    }
+
+    /// 记录请求失败
+    ///
+    /// 上游限流（429/529、带 Retry-After 的 503）不计入熔断器，改为让供应商进入冷却期
+    async fn record_failure(
+        &self,
+        provider: &Provider,
+        app_type_str: &str,
+        used_half_open_permit: bool,
+        error: &ProxyError,
+    ) {
+        if let Some(cooldown) = cooldown_for(error) {
+            self.router
+                .record_throttled(
+                    provider,
+                    app_type_str,
+                    used_half_open_permit,
+                    map_proxy_error_to_status(error),
+                    cooldown,
+                )
+                .await;
+            return;
         }
 
-        // 发送请求
-        log::info!("[{}] 发送请求到: {}", adapter.name(), url);
-        let response = request.json(&request_body).send().await.map_err(|e| {
-            log::error!("[{}] 请求失败: {}", adapter.name(), e);
-            if e.is_timeout() {
-                ProxyError::Timeout(format!("请求超时: {e}"))
-            } else if e.is_connect() {
-                ProxyError::ForwardFailed(format!("连接失败: {e}"))
-            } else {
-                ProxyError::ForwardFailed(e.to_string())
+        if let Err(e) = self
+            .router
+            .record_result(
+                &provider.id,
+                app_type_str,
+                used_half_open_permit,
+                false,
+                Some(error.to_string()),
+            )
+            .await
+        {
+            log::warn!("Failed to record failure: {e}");
+        }
+    }
+
+    /// 转发单个请求（使用适配器）
+    ///
+    /// 供应商配置了 Key 池时按策略选择 Key，Key 因认证失败或额度耗尽被停用后换下一个 Key 重试
+    async fn forward(
+        &self,
+        provider: &Provider,
+        app_type_str: &str,
+        endpoint: &str,
+        body: &Value,
+        headers: &axum::http::HeaderMap,
+        adapter: &dyn ProviderAdapter,
+    ) -> Result<Response, ProxyError> {
+        // 按供应商出站设置（上游代理、根证书）选择客户端；设置有误时视为该供应商转发失败
+        let client = http_client::client_for(self.client_options, provider.outbound())
+            .map_err(ProxyError::ForwardFailed)?;
+        let auth = adapter.extract_auth(provider);
+        let session_id = request_session_id(body, headers);
+        let mut last_error = None;
+
+        // 每轮要么返回，要么停用一个 Key，循环次数不超过 Key 的数量
+        loop {
+            let api_key = match self.router.key_pools().select(
+                provider,
+                app_type_str,
+                auth.as_ref(),
+                session_id.as_deref(),
+            ) {
+                Ok(api_key) => api_key,
+                Err(e) => return Err(last_error.unwrap_or(e)),
+            };
+
+            let result = self
+                .forward_with_key(
+                    &client,
+                    provider,
+                    app_type_str,
+                    endpoint,
+                    body,
+                    headers,
+                    adapter,
+                    api_key.as_deref(),
+                )
+                .await;
+            match (api_key, result) {
+                (Some(api_key), Err(e))
+                    if self.router.key_pools().report_failure(
+                        provider,
+                        app_type_str,
+                        &api_key,
+                        &e,
+                    ) =>
+                {
+                    last_error = Some(e);
+                }
+                (_, result) => return result,
             }
-        })?;
+        }
+    }
 
-        // 检查响应状态
-        let status = response.status();
-        log::info!("[{}] 响应状态: {}", adapter.name(), status);
+    /// 使用指定 Key 转发（None 表示使用供应商自身的 Key）
+    ///
+    /// 自动去掉该供应商已知不支持的字段；上游因新的不支持字段拒绝请求时，去掉该字段后重试一次
+    #[allow(clippy::too_many_arguments)]
+    async fn forward_with_key(
+        &self,
+        client: &Client,
+        provider: &Provider,
+        app_type_str: &str,
+        endpoint: &str,
+        body: &Value,
+        headers: &axum::http::HeaderMap,
+        adapter: &dyn ProviderAdapter,
+        api_key: Option<&str>,
+    ) -> Result<Response, ProxyError> {
+        let mut stripped = self.router.incompatible_fields(&provider.id, app_type_str);
 
-        if status.is_success() {
-            Ok(response)
-        } else {
-            let status_code = status.as_u16();
-            let body_text = response.text().await.ok();
-            log::error!(
-                "[{}] 上游错误 ({}): {:?}",
-                adapter.name(),
-                status_code,
-                body_text
-            );
+        let options = UpstreamOptions {
+            stripped_fields: &stripped,
+            api_key,
+        };
+        let error = match send_upstream_request(
+            client, provider, endpoint, body, headers, adapter, &options,
+        )
+        .await
+        {
+            Ok(response) => return Ok(response),
+            Err(error) => error,
+        };
+        let Some(field) = error.rejected_field().filter(|f| !stripped.contains(f)) else {
+            return Err(error);
+        };
+
+        log::warn!(
+            "[{}] Provider {} 拒绝了字段 {field}，去掉该字段后重试",
+            adapter.name(),
+            provider.name
+        );
+        stripped.push(field.clone());
+        let options = UpstreamOptions {
+            stripped_fields: &stripped,
+            api_key,
+        };
+        let retried =
+            send_upstream_request(client, provider, endpoint, body, headers, adapter, &options)
+                .await;
 
-            Err(ProxyError::UpstreamError {
-                status: status_code,
-                body: body_text,
-            })
+        // 重试成功，或上游不再因该字段拒绝时，才认定该字段不受支持
+        let resolved = match &retried {
+            Ok(_) => true,
+            Err(e) => e.rejected_field().is_some_and(|f| f != field),
+        };
+        if resolved {
+            self.router
+                .record_incompatibility(provider, app_type_str, &field, &error);
         }
+        retried
     }
 
     /// 分类ProxyError
@@ -570,14 +960,16 @@ impl RequestForwarder {
     /// 设计原则：既然用户配置了多个供应商，就应该让所有供应商都尝试一遍。
     /// 只有明确是客户端中断的情况才不重试。
     fn should_retry_same_provider(&self, error: &ProxyError) -> bool {
+        // 上游限流：同一 Provider 内重试只会再次被限流，交给冷却与 failover
+        if cooldown_for(error).is_some() {
+            return false;
+        }
         match error {
             // 网络类错误：短暂抖动时同一 Provider 内重试有意义
             ProxyError::Timeout(_) => true,
             ProxyError::ForwardFailed(_) => true,
             // 上游 HTTP 错误：只对“可能瞬态”的状态码做同 Provider 重试（其余交给 failover）
-            ProxyError::UpstreamError { status, .. } => {
-                *status == 408 || *status == 429 || *status >= 500
-            }
+            ProxyError::UpstreamError { status, .. } => *status == 408 || *status >= 500,
             _ => false,
         }
     }
@@ -605,3 +997,278 @@ impl RequestForwarder {
         }
     }
 }
+
+/// 单次上游请求的附加选项
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct UpstreamOptions<'a> {
+    /// 该供应商不支持、需要从最终请求体中去掉的字段
+    pub stripped_fields: &'a [String],
+    /// Key 池选中的 API Key（None 使用供应商自身的 Key）
+    pub api_key: Option<&'a str>,
+}
+
+/// 向单个 Provider 发送请求（模型映射、格式转换、认证、请求头白名单）
+///
+/// 不涉及重试、熔断器和故障转移，供转发器与请求重放共用。
+pub(crate) async fn send_upstream_request(
+    client: &Client,
+    provider: &Provider,
+    endpoint: &str,
+    body: &Value,
+    headers: &axum::http::HeaderMap,
+    adapter: &dyn ProviderAdapter,
+    options: &UpstreamOptions<'_>,
+) -> Result<Response, ProxyError> {
+    // 使用适配器提取 base_url
+    let base_url = adapter.extract_base_url(provider)?;
+    log::info!("[{}] base_url: {}", adapter.name(), base_url);
+
+    // 检查是否需要格式转换
+    let needs_transform = adapter.needs_transform(provider);
+
+    // 记录原始请求 JSON
+    log::info!(
+        "[{}] ====== 请求开始 ======\n>>> 原始请求 JSON:\n{}",
+        adapter.name(),
+        serde_json::to_string_pretty(body).unwrap_or_else(|_| body.to_string())
+    );
+
+    // 应用模型映射（独立于格式转换）
+    let (mapped_body, _original_model, mapped_model) =
+        super::model_mapper::apply_model_mapping(body.clone(), provider);
+
+    if let Some(ref mapped) = mapped_model {
+        log::info!(
+            "[{}] >>> 模型映射后的请求 JSON:\n{}",
+            adapter.name(),
+            serde_json::to_string_pretty(&mapped_body).unwrap_or_default()
+        );
+        log::info!("[{}] 模型已映射到: {}", adapter.name(), mapped);
+    }
+
+    // 使用适配器计算上游端点并构建 URL（格式转换时端点可能改变）
+    let effective_endpoint = adapter.upstream_endpoint(endpoint, provider, &mapped_body);
+    let url = adapter.build_url(&base_url, &effective_endpoint);
+
+    // 转换请求体（如果需要）
+    let mut request_body = if needs_transform {
+        log::info!("[{}] 转换请求格式", adapter.name());
+        let transformed = adapter.transform_request(mapped_body, provider)?;
+        log::info!(
+            "[{}] >>> 转换后的请求 JSON:\n{}",
+            adapter.name(),
+            serde_json::to_string_pretty(&transformed).unwrap_or_default()
+        );
+        transformed
+    } else {
+        mapped_body
+    };
+
+    // 规则匹配与模板变量使用映射后的模型
+    let model = mapped_model
+        .as_deref()
+        .or_else(|| body.get("model").and_then(|m| m.as_str()))
+        .unwrap_or_default();
+
+    // 执行供应商的请求体改写规则（作用于最终发往上游的请求体）
+    apply_body_patches(&mut request_body, provider.body_patches(), model);
+    if !options.stripped_fields.is_empty() {
+        apply_body_patches(
+            &mut request_body,
+            &strip_patches(options.stripped_fields),
+            model,
+        );
+    }
+
+    log::info!(
+        "[{}] 转发请求: {} -> {}",
+        adapter.name(),
+        provider.name,
+        url
+    );
+
+    // 构建请求
+    let mut request = client.post(&url);
+
+    // 只透传必要的 Headers（白名单模式）
+    let allowed_headers = [
+        "accept",
+        "user-agent",
+        "x-request-id",
+        "x-stainless-arch",
+        "x-stainless-lang",
+        "x-stainless-os",
+        "x-stainless-package-version",
+        "x-stainless-runtime",
+        "x-stainless-runtime-version",
+    ];
+
+    for (key, value) in headers {
+        let key_str = key.as_str().to_lowercase();
+        if allowed_headers.contains(&key_str.as_str()) {
+            request = request.header(key, value);
+        }
+    }
+
+    // 确保 Content-Type 是 json
+    request = request.header("Content-Type", "application/json");
+
+    // 使用适配器添加认证头（Key 池选中的 Key 沿用供应商自身 Key 的认证方式）
+    let auth = adapter
+        .extract_auth(provider)
+        .map(|auth| match options.api_key {
+            Some(api_key) => auth.with_api_key(api_key),
+            None => auth,
+        });
+    if let Some(auth) = &auth {
+        log::debug!(
+            "[{}] 使用认证: {:?} (key: {})",
+            adapter.name(),
+            auth.strategy,
+            auth.masked_key()
+        );
+        request = adapter.add_auth_headers(request, auth);
+    } else {
+        log::error!(
+            "[{}] 未找到 API Key！Provider: {}",
+            adapter.name(),
+            provider.name
+        );
+    }
+
+    let mut request = request.json(&request_body).build().map_err(|e| {
+        log::error!("[{}] 构建请求失败: {}", adapter.name(), e);
+        ProxyError::ForwardFailed(e.to_string())
+    })?;
+
+    // 在认证头之后执行供应商的请求头改写规则
+    let session_id = request_session_id(body, headers);
+    let vars = TemplateVars {
+        session_id: session_id.as_deref(),
+        provider_id: &provider.id,
+        provider_name: &provider.name,
+        model,
+    };
+    let rewrites = apply_header_rules(request.headers_mut(), provider.header_rules(), &vars);
+    let upstream_request = UpstreamRequestInfo::new(
+        request.headers(),
+        rewrites,
+        auth.as_ref().map(AuthInfo::masked_key),
+    );
+
+    // 发送请求
+    log::info!("[{}] 发送请求到: {}", adapter.name(), url);
+    let mut response = client.execute(request).await.map_err(|e| {
+        log::error!("[{}] 请求失败: {}", adapter.name(), e);
+        if e.is_timeout() {
+            ProxyError::Timeout(format!("请求超时: {e}"))
+        } else if e.is_connect() {
+            ProxyError::ForwardFailed(format!("连接失败: {e}"))
+        } else {
+            ProxyError::ForwardFailed(e.to_string())
+        }
+    })?;
+
+    // 检查响应状态
+    let status = response.status();
+    log::info!("[{}] 响应状态: {}", adapter.name(), status);
+
+    if status.is_success() {
+        response.extensions_mut().insert(upstream_request);
+        Ok(response)
+    } else {
+        let status_code = status.as_u16();
+        let retry_after = parse_cooldown(response.headers(), chrono::Utc::now());
+        let body_text = response.text().await.ok();
+        log::error!(
+            "[{}] 上游错误 ({}): {:?}",
+            adapter.name(),
+            status_code,
+            body_text
+        );
+
+        Err(ProxyError::UpstreamError {
+            status: status_code,
+            body: body_text,
+            retry_after,
+        })
+    }
+}
+
+/// 等待响应体首个数据块到达，再把它拼回响应体
+///
+/// reqwest 在收到响应头时即返回，但部分上游会先返回响应头再长时间无输出，
+/// 因此对冲以首个数据块作为"首字节"判定胜负。
+async fn await_first_chunk(response: Response) -> Result<Response, ProxyError> {
+    let status = response.status();
+    let headers = response.headers().clone();
+    let extensions = response.extensions().clone();
+    let mut stream = response.bytes_stream();
+
+    let first = match stream.next().await {
+        Some(Ok(chunk)) => Some(chunk),
+        Some(Err(e)) => {
+            return Err(ProxyError::ForwardFailed(format!(
+                "读取首个数据块失败: {e}"
+            )));
+        }
+        None => None,
+    };
+
+    let body = futures::stream::iter(first.map(Ok)).chain(stream);
+    Ok(rebuild_response(status, headers, extensions, body))
+}
+
+/// 让守卫（在途计数、限流许可）随响应体一起释放（响应体读取完毕或被丢弃时 drop）
+fn hold_until_body_end<G: Send + Sync + 'static>(response: Response, guard: G) -> Response {
+    let status = response.status();
+    let headers = response.headers().clone();
+    let extensions = response.extensions().clone();
+    let body = response.bytes_stream().map(move |chunk| {
+        let _guard = &guard;
+        chunk
+    });
+    rebuild_response(status, headers, extensions, body)
+}
+
+/// 用新的响应体流重建响应，保留状态码、响应头与扩展（上游请求信息）
+fn rebuild_response(
+    status: reqwest::StatusCode,
+    headers: reqwest::header::HeaderMap,
+    extensions: axum::http::Extensions,
+    body: impl futures::Stream<Item = reqwest::Result<bytes::Bytes>> + Send + Sync + 'static,
+) -> Response {
+    let mut rebuilt = axum::http::Response::new(reqwest::Body::wrap_stream(body));
+    *rebuilt.status_mut() = status;
+    *rebuilt.headers_mut() = headers;
+    *rebuilt.extensions_mut() = extensions;
+    Response::from(rebuilt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_await_first_chunk_preserves_response() {
+        let chunks: Vec<Result<bytes::Bytes, std::io::Error>> = vec![
+            Ok(bytes::Bytes::from("data: a\n\n")),
+            Ok(bytes::Bytes::from("data: b\n\n")),
+        ];
+        let mut upstream =
+            axum::http::Response::new(reqwest::Body::wrap_stream(futures::stream::iter(chunks)));
+        *upstream.status_mut() = reqwest::StatusCode::CREATED;
+        upstream.headers_mut().insert(
+            "content-type",
+            axum::http::HeaderValue::from_static("text/event-stream"),
+        );
+
+        let response = await_first_chunk(Response::from(upstream)).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "text/event-stream"
+        );
+        assert_eq!(response.text().await.unwrap(), "data: a\n\ndata: b\n\n");
+    }
+}
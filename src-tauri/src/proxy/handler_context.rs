@@ -3,11 +3,24 @@
 //! 提供请求生命周期的上下文管理，封装通用初始化逻辑
 
 use crate::app_config::AppType;
+use crate::error::AppError;
 use crate::provider::Provider;
 use crate::proxy::{
-    forwarder::RequestForwarder, server::ProxyState, types::AppProxyConfig, ProxyError,
+    forwarder::{HedgeConfig, RequestForwarder},
+    header_rules::UpstreamRequestInfo,
+    model_mapper::has_thinking_enabled,
+    payload_capture::PayloadCapture,
+    providers::get_adapter,
+    response_cache::{self, ResponseCacheWriter},
+    server::ProxyState,
+    session::{extract_session_id, session_id_from_headers},
+    stream_recovery::StreamRecovery,
+    types::AppProxyConfig,
+    usage::logger::UsageLogger,
+    ProxyError,
 };
-use std::time::Instant;
+use std::sync::{atomic::AtomicU32, Arc};
+use std::time::{Duration, Instant};
 
 /// 流式超时配置
 #[derive(Debug, Clone, Copy)]
@@ -46,14 +59,29 @@ pub struct RequestContext {
     pub tag: &'static str,
     /// 应用类型字符串（如 "claude"、"codex"、"gemini"）
     pub app_type_str: &'static str,
-    /// 应用类型（预留，目前通过 app_type_str 使用）
-    #[allow(dead_code)]
+    /// 应用类型
     pub app_type: AppType,
+    /// 本地响应缓存键及计算该键时的供应商 ID（启用缓存且请求可缓存时由 `lookup_response_cache` 设置）
+    pub cache_key: Option<(String, String)>,
+    /// 请求 ID（请求日志与载荷共用，便于关联）
+    pub request_id: String,
+    /// 客户端会话 ID（从请求体或请求头提取）
+    pub session_id: Option<String>,
+    /// 实际使用的 API Key（已遮蔽，转发成功后从上游响应扩展中读取）
+    pub api_key: Option<String>,
+    /// 载荷捕获器（启用载荷捕获时由 `capture_request` 设置）
+    pub capture: Option<PayloadCapture>,
+    /// 流式中断续写所需的原始请求（启用流式中断恢复时由 `enable_stream_recovery` 设置）
+    recovery_request: Option<(serde_json::Value, axum::http::HeaderMap)>,
+    /// 流式中断后的续写次数
+    pub stream_recoveries: Arc<AtomicU32>,
 }
 
 impl RequestContext {
     /// 创建请求上下文
     ///
+    /// 模型名称从请求体的 `model` 字段提取。
+    ///
     /// # Arguments
     /// * `state` - 代理服务器状态
     /// * `body` - 请求体 JSON
@@ -69,6 +97,28 @@ impl RequestContext {
         app_type: AppType,
         tag: &'static str,
         app_type_str: &'static str,
+    ) -> Result<Self, ProxyError> {
+        // 从请求体提取模型名称
+        let request_model = body
+            .get("model")
+            .and_then(|m| m.as_str())
+            .unwrap_or("unknown")
+            .to_string();
+
+        Self::new_with_model(state, body, request_model, app_type, tag, app_type_str).await
+    }
+
+    /// 使用已知模型名称创建请求上下文
+    ///
+    /// 模型名称参与路由规则匹配，因此必须在选择 Provider 之前确定
+    /// （Gemini 的模型名称在 URI 中，见 [`Self::model_from_uri`]）。
+    pub async fn new_with_model(
+        state: &ProxyState,
+        body: &serde_json::Value,
+        request_model: String,
+        app_type: AppType,
+        tag: &'static str,
+        app_type_str: &'static str,
     ) -> Result<Self, ProxyError> {
         let start_time = Instant::now();
 
@@ -82,20 +132,18 @@ impl RequestContext {
         let current_provider_id =
             crate::settings::get_current_provider(&app_type).unwrap_or_default();
 
-        // 从请求体提取模型名称
-        let request_model = body
-            .get("model")
-            .and_then(|m| m.as_str())
-            .unwrap_or("unknown")
-            .to_string();
+        let thinking = has_thinking_enabled(body);
 
-        // 使用共享的 ProviderRouter 选择 Provider（熔断器状态跨请求保持）
+        // 使用共享的 ProviderRouter 选择 Provider（熔断器状态跨请求保持，支持模型路由规则）
         // 注意：只在这里调用一次，结果传递给 forwarder，避免重复消耗 HalfOpen 名额
         let providers = state
             .provider_router
-            .select_providers(app_type_str)
+            .select_providers_for_model(app_type_str, &request_model, thinking)
             .await
-            .map_err(|e| ProxyError::DatabaseError(e.to_string()))?;
+            .map_err(|e| match e {
+                AppError::SpendLimitExceeded(msg) => ProxyError::SpendLimitExceeded(msg),
+                e => ProxyError::DatabaseError(e.to_string()),
+            })?;
 
         let provider = providers
             .first()
@@ -120,6 +168,13 @@ impl RequestContext {
             tag,
             app_type_str,
             app_type,
+            cache_key: None,
+            request_id: uuid::Uuid::new_v4().to_string(),
+            session_id: extract_session_id(body),
+            api_key: None,
+            capture: None,
+            recovery_request: None,
+            stream_recoveries: Arc::new(AtomicU32::new(0)),
         })
     }
 
@@ -127,29 +182,34 @@ impl RequestContext {
     ///
     /// Gemini API 的模型名称在 URI 中，格式如：
     /// `/v1beta/models/gemini-pro:generateContent`
-    pub fn with_model_from_uri(mut self, uri: &axum::http::Uri) -> Self {
+    pub fn model_from_uri(uri: &axum::http::Uri) -> String {
         let endpoint = uri
             .path_and_query()
             .map(|pq| pq.as_str())
             .unwrap_or(uri.path());
 
-        self.request_model = endpoint
+        endpoint
             .split('/')
             .find(|s| s.starts_with("models/"))
             .and_then(|s| s.strip_prefix("models/"))
             .map(|s| s.split(':').next().unwrap_or(s))
             .unwrap_or("unknown")
-            .to_string();
-
-        log::info!("[{}] 从 URI 提取模型: {}", self.tag, self.request_model);
-        self
+            .to_string()
     }
 
     /// 创建 RequestForwarder
     ///
     /// 使用共享的 ProviderRouter，确保熔断器状态跨请求保持
     pub fn create_forwarder(&self, state: &ProxyState) -> RequestForwarder {
-        RequestForwarder::new(
+        // 负载均衡模式下链首由策略选出，以链首为基准，仅在真正故障转移时才同步 UI/托盘，
+        // 避免每个请求都触发供应商切换
+        let baseline_provider_id = if self.app_config.load_balance_strategy.is_balanced() {
+            self.provider.id.clone()
+        } else {
+            self.current_provider_id.clone()
+        };
+
+        let forwarder = RequestForwarder::new(
             state.provider_router.clone(),
             self.app_config.non_streaming_timeout as u64,
             self.app_config.max_retries as u8,
@@ -157,10 +217,208 @@ impl RequestContext {
             state.current_providers.clone(),
             state.failover_manager.clone(),
             state.app_handle.clone(),
-            self.current_provider_id.clone(),
+            baseline_provider_id,
             self.app_config.streaming_first_byte_timeout as u64,
             self.app_config.streaming_idle_timeout as u64,
-        )
+        );
+
+        if self.app_config.hedge_enabled && self.app_config.hedge_delay_ms > 0 {
+            forwarder.with_hedging(HedgeConfig {
+                delay: Duration::from_millis(self.app_config.hedge_delay_ms as u64),
+                db: state.db.clone(),
+                metrics: state.metrics.clone(),
+                request_id: self.request_id.clone(),
+                session_id: self.session_id.clone(),
+                request_model: self.request_model.clone(),
+            })
+        } else {
+            forwarder
+        }
+    }
+
+    /// 开始捕获请求载荷
+    ///
+    /// 请求体中未携带会话 ID 时尝试从 `session_id` / `x-session-id` 请求头获取；
+    /// 仅在该应用启用载荷捕获时创建捕获器（需在查询响应缓存之前调用）。
+    pub fn capture_request(
+        &mut self,
+        state: &ProxyState,
+        endpoint: &str,
+        headers: &axum::http::HeaderMap,
+        body: &serde_json::Value,
+    ) {
+        if self.session_id.is_none() {
+            self.session_id = session_id_from_headers(headers);
+        }
+
+        if !self.app_config.payload_capture_enabled {
+            return;
+        }
+        self.capture = Some(PayloadCapture::new(
+            state.db.clone(),
+            self.request_id.clone(),
+            self.session_id.clone(),
+            self.app_type_str,
+            &self.request_model,
+            endpoint,
+            headers,
+            body,
+            self.app_config.payload_max_body_kb as usize * 1024,
+            self.app_config.payload_retention_days,
+        ));
+    }
+
+    /// 保存流式请求的原始请求，供上游中途中断时续写（仅在该应用启用流式中断恢复时生效）
+    pub fn enable_stream_recovery(
+        &mut self,
+        body: &serde_json::Value,
+        headers: &axum::http::HeaderMap,
+    ) {
+        if self.app_config.stream_recovery_enabled
+            && self.app_config.stream_recovery_max_attempts > 0
+        {
+            self.recovery_request = Some((body.clone(), headers.clone()));
+        }
+    }
+
+    /// 创建流式中断恢复器，续写时优先使用中断的供应商，其后为故障转移链中的其他供应商
+    ///
+    /// 续写的响应直接拼接到已透传的流后面，因此只使用无需格式转换（与中断的供应商同为原生格式）的供应商
+    pub fn stream_recovery(&self, state: &ProxyState) -> Option<StreamRecovery> {
+        let (body, headers) = self.recovery_request.clone()?;
+        let adapter = get_adapter(&self.app_type);
+        if adapter.needs_transform(&self.provider) {
+            return None;
+        }
+        let providers = std::iter::once(self.provider.clone())
+            .chain(
+                self.providers
+                    .iter()
+                    .filter(|p| p.id != self.provider.id && !adapter.needs_transform(p))
+                    .cloned(),
+            )
+            .collect();
+
+        Some(StreamRecovery {
+            forwarder: self.create_forwarder(state),
+            body,
+            headers,
+            providers,
+            max_attempts: self.app_config.stream_recovery_max_attempts,
+            recoveries: self.stream_recoveries.clone(),
+            timeout: self.streaming_timeout_config(),
+            tag: self.tag,
+        })
+    }
+
+    /// 记录转发成功的供应商与实际使用的 API Key
+    pub fn accept_upstream(&mut self, provider: Provider, response: &reqwest::Response) {
+        self.provider = provider;
+        self.api_key = response
+            .extensions()
+            .get::<UpstreamRequestInfo>()
+            .and_then(|info| info.api_key.clone());
+    }
+
+    /// 附加上游请求头与响应头后的载荷捕获器（未启用捕获时返回 None）
+    pub fn capture_with_response(&self, response: &reqwest::Response) -> Option<PayloadCapture> {
+        self.capture.clone().map(|capture| {
+            capture
+                .with_response_headers(response.headers())
+                .with_upstream_request(response.extensions().get::<UpstreamRequestInfo>())
+        })
+    }
+
+    /// 查询本地响应缓存
+    ///
+    /// 仅在该应用启用响应缓存且请求为确定性请求（temperature 为 0）时生效。
+    /// 计算出的缓存键保存在上下文中，供响应处理阶段写入；命中时直接返回缓存的响应并记录请求日志。
+    pub fn lookup_response_cache(
+        &mut self,
+        state: &ProxyState,
+        endpoint: &str,
+        body: &serde_json::Value,
+    ) -> Option<axum::response::Response> {
+        if !self.app_config.response_cache_enabled {
+            return None;
+        }
+        let key = response_cache::cache_key(
+            self.app_type_str,
+            &self.provider.id,
+            &self.request_model,
+            endpoint,
+            body,
+        )?;
+
+        let cached = state.db.get_cached_response(&key).unwrap_or_else(|e| {
+            log::warn!("[{}] 读取响应缓存失败: {e}", self.tag);
+            None
+        });
+        self.cache_key = Some((self.provider.id.clone(), key));
+        let cached = cached?;
+
+        log::info!(
+            "[{}] 命中本地响应缓存 (provider: {}, {} bytes)",
+            self.tag,
+            cached.provider_id,
+            cached.body.len()
+        );
+
+        if let Err(e) = UsageLogger::new(&state.db)
+            .with_metrics(&state.metrics)
+            .log_cache_hit(
+                self.request_id.clone(),
+                cached.provider_id.clone(),
+                self.app_type_str.to_string(),
+                self.request_model.clone(),
+                cached.status_code,
+                self.latency_ms(),
+                cached.is_streaming,
+                self.session_id.clone(),
+            )
+        {
+            log::warn!("[{}] 记录缓存命中日志失败: {e}", self.tag);
+        }
+        if let Some(capture) = &self.capture {
+            capture.record(
+                &cached.provider_id,
+                cached.status_code,
+                Some(String::from_utf8_lossy(&cached.body).to_string()),
+                cached.is_streaming,
+            );
+        }
+
+        let mut builder = axum::response::Response::builder()
+            .status(cached.status_code)
+            .header(response_cache::CACHE_HIT_HEADER, "hit");
+        if !cached.content_type.is_empty() {
+            builder = builder.header("content-type", cached.content_type);
+        }
+        builder.body(axum::body::Body::from(cached.body)).ok()
+    }
+
+    /// 创建响应缓存写入器（未计算出缓存键时返回 None）
+    ///
+    /// 缓存键按查询时的首选供应商计算，故障转移到其他供应商后的响应不写入该键
+    pub fn response_cache_writer(&self, state: &ProxyState) -> Option<ResponseCacheWriter> {
+        let (key_provider_id, cache_key) = self.cache_key.clone()?;
+        if key_provider_id != self.provider.id {
+            log::debug!(
+                "[{}] 响应来自故障转移后的供应商 {}，不写入缓存",
+                self.tag,
+                self.provider.id
+            );
+            return None;
+        }
+        Some(ResponseCacheWriter {
+            db: state.db.clone(),
+            cache_key,
+            app_type: self.app_type_str.to_string(),
+            provider_id: self.provider.id.clone(),
+            model: self.request_model.clone(),
+            ttl_seconds: self.app_config.response_cache_ttl_seconds as u64,
+            max_bytes: self.app_config.response_cache_max_mb as u64 * 1024 * 1024,
+        })
     }
 
     /// 获取 Provider 列表（用于故障转移）
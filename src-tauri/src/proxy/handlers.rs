@@ -5,7 +5,7 @@
 //! 重构后的结构：
 //! - 通用逻辑提取到 `handler_context` 和 `response_processor` 模块
 //! - 各 handler 只保留独特的业务逻辑
-//! - Claude 的格式转换逻辑保留在此文件（用于 OpenRouter 旧接口回退）
+//! - Claude 的格式转换逻辑保留在此文件（OpenAI 兼容 / Gemini 上游、OpenRouter 旧接口回退）
 
 use super::{
     error_mapper::{get_error_message, map_proxy_error_to_status},
@@ -13,8 +13,17 @@ use super::{
         CLAUDE_PARSER_CONFIG, CODEX_PARSER_CONFIG, GEMINI_PARSER_CONFIG, OPENAI_PARSER_CONFIG,
     },
     handler_context::RequestContext,
-    providers::{get_adapter, streaming::create_anthropic_sse_stream, transform},
-    response_processor::{create_logged_passthrough_stream, process_response, SseUsageCollector},
+    metrics,
+    providers::{
+        chat_bridge, gemini_streaming::create_anthropic_sse_stream_from_gemini, gemini_transform,
+        get_adapter, responses_bridge, streaming::create_anthropic_sse_stream, transform,
+        ClaudeAdapter, CodexAdapter, ProviderAdapter, ProviderType,
+    },
+    rate_limiter::estimate_input_tokens,
+    response_processor::{
+        create_logged_passthrough_stream, create_usage_collector, is_sse_response,
+        process_response, SseUsageCollector,
+    },
     server::ProxyState,
     types::*,
     usage::parser::TokenUsage,
@@ -22,6 +31,7 @@ use super::{
 };
 use crate::app_config::AppType;
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use futures::StreamExt;
 use rust_decimal::Decimal;
 use serde_json::{json, Value};
 use std::str::FromStr;
@@ -43,10 +53,25 @@ pub async fn health_check() -> (StatusCode, Json<Value>) {
 
 /// 获取服务状态
 pub async fn get_status(State(state): State<ProxyState>) -> Result<Json<ProxyStatus>, ProxyError> {
-    let status = state.status.read().await.clone();
+    let mut status = state.status.read().await.clone();
+    status.cooldowns = state.provider_router.list_cooldowns().await;
     Ok(Json(status))
 }
 
+/// Prometheus 指标（文本格式）
+pub async fn metrics(State(state): State<ProxyState>) -> impl IntoResponse {
+    let mut status = state.status.read().await.clone();
+    if let Some(start) = *state.start_time.read().await {
+        status.uptime_seconds = start.elapsed().as_secs();
+    }
+    let breakers = state.provider_router.list_circuit_breaker_stats().await;
+
+    (
+        [(axum::http::header::CONTENT_TYPE, metrics::CONTENT_TYPE)],
+        state.metrics.render(&status, &breakers),
+    )
+}
+
 // ============================================================================
 // Claude API 处理器（包含格式转换逻辑）
 // ============================================================================
@@ -68,6 +93,14 @@ pub async fn handle_messages(
         .and_then(|s| s.as_bool())
         .unwrap_or(false);
 
+    ctx.capture_request(&state, "/v1/messages", &headers, &body);
+    if let Some(response) = ctx.lookup_response_cache(&state, "/v1/messages", &body) {
+        return Ok(response);
+    }
+    if is_stream {
+        ctx.enable_stream_recovery(&body, &headers);
+    }
+
     // 转发请求
     let forwarder = ctx.create_forwarder(&state);
     let result = match forwarder
@@ -90,10 +123,10 @@ pub async fn handle_messages(
         }
     };
 
-    ctx.provider = result.provider;
+    ctx.accept_upstream(result.provider, &result.response);
     let response = result.response;
 
-    // 检查是否需要格式转换（OpenRouter 等中转服务）
+    // 检查是否需要格式转换（OpenAI 兼容 / Gemini 上游、OpenRouter）
     let adapter = get_adapter(&AppType::Claude);
     let needs_transform = adapter.needs_transform(&ctx.provider);
 
@@ -116,9 +149,62 @@ pub async fn handle_messages(
     process_response(response, &ctx, &state, &CLAUDE_PARSER_CONFIG).await
 }
 
+/// 处理 /v1/messages/count_tokens 请求（Claude Code 的输入 Token 预估）
+///
+/// OpenAI 兼容 / Gemini 上游没有对应端点，且该端点不能套用 Messages 的格式转换：
+/// - 首选供应商需要格式转换时，由代理按请求体长度本地估算
+/// - 否则只在原生 Anthropic 格式的供应商间透传请求体
+pub async fn handle_count_tokens(
+    State(state): State<ProxyState>,
+    headers: axum::http::HeaderMap,
+    Json(body): Json<Value>,
+) -> Result<axum::response::Response, ProxyError> {
+    let mut ctx = RequestContext::new(&state, &body, AppType::Claude, "Claude", "claude").await?;
+
+    let adapter = get_adapter(&AppType::Claude);
+    let providers: Vec<_> = ctx
+        .get_providers()
+        .into_iter()
+        .filter(|p| !adapter.needs_transform(p))
+        .collect();
+    if adapter.needs_transform(&ctx.provider) || providers.is_empty() {
+        let input_tokens = estimate_input_tokens(&body);
+        log::debug!(
+            "[Claude] Provider {} 不支持 count_tokens，本地估算: {input_tokens}",
+            ctx.provider.name
+        );
+        return Ok(Json(json!({ "input_tokens": input_tokens })).into_response());
+    }
+
+    let forwarder = ctx.create_forwarder(&state);
+    let result = match forwarder
+        .forward_with_retry(
+            &AppType::Claude,
+            "/v1/messages/count_tokens",
+            body,
+            headers,
+            providers,
+        )
+        .await
+    {
+        Ok(result) => result,
+        Err(mut err) => {
+            if let Some(provider) = err.provider.take() {
+                ctx.provider = provider;
+            }
+            log_forward_error(&state, &ctx, false, &err.error);
+            return Err(err.error);
+        }
+    };
+
+    ctx.accept_upstream(result.provider, &result.response);
+    process_response(result.response, &ctx, &state, &CLAUDE_PARSER_CONFIG).await
+}
+
 /// Claude 格式转换处理（独有逻辑）
 ///
-/// 处理 OpenRouter 旧 OpenAI 兼容接口的回退方案（当前默认不启用）
+/// 处理 OpenAI Chat Completions 兼容上游（DeepSeek、vLLM、Kimi 等）、
+/// Gemini generateContent 上游，以及 OpenRouter 旧 OpenAI 兼容接口的回退方案
 async fn handle_claude_transform(
     response: reqwest::Response,
     ctx: &RequestContext,
@@ -127,32 +213,54 @@ async fn handle_claude_transform(
     is_stream: bool,
 ) -> Result<axum::response::Response, ProxyError> {
     let status = response.status();
+    let is_gemini = ClaudeAdapter::new().provider_type(&ctx.provider) == ProviderType::GeminiCompat;
+    let upstream_format = if is_gemini { "Gemini" } else { "OpenAI" };
+    let capture = ctx.capture_with_response(&response);
 
     if is_stream {
-        // 流式响应转换 (OpenAI SSE → Anthropic SSE)
-        log::info!("[Claude] 开始流式响应转换 (OpenAI SSE → Anthropic SSE)");
+        // 流式响应转换 (OpenAI/Gemini SSE → Anthropic SSE)
+        log::info!("[Claude] 开始流式响应转换 ({upstream_format} SSE → Anthropic SSE)");
 
         let stream = response.bytes_stream();
-        let sse_stream = create_anthropic_sse_stream(stream);
+        let sse_stream: std::pin::Pin<
+            Box<dyn futures::Stream<Item = Result<bytes::Bytes, std::io::Error>> + Send>,
+        > = if is_gemini {
+            Box::pin(create_anthropic_sse_stream_from_gemini(stream))
+        } else {
+            Box::pin(create_anthropic_sse_stream(stream))
+        };
 
         // 创建使用量收集器
         let usage_collector = {
             let state = state.clone();
+            let request_id = ctx.request_id.clone();
+            let session_id = ctx.session_id.clone();
+            let api_key = ctx.api_key.clone();
             let provider_id = ctx.provider.id.clone();
             let model = ctx.request_model.clone();
             let status_code = status.as_u16();
             let start_time = ctx.start_time;
 
             SseUsageCollector::new(start_time, move |events, first_token_ms| {
+                if let Some(capture) = &capture {
+                    capture.record_stream_events(&provider_id, status_code, &events);
+                }
+
                 if let Some(usage) = TokenUsage::from_claude_stream_events(&events) {
                     let latency_ms = start_time.elapsed().as_millis() as u64;
                     let state = state.clone();
+                    let request_id = request_id.clone();
+                    let session_id = session_id.clone();
+                    let api_key = api_key.clone();
                     let provider_id = provider_id.clone();
                     let model = model.clone();
 
                     tokio::spawn(async move {
                         log_usage(
                             &state,
+                            request_id,
+                            session_id,
+                            api_key,
                             &provider_id,
                             "claude",
                             &model,
@@ -165,7 +273,7 @@ async fn handle_claude_transform(
                         .await;
                     });
                 } else {
-                    log::debug!("[Claude] OpenRouter 流式响应缺少 usage 统计，跳过消费记录");
+                    log::debug!("[Claude] 转换后的流式响应缺少 usage 统计，跳过消费记录");
                 }
             })
         };
@@ -175,7 +283,11 @@ async fn handle_claude_transform(
 
         let logged_stream = create_logged_passthrough_stream(
             sse_stream,
-            "Claude/OpenRouter",
+            if is_gemini {
+                "Claude/Gemini"
+            } else {
+                "Claude/OpenAI"
+            },
             Some(usage_collector),
             timeout_config,
         );
@@ -199,8 +311,8 @@ async fn handle_claude_transform(
         return Ok((headers, body).into_response());
     }
 
-    // 非流式响应转换 (OpenAI → Anthropic)
-    log::info!("[Claude] 开始转换响应 (OpenAI → Anthropic)");
+    // 非流式响应转换 (OpenAI/Gemini → Anthropic)
+    log::info!("[Claude] 开始转换响应 ({upstream_format} → Anthropic)");
 
     let response_headers = response.headers().clone();
 
@@ -210,21 +322,37 @@ async fn handle_claude_transform(
     })?;
 
     let body_str = String::from_utf8_lossy(&body_bytes);
-    log::info!("[Claude] OpenAI 响应长度: {} bytes", body_bytes.len());
-    log::debug!("[Claude] OpenAI 原始响应: {body_str}");
+    if let Some(capture) = &capture {
+        capture.record(
+            &ctx.provider.id,
+            status.as_u16(),
+            Some(body_str.to_string()),
+            false,
+        );
+    }
+    log::info!(
+        "[Claude] {upstream_format} 响应长度: {} bytes",
+        body_bytes.len()
+    );
+    log::debug!("[Claude] {upstream_format} 原始响应: {body_str}");
 
-    let openai_response: Value = serde_json::from_slice(&body_bytes).map_err(|e| {
-        log::error!("[Claude] 解析 OpenAI 响应失败: {e}, body: {body_str}");
-        ProxyError::TransformError(format!("Failed to parse OpenAI response: {e}"))
+    let upstream_response: Value = serde_json::from_slice(&body_bytes).map_err(|e| {
+        log::error!("[Claude] 解析 {upstream_format} 响应失败: {e}, body: {body_str}");
+        ProxyError::TransformError(format!("Failed to parse {upstream_format} response: {e}"))
     })?;
 
-    log::info!("[Claude] 解析 OpenAI 响应成功");
+    log::info!("[Claude] 解析 {upstream_format} 响应成功");
     log::info!(
-        "[Claude] <<< OpenAI 响应 JSON:\n{}",
-        serde_json::to_string_pretty(&openai_response).unwrap_or_default()
+        "[Claude] <<< {upstream_format} 响应 JSON:\n{}",
+        serde_json::to_string_pretty(&upstream_response).unwrap_or_default()
     );
 
-    let anthropic_response = transform::openai_to_anthropic(openai_response).map_err(|e| {
+    let converted = if is_gemini {
+        gemini_transform::gemini_to_anthropic(upstream_response)
+    } else {
+        transform::openai_to_anthropic(upstream_response)
+    };
+    let anthropic_response = converted.map_err(|e| {
         log::error!("[Claude] 转换响应失败: {e}");
         e
     })?;
@@ -245,11 +373,17 @@ async fn handle_claude_transform(
 
         tokio::spawn({
             let state = state.clone();
+            let request_id = ctx.request_id.clone();
+            let session_id = ctx.session_id.clone();
+            let api_key = ctx.api_key.clone();
             let provider_id = ctx.provider.id.clone();
             let model = model.to_string();
             async move {
                 log_usage(
                     &state,
+                    request_id,
+                    session_id,
+                    api_key,
                     &provider_id,
                     "claude",
                     &model,
@@ -293,16 +427,214 @@ async fn handle_claude_transform(
     Ok(builder.body(body).unwrap())
 }
 
+/// 处理 Chat Completions 客户端请求（OpenAI Chat Completions → 当前 Claude 供应商）
+///
+/// 由 `/claude/v1/chat/completions` 或模型名为 `claude-*` 的 `/v1/chat/completions` 进入。
+/// 请求转换为 Anthropic Messages 后走 Claude 的供应商链；若上游本身需要格式转换
+/// （OpenAI 兼容 / Gemini），先还原为 Anthropic 格式再转换为 Chat 格式。
+pub async fn handle_claude_chat_completions(
+    State(state): State<ProxyState>,
+    headers: axum::http::HeaderMap,
+    Json(body): Json<Value>,
+) -> Result<axum::response::Response, ProxyError> {
+    log::info!("[Claude/Chat] ====== /v1/chat/completions 请求开始 ======");
+
+    let include_usage = body
+        .pointer("/stream_options/include_usage")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let anthropic_body = chat_bridge::chat_to_anthropic(body)?;
+
+    let mut ctx = RequestContext::new(
+        &state,
+        &anthropic_body,
+        AppType::Claude,
+        "Claude/Chat",
+        "claude",
+    )
+    .await?;
+
+    let is_stream = anthropic_body
+        .get("stream")
+        .and_then(|s| s.as_bool())
+        .unwrap_or(false);
+
+    // 捕获转换后发往上游的 Anthropic 请求
+    ctx.capture_request(&state, "/v1/messages", &headers, &anthropic_body);
+
+    let forwarder = ctx.create_forwarder(&state);
+    let result = match forwarder
+        .forward_with_retry(
+            &AppType::Claude,
+            "/v1/messages",
+            anthropic_body,
+            headers,
+            ctx.get_providers(),
+        )
+        .await
+    {
+        Ok(result) => result,
+        Err(mut err) => {
+            if let Some(provider) = err.provider.take() {
+                ctx.provider = provider;
+            }
+            log_forward_error(&state, &ctx, is_stream, &err.error);
+            return Err(err.error);
+        }
+    };
+
+    ctx.accept_upstream(result.provider, &result.response);
+    let response = result.response;
+    let status = response.status();
+
+    let adapter = ClaudeAdapter::new();
+    let needs_transform = adapter.needs_transform(&ctx.provider);
+    let capture = ctx.capture_with_response(&response);
+    let is_gemini = adapter.provider_type(&ctx.provider) == ProviderType::GeminiCompat;
+
+    log::info!(
+        "[Claude/Chat] Provider: {}, 上游响应状态: {status}, needs_transform: {needs_transform}",
+        ctx.provider.name
+    );
+
+    if is_stream {
+        let upstream = response.bytes_stream();
+        let anthropic_stream: std::pin::Pin<
+            Box<dyn futures::Stream<Item = Result<bytes::Bytes, std::io::Error>> + Send>,
+        > = if !needs_transform {
+            Box::pin(upstream.map(|chunk| chunk.map_err(|e| std::io::Error::other(e.to_string()))))
+        } else if is_gemini {
+            Box::pin(create_anthropic_sse_stream_from_gemini(upstream))
+        } else {
+            Box::pin(create_anthropic_sse_stream(upstream))
+        };
+
+        // 在 Anthropic 事件上收集 usage，再转换为 Chat chunk
+        let usage_collector = create_usage_collector(
+            &ctx,
+            &state,
+            status.as_u16(),
+            &CLAUDE_PARSER_CONFIG,
+            capture,
+        );
+        let logged_stream = create_logged_passthrough_stream(
+            anthropic_stream,
+            "Claude/Chat",
+            Some(usage_collector),
+            ctx.streaming_timeout_config(),
+        );
+        let chat_stream = chat_bridge::create_chat_sse_stream(logged_stream, include_usage);
+
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert(
+            "Content-Type",
+            axum::http::HeaderValue::from_static("text/event-stream"),
+        );
+        headers.insert(
+            "Cache-Control",
+            axum::http::HeaderValue::from_static("no-cache"),
+        );
+
+        let body = axum::body::Body::from_stream(chat_stream);
+        log::info!("[Claude/Chat] ====== 请求结束 (流式桥接) ======");
+        return Ok((headers, body).into_response());
+    }
+
+    let body_bytes = response.bytes().await.map_err(|e| {
+        log::error!("[Claude/Chat] 读取响应体失败: {e}");
+        ProxyError::ForwardFailed(format!("Failed to read response body: {e}"))
+    })?;
+
+    if let Some(capture) = &capture {
+        capture.record(
+            &ctx.provider.id,
+            status.as_u16(),
+            Some(String::from_utf8_lossy(&body_bytes).to_string()),
+            false,
+        );
+    }
+
+    let upstream_response: Value = serde_json::from_slice(&body_bytes).map_err(|e| {
+        log::error!("[Claude/Chat] 解析上游响应失败: {e}");
+        ProxyError::TransformError(format!("Failed to parse upstream response: {e}"))
+    })?;
+
+    let anthropic_response = if !needs_transform {
+        upstream_response
+    } else if is_gemini {
+        gemini_transform::gemini_to_anthropic(upstream_response)?
+    } else {
+        transform::openai_to_anthropic(upstream_response)?
+    };
+
+    if let Some(usage) = TokenUsage::from_claude_response(&anthropic_response) {
+        let model = anthropic_response
+            .get("model")
+            .and_then(|m| m.as_str())
+            .unwrap_or(&ctx.request_model)
+            .to_string();
+        let latency_ms = ctx.latency_ms();
+
+        tokio::spawn({
+            let state = state.clone();
+            let request_id = ctx.request_id.clone();
+            let session_id = ctx.session_id.clone();
+            let api_key = ctx.api_key.clone();
+            let provider_id = ctx.provider.id.clone();
+            async move {
+                log_usage(
+                    &state,
+                    request_id,
+                    session_id,
+                    api_key,
+                    &provider_id,
+                    "claude",
+                    &model,
+                    usage,
+                    latency_ms,
+                    None,
+                    false,
+                    status.as_u16(),
+                )
+                .await;
+            }
+        });
+    }
+
+    let chat_response = chat_bridge::anthropic_to_chat(anthropic_response)?;
+    log::info!("[Claude/Chat] ====== 请求结束 (桥接) ======");
+
+    Ok((
+        status,
+        [("content-type", "application/json")],
+        Json(chat_response),
+    )
+        .into_response())
+}
+
 // ============================================================================
 // Codex API 处理器
 // ============================================================================
 
 /// 处理 /v1/chat/completions 请求（OpenAI Chat Completions API - Codex CLI）
+///
+/// 未带 `/codex` 前缀的请求若指定裸 `claude-*` 模型，交给 Claude 供应商处理（Chat ⇄ Anthropic 桥接）；
+/// 显式的 `/codex/...` 路径与带厂商前缀的模型名（如 `anthropic/claude-*`）始终走 Codex 供应商。
 pub async fn handle_chat_completions(
     State(state): State<ProxyState>,
+    uri: axum::http::Uri,
     headers: axum::http::HeaderMap,
     Json(body): Json<Value>,
 ) -> Result<axum::response::Response, ProxyError> {
+    if !uri.path().starts_with("/codex/")
+        && body
+            .get("model")
+            .and_then(|m| m.as_str())
+            .is_some_and(chat_bridge::is_claude_model)
+    {
+        return handle_claude_chat_completions(State(state), headers, Json(body)).await;
+    }
+
     log::info!("[Codex] ====== /v1/chat/completions 请求开始 ======");
 
     let mut ctx = RequestContext::new(&state, &body, AppType::Codex, "Codex", "codex").await?;
@@ -318,6 +650,11 @@ pub async fn handle_chat_completions(
         is_stream
     );
 
+    ctx.capture_request(&state, "/v1/chat/completions", &headers, &body);
+    if let Some(response) = ctx.lookup_response_cache(&state, "/v1/chat/completions", &body) {
+        return Ok(response);
+    }
+
     let forwarder = ctx.create_forwarder(&state);
     let result = match forwarder
         .forward_with_retry(
@@ -339,7 +676,7 @@ pub async fn handle_chat_completions(
         }
     };
 
-    ctx.provider = result.provider;
+    ctx.accept_upstream(result.provider, &result.response);
     let response = result.response;
 
     log::info!("[Codex] 上游响应状态: {}", response.status());
@@ -347,7 +684,7 @@ pub async fn handle_chat_completions(
     process_response(response, &ctx, &state, &OPENAI_PARSER_CONFIG).await
 }
 
-/// 处理 /v1/responses 请求（OpenAI Responses API - Codex CLI 透传）
+/// 处理 /v1/responses 请求（OpenAI Responses API - Codex CLI 透传，或桥接到 Chat Completions）
 pub async fn handle_responses(
     State(state): State<ProxyState>,
     headers: axum::http::HeaderMap,
@@ -360,6 +697,11 @@ pub async fn handle_responses(
         .and_then(|v| v.as_bool())
         .unwrap_or(false);
 
+    ctx.capture_request(&state, "/v1/responses", &headers, &body);
+    if let Some(response) = ctx.lookup_response_cache(&state, "/v1/responses", &body) {
+        return Ok(response);
+    }
+
     let forwarder = ctx.create_forwarder(&state);
     let result = match forwarder
         .forward_with_retry(
@@ -381,14 +723,126 @@ pub async fn handle_responses(
         }
     };
 
-    ctx.provider = result.provider;
+    ctx.accept_upstream(result.provider, &result.response);
     let response = result.response;
 
     log::info!("[Codex] 上游响应状态: {}", response.status());
 
+    // 仅支持 Chat Completions 的上游：把响应转换回 Responses 格式
+    if CodexAdapter::new().needs_transform(&ctx.provider) {
+        return handle_codex_chat_bridge(response, &ctx, &state).await;
+    }
+
     process_response(response, &ctx, &state, &CODEX_PARSER_CONFIG).await
 }
 
+/// Codex Responses ⇄ Chat Completions 桥接响应处理
+///
+/// usage 统一按上游的 Chat Completions 格式解析（`OPENAI_PARSER_CONFIG`）
+async fn handle_codex_chat_bridge(
+    response: reqwest::Response,
+    ctx: &RequestContext,
+    state: &ProxyState,
+) -> Result<axum::response::Response, ProxyError> {
+    let status = response.status();
+    let capture = ctx.capture_with_response(&response);
+
+    if is_sse_response(&response) {
+        log::info!("[Codex] 开始流式响应转换 (Chat SSE → Responses SSE)");
+
+        let stream = response
+            .bytes_stream()
+            .map(|chunk| chunk.map_err(|e| std::io::Error::other(e.to_string())));
+
+        // 先在上游 Chat chunk 上收集 usage，再转换为 Responses 事件
+        let usage_collector =
+            create_usage_collector(ctx, state, status.as_u16(), &OPENAI_PARSER_CONFIG, capture);
+        let logged_stream = create_logged_passthrough_stream(
+            stream,
+            "Codex/Chat",
+            Some(usage_collector),
+            ctx.streaming_timeout_config(),
+        );
+        let responses_stream = responses_bridge::create_responses_sse_stream(logged_stream);
+
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert(
+            "Content-Type",
+            axum::http::HeaderValue::from_static("text/event-stream"),
+        );
+        headers.insert(
+            "Cache-Control",
+            axum::http::HeaderValue::from_static("no-cache"),
+        );
+
+        let body = axum::body::Body::from_stream(responses_stream);
+        log::info!("[Codex] ====== 请求结束 (流式桥接) ======");
+        return Ok((headers, body).into_response());
+    }
+
+    let body_bytes = response.bytes().await.map_err(|e| {
+        log::error!("[Codex] 读取响应体失败: {e}");
+        ProxyError::ForwardFailed(format!("Failed to read response body: {e}"))
+    })?;
+
+    if let Some(capture) = &capture {
+        capture.record(
+            &ctx.provider.id,
+            status.as_u16(),
+            Some(String::from_utf8_lossy(&body_bytes).to_string()),
+            false,
+        );
+    }
+
+    let chat_response: Value = serde_json::from_slice(&body_bytes).map_err(|e| {
+        log::error!("[Codex] 解析 Chat Completions 响应失败: {e}");
+        ProxyError::TransformError(format!("Failed to parse Chat Completions response: {e}"))
+    })?;
+
+    if let Some(usage) = TokenUsage::from_openai_response(&chat_response) {
+        let model = usage
+            .model
+            .clone()
+            .unwrap_or_else(|| ctx.request_model.clone());
+        let latency_ms = ctx.latency_ms();
+
+        tokio::spawn({
+            let state = state.clone();
+            let request_id = ctx.request_id.clone();
+            let session_id = ctx.session_id.clone();
+            let api_key = ctx.api_key.clone();
+            let provider_id = ctx.provider.id.clone();
+            async move {
+                log_usage(
+                    &state,
+                    request_id,
+                    session_id,
+                    api_key,
+                    &provider_id,
+                    "codex",
+                    &model,
+                    usage,
+                    latency_ms,
+                    None,
+                    false,
+                    status.as_u16(),
+                )
+                .await;
+            }
+        });
+    }
+
+    let responses_body = responses_bridge::chat_to_responses(chat_response)?;
+    log::info!("[Codex] ====== 请求结束 (桥接) ======");
+
+    Ok((
+        status,
+        [("content-type", "application/json")],
+        Json(responses_body),
+    )
+        .into_response())
+}
+
 // ============================================================================
 // Gemini API 处理器
 // ============================================================================
@@ -400,10 +854,18 @@ pub async fn handle_gemini(
     headers: axum::http::HeaderMap,
     Json(body): Json<Value>,
 ) -> Result<axum::response::Response, ProxyError> {
-    // Gemini 的模型名称在 URI 中
-    let mut ctx = RequestContext::new(&state, &body, AppType::Gemini, "Gemini", "gemini")
-        .await?
-        .with_model_from_uri(&uri);
+    // Gemini 的模型名称在 URI 中（需在选择 Provider 前确定，以便匹配路由规则）
+    let request_model = RequestContext::model_from_uri(&uri);
+    log::info!("[Gemini] 从 URI 提取模型: {request_model}");
+    let mut ctx = RequestContext::new_with_model(
+        &state,
+        &body,
+        request_model,
+        AppType::Gemini,
+        "Gemini",
+        "gemini",
+    )
+    .await?;
 
     // 提取完整的路径和查询参数
     let endpoint = uri
@@ -418,6 +880,11 @@ pub async fn handle_gemini(
         .and_then(|v| v.as_bool())
         .unwrap_or(false);
 
+    ctx.capture_request(&state, endpoint, &headers, &body);
+    if let Some(response) = ctx.lookup_response_cache(&state, endpoint, &body) {
+        return Ok(response);
+    }
+
     let forwarder = ctx.create_forwarder(&state);
     let result = match forwarder
         .forward_with_retry(
@@ -439,7 +906,7 @@ pub async fn handle_gemini(
         }
     };
 
-    ctx.provider = result.provider;
+    ctx.accept_upstream(result.provider, &result.response);
     let response = result.response;
 
     log::info!("[Gemini] 上游响应状态: {}", response.status());
@@ -459,13 +926,20 @@ fn log_forward_error(
 ) {
     use super::usage::logger::UsageLogger;
 
-    let logger = UsageLogger::new(&state.db);
+    let logger = UsageLogger::new(&state.db).with_metrics(&state.metrics);
     let status_code = map_proxy_error_to_status(error);
     let error_message = get_error_message(error);
-    let request_id = uuid::Uuid::new_v4().to_string();
+    if let Some(capture) = &ctx.capture {
+        capture.record(
+            &ctx.provider.id,
+            status_code,
+            Some(error_message.clone()),
+            is_streaming,
+        );
+    }
 
     if let Err(e) = logger.log_error_with_context(
-        request_id.clone(),
+        ctx.request_id.clone(),
         ctx.provider.id.clone(),
         ctx.app_type_str.to_string(),
         ctx.request_model.clone(),
@@ -473,7 +947,11 @@ fn log_forward_error(
         error_message,
         ctx.latency_ms(),
         is_streaming,
-        Some(request_id),
+        Some(
+            ctx.session_id
+                .clone()
+                .unwrap_or_else(|| ctx.request_id.clone()),
+        ),
         None,
     ) {
         log::warn!("记录失败请求日志失败: {e}");
@@ -484,6 +962,9 @@ fn log_forward_error(
 #[allow(clippy::too_many_arguments)]
 async fn log_usage(
     state: &ProxyState,
+    request_id: String,
+    session_id: Option<String>,
+    api_key: Option<String>,
     provider_id: &str,
     app_type: &str,
     model: &str,
@@ -495,7 +976,9 @@ async fn log_usage(
 ) {
     use super::usage::logger::UsageLogger;
 
-    let logger = UsageLogger::new(&state.db);
+    let logger = UsageLogger::new(&state.db)
+        .with_metrics(&state.metrics)
+        .with_api_key(api_key.as_deref());
 
     // 获取 provider 的 cost_multiplier
     let multiplier = match state.db.get_provider_by_id(provider_id, app_type) {
@@ -513,8 +996,6 @@ async fn log_usage(
         _ => Decimal::from(1),
     };
 
-    let request_id = uuid::Uuid::new_v4().to_string();
-
     if let Err(e) = logger.log_with_calculation(
         request_id,
         provider_id.to_string(),
@@ -525,7 +1006,7 @@ async fn log_usage(
         latency_ms,
         first_token_ms,
         status_code,
-        None,
+        session_id,
         None, // provider_type
         is_streaming,
     ) {
@@ -0,0 +1,216 @@
+//! 请求头改写
+//!
+//! 部分中转服务要求额外的请求头（`HTTP-Referer`、`X-Title`、组织 ID、自定义 `User-Agent`），
+//! 另一些会拒绝客户端携带的请求头（如 `anthropic-beta`）。供应商 meta 中的 `headerRules`
+//! 在适配器设置认证头之后按顺序执行，执行结果随响应一起交给载荷捕获，便于在请求详情中排查。
+
+use super::payload_capture::redact_headers;
+use crate::provider::{HeaderRule, HeaderRuleAction};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde_json::Value;
+
+/// 模板变量
+#[derive(Debug, Default, Clone)]
+pub struct TemplateVars<'a> {
+    pub session_id: Option<&'a str>,
+    pub provider_id: &'a str,
+    pub provider_name: &'a str,
+    pub model: &'a str,
+}
+
+impl TemplateVars<'_> {
+    /// 替换模板变量；未知的 `{...}` 原样保留（不影响正则替换中的 `${1}`）
+    pub fn render(&self, template: &str) -> String {
+        let timestamp = chrono::Utc::now().timestamp().to_string();
+        [
+            ("{session_id}", self.session_id.unwrap_or_default()),
+            ("{provider_id}", self.provider_id),
+            ("{provider_name}", self.provider_name),
+            ("{model}", self.model),
+            ("{timestamp}", timestamp.as_str()),
+        ]
+        .iter()
+        .fold(template.to_string(), |acc, (name, value)| {
+            acc.replace(name, value)
+        })
+    }
+}
+
+/// 实际发往上游的请求信息（通过响应扩展传递给载荷捕获）
+#[derive(Debug, Clone, Default)]
+pub struct UpstreamRequestInfo {
+    /// 改写后的请求头（已遮蔽密钥）
+    pub headers: Value,
+    /// 生效的改写规则说明
+    pub rewrites: Vec<String>,
+    /// 实际使用的 API Key（已遮蔽）
+    pub api_key: Option<String>,
+}
+
+impl UpstreamRequestInfo {
+    pub fn new(headers: &HeaderMap, rewrites: Vec<String>, api_key: Option<String>) -> Self {
+        Self {
+            headers: redact_headers(headers),
+            rewrites,
+            api_key,
+        }
+    }
+}
+
+/// 按顺序执行改写规则，返回实际生效的规则说明
+///
+/// 名称或值非法、正则无法编译的规则会被跳过并记录警告，不影响请求发送。
+pub fn apply_header_rules(
+    headers: &mut HeaderMap,
+    rules: &[HeaderRule],
+    vars: &TemplateVars,
+) -> Vec<String> {
+    let mut applied = Vec::new();
+
+    for rule in rules.iter().filter(|rule| rule.enabled) {
+        let Ok(name) = HeaderName::from_bytes(rule.name.trim().as_bytes()) else {
+            log::warn!("[HeaderRules] 跳过非法的请求头名称: {}", rule.name);
+            continue;
+        };
+        let value = rule.value.as_deref().map(|v| vars.render(v));
+        let header_value = |value: &str| {
+            HeaderValue::from_str(value)
+                .map_err(|_| log::warn!("[HeaderRules] 跳过非法的请求头值: {name}"))
+                .ok()
+        };
+
+        match rule.action {
+            HeaderRuleAction::Set => {
+                if let Some(v) = value.as_deref().and_then(header_value) {
+                    headers.insert(&name, v);
+                    applied.push(format!("set {name}"));
+                }
+            }
+            HeaderRuleAction::Append => {
+                if let Some(v) = value.as_deref().and_then(header_value) {
+                    headers.append(&name, v);
+                    applied.push(format!("append {name}"));
+                }
+            }
+            HeaderRuleAction::Remove => {
+                if headers.remove(&name).is_some() {
+                    applied.push(format!("remove {name}"));
+                }
+            }
+            HeaderRuleAction::Replace => {
+                let Some(pattern) = rule.pattern.as_deref().filter(|p| !p.is_empty()) else {
+                    continue;
+                };
+                let regex = match regex::Regex::new(pattern) {
+                    Ok(regex) => regex,
+                    Err(e) => {
+                        log::warn!("[HeaderRules] 跳过无效的正则 {pattern}: {e}");
+                        continue;
+                    }
+                };
+                let replacement = value.unwrap_or_default();
+                let current: Vec<String> = headers
+                    .get_all(&name)
+                    .iter()
+                    .filter_map(|v| v.to_str().ok().map(str::to_string))
+                    .collect();
+                if !current.iter().any(|v| regex.is_match(v)) {
+                    continue;
+                }
+
+                let replaced: Vec<HeaderValue> = current
+                    .iter()
+                    .map(|v| regex.replace_all(v, replacement.as_str()))
+                    .filter(|v| !v.trim().is_empty())
+                    .filter_map(|v| header_value(&v))
+                    .collect();
+                headers.remove(&name);
+                for v in replaced {
+                    headers.append(&name, v);
+                }
+                applied.push(format!("replace {name}"));
+            }
+        }
+    }
+
+    if !applied.is_empty() {
+        log::debug!("[HeaderRules] 已改写请求头: {}", applied.join(", "));
+    }
+    applied
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(action: HeaderRuleAction, name: &str, value: Option<&str>) -> HeaderRule {
+        HeaderRule {
+            action,
+            name: name.to_string(),
+            value: value.map(str::to_string),
+            pattern: None,
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_apply_header_rules() {
+        let mut headers = HeaderMap::new();
+        headers.insert("anthropic-beta", HeaderValue::from_static("foo"));
+        headers.insert("user-agent", HeaderValue::from_static("claude-cli/1.0.0"));
+
+        let mut replace = rule(HeaderRuleAction::Replace, "User-Agent", Some("my-cli/$1"));
+        replace.pattern = Some(r"claude-cli/(\S+)".to_string());
+        let mut disabled = rule(HeaderRuleAction::Set, "x-disabled", Some("1"));
+        disabled.enabled = false;
+        let rules = vec![
+            rule(HeaderRuleAction::Remove, "anthropic-beta", None),
+            rule(
+                HeaderRuleAction::Set,
+                "X-Title",
+                Some("cc-switch {session_id}"),
+            ),
+            rule(HeaderRuleAction::Append, "x-org", Some("{provider_id}")),
+            rule(HeaderRuleAction::Append, "x-org", Some("extra")),
+            replace,
+            disabled,
+            rule(HeaderRuleAction::Remove, "x-missing", None),
+        ];
+        let vars = TemplateVars {
+            session_id: Some("s-1"),
+            provider_id: "p1",
+            ..Default::default()
+        };
+
+        let applied = apply_header_rules(&mut headers, &rules, &vars);
+        assert!(headers.get("anthropic-beta").is_none());
+        assert_eq!(headers.get("x-title").unwrap(), "cc-switch s-1");
+        assert_eq!(headers.get_all("x-org").iter().count(), 2);
+        assert_eq!(headers.get("user-agent").unwrap(), "my-cli/1.0.0");
+        assert!(headers.get("x-disabled").is_none());
+        assert_eq!(
+            applied,
+            vec![
+                "remove anthropic-beta",
+                "set x-title",
+                "append x-org",
+                "append x-org",
+                "replace user-agent"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_rules_are_skipped() {
+        let mut headers = HeaderMap::new();
+        let mut bad_regex = rule(HeaderRuleAction::Replace, "user-agent", Some("x"));
+        bad_regex.pattern = Some("(".to_string());
+        let rules = vec![
+            rule(HeaderRuleAction::Set, "bad header", Some("1")),
+            rule(HeaderRuleAction::Set, "x-bad-value", Some("line\nbreak")),
+            bad_regex,
+        ];
+        assert!(apply_header_rules(&mut headers, &rules, &TemplateVars::default()).is_empty());
+        assert!(headers.is_empty());
+    }
+}
@@ -1,7 +1,298 @@
 //! 健康检查器
 //!
-//! 负责定期检查Provider健康状态（占位实现）
+//! 后台定期对故障转移队列中的供应商做流式探测，结果通过 `ProviderRouter::record_result` 计入熔断器：
+//! - 熔断中的供应商冷却结束后由探测占用半开名额，恢复不再依赖用户请求
+//! - 健康的供应商按 `health_probe_interval_secs` 周期探测，提前发现故障
+//! - 每次探测按模型定价估算费用并写入探测日志，当日累计达到 `health_probe_daily_cost_usd` 后停止探测
 
-// 占位实现，稍后添加完整逻辑
-#[allow(dead_code)]
-pub struct HealthChecker;
+use crate::app_config::AppType;
+use crate::database::Database;
+use crate::provider::Provider;
+use crate::proxy::provider_router::ProviderRouter;
+use crate::proxy::spend_limit::parse_limit;
+use crate::proxy::usage::{CostCalculator, TokenUsage, UsageLogger};
+use crate::services::stream_check::{StreamCheckConfig, StreamCheckService};
+use chrono::{NaiveDate, Utc};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// 检查周期：熔断中的供应商最迟在冷却结束后一个周期内被探测
+const PROBE_TICK: Duration = Duration::from_secs(10);
+
+/// 健康供应商的最小探测间隔（秒）
+const MIN_PROBE_INTERVAL_SECS: u32 = 30;
+
+/// 探测请求的估算输入 token 数（单条 "hi" 消息，含各家协议的固定开销）
+const PROBE_INPUT_TOKENS: u32 = 16;
+
+/// 探测请求的估算输出 token 数（max_tokens = 1）
+const PROBE_OUTPUT_TOKENS: u32 = 1;
+
+/// 每日探测次数计数（跨日自动清零）
+#[derive(Debug, Default)]
+struct DailyBudget {
+    date: Option<NaiveDate>,
+    used: u32,
+}
+
+impl DailyBudget {
+    /// 今日额度是否已用完，`limit` 为 0 表示不限
+    fn used_up(&self, today: NaiveDate, limit: u32) -> bool {
+        limit > 0 && self.date == Some(today) && self.used >= limit
+    }
+
+    /// 占用一次探测额度
+    fn take(&mut self, today: NaiveDate) {
+        if self.date != Some(today) {
+            self.date = Some(today);
+            self.used = 0;
+        }
+        self.used += 1;
+    }
+}
+
+/// 后台健康探测器
+pub struct HealthChecker {
+    db: Arc<Database>,
+    router: Arc<ProviderRouter>,
+    /// 上次探测时间 - key 格式: "app_type:provider_id"
+    last_probed: HashMap<String, Instant>,
+    /// 每个应用当天的探测次数
+    budgets: HashMap<String, DailyBudget>,
+}
+
+impl HealthChecker {
+    pub fn new(db: Arc<Database>, router: Arc<ProviderRouter>) -> Self {
+        Self {
+            db,
+            router,
+            last_probed: HashMap::new(),
+            budgets: HashMap::new(),
+        }
+    }
+
+    /// 启动后台探测任务（代理停止时 abort 返回的句柄）
+    pub fn spawn(mut self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(PROBE_TICK);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                for app_type in [AppType::Claude, AppType::Codex, AppType::Gemini] {
+                    self.probe_app(&app_type).await;
+                }
+            }
+        })
+    }
+
+    async fn probe_app(&mut self, app_type: &AppType) {
+        let app = app_type.as_str();
+        let config = match self.db.get_proxy_config_for_app(app).await {
+            Ok(config) => config,
+            Err(e) => {
+                log::debug!("[{app}] 读取健康探测配置失败: {e}");
+                return;
+            }
+        };
+        // 仅在接管且开启自动故障转移时探测（其余情况熔断器不参与路由）
+        if !(config.enabled && config.auto_failover_enabled && config.health_probe_enabled) {
+            return;
+        }
+
+        let providers = match self.db.get_failover_providers(app) {
+            Ok(providers) => providers,
+            Err(e) => {
+                log::warn!("[{app}] 读取故障转移队列失败: {e}");
+                return;
+            }
+        };
+
+        // 探测不重试，失败直接计入熔断器
+        let mut check_config = StreamCheckConfig {
+            max_retries: 0,
+            ..self.db.get_stream_check_config().unwrap_or_default()
+        };
+        // 应用级探测模型只替换默认模型，供应商自身配置的模型优先（避免探测供应商不支持的模型）
+        if let Some(model) = non_empty(config.health_probe_model.as_deref()) {
+            let default_model = match app_type {
+                AppType::Claude => &mut check_config.claude_model,
+                AppType::Codex => &mut check_config.codex_model,
+                AppType::Gemini => &mut check_config.gemini_model,
+            };
+            *default_model = model.to_string();
+        }
+        let interval = Duration::from_secs(
+            config
+                .health_probe_interval_secs
+                .max(MIN_PROBE_INTERVAL_SECS) as u64,
+        );
+        let cost_cap = parse_limit(config.health_probe_daily_cost_usd.as_deref());
+
+        for provider in providers {
+            let key = format!("{app}:{}", provider.id);
+            let due = self
+                .last_probed
+                .get(&key)
+                .is_none_or(|at| at.elapsed() >= interval);
+
+            // 额度用完时不再占用半开名额，熔断器回到由用户请求探测
+            let today = chrono::Local::now().date_naive();
+            let budget = self.budgets.entry(app.to_string()).or_default();
+            if budget.used_up(today, config.health_probe_daily_limit) {
+                log::debug!("[{app}] 今日健康探测次数已达上限，跳过");
+                return;
+            }
+            if let Some(cap) = cost_cap {
+                // 与消费上限一致按 UTC 自然日统计
+                let day_start = Utc::now()
+                    .date_naive()
+                    .and_time(chrono::NaiveTime::MIN)
+                    .and_utc()
+                    .timestamp();
+                match self.db.get_health_probe_cost_since(app, day_start) {
+                    Ok(spent) if spent >= cap => {
+                        log::debug!(
+                            "[{app}] 今日健康探测费用 ${spent:.4} 已达上限 ${cap:.4}，跳过"
+                        );
+                        return;
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        // 无法确认费用时不探测，避免突破上限
+                        log::warn!("[{app}] 读取健康探测费用失败: {e}");
+                        return;
+                    }
+                }
+            }
+
+            // Closed 状态下 allow 无副作用；Open 冷却结束 / HalfOpen 空闲时会占用半开名额
+            let permit = self.router.allow_provider_request(&provider.id, app).await;
+            if !permit.allowed || (!permit.used_half_open_permit && !due) {
+                continue;
+            }
+            budget.take(today);
+
+            let model = non_empty(
+                provider
+                    .meta
+                    .as_ref()
+                    .and_then(|meta| meta.health_probe_model.as_deref()),
+            );
+            let result =
+                StreamCheckService::check_with_model(app_type, &provider, &check_config, model)
+                    .await;
+            self.last_probed.insert(key, Instant::now());
+
+            // 探测本身无法发起（缺少地址 / Key 等）不代表供应商故障，不计入熔断器
+            let result = match result {
+                Ok(result) => result,
+                Err(e) => {
+                    log::warn!(
+                        "[{app}] 健康探测 {} ({}) 未能发起: {e}",
+                        provider.name,
+                        provider.id
+                    );
+                    self.router
+                        .release_permit(&provider.id, app, permit.used_half_open_permit)
+                        .await;
+                    continue;
+                }
+            };
+
+            log::info!(
+                "[{app}] 健康探测 {} ({}): {}{}",
+                provider.name,
+                provider.id,
+                result.message,
+                if permit.used_half_open_permit {
+                    "（半开探测）"
+                } else {
+                    ""
+                }
+            );
+            // 失败的探测同样按估算费用计入（偏保守）；无定价的模型不计费
+            let cost = probe_cost(&self.db, &provider, &result.model_used);
+            let _ = self.db.save_health_probe_log(
+                &provider.id,
+                &provider.name,
+                app,
+                &result,
+                cost.as_deref(),
+            );
+
+            let error = (!result.success).then(|| format!("健康探测失败: {}", result.message));
+            if let Err(e) = self
+                .router
+                .record_result(
+                    &provider.id,
+                    app,
+                    permit.used_half_open_permit,
+                    result.success,
+                    error,
+                )
+                .await
+            {
+                log::warn!("[{app}] 记录健康探测结果失败: {e}");
+            }
+        }
+    }
+}
+
+fn non_empty(model: Option<&str>) -> Option<&str> {
+    model.map(str::trim).filter(|m| !m.is_empty())
+}
+
+/// 按模型定价与供应商成本倍数估算单次探测的费用
+fn probe_cost(db: &Database, provider: &Provider, model: &str) -> Option<String> {
+    let multiplier = provider
+        .meta
+        .as_ref()
+        .and_then(|meta| meta.cost_multiplier.as_deref())
+        .and_then(|cm| Decimal::from_str(cm).ok())
+        .unwrap_or(Decimal::from(1));
+    let pricing = UsageLogger::new(db).get_model_pricing(model).ok()??;
+    let usage = TokenUsage {
+        input_tokens: PROBE_INPUT_TOKENS,
+        output_tokens: PROBE_OUTPUT_TOKENS,
+        cache_read_tokens: 0,
+        cache_creation_tokens: 0,
+        model: Some(model.to_string()),
+    };
+    CostCalculator::try_calculate(&usage, Some(&pricing), multiplier)
+        .map(|cost| cost.total_cost.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_daily_budget_resets_next_day() {
+        let day1 = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let day2 = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let mut budget = DailyBudget::default();
+
+        budget.take(day1);
+        assert!(!budget.used_up(day1, 2));
+        budget.take(day1);
+        assert!(budget.used_up(day1, 2));
+
+        assert!(!budget.used_up(day2, 2));
+        budget.take(day2);
+        assert_eq!(budget.used, 1);
+    }
+
+    #[test]
+    fn test_daily_budget_zero_is_unlimited() {
+        let day = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let mut budget = DailyBudget::default();
+        for _ in 0..1000 {
+            budget.take(day);
+        }
+        assert!(!budget.used_up(day, 0));
+    }
+}
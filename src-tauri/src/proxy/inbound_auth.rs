@@ -0,0 +1,206 @@
+//! 代理入站认证
+//!
+//! 监听地址设为 `0.0.0.0` 等非回环地址时，局域网内的任何人都能通过代理使用我们的 API Key。
+//! 开启后要求客户端通过 `Authorization: Bearer` / `x-api-key` / `x-goog-api-key` 携带本地访问令牌，
+//! 并可按来源 CIDR 限制访问。校验发生在转发之前，客户端携带的令牌不会透传到上游
+//! （上游认证头由适配器按供应商配置重新设置）。
+
+use super::server::ProxyState;
+use super::ProxyError;
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::HeaderMap;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::net::{IpAddr, SocketAddr};
+
+/// 本地访问令牌前缀（用于识别 Live 配置中由代理写入的令牌）
+pub const ACCESS_TOKEN_PREFIX: &str = "ccs-proxy-";
+
+/// 生成新的本地访问令牌
+pub fn generate_access_token() -> String {
+    format!(
+        "{ACCESS_TOKEN_PREFIX}{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+/// 监听地址是否仅限本机访问
+pub fn is_loopback_address(listen_address: &str) -> bool {
+    listen_address == "localhost"
+        || listen_address
+            .trim_matches(|c| c == '[' || c == ']')
+            .parse::<IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+/// 解析 CIDR（如 `192.168.1.0/24`、`fd00::/8`），单个 IP 视为主机地址
+pub fn parse_cidr(value: &str) -> Option<(IpAddr, u8)> {
+    let value = value.trim();
+    let (addr, prefix) = match value.split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix.parse::<u8>().ok()?)),
+        None => (value, None),
+    };
+    let addr: IpAddr = addr.parse().ok()?;
+    let max = if addr.is_ipv4() { 32 } else { 128 };
+    let prefix = prefix.unwrap_or(max);
+    (prefix <= max).then_some((addr, prefix))
+}
+
+fn cidr_contains((network, prefix): (IpAddr, u8), ip: IpAddr) -> bool {
+    match (network, ip) {
+        (IpAddr::V4(network), IpAddr::V4(ip)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            u32::from(network) & mask == u32::from(ip) & mask
+        }
+        (IpAddr::V6(network), IpAddr::V6(ip)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            u128::from(network) & mask == u128::from(ip) & mask
+        }
+        _ => false,
+    }
+}
+
+/// 来源地址是否允许访问：回环地址始终允许，允许列表为空时不限制
+pub fn is_ip_allowed(ip: IpAddr, allowed_cidrs: &[String]) -> bool {
+    let ip = ip.to_canonical();
+    if ip.is_loopback() || allowed_cidrs.iter().all(|c| c.trim().is_empty()) {
+        return true;
+    }
+    allowed_cidrs
+        .iter()
+        .filter_map(|c| parse_cidr(c))
+        .any(|cidr| cidr_contains(cidr, ip))
+}
+
+/// 从请求头提取客户端令牌
+pub fn extract_client_token(headers: &HeaderMap) -> Option<&str> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+    };
+
+    header("authorization")
+        .map(|v| {
+            v.strip_prefix("Bearer ")
+                .or_else(|| v.strip_prefix("bearer "))
+                .unwrap_or(v)
+                .trim()
+        })
+        .or_else(|| header("x-api-key"))
+        .or_else(|| header("x-goog-api-key"))
+}
+
+/// 常量时间比较，避免通过响应时间猜测令牌
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// 入站认证中间件
+///
+/// `/health` 只检查来源地址，不要求令牌，便于探活。
+pub async fn require_client_auth(
+    State(state): State<ProxyState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let config = match state.db.get_global_proxy_config().await {
+        Ok(config) => config,
+        Err(e) => return ProxyError::DatabaseError(e.to_string()).into_response(),
+    };
+
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    if let Some(ip) = peer {
+        if !is_ip_allowed(ip, &config.allowed_cidrs) {
+            log::warn!("[InboundAuth] 拒绝来自 {ip} 的请求：不在允许列表中");
+            return ProxyError::Forbidden(format!("来源地址 {ip} 不在允许列表中")).into_response();
+        }
+    }
+
+    if config.auth_enabled && request.uri().path() != "/health" {
+        let expected = config.access_token.as_deref().unwrap_or_default();
+        let authorized = !expected.is_empty()
+            && extract_client_token(request.headers())
+                .is_some_and(|token| constant_time_eq(token, expected));
+        if !authorized {
+            log::warn!(
+                "[InboundAuth] 拒绝未携带有效访问令牌的请求: {} (来源: {})",
+                request.uri().path(),
+                peer.map(|ip| ip.to_string()).unwrap_or_default()
+            );
+            return ProxyError::AuthError("缺少或无效的本地访问令牌".to_string()).into_response();
+        }
+    }
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_cidr_matching() {
+        let allowed = vec!["192.168.1.0/24".to_string(), "10.0.0.5".to_string()];
+        assert!(is_ip_allowed(ip("192.168.1.42"), &allowed));
+        assert!(is_ip_allowed(ip("10.0.0.5"), &allowed));
+        assert!(!is_ip_allowed(ip("10.0.0.6"), &allowed));
+        assert!(!is_ip_allowed(ip("192.168.2.1"), &allowed));
+        // 回环地址（含 IPv4 映射的 IPv6）始终允许
+        assert!(is_ip_allowed(ip("127.0.0.1"), &allowed));
+        assert!(is_ip_allowed(ip("::ffff:192.168.1.9"), &allowed));
+        // 空列表不限制
+        assert!(is_ip_allowed(ip("203.0.113.7"), &[]));
+
+        assert!(is_ip_allowed(ip("fd00::1"), &["fd00::/8".to_string()]));
+        assert!(is_ip_allowed(ip("8.8.8.8"), &["0.0.0.0/0".to_string()]));
+        assert_eq!(parse_cidr("10.0.0.0/33"), None);
+        assert_eq!(parse_cidr("not-an-ip"), None);
+    }
+
+    #[test]
+    fn test_extract_client_token() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "authorization",
+            HeaderValue::from_static("Bearer ccs-proxy-abc"),
+        );
+        assert_eq!(extract_client_token(&headers), Some("ccs-proxy-abc"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-goog-api-key", HeaderValue::from_static("ccs-proxy-xyz"));
+        assert_eq!(extract_client_token(&headers), Some("ccs-proxy-xyz"));
+
+        assert_eq!(extract_client_token(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn test_loopback_address_and_token_format() {
+        assert!(is_loopback_address("127.0.0.1"));
+        assert!(is_loopback_address("::1"));
+        assert!(is_loopback_address("localhost"));
+        assert!(!is_loopback_address("0.0.0.0"));
+        assert!(!is_loopback_address("192.168.1.2"));
+
+        let token = generate_access_token();
+        assert!(token.starts_with(ACCESS_TOKEN_PREFIX));
+        assert_ne!(token, generate_access_token());
+        assert!(constant_time_eq(&token, &token.clone()));
+        assert!(!constant_time_eq(&token, "ccs-proxy-"));
+    }
+}
@@ -0,0 +1,521 @@
+//! API Key 池
+//!
+//! 供应商自身的 API Key 与 meta 中 `keyPool` 的 Key 组成候选列表，转发时按策略选择：
+//! - `round_robin`：轮询
+//! - `least_used`：选择本次运行中使用次数最少的 Key
+//! - `sticky`：同一会话固定使用同一个 Key
+//!
+//! 上游返回 401 时停用该 Key（直到手动重置或代理重启）；403 可能只是临时的风控或地区限制，
+//! 按连续次数指数退避停用；额度耗尽（402、带额度提示的 429）时停用一段时间。
+//! 停用后转发器换下一个可用的 Key 重试。状态只保存在内存中。
+
+use super::error::ProxyError;
+use super::providers::{AuthInfo, AuthStrategy};
+use crate::provider::{KeyPoolStrategy, Provider};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 额度耗尽时的默认停用时长（上游未给出 Retry-After 时）
+const QUOTA_DISABLE_DURATION: Duration = Duration::from_secs(3600);
+
+/// 403 首次停用时长，之后每次翻倍
+const FORBIDDEN_DISABLE_BASE: Duration = Duration::from_secs(60);
+
+/// 403 停用时长上限
+const FORBIDDEN_DISABLE_MAX: Duration = Duration::from_secs(3600);
+
+/// 每个供应商最多记住的会话数（sticky 策略），超出后清空重新分配
+const MAX_STICKY_SESSIONS: usize = 1024;
+
+/// 上游错误信息中表示额度耗尽的关键词（小写）
+const QUOTA_KEYWORDS: &[&str] = &[
+    "quota",
+    "insufficient",
+    "billing",
+    "credit",
+    "balance",
+    "余额",
+    "额度",
+];
+
+#[derive(Debug, Default)]
+struct PoolState {
+    cursor: usize,
+    /// Key -> 选中次数
+    usage: HashMap<String, u64>,
+    /// Key -> 停用信息
+    disabled: HashMap<String, DisabledKey>,
+    /// Key -> 403 累计次数（决定退避时长，手动重置时清零）
+    forbidden_strikes: HashMap<String, u32>,
+    /// 会话 ID -> Key
+    sticky: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone)]
+struct DisabledKey {
+    reason: String,
+    /// None 表示直到手动重置
+    until: Option<Instant>,
+}
+
+impl PoolState {
+    fn is_disabled(&mut self, key: &str) -> bool {
+        match self.disabled.get(key) {
+            Some(DisabledKey {
+                until: Some(until), ..
+            }) if *until <= Instant::now() => {
+                self.disabled.remove(key);
+                false
+            }
+            Some(_) => true,
+            None => false,
+        }
+    }
+}
+
+/// Key 的运行状态（用于界面展示，Key 已遮蔽）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolKeyStatus {
+    pub masked_key: String,
+    pub label: Option<String>,
+    /// 是否为供应商自身配置的 Key
+    pub primary: bool,
+    /// 配置中是否启用
+    pub enabled: bool,
+    /// 运行中被停用的原因
+    pub disabled_reason: Option<String>,
+    /// 剩余停用时间（秒），None 表示未停用或需手动重置
+    pub disabled_for_secs: Option<u64>,
+    /// 本次运行中被选中的次数
+    pub selected_count: u64,
+}
+
+/// 候选 Key
+#[derive(Debug, Clone)]
+struct Candidate {
+    key: String,
+    label: Option<String>,
+    primary: bool,
+    enabled: bool,
+}
+
+/// API Key 池管理器（key 格式: "app_type:provider_id"）
+#[derive(Debug, Default)]
+pub struct KeyPoolManager {
+    pools: Mutex<HashMap<String, PoolState>>,
+}
+
+impl KeyPoolManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 为本次请求选择 Key
+    ///
+    /// 供应商未配置 Key 池时返回 `Ok(None)`（使用供应商自身的 Key）；
+    /// 所有 Key 均被停用时返回错误，交给故障转移处理。
+    pub fn select(
+        &self,
+        provider: &Provider,
+        app_type: &str,
+        auth: Option<&AuthInfo>,
+        session_id: Option<&str>,
+    ) -> Result<Option<String>, ProxyError> {
+        let Some(pool) = provider.key_pool() else {
+            return Ok(None);
+        };
+        let candidates = candidates(provider, auth);
+
+        let mut pools = self.pools.lock().unwrap_or_else(|e| e.into_inner());
+        let state = pools.entry(pool_key(app_type, &provider.id)).or_default();
+        let available: Vec<&str> = candidates
+            .iter()
+            .filter(|c| c.enabled && !state.is_disabled(&c.key))
+            .map(|c| c.key.as_str())
+            .collect();
+        if available.is_empty() {
+            return Err(ProxyError::AuthError(format!(
+                "供应商 {} 的 API Key 均已停用",
+                provider.name
+            )));
+        }
+
+        let least_used = |state: &PoolState| {
+            available
+                .iter()
+                .min_by_key(|key| state.usage.get(**key).copied().unwrap_or(0))
+                .map(|key| key.to_string())
+                .unwrap_or_default()
+        };
+        let selected = match pool.strategy {
+            KeyPoolStrategy::RoundRobin => {
+                let key = available[state.cursor % available.len()].to_string();
+                state.cursor = state.cursor.wrapping_add(1);
+                key
+            }
+            KeyPoolStrategy::LeastUsed => least_used(state),
+            KeyPoolStrategy::Sticky => match session_id {
+                Some(session_id) => match state.sticky.get(session_id) {
+                    Some(key) if available.contains(&key.as_str()) => key.clone(),
+                    _ => {
+                        let key = least_used(state);
+                        if state.sticky.len() >= MAX_STICKY_SESSIONS {
+                            state.sticky.clear();
+                        }
+                        state.sticky.insert(session_id.to_string(), key.clone());
+                        key
+                    }
+                },
+                None => least_used(state),
+            },
+        };
+
+        *state.usage.entry(selected.clone()).or_default() += 1;
+        Ok(Some(selected))
+    }
+
+    /// 根据请求错误判断是否停用该 Key，返回是否已停用
+    pub fn report_failure(
+        &self,
+        provider: &Provider,
+        app_type: &str,
+        api_key: &str,
+        error: &ProxyError,
+    ) -> bool {
+        let Some((reason, policy)) = disable_reason(error) else {
+            return false;
+        };
+
+        let mut pools = self.pools.lock().unwrap_or_else(|e| e.into_inner());
+        let state = pools.entry(pool_key(app_type, &provider.id)).or_default();
+        state.sticky.retain(|_, key| key != api_key);
+        let duration = match policy {
+            DisablePolicy::UntilReset => None,
+            DisablePolicy::For(duration) => Some(duration),
+            DisablePolicy::Backoff => {
+                let strikes = state
+                    .forbidden_strikes
+                    .entry(api_key.to_string())
+                    .or_default();
+                *strikes += 1;
+                Some(forbidden_backoff(*strikes))
+            }
+        };
+        state.disabled.insert(
+            api_key.to_string(),
+            DisabledKey {
+                reason: reason.clone(),
+                until: duration.map(|d| Instant::now() + d),
+            },
+        );
+
+        log::warn!(
+            "[KeyPool] 供应商 {} 的 Key {} 已停用: {reason}",
+            provider.name,
+            mask(api_key)
+        );
+        true
+    }
+
+    /// 列出供应商 Key 池的运行状态
+    pub fn status(
+        &self,
+        provider: &Provider,
+        app_type: &str,
+        auth: Option<&AuthInfo>,
+    ) -> Vec<PoolKeyStatus> {
+        if provider.key_pool().is_none() {
+            return Vec::new();
+        }
+
+        let mut pools = self.pools.lock().unwrap_or_else(|e| e.into_inner());
+        let state = pools.entry(pool_key(app_type, &provider.id)).or_default();
+        let now = Instant::now();
+        candidates(provider, auth)
+            .into_iter()
+            .map(|c| {
+                let disabled = state
+                    .is_disabled(&c.key)
+                    .then(|| state.disabled.get(&c.key).cloned())
+                    .flatten();
+                PoolKeyStatus {
+                    masked_key: mask(&c.key),
+                    label: c.label,
+                    primary: c.primary,
+                    enabled: c.enabled,
+                    disabled_reason: disabled.as_ref().map(|d| d.reason.clone()),
+                    disabled_for_secs: disabled
+                        .and_then(|d| d.until)
+                        .map(|until| until.saturating_duration_since(now).as_secs()),
+                    selected_count: state.usage.get(&c.key).copied().unwrap_or(0),
+                }
+            })
+            .collect()
+    }
+
+    /// 重新启用供应商被停用的 Key
+    pub fn reset(&self, provider_id: &str, app_type: &str) {
+        let mut pools = self.pools.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(state) = pools.get_mut(&pool_key(app_type, provider_id)) {
+            state.disabled.clear();
+            state.forbidden_strikes.clear();
+        }
+    }
+}
+
+fn pool_key(app_type: &str, provider_id: &str) -> String {
+    format!("{app_type}:{provider_id}")
+}
+
+/// 遮蔽 Key（与日志中的显示一致，遮蔽方式与认证策略无关）
+pub fn mask(key: &str) -> String {
+    AuthInfo::new(key.to_string(), AuthStrategy::Bearer).masked_key()
+}
+
+/// 候选 Key：供应商自身的 Key 在前，其后为 Key 池中的 Key（去重）
+fn candidates(provider: &Provider, auth: Option<&AuthInfo>) -> Vec<Candidate> {
+    let mut candidates: Vec<Candidate> = auth
+        .filter(|auth| !auth.api_key.trim().is_empty())
+        .map(|auth| Candidate {
+            key: auth.api_key.clone(),
+            label: None,
+            primary: true,
+            enabled: true,
+        })
+        .into_iter()
+        .collect();
+
+    for key in provider
+        .key_pool()
+        .map(|p| p.keys.as_slice())
+        .unwrap_or_default()
+    {
+        let trimmed = key.key.trim();
+        if trimmed.is_empty() || candidates.iter().any(|c| c.key == trimmed) {
+            continue;
+        }
+        candidates.push(Candidate {
+            key: trimmed.to_string(),
+            label: key.label.clone(),
+            primary: false,
+            enabled: key.enabled,
+        });
+    }
+    candidates
+}
+
+/// Key 的停用方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DisablePolicy {
+    /// 直到手动重置或代理重启
+    UntilReset,
+    /// 停用固定时长
+    For(Duration),
+    /// 按累计次数指数退避
+    Backoff,
+}
+
+/// 第 `strikes` 次 403 的停用时长：从 `FORBIDDEN_DISABLE_BASE` 起翻倍，不超过上限
+fn forbidden_backoff(strikes: u32) -> Duration {
+    let factor = 1u32 << strikes.saturating_sub(1).min(16);
+    FORBIDDEN_DISABLE_BASE
+        .saturating_mul(factor)
+        .min(FORBIDDEN_DISABLE_MAX)
+}
+
+/// 需要停用 Key 的错误：401 永久停用，403 退避停用，额度耗尽停用一段时间
+fn disable_reason(error: &ProxyError) -> Option<(String, DisablePolicy)> {
+    let ProxyError::UpstreamError {
+        status,
+        body,
+        retry_after,
+    } = error
+    else {
+        return None;
+    };
+    let quota_exhausted = || {
+        body.as_deref().is_some_and(|body| {
+            let body = body.to_lowercase();
+            QUOTA_KEYWORDS.iter().any(|keyword| body.contains(keyword))
+        })
+    };
+    let quota_duration = DisablePolicy::For(retry_after.unwrap_or(QUOTA_DISABLE_DURATION));
+
+    match status {
+        401 => Some((format!("认证失败 ({status})"), DisablePolicy::UntilReset)),
+        403 => Some((format!("拒绝访问 ({status})"), DisablePolicy::Backoff)),
+        402 => Some((format!("额度不足 ({status})"), quota_duration)),
+        429 if quota_exhausted() => Some((format!("额度不足 ({status})"), quota_duration)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::{KeyPool, PoolKey, ProviderMeta};
+    use serde_json::json;
+
+    fn provider(strategy: KeyPoolStrategy) -> Provider {
+        let mut provider =
+            Provider::with_id("p1".to_string(), "Relay".to_string(), json!({}), None);
+        provider.meta = Some(ProviderMeta {
+            key_pool: Some(KeyPool {
+                strategy,
+                keys: ["sk-pool-b-0000", "sk-pool-c-0000", "sk-main-a-0000"]
+                    .iter()
+                    .map(|key| PoolKey {
+                        key: key.to_string(),
+                        label: None,
+                        enabled: true,
+                    })
+                    .collect(),
+            }),
+            ..Default::default()
+        });
+        provider
+    }
+
+    fn auth() -> AuthInfo {
+        AuthInfo::new("sk-main-a-0000".to_string(), AuthStrategy::Anthropic)
+    }
+
+    fn upstream(status: u16, body: &str) -> ProxyError {
+        ProxyError::UpstreamError {
+            status,
+            body: Some(body.to_string()),
+            retry_after: None,
+        }
+    }
+
+    #[test]
+    fn test_round_robin_and_disable_on_auth_error() {
+        let manager = KeyPoolManager::new();
+        let provider = provider(KeyPoolStrategy::RoundRobin);
+        let auth = auth();
+        let select = || {
+            manager
+                .select(&provider, "claude", Some(&auth), None)
+                .unwrap()
+                .unwrap()
+        };
+
+        // 供应商自身的 Key 在前，重复的 Key 只出现一次
+        assert_eq!(
+            [select(), select(), select(), select()],
+            [
+                "sk-main-a-0000",
+                "sk-pool-b-0000",
+                "sk-pool-c-0000",
+                "sk-main-a-0000"
+            ]
+        );
+
+        assert!(!manager.report_failure(&provider, "claude", "sk-pool-b-0000", &upstream(500, "")));
+        assert!(manager.report_failure(&provider, "claude", "sk-pool-b-0000", &upstream(401, "")));
+        assert!(manager.report_failure(
+            &provider,
+            "claude",
+            "sk-pool-c-0000",
+            &upstream(
+                429,
+                r#"{"error":{"message":"You exceeded your current quota"}}"#
+            )
+        ));
+        assert_eq!(select(), "sk-main-a-0000");
+        assert_eq!(select(), "sk-main-a-0000");
+
+        let status = manager.status(&provider, "claude", Some(&auth));
+        assert_eq!(status.len(), 3);
+        assert_eq!(status[1].masked_key, "sk-p...0000");
+        assert_eq!(status[1].disabled_reason.as_deref(), Some("认证失败 (401)"));
+        assert!(status[1].disabled_for_secs.is_none());
+        assert!(status[2].disabled_for_secs.is_some());
+
+        assert!(manager.report_failure(&provider, "claude", "sk-main-a-0000", &upstream(403, "")));
+        assert!(manager
+            .select(&provider, "claude", Some(&auth), None)
+            .is_err());
+        // 403 只是临时停用
+        let status = manager.status(&provider, "claude", Some(&auth));
+        assert_eq!(status[0].disabled_reason.as_deref(), Some("拒绝访问 (403)"));
+        assert!(status[0].disabled_for_secs.is_some_and(|s| s <= 60));
+
+        manager.reset("p1", "claude");
+        assert!(manager
+            .select(&provider, "claude", Some(&auth), None)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_forbidden_backoff_doubles_until_cap() {
+        assert_eq!(forbidden_backoff(1), Duration::from_secs(60));
+        assert_eq!(forbidden_backoff(2), Duration::from_secs(120));
+        assert_eq!(forbidden_backoff(3), Duration::from_secs(240));
+        assert_eq!(forbidden_backoff(7), FORBIDDEN_DISABLE_MAX);
+        assert_eq!(forbidden_backoff(u32::MAX), FORBIDDEN_DISABLE_MAX);
+
+        let manager = KeyPoolManager::new();
+        let provider = provider(KeyPoolStrategy::RoundRobin);
+        let auth = auth();
+        for _ in 0..2 {
+            manager.report_failure(&provider, "claude", "sk-pool-b-0000", &upstream(403, ""));
+        }
+        let status = manager.status(&provider, "claude", Some(&auth));
+        assert!(status[1]
+            .disabled_for_secs
+            .is_some_and(|s| (61..=120).contains(&s)));
+    }
+
+    #[test]
+    fn test_sticky_and_least_used() {
+        let manager = KeyPoolManager::new();
+        let sticky = provider(KeyPoolStrategy::Sticky);
+        let first = manager
+            .select(&sticky, "claude", None, Some("s1"))
+            .unwrap()
+            .unwrap();
+        for _ in 0..3 {
+            assert_eq!(
+                manager
+                    .select(&sticky, "claude", None, Some("s1"))
+                    .unwrap()
+                    .unwrap(),
+                first
+            );
+        }
+        // 新会话分配到使用最少的 Key
+        let second = manager
+            .select(&sticky, "claude", None, Some("s2"))
+            .unwrap()
+            .unwrap();
+        assert_ne!(second, first);
+
+        let least_used = provider(KeyPoolStrategy::LeastUsed);
+        let picks: Vec<String> = (0..3)
+            .map(|_| {
+                manager
+                    .select(&least_used, "codex", None, None)
+                    .unwrap()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(picks.len(), 3);
+        assert!(picks
+            .iter()
+            .all(|k| picks.iter().filter(|p| *p == k).count() == 1));
+    }
+
+    #[test]
+    fn test_provider_without_pool_uses_own_key() {
+        let manager = KeyPoolManager::new();
+        let provider = Provider::with_id("p2".to_string(), "Plain".to_string(), json!({}), None);
+        assert!(manager
+            .select(&provider, "claude", Some(&auth()), None)
+            .unwrap()
+            .is_none());
+    }
+}
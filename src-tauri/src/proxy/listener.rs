@@ -0,0 +1,219 @@
+//! 代理监听器
+//!
+//! 支持三种监听方式：
+//! - `tcp`：明文 HTTP，使用 `axum::serve`
+//! - `tls`：HTTPS，证书见 [`super::tls`]
+//! - `unix`：Unix 域套接字，仅限本机，没有来源 IP（入站认证的 CIDR 校验会跳过，令牌校验仍然生效）
+//!
+//! TLS 与 Unix 套接字使用手写的 accept 循环，由 hyper 逐连接提供 HTTP/1.1 服务，关闭时等待在途连接结束。
+
+use super::types::{ListenMode, ProxyConfig};
+use super::ProxyError;
+use axum::extract::ConnectInfo;
+use axum::Router;
+use hyper::body::Incoming;
+use hyper_util::rt::TokioIo;
+use hyper_util::server::graceful::GracefulShutdown;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tokio_rustls::TlsAcceptor;
+use tower::Service;
+
+/// 默认 Unix 域套接字文件名（位于应用配置目录）
+const DEFAULT_SOCKET_FILE: &str = "proxy.sock";
+
+/// 解析 Unix 域套接字路径，未配置时使用 `~/.cc-switch/proxy.sock`
+pub fn resolve_unix_socket_path(path: Option<&str>) -> PathBuf {
+    path.map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| crate::config::get_app_config_dir().join(DEFAULT_SOCKET_FILE))
+}
+
+/// 已绑定的监听器
+pub enum ProxyListener {
+    Tcp(TcpListener),
+    Tls(TcpListener, TlsAcceptor),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixListener, PathBuf),
+}
+
+impl ProxyListener {
+    /// 按配置绑定监听器
+    pub async fn bind(config: &ProxyConfig) -> Result<Self, ProxyError> {
+        match config.listen_mode {
+            ListenMode::Tcp => Ok(Self::Tcp(bind_tcp(config).await?)),
+            ListenMode::Tls => {
+                // 先加载证书，证书有问题时不占用端口
+                let acceptor = super::tls::build_acceptor(
+                    config.tls_cert_path.as_deref(),
+                    config.tls_key_path.as_deref(),
+                )?;
+                Ok(Self::Tls(bind_tcp(config).await?, acceptor))
+            }
+            #[cfg(unix)]
+            ListenMode::Unix => {
+                let path = resolve_unix_socket_path(config.unix_socket_path.as_deref());
+                bind_unix(path)
+            }
+            #[cfg(not(unix))]
+            ListenMode::Unix => Err(ProxyError::BindFailed(
+                "当前平台不支持 Unix 域套接字监听".to_string(),
+            )),
+        }
+    }
+
+    /// 监听位置描述（用于日志）
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Tcp(listener) => format!("http://{}", local_addr(listener)),
+            Self::Tls(listener, _) => format!("https://{}", local_addr(listener)),
+            #[cfg(unix)]
+            Self::Unix(_, path) => format!("unix:{}", path.display()),
+        }
+    }
+
+    /// 提供服务直到收到关闭信号
+    pub async fn serve(self, app: Router, shutdown_rx: oneshot::Receiver<()>) {
+        match self {
+            Self::Tcp(listener) => {
+                axum::serve(
+                    listener,
+                    app.into_make_service_with_connect_info::<SocketAddr>(),
+                )
+                .with_graceful_shutdown(async {
+                    shutdown_rx.await.ok();
+                })
+                .await
+                .ok();
+            }
+            Self::Tls(listener, acceptor) => {
+                serve_accept_loop(app, shutdown_rx, || async {
+                    let (stream, addr) = listener.accept().await?;
+                    let acceptor = acceptor.clone();
+                    Ok((async move { acceptor.accept(stream).await }, Some(addr)))
+                })
+                .await;
+            }
+            #[cfg(unix)]
+            Self::Unix(listener, path) => {
+                serve_accept_loop(app, shutdown_rx, || async {
+                    let (stream, _) = listener.accept().await?;
+                    Ok((async move { Ok(stream) }, None))
+                })
+                .await;
+                if let Err(e) = std::fs::remove_file(&path) {
+                    log::debug!("删除 Unix 套接字文件失败 {}: {e}", path.display());
+                }
+            }
+        }
+    }
+}
+
+async fn bind_tcp(config: &ProxyConfig) -> Result<TcpListener, ProxyError> {
+    let addr: SocketAddr = format!("{}:{}", config.listen_address, config.listen_port)
+        .parse()
+        .map_err(|e| ProxyError::BindFailed(format!("无效的地址: {e}")))?;
+    TcpListener::bind(&addr)
+        .await
+        .map_err(|e| ProxyError::BindFailed(e.to_string()))
+}
+
+#[cfg(unix)]
+fn bind_unix(path: PathBuf) -> Result<ProxyListener, ProxyError> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| ProxyError::BindFailed(format!("创建套接字目录失败: {e}")))?;
+    }
+    // 清理上次异常退出遗留的套接字文件；普通文件不删除，避免误删用户数据
+    if let Ok(meta) = std::fs::symlink_metadata(&path) {
+        if !meta.file_type().is_socket() {
+            return Err(ProxyError::BindFailed(format!(
+                "{} 已存在且不是套接字文件",
+                path.display()
+            )));
+        }
+        std::fs::remove_file(&path)
+            .map_err(|e| ProxyError::BindFailed(format!("删除旧套接字文件失败: {e}")))?;
+    }
+
+    let listener = tokio::net::UnixListener::bind(&path)
+        .map_err(|e| ProxyError::BindFailed(format!("{}: {e}", path.display())))?;
+    // 仅当前用户可连接
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+        .map_err(|e| ProxyError::BindFailed(format!("设置套接字权限失败: {e}")))?;
+
+    Ok(ProxyListener::Unix(listener, path))
+}
+
+fn local_addr(listener: &TcpListener) -> String {
+    listener
+        .local_addr()
+        .map(|addr| addr.to_string())
+        .unwrap_or_default()
+}
+
+/// 手写 accept 循环
+///
+/// `accept` 返回 (连接握手 future, 来源地址)；握手（TLS）在独立任务中完成，避免慢客户端阻塞 accept。
+async fn serve_accept_loop<A, AF, H, S>(
+    app: Router,
+    mut shutdown_rx: oneshot::Receiver<()>,
+    accept: A,
+) where
+    A: Fn() -> AF,
+    AF: std::future::Future<Output = std::io::Result<(H, Option<SocketAddr>)>>,
+    H: std::future::Future<Output = std::io::Result<S>> + Send + 'static,
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let graceful = GracefulShutdown::new();
+
+    loop {
+        let (handshake, addr) = tokio::select! {
+            _ = &mut shutdown_rx => break,
+            accepted = accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    log::warn!("接受代理连接失败: {e}");
+                    // 避免 fd 耗尽等持续错误时空转
+                    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                    continue;
+                }
+            },
+        };
+
+        let app = app.clone();
+        let watcher = graceful.watcher();
+        tokio::spawn(async move {
+            let stream = match handshake.await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    log::debug!("代理连接握手失败: {e}");
+                    return;
+                }
+            };
+
+            let service =
+                hyper::service::service_fn(move |mut request: hyper::Request<Incoming>| {
+                    if let Some(addr) = addr {
+                        request.extensions_mut().insert(ConnectInfo(addr));
+                    }
+                    // Router 始终就绪，无需 poll_ready
+                    app.clone().call(request)
+                });
+            let connection = hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service);
+            if let Err(e) = watcher.watch(connection).await {
+                log::debug!("代理连接异常结束: {e}");
+            }
+        });
+    }
+
+    // 与 axum::serve 的优雅关闭一致：停止接受新连接，等待在途请求完成
+    graceful.shutdown().await;
+}
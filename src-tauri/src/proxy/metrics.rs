@@ -0,0 +1,444 @@
+//! Prometheus 指标
+//!
+//! 为 `/metrics` 端点提供 Prometheus 文本格式（0.0.4）的指标：
+//! - 请求数、token 数、费用：由 `UsageLogger` 在记录请求日志时累加
+//! - 延迟与首字节时间（TTFB）直方图：同上
+//! - 连接数、故障转移次数：来自 `ProxyStatus`
+//! - 熔断器状态：来自 `CircuitBreakerStats`
+//!
+//! 指标只保存在内存中，代理重启后从零开始计数。
+
+use super::circuit_breaker::{CircuitBreakerStats, CircuitState};
+use super::types::ProxyStatus;
+use super::usage::logger::RequestLog;
+use rust_decimal::prelude::ToPrimitive;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+
+/// Prometheus 文本格式的 Content-Type
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// 请求延迟直方图的桶上界（秒）
+const LATENCY_BUCKETS: &[f64] = &[
+    0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0,
+];
+
+/// 首字节时间直方图的桶上界（秒）
+const TTFB_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// (app, provider, model)
+type ModelKey = (String, String, String);
+
+/// (app, provider, model, status)
+type StatusKey = (String, String, String, u16);
+
+struct Histogram {
+    bounds: &'static [f64],
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            counts: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        for (bound, count) in self.bounds.iter().zip(self.counts.iter_mut()) {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+#[derive(Default)]
+struct MetricsInner {
+    requests: BTreeMap<StatusKey, u64>,
+    cache_hits: BTreeMap<ModelKey, u64>,
+    /// key 额外包含 token 类型（input / output / cache_read / cache_creation）
+    tokens: BTreeMap<(ModelKey, &'static str), u64>,
+    cost_usd: BTreeMap<ModelKey, f64>,
+    latency: BTreeMap<ModelKey, Histogram>,
+    ttfb: BTreeMap<ModelKey, Histogram>,
+}
+
+/// 熔断器状态样本
+pub struct BreakerSample {
+    pub app_type: String,
+    pub provider_id: String,
+    pub stats: CircuitBreakerStats,
+}
+
+/// 代理指标注册表
+#[derive(Default)]
+pub struct ProxyMetrics {
+    inner: Mutex<MetricsInner>,
+}
+
+impl ProxyMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 累加一条请求日志
+    pub fn observe_request(&self, log: &RequestLog) {
+        let key: ModelKey = (
+            log.app_type.clone(),
+            log.provider_id.clone(),
+            log.model.clone(),
+        );
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+
+        *inner
+            .requests
+            .entry((key.0.clone(), key.1.clone(), key.2.clone(), log.status_code))
+            .or_default() += 1;
+
+        if log.cache_hit {
+            *inner.cache_hits.entry(key.clone()).or_default() += 1;
+        }
+
+        for (kind, value) in [
+            ("input", log.usage.input_tokens),
+            ("output", log.usage.output_tokens),
+            ("cache_read", log.usage.cache_read_tokens),
+            ("cache_creation", log.usage.cache_creation_tokens),
+        ] {
+            if value > 0 {
+                *inner.tokens.entry((key.clone(), kind)).or_default() += value as u64;
+            }
+        }
+
+        if let Some(cost) = log.cost.as_ref().and_then(|c| c.total_cost.to_f64()) {
+            *inner.cost_usd.entry(key.clone()).or_default() += cost;
+        }
+
+        if let Some(first_token_ms) = log.first_token_ms {
+            inner
+                .ttfb
+                .entry(key.clone())
+                .or_insert_with(|| Histogram::new(TTFB_BUCKETS))
+                .observe(first_token_ms as f64 / 1000.0);
+        }
+
+        inner
+            .latency
+            .entry(key)
+            .or_insert_with(|| Histogram::new(LATENCY_BUCKETS))
+            .observe(log.latency_ms as f64 / 1000.0);
+    }
+
+    /// 渲染为 Prometheus 文本格式
+    pub fn render(&self, status: &ProxyStatus, breakers: &[BreakerSample]) -> String {
+        let mut out = String::new();
+
+        write_header(&mut out, "cc_switch_proxy_up", "gauge", "代理服务是否运行");
+        let _ = writeln!(out, "cc_switch_proxy_up {}", status.running as u8);
+        write_header(
+            &mut out,
+            "cc_switch_proxy_uptime_seconds",
+            "gauge",
+            "代理运行时间（秒）",
+        );
+        let _ = writeln!(
+            out,
+            "cc_switch_proxy_uptime_seconds {}",
+            status.uptime_seconds
+        );
+        write_header(
+            &mut out,
+            "cc_switch_proxy_active_connections",
+            "gauge",
+            "活跃连接数",
+        );
+        let _ = writeln!(
+            out,
+            "cc_switch_proxy_active_connections {}",
+            status.active_connections
+        );
+        write_header(
+            &mut out,
+            "cc_switch_proxy_failovers_total",
+            "counter",
+            "供应商故障转移次数",
+        );
+        let _ = writeln!(
+            out,
+            "cc_switch_proxy_failovers_total {}",
+            status.failover_count
+        );
+
+        {
+            let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+
+            write_header(
+                &mut out,
+                "cc_switch_proxy_requests_total",
+                "counter",
+                "代理请求数",
+            );
+            for ((app, provider, model, status_code), value) in &inner.requests {
+                let labels = labels(&[
+                    ("app", app),
+                    ("provider", provider),
+                    ("model", model),
+                    ("status", &status_code.to_string()),
+                ]);
+                let _ = writeln!(out, "cc_switch_proxy_requests_total{labels} {value}");
+            }
+
+            write_header(
+                &mut out,
+                "cc_switch_proxy_cache_hits_total",
+                "counter",
+                "命中本地响应缓存的请求数",
+            );
+            for (key, value) in &inner.cache_hits {
+                let _ = writeln!(
+                    out,
+                    "cc_switch_proxy_cache_hits_total{} {value}",
+                    model_labels(key, &[])
+                );
+            }
+
+            write_header(
+                &mut out,
+                "cc_switch_proxy_tokens_total",
+                "counter",
+                "token 用量",
+            );
+            for ((key, kind), value) in &inner.tokens {
+                let _ = writeln!(
+                    out,
+                    "cc_switch_proxy_tokens_total{} {value}",
+                    model_labels(key, &[("type", kind)])
+                );
+            }
+
+            write_header(
+                &mut out,
+                "cc_switch_proxy_cost_usd_total",
+                "counter",
+                "请求费用（USD）",
+            );
+            for (key, value) in &inner.cost_usd {
+                let _ = writeln!(
+                    out,
+                    "cc_switch_proxy_cost_usd_total{} {value}",
+                    model_labels(key, &[])
+                );
+            }
+
+            write_histograms(
+                &mut out,
+                "cc_switch_proxy_request_duration_seconds",
+                "请求总耗时（秒）",
+                &inner.latency,
+            );
+            write_histograms(
+                &mut out,
+                "cc_switch_proxy_time_to_first_byte_seconds",
+                "首字节时间（秒，仅流式请求）",
+                &inner.ttfb,
+            );
+        }
+
+        write_header(
+            &mut out,
+            "cc_switch_proxy_circuit_breaker_state",
+            "gauge",
+            "熔断器状态（0=closed, 1=open, 2=half_open）",
+        );
+        for sample in breakers {
+            let state = match sample.stats.state {
+                CircuitState::Closed => 0,
+                CircuitState::Open => 1,
+                CircuitState::HalfOpen => 2,
+            };
+            let _ = writeln!(
+                out,
+                "cc_switch_proxy_circuit_breaker_state{} {state}",
+                breaker_labels(sample)
+            );
+        }
+
+        write_header(
+            &mut out,
+            "cc_switch_proxy_circuit_breaker_consecutive_failures",
+            "gauge",
+            "熔断器连续失败次数",
+        );
+        for sample in breakers {
+            let _ = writeln!(
+                out,
+                "cc_switch_proxy_circuit_breaker_consecutive_failures{} {}",
+                breaker_labels(sample),
+                sample.stats.consecutive_failures
+            );
+        }
+
+        write_header(
+            &mut out,
+            "cc_switch_proxy_provider_cooldown_seconds",
+            "gauge",
+            "上游限流冷却剩余秒数",
+        );
+        for sample in breakers {
+            let _ = writeln!(
+                out,
+                "cc_switch_proxy_provider_cooldown_seconds{} {}",
+                breaker_labels(sample),
+                sample.stats.cooldown_remaining_secs.unwrap_or(0)
+            );
+        }
+
+        out
+    }
+}
+
+fn write_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+fn write_histograms(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    histograms: &BTreeMap<ModelKey, Histogram>,
+) {
+    write_header(out, name, "histogram", help);
+    for (key, histogram) in histograms {
+        for (bound, count) in histogram.bounds.iter().zip(&histogram.counts) {
+            let _ = writeln!(
+                out,
+                "{name}_bucket{} {count}",
+                model_labels(key, &[("le", &bound.to_string())])
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{name}_bucket{} {}",
+            model_labels(key, &[("le", "+Inf")]),
+            histogram.count
+        );
+        let _ = writeln!(
+            out,
+            "{name}_sum{} {}",
+            model_labels(key, &[]),
+            histogram.sum
+        );
+        let _ = writeln!(
+            out,
+            "{name}_count{} {}",
+            model_labels(key, &[]),
+            histogram.count
+        );
+    }
+}
+
+fn model_labels((app, provider, model): &ModelKey, extra: &[(&str, &str)]) -> String {
+    let mut pairs = vec![
+        ("app", app.as_str()),
+        ("provider", provider),
+        ("model", model),
+    ];
+    pairs.extend_from_slice(extra);
+    labels(&pairs)
+}
+
+fn breaker_labels(sample: &BreakerSample) -> String {
+    labels(&[("app", &sample.app_type), ("provider", &sample.provider_id)])
+}
+
+fn labels(pairs: &[(&str, &str)]) -> String {
+    let body = pairs
+        .iter()
+        .map(|(name, value)| format!("{name}=\"{}\"", escape_label(value)))
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("{{{body}}}")
+}
+
+/// 转义标签值中的反斜杠、双引号和换行
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::usage::parser::TokenUsage;
+
+    fn request_log(status_code: u16, latency_ms: u64, first_token_ms: Option<u64>) -> RequestLog {
+        RequestLog {
+            request_id: "r1".to_string(),
+            provider_id: "p1".to_string(),
+            app_type: "claude".to_string(),
+            model: "claude-sonnet-4".to_string(),
+            usage: TokenUsage {
+                input_tokens: 100,
+                output_tokens: 20,
+                ..Default::default()
+            },
+            cost: None,
+            latency_ms,
+            first_token_ms,
+            status_code,
+            error_message: None,
+            session_id: None,
+            provider_type: None,
+            is_streaming: first_token_ms.is_some(),
+            cost_multiplier: "1".to_string(),
+            cache_hit: false,
+        }
+    }
+
+    #[test]
+    fn test_render_counters_and_histograms() {
+        let metrics = ProxyMetrics::new();
+        metrics.observe_request(&request_log(200, 800, Some(200)));
+        metrics.observe_request(&request_log(200, 3000, None));
+        metrics.observe_request(&request_log(500, 100, None));
+
+        let text = metrics.render(&ProxyStatus::default(), &[]);
+        let labels = r#"app="claude",provider="p1",model="claude-sonnet-4""#;
+
+        assert!(text.contains(&format!(
+            "cc_switch_proxy_requests_total{{{labels},status=\"200\"}} 2"
+        )));
+        assert!(text.contains(&format!(
+            "cc_switch_proxy_requests_total{{{labels},status=\"500\"}} 1"
+        )));
+        assert!(text.contains(&format!(
+            "cc_switch_proxy_tokens_total{{{labels},type=\"input\"}} 300"
+        )));
+        assert!(text.contains(&format!(
+            "cc_switch_proxy_request_duration_seconds_bucket{{{labels},le=\"1\"}} 2"
+        )));
+        assert!(text.contains(&format!(
+            "cc_switch_proxy_request_duration_seconds_count{{{labels}}} 3"
+        )));
+        assert!(text.contains(&format!(
+            "cc_switch_proxy_time_to_first_byte_seconds_count{{{labels}}} 1"
+        )));
+    }
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
@@ -2,7 +2,10 @@
 //!
 //! 提供本地HTTP代理服务，支持多Provider故障转移和请求透传
 
+pub mod body_patch;
 pub mod circuit_breaker;
+pub mod compat;
+pub mod cooldown;
 pub mod error;
 pub mod error_mapper;
 pub(crate) mod failover_switch;
@@ -10,14 +13,28 @@ mod forwarder;
 pub mod handler_config;
 pub mod handler_context;
 mod handlers;
+pub mod header_rules;
 mod health;
+pub mod http_client;
+pub mod inbound_auth;
+pub mod key_pool;
+pub mod listener;
+pub mod metrics;
 pub mod model_mapper;
+pub mod payload_capture;
 pub mod provider_router;
 pub mod providers;
+pub mod rate_limiter;
+pub mod response_cache;
 pub mod response_handler;
 pub mod response_processor;
+pub mod routing_rules;
+pub mod schedule;
 pub(crate) mod server;
 pub mod session;
+pub mod spend_limit;
+pub mod stream_recovery;
+pub mod tls;
 pub(crate) mod types;
 pub mod usage;
 
@@ -28,6 +45,7 @@ pub use circuit_breaker::{
 };
 #[allow(unused_imports)]
 pub use error::ProxyError;
+pub(crate) use forwarder::{send_upstream_request, UpstreamOptions};
 #[allow(unused_imports)]
 pub use provider_router::ProviderRouter;
 #[allow(unused_imports)]
@@ -0,0 +1,400 @@
+//! 请求/响应载荷捕获
+//!
+//! 调试用的可选功能：按请求保存请求体、响应体（流式响应保存 `SseUsageCollector` 收集到的事件）
+//! 以及请求/响应头，通过 request_id / session_id 与 `proxy_request_logs` 关联。
+//! 请求头中的密钥只保留首尾 4 个字符（同 `AuthInfo::masked_key`），Cookie 整体遮蔽后再落库。
+
+use super::header_rules::UpstreamRequestInfo;
+use crate::database::Database;
+use axum::http::HeaderMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::sync::Arc;
+
+/// 需要遮蔽的请求/响应头（小写）
+const SENSITIVE_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "x-api-key",
+    "x-goog-api-key",
+    "api-key",
+    "cookie",
+    "set-cookie",
+];
+
+/// 整体遮蔽的请求/响应头（值由多个键值对组成，不保留任何部分）
+const OPAQUE_SENSITIVE_HEADERS: &[&str] = &["cookie", "set-cookie"];
+
+/// 遮蔽时保留的认证方案前缀（小写）
+const AUTH_SCHEMES: &[&str] = &["bearer", "basic"];
+
+/// 需要遮蔽的查询参数（Gemini 支持 `?key=` 传递 API Key）
+const SENSITIVE_QUERY_PARAMS: &[&str] = &["key", "api_key"];
+
+/// 捕获的请求载荷
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestPayload {
+    pub request_id: String,
+    pub session_id: Option<String>,
+    pub app_type: String,
+    pub provider_id: String,
+    pub model: String,
+    pub endpoint: String,
+    pub request_headers: Value,
+    pub request_body: String,
+    pub status_code: u16,
+    pub response_headers: Option<Value>,
+    pub response_body: Option<String>,
+    pub is_streaming: bool,
+    /// 请求体或响应体是否因超出大小上限被截断
+    pub truncated: bool,
+    pub created_at: i64,
+    /// 实际发往上游的请求头（执行请求头改写规则之后，已遮蔽）
+    #[serde(default)]
+    pub upstream_headers: Option<Value>,
+    /// 生效的请求头改写规则
+    #[serde(default)]
+    pub header_rewrites: Vec<String>,
+}
+
+/// 会话中的一轮请求（不含载荷正文，用于会话视图列表）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionTurn {
+    pub request_id: String,
+    pub app_type: String,
+    pub provider_id: String,
+    pub provider_name: Option<String>,
+    pub model: String,
+    pub endpoint: String,
+    pub status_code: u16,
+    pub is_streaming: bool,
+    pub request_bytes: u64,
+    pub response_bytes: u64,
+    pub input_tokens: Option<u32>,
+    pub output_tokens: Option<u32>,
+    pub total_cost_usd: Option<String>,
+    pub latency_ms: Option<u64>,
+    pub created_at: i64,
+}
+
+/// 是否为需要遮蔽的敏感请求/响应头
+pub fn is_sensitive_header(name: &str) -> bool {
+    SENSITIVE_HEADERS.contains(&name.to_lowercase().as_str())
+}
+
+/// 遮蔽密钥，仅保留 `Bearer ` / `Basic ` 认证方案前缀
+pub fn mask_secret(value: &str) -> String {
+    match value.split_once(' ') {
+        Some((scheme, secret)) if AUTH_SCHEMES.contains(&scheme.to_lowercase().as_str()) => {
+            format!("{scheme} {}", mask_chars(secret.trim()))
+        }
+        _ => mask_chars(value.trim()),
+    }
+}
+
+/// 显示前 4 个和后 4 个字符，中间用 `...` 代替；不足 9 个字符时返回 `***`
+///
+/// 按字符而非字节截取，非 ASCII 值（含 `from_utf8_lossy` 产生的替换字符）不会 panic
+fn mask_chars(secret: &str) -> String {
+    let count = secret.chars().count();
+    if count <= 8 {
+        return "***".to_string();
+    }
+    let head: String = secret.chars().take(4).collect();
+    let tail: String = secret.chars().skip(count - 4).collect();
+    format!("{head}...{tail}")
+}
+
+/// 把请求头转换为 JSON 对象，敏感头的值被遮蔽
+pub fn redact_headers(headers: &HeaderMap) -> Value {
+    let mut map = Map::new();
+    for key in headers.keys() {
+        let name = key.as_str().to_lowercase();
+        let sensitive = is_sensitive_header(&name);
+        let opaque = OPAQUE_SENSITIVE_HEADERS.contains(&name.as_str());
+        let values: Vec<String> = headers
+            .get_all(key)
+            .iter()
+            .map(|v| {
+                let value = String::from_utf8_lossy(v.as_bytes()).to_string();
+                if opaque {
+                    "***".to_string()
+                } else if sensitive {
+                    mask_secret(&value)
+                } else {
+                    value
+                }
+            })
+            .collect();
+        map.insert(name, Value::String(values.join(", ")));
+    }
+    Value::Object(map)
+}
+
+/// 遮蔽端点查询参数中的密钥
+pub fn redact_endpoint(endpoint: &str) -> String {
+    let Some((path, query)) = endpoint.split_once('?') else {
+        return endpoint.to_string();
+    };
+    let query = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, value)) if SENSITIVE_QUERY_PARAMS.contains(&name) => {
+                format!("{name}={}", mask_secret(value))
+            }
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    format!("{path}?{query}")
+}
+
+/// 去除端点查询参数中的密钥（重放时由适配器重新添加认证）
+pub fn strip_secret_params(endpoint: &str) -> String {
+    let Some((path, query)) = endpoint.split_once('?') else {
+        return endpoint.to_string();
+    };
+    let query = query
+        .split('&')
+        .filter(|pair| {
+            let name = pair.split_once('=').map_or(*pair, |(name, _)| name);
+            !SENSITIVE_QUERY_PARAMS.contains(&name)
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    if query.is_empty() {
+        path.to_string()
+    } else {
+        format!("{path}?{query}")
+    }
+}
+
+/// 按字节上限截断文本（保证 UTF-8 边界），返回 (文本, 是否截断)
+pub fn truncate_body(body: String, max_bytes: usize) -> (String, bool) {
+    if body.len() <= max_bytes {
+        return (body, false);
+    }
+    let mut end = max_bytes;
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+    (body[..end].to_string(), true)
+}
+
+/// 单个请求的载荷捕获器
+///
+/// 由 `RequestContext::capture_request` 在转发前创建，持有已遮蔽的请求部分；
+/// 响应处理阶段调用 `record` / `record_stream_events` 补齐响应并落库。
+#[derive(Clone)]
+pub struct PayloadCapture {
+    db: Arc<Database>,
+    request_id: String,
+    session_id: Option<String>,
+    app_type: String,
+    model: String,
+    endpoint: String,
+    request_headers: Value,
+    request_body: String,
+    response_headers: Option<Value>,
+    upstream_headers: Option<Value>,
+    header_rewrites: Vec<String>,
+    max_body_bytes: usize,
+    retention_days: u32,
+}
+
+impl PayloadCapture {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        db: Arc<Database>,
+        request_id: String,
+        session_id: Option<String>,
+        app_type: &str,
+        model: &str,
+        endpoint: &str,
+        headers: &HeaderMap,
+        body: &Value,
+        max_body_bytes: usize,
+        retention_days: u32,
+    ) -> Self {
+        Self {
+            db,
+            request_id,
+            session_id,
+            app_type: app_type.to_string(),
+            model: model.to_string(),
+            endpoint: redact_endpoint(endpoint),
+            request_headers: redact_headers(headers),
+            request_body: body.to_string(),
+            response_headers: None,
+            upstream_headers: None,
+            header_rewrites: Vec::new(),
+            max_body_bytes,
+            retention_days,
+        }
+    }
+
+    /// 附加上游响应头（已遮蔽）
+    pub fn with_response_headers(mut self, headers: &HeaderMap) -> Self {
+        self.response_headers = Some(redact_headers(headers));
+        self
+    }
+
+    /// 附加实际发往上游的请求信息（请求头改写之后）
+    pub fn with_upstream_request(mut self, info: Option<&UpstreamRequestInfo>) -> Self {
+        if let Some(info) = info {
+            self.upstream_headers = Some(info.headers.clone());
+            self.header_rewrites = info.rewrites.clone();
+        }
+        self
+    }
+
+    /// 记录完整响应体，失败只记录日志
+    pub fn record(
+        &self,
+        provider_id: &str,
+        status_code: u16,
+        response_body: Option<String>,
+        is_streaming: bool,
+    ) {
+        let (request_body, request_truncated) =
+            truncate_body(self.request_body.clone(), self.max_body_bytes);
+        let (response_body, response_truncated) = match response_body {
+            Some(body) => {
+                let (body, truncated) = truncate_body(body, self.max_body_bytes);
+                (Some(body), truncated)
+            }
+            None => (None, false),
+        };
+
+        let payload = RequestPayload {
+            request_id: self.request_id.clone(),
+            session_id: self.session_id.clone(),
+            app_type: self.app_type.clone(),
+            provider_id: provider_id.to_string(),
+            model: self.model.clone(),
+            endpoint: self.endpoint.clone(),
+            request_headers: self.request_headers.clone(),
+            request_body,
+            status_code,
+            response_headers: self.response_headers.clone(),
+            response_body,
+            is_streaming,
+            truncated: request_truncated || response_truncated,
+            created_at: chrono::Utc::now().timestamp(),
+            upstream_headers: self.upstream_headers.clone(),
+            header_rewrites: self.header_rewrites.clone(),
+        };
+
+        if let Err(e) = self.db.save_request_payload(&payload, self.retention_days) {
+            log::warn!("[PayloadCapture] 保存请求载荷失败: {e}");
+        }
+    }
+
+    /// 记录流式响应：保存 `SseUsageCollector` 收集到的事件（JSON 数组）
+    pub fn record_stream_events(&self, provider_id: &str, status_code: u16, events: &[Value]) {
+        let body = serde_json::to_string(events).unwrap_or_default();
+        self.record(provider_id, status_code, Some(body), true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_redact_headers_masks_secrets() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "Authorization",
+            HeaderValue::from_static("Bearer sk-1234567890abcdef"),
+        );
+        headers.insert("x-api-key", HeaderValue::from_static("sk-ant-abcdefgh1234"));
+        headers.insert("anthropic-version", HeaderValue::from_static("2023-06-01"));
+
+        let redacted = redact_headers(&headers);
+        assert_eq!(redacted["authorization"], "Bearer sk-1...cdef");
+        assert_eq!(redacted["x-api-key"], "sk-a...1234");
+        assert_eq!(redacted["anthropic-version"], "2023-06-01");
+    }
+
+    #[test]
+    fn test_redact_headers_masks_cookies_wholesale() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "cookie",
+            HeaderValue::from_static("a=b; token=secret-session-value"),
+        );
+        headers.append("set-cookie", HeaderValue::from_static("sid=abc; Path=/"));
+        headers.append(
+            "set-cookie",
+            HeaderValue::from_static("token=xyz; HttpOnly"),
+        );
+
+        let redacted = redact_headers(&headers);
+        assert_eq!(redacted["cookie"], "***");
+        assert_eq!(redacted["set-cookie"], "***, ***");
+    }
+
+    #[test]
+    fn test_mask_secret_non_ascii_and_unknown_scheme() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-api-key",
+            HeaderValue::from_bytes("密钥密钥密钥密钥密钥".as_bytes()).unwrap(),
+        );
+        headers.insert(
+            "authorization",
+            HeaderValue::from_bytes(b"Bearer \xff\xfe\xfd-secret-token").unwrap(),
+        );
+        let redacted = redact_headers(&headers);
+        assert_eq!(redacted["x-api-key"], "密钥密钥...密钥密钥");
+        assert_eq!(
+            redacted["authorization"],
+            "Bearer \u{FFFD}\u{FFFD}\u{FFFD}-...oken"
+        );
+
+        // 非认证方案前缀不保留
+        assert_eq!(mask_secret("token abcdefghijkl"), "toke...ijkl");
+        assert_eq!(
+            mask_secret("Basic dXNlcjpwYXNzd29yZA=="),
+            "Basic dXNl...ZA=="
+        );
+    }
+
+    #[test]
+    fn test_redact_endpoint_masks_key_param() {
+        assert_eq!(
+            redact_endpoint("/v1beta/models/gemini:generateContent?key=AIzaSyABCDEFG123&alt=sse"),
+            "/v1beta/models/gemini:generateContent?key=AIza...G123&alt=sse"
+        );
+        assert_eq!(redact_endpoint("/v1/messages"), "/v1/messages");
+    }
+
+    #[test]
+    fn test_strip_secret_params() {
+        assert_eq!(
+            strip_secret_params(
+                "/v1beta/models/gemini:streamGenerateContent?key=AIza...G123&alt=sse"
+            ),
+            "/v1beta/models/gemini:streamGenerateContent?alt=sse"
+        );
+        assert_eq!(
+            strip_secret_params("/v1beta/models/gemini:generateContent?key=AIza...G123"),
+            "/v1beta/models/gemini:generateContent"
+        );
+    }
+
+    #[test]
+    fn test_truncate_body_respects_char_boundary() {
+        let (body, truncated) = truncate_body("你好世界".to_string(), 7);
+        assert_eq!(body, "你好");
+        assert!(truncated);
+
+        let (body, truncated) = truncate_body("hello".to_string(), 10);
+        assert_eq!(body, "hello");
+        assert!(!truncated);
+    }
+}
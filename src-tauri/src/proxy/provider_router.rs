@@ -4,18 +4,74 @@
 
 use crate::database::Database;
 use crate::error::AppError;
-use crate::provider::Provider;
-use crate::proxy::circuit_breaker::{AllowResult, CircuitBreaker, CircuitBreakerConfig};
+use crate::provider::{Provider, SpendLimitMode};
+use crate::proxy::circuit_breaker::{
+    AllowResult, CircuitBreaker, CircuitBreakerConfig, TransitionListener,
+};
+use crate::proxy::cooldown::ProviderCooldown;
+use crate::proxy::key_pool::KeyPoolManager;
+use crate::proxy::metrics::BreakerSample;
+use crate::proxy::rate_limiter::{RateLimitPermit, RateLimitRejection, RateLimiter, RateLimits};
+use crate::proxy::routing_rules::{find_matching_rule, RoutingRule};
+use crate::proxy::spend_limit::{
+    exceeded_period, parse_limit, SpendAlerts, SpendLimitExceeded, SpendScope,
+};
+use crate::proxy::types::LoadBalanceStrategy;
+use crate::proxy::ProxyError;
+use crate::services::usage_stats::SpendTotals;
+use rand::Rng;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
+/// least_latency 策略统计延迟的时间窗口（秒）
+const LATENCY_WINDOW_SECS: i64 = 15 * 60;
+
+/// 消费统计缓存有效期
+const SPEND_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// 上游限流冷却条目
+struct CooldownEntry {
+    provider_name: String,
+    status_code: u16,
+    until: Instant,
+}
+
 /// 供应商路由器
 pub struct ProviderRouter {
     /// 数据库连接
     db: Arc<Database>,
     /// 熔断器管理器 - key 格式: "app_type:provider_id"
     circuit_breakers: Arc<RwLock<HashMap<String, Arc<CircuitBreaker>>>>,
+    /// 在途请求计数 - key 格式: "app_type:provider_id"
+    in_flight: Arc<RwLock<HashMap<String, Arc<AtomicUsize>>>>,
+    /// 轮询游标 - key 为 app_type（路由规则为 "app_type:rule:rule_id"）
+    round_robin_cursors: Arc<RwLock<HashMap<String, usize>>>,
+    /// 供应商限流器 - key 格式: "app_type:provider_id"
+    rate_limiter: RateLimiter,
+    /// 上游限流冷却（与熔断器独立）- key 格式: "app_type:provider_id"
+    cooldowns: Arc<RwLock<HashMap<String, CooldownEntry>>>,
+    /// API Key 池的选择与停用状态 - key 格式: "app_type:provider_id"
+    key_pools: KeyPoolManager,
+    /// 消费限额告警（每个限额每个统计周期通知一次）
+    spend_alerts: SpendAlerts,
+    /// 消费统计缓存 - key 格式: "app_type:provider_id"（不限维度时为 "*"）
+    spend_cache: Arc<std::sync::Mutex<HashMap<String, (Instant, SpendTotals)>>>,
+    /// AppHandle，用于发射 `provider-limit-exceeded` 事件
+    app_handle: Option<tauri::AppHandle>,
+}
+
+/// 在途请求守卫
+///
+/// 由 `ProviderRouter::begin_request()` 返回，drop 时自动递减计数。
+pub struct InFlightGuard(Arc<AtomicUsize>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
 }
 
 impl ProviderRouter {
@@ -24,29 +80,59 @@ impl ProviderRouter {
         Self {
             db,
             circuit_breakers: Arc::new(RwLock::new(HashMap::new())),
+            in_flight: Arc::new(RwLock::new(HashMap::new())),
+            round_robin_cursors: Arc::new(RwLock::new(HashMap::new())),
+            rate_limiter: RateLimiter::new(),
+            cooldowns: Arc::new(RwLock::new(HashMap::new())),
+            key_pools: KeyPoolManager::new(),
+            spend_alerts: SpendAlerts::default(),
+            spend_cache: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            app_handle: None,
         }
     }
 
+    /// 设置用于发射事件的 AppHandle
+    pub fn with_app_handle(mut self, app_handle: Option<tauri::AppHandle>) -> Self {
+        self.app_handle = app_handle;
+        self
+    }
+
     /// 选择可用的供应商（支持故障转移）
     ///
     /// 返回按优先级排序的可用供应商列表：
     /// - 故障转移关闭时：仅返回当前供应商
-    /// - 故障转移开启时：完全按照故障转移队列顺序返回，忽略当前供应商设置
+    /// - 故障转移开启时：按照故障转移队列返回，再按负载均衡策略重排，忽略当前供应商设置
+    ///
+    /// 应用级或全局消费已达上限时返回 `AppError::SpendLimitExceeded`；
+    /// 超出自身限额的供应商（hard 模式）会被跳过。
     pub async fn select_providers(&self, app_type: &str) -> Result<Vec<Provider>, AppError> {
+        self.check_spend_caps(app_type).await?;
+        self.select_default_providers(app_type).await
+    }
+
+    async fn select_default_providers(&self, app_type: &str) -> Result<Vec<Provider>, AppError> {
         let mut result = Vec::new();
+        let mut over_budget = 0;
 
-        // 检查该应用的自动故障转移开关是否开启（从 proxy_config 表读取）
-        let auto_failover_enabled = match self.db.get_proxy_config_for_app(app_type).await {
+        // 检查该应用的自动故障转移开关和负载均衡策略（从 proxy_config 表读取）
+        let (auto_failover_enabled, strategy) = match self
+            .db
+            .get_proxy_config_for_app(app_type)
+            .await
+        {
             Ok(config) => {
                 let enabled = config.auto_failover_enabled;
-                log::info!("[{app_type}] Failover enabled from proxy_config: {enabled}");
-                enabled
+                log::info!(
+                    "[{app_type}] Failover enabled from proxy_config: {enabled}, strategy: {}",
+                    config.load_balance_strategy.as_str()
+                );
+                (enabled, config.load_balance_strategy)
             }
             Err(e) => {
                 log::error!(
-                    "[{app_type}] Failed to read proxy_config for auto_failover_enabled: {e}, defaulting to disabled"
-                );
-                false
+                        "[{app_type}] Failed to read proxy_config for auto_failover_enabled: {e}, defaulting to disabled"
+                    );
+                (false, LoadBalanceStrategy::Priority)
             }
         };
 
@@ -60,6 +146,12 @@ impl ProviderRouter {
             );
 
             for provider in failover_providers {
+                // 检查消费限额
+                if self.is_over_budget(app_type, &provider) {
+                    over_budget += 1;
+                    continue;
+                }
+
                 // 检查熔断器状态
                 let circuit_key = format!("{}:{}", app_type, provider.id);
                 let breaker = self.get_or_create_circuit_breaker(&circuit_key).await;
@@ -81,6 +173,10 @@ impl ProviderRouter {
                     );
                 }
             }
+
+            result = self
+                .apply_load_balance(app_type, app_type, strategy, result)
+                .await;
         } else {
             // 故障转移关闭：仅使用当前供应商，跳过熔断器检查
             // 原因：单 Provider 场景下，熔断器打开会导致所有请求失败，用户体验差
@@ -94,11 +190,21 @@ impl ProviderRouter {
                         current.name,
                         current.id
                     );
-                    result.push(current);
+                    if self.is_over_budget(app_type, &current) {
+                        over_budget += 1;
+                    } else {
+                        result.push(current);
+                    }
                 }
             }
         }
 
+        if result.is_empty() && over_budget > 0 {
+            return Err(AppError::SpendLimitExceeded(format!(
+                "{app_type} 的可用供应商均已超出消费限额"
+            )));
+        }
+
         if result.is_empty() {
             return Err(AppError::Config(format!(
                 "No available provider for {app_type} (all circuit breakers open or no providers configured)"
@@ -114,6 +220,366 @@ impl ProviderRouter {
         Ok(result)
     }
 
+    /// 按请求模型选择供应商（支持模型路由规则）
+    ///
+    /// - 命中路由规则：使用规则自带的供应商链，与默认选择一样受故障转移开关、熔断器和负载均衡约束
+    /// - 未命中规则，或规则链上的供应商全部不可用：回退到 `select_providers()`
+    pub async fn select_providers_for_model(
+        &self,
+        app_type: &str,
+        model: &str,
+        thinking: bool,
+    ) -> Result<Vec<Provider>, AppError> {
+        let rules = self.db.get_routing_rules(app_type).unwrap_or_else(|e| {
+            log::warn!("[{app_type}] Failed to load routing rules: {e}");
+            Vec::new()
+        });
+
+        if let Some(rule) = find_matching_rule(&rules, model, thinking) {
+            log::info!(
+                "[{}] Model '{}' (thinking: {}) matched routing rule '{}' ({})",
+                app_type,
+                model,
+                thinking,
+                rule.name,
+                rule.model_pattern
+            );
+
+            self.check_spend_caps(app_type).await?;
+            let chain = self.collect_rule_chain(app_type, rule).await?;
+            if !chain.is_empty() {
+                return Ok(chain);
+            }
+
+            log::warn!(
+                "[{}] All providers of routing rule '{}' are unavailable, falling back to default selection",
+                app_type,
+                rule.name
+            );
+        }
+
+        self.select_providers(app_type).await
+    }
+
+    /// 构建路由规则的供应商链
+    ///
+    /// 与 `select_providers()` 一致：跳过超出消费限额的供应商；
+    /// 故障转移关闭时只使用规则的第一个供应商（跳过熔断器检查），开启时经过熔断器过滤和负载均衡重排。
+    async fn collect_rule_chain(
+        &self,
+        app_type: &str,
+        rule: &RoutingRule,
+    ) -> Result<Vec<Provider>, AppError> {
+        let (auto_failover_enabled, strategy) = match self
+            .db
+            .get_proxy_config_for_app(app_type)
+            .await
+        {
+            Ok(config) => (config.auto_failover_enabled, config.load_balance_strategy),
+            Err(e) => {
+                log::error!(
+                        "[{app_type}] Failed to read proxy_config for routing rule: {e}, defaulting to failover disabled"
+                    );
+                (false, LoadBalanceStrategy::Priority)
+            }
+        };
+
+        let mut providers = Vec::new();
+        for provider_id in &rule.provider_ids {
+            match self.db.get_provider_by_id(provider_id, app_type)? {
+                Some(provider) => providers.push(provider),
+                None => log::warn!(
+                    "[{app_type}] Routing rule '{}' references missing provider {provider_id}",
+                    rule.name
+                ),
+            }
+        }
+
+        if !auto_failover_enabled {
+            providers.truncate(1);
+            providers.retain(|provider| !self.is_over_budget(app_type, provider));
+            return Ok(providers);
+        }
+
+        providers.retain(|provider| !self.is_over_budget(app_type, provider));
+        let mut available = Vec::with_capacity(providers.len());
+        for provider in providers {
+            let circuit_key = format!("{}:{}", app_type, provider.id);
+            let breaker = self.get_or_create_circuit_breaker(&circuit_key).await;
+            if breaker.is_available().await {
+                available.push(provider);
+            } else {
+                log::debug!(
+                    "[{}] Rule provider {} circuit breaker open, skipping",
+                    app_type,
+                    provider.name
+                );
+            }
+        }
+
+        let cursor_key = format!("{app_type}:rule:{}", rule.id);
+
+        Ok(self
+            .apply_load_balance(app_type, &cursor_key, strategy, available)
+            .await)
+    }
+
+    /// 检查应用级与全局消费上限
+    async fn check_spend_caps(&self, app_type: &str) -> Result<(), AppError> {
+        if let Ok(config) = self.db.get_proxy_config_for_app(app_type).await {
+            let daily = parse_limit(config.spend_limit_daily_usd.as_deref());
+            let monthly = parse_limit(config.spend_limit_monthly_usd.as_deref());
+            if daily.is_some() || monthly.is_some() {
+                if let Some((period, usage_usd, limit_usd)) = self
+                    .spend_totals(Some(app_type), None)
+                    .map(|totals| exceeded_period(totals, daily, monthly))
+                    .unwrap_or_else(|e| {
+                        log::warn!("[{app_type}] Failed to load app spend totals: {e}");
+                        None
+                    })
+                {
+                    return Err(self.reject_over_cap(SpendLimitExceeded {
+                        scope: SpendScope::App,
+                        app_type: Some(app_type.to_string()),
+                        provider_id: None,
+                        provider_name: None,
+                        period,
+                        usage_usd,
+                        limit_usd,
+                        soft: false,
+                    }));
+                }
+            }
+        }
+
+        if let Ok(config) = self.db.get_global_proxy_config().await {
+            let daily = parse_limit(config.spend_limit_daily_usd.as_deref());
+            let monthly = parse_limit(config.spend_limit_monthly_usd.as_deref());
+            if daily.is_some() || monthly.is_some() {
+                if let Some((period, usage_usd, limit_usd)) = self
+                    .spend_totals(None, None)
+                    .map(|totals| exceeded_period(totals, daily, monthly))
+                    .unwrap_or_else(|e| {
+                        log::warn!("Failed to load global spend totals: {e}");
+                        None
+                    })
+                {
+                    return Err(self.reject_over_cap(SpendLimitExceeded {
+                        scope: SpendScope::Global,
+                        app_type: None,
+                        provider_id: None,
+                        provider_name: None,
+                        period,
+                        usage_usd,
+                        limit_usd,
+                        soft: false,
+                    }));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 读取今日 / 本月消费（带短期缓存）
+    ///
+    /// 限额判断允许 `SPEND_CACHE_TTL` 内的滞后，避免每个请求、每个候选供应商都聚合一次请求日志
+    fn spend_totals(
+        &self,
+        app_type: Option<&str>,
+        provider_id: Option<&str>,
+    ) -> Result<SpendTotals, AppError> {
+        let key = format!("{}:{}", app_type.unwrap_or("*"), provider_id.unwrap_or("*"));
+        let cached = self
+            .spend_cache
+            .lock()
+            .ok()
+            .and_then(|cache| cache.get(&key).copied())
+            .filter(|(at, _)| at.elapsed() < SPEND_CACHE_TTL);
+        if let Some((_, totals)) = cached {
+            return Ok(totals);
+        }
+
+        let totals = self.db.get_spend_totals(app_type, provider_id)?;
+        if let Ok(mut cache) = self.spend_cache.lock() {
+            cache.insert(key, (Instant::now(), totals));
+        }
+        Ok(totals)
+    }
+
+    fn reject_over_cap(&self, exceeded: SpendLimitExceeded) -> AppError {
+        self.spend_alerts
+            .notify(self.app_handle.as_ref(), &exceeded);
+        AppError::SpendLimitExceeded(exceeded.message())
+    }
+
+    /// 供应商是否超出自身消费限额且应被跳过
+    ///
+    /// 超出限额时发出告警；soft 模式只告警，返回 false。
+    fn is_over_budget(&self, app_type: &str, provider: &Provider) -> bool {
+        let Some(meta) = provider.meta.as_ref() else {
+            return false;
+        };
+        let daily = parse_limit(meta.limit_daily_usd.as_deref());
+        let monthly = parse_limit(meta.limit_monthly_usd.as_deref());
+        if daily.is_none() && monthly.is_none() {
+            return false;
+        }
+
+        let totals = match self.spend_totals(Some(app_type), Some(&provider.id)) {
+            Ok(totals) => totals,
+            Err(e) => {
+                log::warn!(
+                    "[{app_type}] Failed to load spend totals for {}: {e}",
+                    provider.name
+                );
+                return false;
+            }
+        };
+        let Some((period, usage_usd, limit_usd)) = exceeded_period(totals, daily, monthly) else {
+            return false;
+        };
+
+        let soft = meta.limit_mode.unwrap_or_default() == SpendLimitMode::Soft;
+        self.spend_alerts.notify(
+            self.app_handle.as_ref(),
+            &SpendLimitExceeded {
+                scope: SpendScope::Provider,
+                app_type: Some(app_type.to_string()),
+                provider_id: Some(provider.id.clone()),
+                provider_name: Some(provider.name.clone()),
+                period,
+                usage_usd,
+                limit_usd,
+                soft,
+            },
+        );
+        if !soft {
+            log::debug!(
+                "[{}] Provider {} over spend limit, skipping",
+                app_type,
+                provider.name
+            );
+        }
+        !soft
+    }
+
+    /// 按负载均衡策略重排可用供应商
+    ///
+    /// 只调整顺序不做增删，排在后面的供应商仍作为故障转移的兜底。
+    async fn apply_load_balance(
+        &self,
+        app_type: &str,
+        cursor_key: &str,
+        strategy: LoadBalanceStrategy,
+        mut providers: Vec<Provider>,
+    ) -> Vec<Provider> {
+        if providers.len() <= 1 {
+            return providers;
+        }
+
+        match strategy {
+            LoadBalanceStrategy::Priority => {}
+            LoadBalanceStrategy::WeightedRandom => {
+                let mut rng = rand::thread_rng();
+                providers = weighted_shuffle(providers, |total| rng.gen_range(0..total));
+            }
+            LoadBalanceStrategy::RoundRobin => {
+                let start = {
+                    let mut cursors = self.round_robin_cursors.write().await;
+                    let cursor = cursors.entry(cursor_key.to_string()).or_insert(0);
+                    let start = *cursor % providers.len();
+                    *cursor = cursor.wrapping_add(1);
+                    start
+                };
+                providers.rotate_left(start);
+            }
+            LoadBalanceStrategy::LeastLatency => {
+                let latencies = self
+                    .db
+                    .get_recent_provider_latencies(app_type, LATENCY_WINDOW_SECS)
+                    .await
+                    .unwrap_or_else(|e| {
+                        log::warn!("[{app_type}] Failed to load provider latencies: {e}");
+                        HashMap::new()
+                    });
+                providers = order_by_latency(providers, &latencies);
+            }
+            LoadBalanceStrategy::LeastInFlight => {
+                let counts: HashMap<String, usize> = {
+                    let in_flight = self.in_flight.read().await;
+                    providers
+                        .iter()
+                        .map(|p| {
+                            let count = in_flight
+                                .get(&format!("{app_type}:{}", p.id))
+                                .map(|c| c.load(Ordering::Relaxed))
+                                .unwrap_or(0);
+                            (p.id.clone(), count)
+                        })
+                        .collect()
+                };
+                providers.sort_by_key(|p| counts.get(&p.id).copied().unwrap_or(0));
+            }
+        }
+
+        log::debug!(
+            "[{}] Load balance ({}) order: {:?}",
+            app_type,
+            strategy.as_str(),
+            providers.iter().map(|p| p.id.as_str()).collect::<Vec<_>>()
+        );
+
+        providers
+    }
+
+    /// 标记一次发往供应商的在途请求
+    ///
+    /// 返回的守卫被 drop 时计数自动递减，供 least_in_flight 策略使用。
+    pub async fn begin_request(&self, provider_id: &str, app_type: &str) -> InFlightGuard {
+        let key = format!("{app_type}:{provider_id}");
+        let counter = {
+            let in_flight = self.in_flight.read().await;
+            in_flight.get(&key).cloned()
+        };
+        let counter = match counter {
+            Some(counter) => counter,
+            None => self
+                .in_flight
+                .write()
+                .await
+                .entry(key)
+                .or_insert_with(|| Arc::new(AtomicUsize::new(0)))
+                .clone(),
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        InFlightGuard(counter)
+    }
+
+    /// 按供应商 meta 中的限流配置获取放行许可
+    ///
+    /// 未配置限流时直接返回 `Ok(None)`；额度不足时在等待队列中等待（`allow_wait` 为 false 时不等待），
+    /// 队列已满或等待超过截止时间时返回拒绝原因，由调用方溢出到下一个供应商。
+    pub async fn acquire_rate_limit(
+        &self,
+        provider: &Provider,
+        app_type: &str,
+        estimated_tokens: u32,
+        allow_wait: bool,
+    ) -> Result<Option<RateLimitPermit>, RateLimitRejection> {
+        let Some(mut limits) = RateLimits::from_provider(provider) else {
+            return Ok(None);
+        };
+        if !allow_wait {
+            limits.max_wait = std::time::Duration::ZERO;
+        }
+        let key = format!("{app_type}:{}", provider.id);
+        self.rate_limiter
+            .acquire(&key, &limits, estimated_tokens)
+            .await
+            .map(Some)
+    }
+
     /// 请求执行前获取熔断器“放行许可”
     ///
     /// - Closed：直接放行
@@ -178,13 +644,146 @@ impl ProviderRouter {
         Ok(())
     }
 
+    /// 释放 HalfOpen 名额，不计入成功或失败（请求被主动取消、未真正发出等与供应商健康无关的情况）
+    pub async fn release_permit(
+        &self,
+        provider_id: &str,
+        app_type: &str,
+        used_half_open_permit: bool,
+    ) {
+        if !used_half_open_permit {
+            return;
+        }
+        let key = format!("{app_type}:{provider_id}");
+        let breaker = self.get_or_create_circuit_breaker(&key).await;
+        breaker.release_permit(true);
+    }
+
+    /// 记录上游限流（429/529 等）：释放 HalfOpen 名额但不计入熔断器，并让供应商进入冷却期
+    pub async fn record_throttled(
+        &self,
+        provider: &Provider,
+        app_type: &str,
+        used_half_open_permit: bool,
+        status_code: u16,
+        cooldown: Duration,
+    ) {
+        let key = format!("{app_type}:{}", provider.id);
+        let breaker = self.get_or_create_circuit_breaker(&key).await;
+        breaker.release_permit(used_half_open_permit);
+
+        log::warn!(
+            "Provider {} 被上游限流 ({status_code})，冷却 {}s",
+            provider.name,
+            cooldown.as_secs()
+        );
+
+        let until = Instant::now() + cooldown;
+        let mut cooldowns = self.cooldowns.write().await;
+        // 已有更长的冷却时保留
+        if cooldowns
+            .get(&key)
+            .is_some_and(|entry| entry.until >= until)
+        {
+            return;
+        }
+        cooldowns.insert(
+            key,
+            CooldownEntry {
+                provider_name: provider.name.clone(),
+                status_code,
+                until,
+            },
+        );
+    }
+
+    /// API Key 池
+    pub fn key_pools(&self) -> &KeyPoolManager {
+        &self.key_pools
+    }
+
+    /// 供应商已知不支持的请求字段（读取失败时视为没有）
+    pub fn incompatible_fields(&self, provider_id: &str, app_type: &str) -> Vec<String> {
+        self.db
+            .get_incompatible_fields(app_type, provider_id)
+            .unwrap_or_else(|e| {
+                log::warn!("读取供应商字段兼容记录失败: {e}");
+                Vec::new()
+            })
+    }
+
+    /// 记住供应商不支持的请求字段
+    pub fn record_incompatibility(
+        &self,
+        provider: &Provider,
+        app_type: &str,
+        field: &str,
+        error: &ProxyError,
+    ) {
+        log::warn!(
+            "Provider {} 不支持请求字段 {field}，后续请求将自动去掉该字段",
+            provider.name
+        );
+        let message = match error {
+            ProxyError::UpstreamError {
+                body: Some(body), ..
+            } => body.clone(),
+            other => other.to_string(),
+        };
+        if let Err(e) =
+            self.db
+                .record_incompatibility(app_type, &provider.id, field, Some(&message))
+        {
+            log::warn!("保存供应商字段兼容记录失败: {e}");
+        }
+    }
+
+    /// 供应商剩余冷却时间；未在冷却中时返回 None
+    pub async fn cooldown_remaining(&self, provider_id: &str, app_type: &str) -> Option<Duration> {
+        let key = format!("{app_type}:{provider_id}");
+        let cooldowns = self.cooldowns.read().await;
+        let remaining = cooldowns
+            .get(&key)?
+            .until
+            .saturating_duration_since(Instant::now());
+        (!remaining.is_zero()).then_some(remaining)
+    }
+
+    /// 列出仍在冷却中的供应商（顺带清理已过期的条目）
+    pub async fn list_cooldowns(&self) -> Vec<ProviderCooldown> {
+        let now = Instant::now();
+        let mut cooldowns = self.cooldowns.write().await;
+        cooldowns.retain(|_, entry| entry.until > now);
+
+        let mut list: Vec<ProviderCooldown> = cooldowns
+            .iter()
+            .filter_map(|(key, entry)| {
+                let (app_type, provider_id) = key.split_once(':')?;
+                Some(ProviderCooldown {
+                    app_type: app_type.to_string(),
+                    provider_id: provider_id.to_string(),
+                    provider_name: entry.provider_name.clone(),
+                    status_code: entry.status_code,
+                    // 向上取整，避免剩余不足 1 秒时显示为 0
+                    remaining_secs: entry.until.saturating_duration_since(now).as_secs() + 1,
+                })
+            })
+            .collect();
+        list.sort_by(|a, b| (&a.app_type, &a.provider_id).cmp(&(&b.app_type, &b.provider_id)));
+        list
+    }
+
     /// 重置熔断器（手动恢复）
     pub async fn reset_circuit_breaker(&self, circuit_key: &str) {
-        let breakers = self.circuit_breakers.read().await;
-        if let Some(breaker) = breakers.get(circuit_key) {
-            log::info!("Manually resetting circuit breaker for {circuit_key}");
-            breaker.reset().await;
+        {
+            let breakers = self.circuit_breakers.read().await;
+            if let Some(breaker) = breakers.get(circuit_key) {
+                log::info!("Manually resetting circuit breaker for {circuit_key}");
+                breaker.reset().await;
+            }
         }
+        // 手动恢复同时解除限流冷却
+        self.cooldowns.write().await.remove(circuit_key);
     }
 
     /// 重置指定供应商的熔断器
@@ -208,20 +807,78 @@ impl ProviderRouter {
         log::info!("已更新 {count} 个熔断器的配置");
     }
 
-    /// 获取熔断器状态
-    #[allow(dead_code)]
+    /// 获取熔断器状态（含上游限流冷却剩余时间）
     pub async fn get_circuit_breaker_stats(
         &self,
         provider_id: &str,
         app_type: &str,
     ) -> Option<crate::proxy::circuit_breaker::CircuitBreakerStats> {
         let circuit_key = format!("{app_type}:{provider_id}");
-        let breakers = self.circuit_breakers.read().await;
+        let breaker = self
+            .circuit_breakers
+            .read()
+            .await
+            .get(&circuit_key)
+            .cloned()?;
 
-        if let Some(breaker) = breakers.get(&circuit_key) {
-            Some(breaker.get_stats().await)
-        } else {
-            None
+        let mut stats = breaker.get_stats().await;
+        stats.cooldown_remaining_secs = self
+            .cooldown_remaining(provider_id, app_type)
+            .await
+            .map(|remaining| remaining.as_secs() + 1);
+        Some(stats)
+    }
+
+    /// 列出所有已创建熔断器的状态（用于 `/metrics`）
+    pub async fn list_circuit_breaker_stats(&self) -> Vec<BreakerSample> {
+        let breakers: Vec<(String, Arc<CircuitBreaker>)> = self
+            .circuit_breakers
+            .read()
+            .await
+            .iter()
+            .map(|(key, breaker)| (key.clone(), breaker.clone()))
+            .collect();
+
+        let mut samples = Vec::with_capacity(breakers.len());
+        for (key, breaker) in breakers {
+            let Some((app_type, provider_id)) = key.split_once(':') else {
+                continue;
+            };
+            let mut stats = breaker.get_stats().await;
+            stats.cooldown_remaining_secs = self
+                .cooldown_remaining(provider_id, app_type)
+                .await
+                .map(|remaining| remaining.as_secs() + 1);
+            samples.push(BreakerSample {
+                app_type: app_type.to_string(),
+                provider_id: provider_id.to_string(),
+                stats,
+            });
+        }
+        samples.sort_by(|a, b| (&a.app_type, &a.provider_id).cmp(&(&b.app_type, &b.provider_id)));
+        samples
+    }
+
+    /// 恢复上次运行时持久化的熔断器状态（代理启动时调用）
+    pub async fn restore_circuit_breakers(&self) {
+        let now = chrono::Utc::now().timestamp();
+        for app_type in ["claude", "codex", "gemini"] {
+            let states = match self.db.get_persisted_circuit_states(app_type) {
+                Ok(states) => states,
+                Err(e) => {
+                    log::warn!("读取 {app_type} 的熔断器状态失败: {e}");
+                    continue;
+                }
+            };
+            for (provider_id, state, opened_at) in states {
+                let key = format!("{app_type}:{provider_id}");
+                let open_for = opened_at.map(|at| Duration::from_secs((now - at).max(0) as u64));
+                self.get_or_create_circuit_breaker(&key)
+                    .await
+                    .restore(state, open_for)
+                    .await;
+                log::info!("已恢复熔断器状态 {key}: {state}");
+            }
         }
     }
 
@@ -274,13 +931,79 @@ impl ProviderRouter {
 
         log::debug!("Creating new circuit breaker for {key} with config: {config:?}");
 
-        let breaker = Arc::new(CircuitBreaker::new(config));
+        // 状态转换写入 provider_health 与转换历史，重启后可恢复
+        let db = self.db.clone();
+        let app = app_type.to_string();
+        let provider_id = key.split_once(':').map_or(key, |(_, id)| id).to_string();
+        let listener: TransitionListener = Arc::new(move |transition| {
+            if let Err(e) = db.record_circuit_transition(
+                &app,
+                &provider_id,
+                transition.from,
+                transition.to,
+                &transition.reason,
+            ) {
+                log::warn!("记录熔断器状态转换失败 ({app}:{provider_id}): {e}");
+            }
+        });
+
+        let breaker = Arc::new(CircuitBreaker::new(config).with_listener(listener));
         breakers.insert(key.to_string(), breaker.clone());
 
         breaker
     }
 }
 
+/// 按近期平均延迟升序排列
+///
+/// 没有延迟样本的供应商保持原顺序排在有样本的供应商之后。
+fn order_by_latency(providers: Vec<Provider>, latencies: &HashMap<String, f64>) -> Vec<Provider> {
+    let (mut sampled, unsampled): (Vec<_>, Vec<_>) = providers
+        .into_iter()
+        .partition(|p| latencies.contains_key(&p.id));
+    sampled.sort_by(|a, b| latencies[&a.id].total_cmp(&latencies[&b.id]));
+    sampled.extend(unsampled);
+    sampled
+}
+
+/// 按权重做不放回抽样，得到加权随机顺序
+///
+/// 权重取自 `meta.loadBalanceWeight`（缺省为 1），权重为 0 的供应商保持原顺序排在最后。
+/// `draw(total)` 返回 `[0, total)` 内的随机数。
+fn weighted_shuffle(providers: Vec<Provider>, mut draw: impl FnMut(u64) -> u64) -> Vec<Provider> {
+    let (mut weighted, zero): (Vec<_>, Vec<_>) = providers
+        .into_iter()
+        .map(|p| {
+            let weight = p
+                .meta
+                .as_ref()
+                .and_then(|m| m.load_balance_weight)
+                .unwrap_or(1) as u64;
+            (weight, p)
+        })
+        .partition(|(weight, _)| *weight > 0);
+
+    let mut ordered = Vec::with_capacity(weighted.len() + zero.len());
+    while !weighted.is_empty() {
+        let total: u64 = weighted.iter().map(|(w, _)| *w).sum();
+        let mut pick = draw(total);
+        let index = weighted
+            .iter()
+            .position(|(w, _)| {
+                if pick < *w {
+                    true
+                } else {
+                    pick -= *w;
+                    false
+                }
+            })
+            .unwrap_or(0);
+        ordered.push(weighted.remove(index).1);
+    }
+    ordered.extend(zero.into_iter().map(|(_, p)| p));
+    ordered
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -390,4 +1113,341 @@ mod tests {
 
         assert!(router.allow_provider_request("b", "claude").await.allowed);
     }
+
+    async fn setup_balanced_router(
+        strategy: LoadBalanceStrategy,
+    ) -> (Arc<Database>, ProviderRouter) {
+        let db = Arc::new(Database::memory().unwrap());
+
+        for (id, sort_index) in [("a", 1), ("b", 2), ("c", 3)] {
+            let mut provider =
+                Provider::with_id(id.to_string(), format!("Provider {id}"), json!({}), None);
+            provider.sort_index = Some(sort_index);
+            db.save_provider("claude", &provider).unwrap();
+            db.add_to_failover_queue("claude", id).unwrap();
+        }
+
+        let mut config = db.get_proxy_config_for_app("claude").await.unwrap();
+        config.auto_failover_enabled = true;
+        config.load_balance_strategy = strategy;
+        db.update_proxy_config_for_app(config).await.unwrap();
+
+        let router = ProviderRouter::new(db.clone());
+        (db, router)
+    }
+
+    fn ids(providers: &[Provider]) -> Vec<&str> {
+        providers.iter().map(|p| p.id.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_round_robin_rotates_queue() {
+        let (_db, router) = setup_balanced_router(LoadBalanceStrategy::RoundRobin).await;
+
+        let first = router.select_providers("claude").await.unwrap();
+        let second = router.select_providers("claude").await.unwrap();
+        let third = router.select_providers("claude").await.unwrap();
+        let fourth = router.select_providers("claude").await.unwrap();
+
+        assert_eq!(ids(&first), vec!["a", "b", "c"]);
+        assert_eq!(ids(&second), vec!["b", "c", "a"]);
+        assert_eq!(ids(&third), vec!["c", "a", "b"]);
+        assert_eq!(ids(&fourth), vec!["a", "b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn test_least_in_flight_prefers_idle_provider() {
+        let (_db, router) = setup_balanced_router(LoadBalanceStrategy::LeastInFlight).await;
+
+        let _a1 = router.begin_request("a", "claude").await;
+        let _a2 = router.begin_request("a", "claude").await;
+        let b1 = router.begin_request("b", "claude").await;
+
+        let providers = router.select_providers("claude").await.unwrap();
+        assert_eq!(ids(&providers), vec!["c", "b", "a"]);
+
+        // 守卫释放后计数回落
+        drop(b1);
+        let providers = router.select_providers("claude").await.unwrap();
+        assert_eq!(ids(&providers), vec!["b", "c", "a"]);
+    }
+
+    #[tokio::test]
+    async fn test_least_latency_uses_request_logs() {
+        let (db, router) = setup_balanced_router(LoadBalanceStrategy::LeastLatency).await;
+
+        let now = chrono::Utc::now().timestamp();
+        {
+            let conn = db.conn.lock().unwrap();
+            for (request_id, provider_id, latency) in
+                [("r1", "a", 900), ("r2", "b", 200), ("r3", "c", 500)]
+            {
+                conn.execute(
+                    "INSERT INTO proxy_request_logs (request_id, provider_id, app_type, model,
+                        latency_ms, status_code, created_at)
+                     VALUES (?1, ?2, 'claude', 'm', ?3, 200, ?4)",
+                    rusqlite::params![request_id, provider_id, latency, now],
+                )
+                .unwrap();
+            }
+        }
+
+        let providers = router.select_providers("claude").await.unwrap();
+        assert_eq!(ids(&providers), vec!["b", "c", "a"]);
+    }
+
+    #[tokio::test]
+    async fn test_load_balance_still_skips_open_breakers() {
+        let (db, router) = setup_balanced_router(LoadBalanceStrategy::RoundRobin).await;
+
+        db.update_circuit_breaker_config(&CircuitBreakerConfig {
+            failure_threshold: 1,
+            timeout_seconds: 3600,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        router
+            .record_result("b", "claude", false, false, Some("fail".to_string()))
+            .await
+            .unwrap();
+
+        for _ in 0..3 {
+            let providers = router.select_providers("claude").await.unwrap();
+            assert_eq!(providers.len(), 2);
+            assert!(providers.iter().all(|p| p.id != "b"));
+        }
+    }
+
+    #[test]
+    fn test_weighted_shuffle_respects_weights() {
+        let with_weight = |id: &str, weight: Option<u32>| {
+            let mut provider = Provider::with_id(id.to_string(), id.to_string(), json!({}), None);
+            provider.meta = Some(crate::provider::ProviderMeta {
+                load_balance_weight: weight,
+                ..Default::default()
+            });
+            provider
+        };
+
+        // a 权重 1，b 权重 3，c 权重 0（仅兜底）
+        let providers = vec![
+            with_weight("a", Some(1)),
+            with_weight("b", Some(3)),
+            with_weight("c", Some(0)),
+        ];
+
+        // 随机数 0 落在 a 的区间 [0,1)
+        let ordered = weighted_shuffle(providers.clone(), |_| 0);
+        assert_eq!(ids(&ordered), vec!["a", "b", "c"]);
+
+        // 随机数 2 落在 b 的区间 [1,4)
+        let ordered = weighted_shuffle(providers.clone(), |_| 2);
+        assert_eq!(ids(&ordered), vec!["b", "a", "c"]);
+
+        // 真实随机抽样：权重为 0 的供应商始终排在加权供应商之后
+        let mut rng = rand::thread_rng();
+        for _ in 0..20 {
+            let ordered = weighted_shuffle(providers.clone(), |total| rng.gen_range(0..total));
+            assert_eq!(ordered.len(), 3);
+            assert_eq!(ordered[2].id, "c");
+        }
+    }
+
+    #[test]
+    fn test_order_by_latency_puts_unsampled_last() {
+        let provider =
+            |id: &str| Provider::with_id(id.to_string(), id.to_string(), json!({}), None);
+        let providers = vec![
+            provider("new1"),
+            provider("slow"),
+            provider("new2"),
+            provider("fast"),
+        ];
+        let latencies = HashMap::from([("slow".to_string(), 900.0), ("fast".to_string(), 120.0)]);
+
+        let ordered = order_by_latency(providers, &latencies);
+        assert_eq!(ids(&ordered), vec!["fast", "slow", "new1", "new2"]);
+    }
+
+    fn save_rule(db: &Database, id: &str, pattern: &str, providers: &[&str]) {
+        db.save_routing_rule(&RoutingRule {
+            id: id.to_string(),
+            app_type: "claude".to_string(),
+            name: id.to_string(),
+            model_pattern: pattern.to_string(),
+            match_type: Default::default(),
+            thinking: None,
+            provider_ids: providers.iter().map(|p| p.to_string()).collect(),
+            enabled: true,
+            sort_index: 0,
+            created_at: 0,
+        })
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_routing_rule_selects_rule_chain() {
+        let (db, router) = setup_balanced_router(LoadBalanceStrategy::Priority).await;
+        save_rule(&db, "haiku", "*haiku*", &["c", "b"]);
+
+        let providers = router
+            .select_providers_for_model("claude", "claude-3-5-haiku", false)
+            .await
+            .unwrap();
+        assert_eq!(ids(&providers), vec!["c", "b"]);
+
+        // 未命中规则时回退到默认队列
+        let providers = router
+            .select_providers_for_model("claude", "claude-opus-4", false)
+            .await
+            .unwrap();
+        assert_eq!(ids(&providers), vec!["a", "b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn test_routing_rule_falls_back_when_chain_unavailable() {
+        let (db, router) = setup_balanced_router(LoadBalanceStrategy::Priority).await;
+        save_rule(&db, "opus", "*opus*", &["b", "c"]);
+
+        db.update_circuit_breaker_config(&CircuitBreakerConfig {
+            failure_threshold: 1,
+            timeout_seconds: 3600,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        router
+            .record_result("b", "claude", false, false, Some("fail".to_string()))
+            .await
+            .unwrap();
+        let providers = router
+            .select_providers_for_model("claude", "claude-opus-4", false)
+            .await
+            .unwrap();
+        assert_eq!(ids(&providers), vec!["c"]);
+
+        router
+            .record_result("c", "claude", false, false, Some("fail".to_string()))
+            .await
+            .unwrap();
+        let providers = router
+            .select_providers_for_model("claude", "claude-opus-4", false)
+            .await
+            .unwrap();
+        assert_eq!(ids(&providers), vec!["a"]);
+    }
+
+    #[tokio::test]
+    async fn test_routing_rule_respects_failover_switch_and_breakers() {
+        let (db, router) = setup_balanced_router(LoadBalanceStrategy::Priority).await;
+        save_rule(&db, "haiku", "*haiku*", &["c", "b"]);
+        save_rule(&db, "opus", "*opus*", &["b"]);
+
+        // 单供应商规则同样经过熔断器过滤
+        db.update_circuit_breaker_config(&CircuitBreakerConfig {
+            failure_threshold: 1,
+            timeout_seconds: 3600,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        router
+            .record_result("b", "claude", false, false, Some("fail".to_string()))
+            .await
+            .unwrap();
+        let providers = router
+            .select_providers_for_model("claude", "claude-opus-4", false)
+            .await
+            .unwrap();
+        assert_eq!(ids(&providers), vec!["a", "c"]);
+
+        // 故障转移关闭时只使用规则的第一个供应商
+        let mut config = db.get_proxy_config_for_app("claude").await.unwrap();
+        config.auto_failover_enabled = false;
+        db.update_proxy_config_for_app(config).await.unwrap();
+        let providers = router
+            .select_providers_for_model("claude", "claude-3-5-haiku", false)
+            .await
+            .unwrap();
+        assert_eq!(ids(&providers), vec!["c"]);
+    }
+
+    #[tokio::test]
+    async fn test_throttled_provider_cools_down_without_tripping_breaker() {
+        let db = Arc::new(Database::memory().unwrap());
+        let router = ProviderRouter::new(db);
+        let provider =
+            Provider::with_id("a".to_string(), "Provider A".to_string(), json!({}), None);
+
+        router
+            .record_throttled(&provider, "claude", false, 429, Duration::from_secs(30))
+            .await;
+
+        let remaining = router.cooldown_remaining("a", "claude").await.unwrap();
+        assert!(remaining > Duration::from_secs(25));
+        assert!(router.cooldown_remaining("a", "codex").await.is_none());
+
+        let stats = router
+            .get_circuit_breaker_stats("a", "claude")
+            .await
+            .unwrap();
+        assert_eq!(stats.failed_requests, 0);
+        assert!(stats.cooldown_remaining_secs.is_some());
+
+        let cooldowns = router.list_cooldowns().await;
+        assert_eq!(cooldowns.len(), 1);
+        assert_eq!(cooldowns[0].status_code, 429);
+
+        // 手动重置熔断器同时解除冷却
+        router.reset_provider_breaker("a", "claude").await;
+        assert!(router.cooldown_remaining("a", "claude").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_spend_limits_skip_providers_and_enforce_app_cap() {
+        let (db, router) = setup_balanced_router(LoadBalanceStrategy::Priority).await;
+
+        for (id, mode) in [("a", SpendLimitMode::Hard), ("b", SpendLimitMode::Soft)] {
+            let mut provider = db.get_provider_by_id(id, "claude").unwrap().unwrap();
+            provider.meta = Some(crate::provider::ProviderMeta {
+                limit_daily_usd: Some("1".to_string()),
+                limit_mode: Some(mode),
+                ..Default::default()
+            });
+            db.save_provider("claude", &provider).unwrap();
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        {
+            let conn = db.conn.lock().unwrap();
+            for (request_id, provider_id) in [("r1", "a"), ("r2", "b")] {
+                conn.execute(
+                    "INSERT INTO proxy_request_logs (request_id, provider_id, app_type, model,
+                        total_cost_usd, status_code, created_at)
+                     VALUES (?1, ?2, 'claude', 'm', '2.5', 200, ?3)",
+                    rusqlite::params![request_id, provider_id, now],
+                )
+                .unwrap();
+            }
+        }
+
+        // a 超出限额被跳过（hard），b 仅告警（soft）
+        let providers = router.select_providers("claude").await.unwrap();
+        assert_eq!(ids(&providers), vec!["b", "c"]);
+
+        // 应用级日上限已达到：直接拒绝
+        let mut config = db.get_proxy_config_for_app("claude").await.unwrap();
+        config.spend_limit_daily_usd = Some("5".to_string());
+        db.update_proxy_config_for_app(config).await.unwrap();
+
+        let err = router.select_providers("claude").await.unwrap_err();
+        assert!(matches!(err, AppError::SpendLimitExceeded(_)));
+        assert!(router
+            .select_providers("codex")
+            .await
+            .is_err_and(|e| !matches!(e, AppError::SpendLimitExceeded(_))));
+    }
 }
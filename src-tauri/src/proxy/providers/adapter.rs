@@ -84,6 +84,19 @@ pub trait ProviderAdapter: Send + Sync {
     /// 添加了认证头的 RequestBuilder
     fn add_auth_headers(&self, request: RequestBuilder, auth: &AuthInfo) -> RequestBuilder;
 
+    /// 计算实际请求的上游端点
+    ///
+    /// 格式转换时上游的端点可能与客户端请求的不同（如 `/v1/messages` → `/v1/chat/completions`，
+    /// 或 Gemini 需要把模型名放进路径）。默认直接使用客户端端点。
+    ///
+    /// # Arguments
+    /// * `endpoint` - 客户端请求的端点
+    /// * `provider` - Provider 配置
+    /// * `body` - 模型映射后、格式转换前的请求体
+    fn upstream_endpoint(&self, endpoint: &str, _provider: &Provider, _body: &Value) -> String {
+        endpoint.to_string()
+    }
+
     /// 是否需要格式转换
     ///
     /// 默认返回 `false`（透传模式）。
@@ -34,6 +34,12 @@ impl AuthInfo {
         }
     }
 
+    /// 替换 API Key，保留认证策略（用于 Key 池轮换）
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = api_key.into();
+        self
+    }
+
     /// 返回遮蔽后的 API Key（用于日志输出）
     ///
     /// 显示前4位和后4位，中间用 `...` 代替
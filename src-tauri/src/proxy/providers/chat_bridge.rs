@@ -0,0 +1,860 @@
+//! Chat Completions ⇄ Anthropic Messages 桥接模块
+//!
+//! 与 `transform`（Anthropic 客户端 → OpenAI 上游）方向相反：
+//! 让只会说 OpenAI Chat Completions 的客户端使用当前的 Claude 供应商。
+//! - 请求：Chat Completions → Anthropic Messages
+//! - 响应：Anthropic Messages → Chat Completions（含流式 chunk 与 tool_calls）
+
+use super::streaming::{convert_sse_stream, SseDataConverter};
+use crate::proxy::error::ProxyError;
+use bytes::Bytes;
+use futures::stream::Stream;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// Anthropic 要求必须提供 max_tokens，客户端未指定时使用该默认值
+const DEFAULT_MAX_TOKENS: u64 = 8192;
+
+/// 判断模型名是否应路由到 Claude 供应商（仅裸 `claude-*`）
+///
+/// 带厂商前缀的模型名（如 OpenRouter 的 `anthropic/claude-*`）属于 Codex 供应商自己的模型，不转交
+pub fn is_claude_model(model: &str) -> bool {
+    model.to_ascii_lowercase().starts_with("claude-")
+}
+
+/// Chat Completions 请求 → Anthropic Messages 请求
+pub fn chat_to_anthropic(body: Value) -> Result<Value, ProxyError> {
+    let mut result = json!({});
+
+    if let Some(model) = body.get("model").and_then(|m| m.as_str()) {
+        result["model"] = json!(model);
+    }
+
+    let mut system_parts: Vec<String> = Vec::new();
+    let mut messages: Vec<Value> = Vec::new();
+
+    if let Some(msgs) = body.get("messages").and_then(|m| m.as_array()) {
+        for msg in msgs {
+            let role = msg.get("role").and_then(|r| r.as_str()).unwrap_or("user");
+            match role {
+                "system" | "developer" => {
+                    let text = content_to_text(msg.get("content"));
+                    if !text.is_empty() {
+                        system_parts.push(text);
+                    }
+                }
+                "assistant" => {
+                    let mut blocks = content_to_blocks(msg.get("content"));
+                    if let Some(calls) = msg.get("tool_calls").and_then(|c| c.as_array()) {
+                        blocks.extend(calls.iter().map(convert_tool_call));
+                    }
+                    push_message(&mut messages, "assistant", blocks);
+                }
+                "tool" | "function" => {
+                    let tool_use_id = msg
+                        .get("tool_call_id")
+                        .or_else(|| msg.get("name"))
+                        .and_then(|i| i.as_str())
+                        .unwrap_or("");
+                    let block = json!({
+                        "type": "tool_result",
+                        "tool_use_id": tool_use_id,
+                        "content": content_to_text(msg.get("content"))
+                    });
+                    push_message(&mut messages, "user", vec![block]);
+                }
+                _ => {
+                    let blocks = content_to_blocks(msg.get("content"));
+                    push_message(&mut messages, "user", blocks);
+                }
+            }
+        }
+    }
+
+    if !system_parts.is_empty() {
+        result["system"] = json!(system_parts.join("\n\n"));
+    }
+    result["messages"] = json!(messages);
+
+    let max_tokens = body
+        .get("max_completion_tokens")
+        .or_else(|| body.get("max_tokens"))
+        .and_then(|v| v.as_u64())
+        .unwrap_or(DEFAULT_MAX_TOKENS);
+    result["max_tokens"] = json!(max_tokens);
+
+    if let Some(v) = body.get("temperature") {
+        result["temperature"] = v.clone();
+    }
+    if let Some(v) = body.get("top_p") {
+        result["top_p"] = v.clone();
+    }
+    match body.get("stop") {
+        Some(Value::String(s)) => result["stop_sequences"] = json!([s]),
+        Some(Value::Array(arr)) if !arr.is_empty() => result["stop_sequences"] = json!(arr),
+        _ => {}
+    }
+    if let Some(v) = body.get("stream") {
+        result["stream"] = v.clone();
+    }
+    if let Some(user) = body.get("user").and_then(|u| u.as_str()) {
+        result["metadata"] = json!({ "user_id": user });
+    }
+
+    if let Some(tools) = body.get("tools").and_then(|t| t.as_array()) {
+        let anthropic_tools: Vec<Value> = tools
+            .iter()
+            .filter(|t| t.get("type").and_then(|v| v.as_str()).unwrap_or("function") == "function")
+            .filter_map(|t| t.get("function"))
+            .map(|f| {
+                let mut tool = json!({
+                    "name": f.get("name").and_then(|n| n.as_str()).unwrap_or(""),
+                    "input_schema": f
+                        .get("parameters")
+                        .cloned()
+                        .unwrap_or_else(|| json!({"type": "object", "properties": {}}))
+                });
+                if let Some(desc) = f.get("description") {
+                    tool["description"] = desc.clone();
+                }
+                tool
+            })
+            .collect();
+
+        if !anthropic_tools.is_empty() {
+            result["tools"] = json!(anthropic_tools);
+        }
+    }
+
+    let mut tool_choice = body.get("tool_choice").and_then(convert_tool_choice);
+    if body.get("parallel_tool_calls").and_then(|p| p.as_bool()) == Some(false)
+        && result.get("tools").is_some()
+    {
+        let choice = tool_choice.get_or_insert_with(|| json!({"type": "auto"}));
+        if choice["type"] != "none" {
+            choice["disable_parallel_tool_use"] = json!(true);
+        }
+    }
+    if let Some(choice) = tool_choice {
+        result["tool_choice"] = choice;
+    }
+
+    Ok(result)
+}
+
+/// 追加消息；与上一条角色相同时合并（Anthropic 要求 user/assistant 交替）
+fn push_message(messages: &mut Vec<Value>, role: &str, blocks: Vec<Value>) {
+    if blocks.is_empty() {
+        return;
+    }
+    if let Some(last) = messages.last_mut() {
+        if last["role"] == role {
+            if let Some(content) = last["content"].as_array_mut() {
+                content.extend(blocks);
+                return;
+            }
+        }
+    }
+    messages.push(json!({ "role": role, "content": blocks }));
+}
+
+/// 提取消息中的纯文本（字符串或 text parts 拼接）
+fn content_to_text(content: Option<&Value>) -> String {
+    match content {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// Chat content（字符串或 parts 数组）→ Anthropic content blocks
+fn content_to_blocks(content: Option<&Value>) -> Vec<Value> {
+    match content {
+        Some(Value::String(s)) if !s.is_empty() => vec![json!({"type": "text", "text": s})],
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|part| match part.get("type").and_then(|t| t.as_str()) {
+                Some("text") => part
+                    .get("text")
+                    .and_then(|t| t.as_str())
+                    .filter(|t| !t.is_empty())
+                    .map(|t| json!({"type": "text", "text": t})),
+                Some("image_url") => part
+                    .get("image_url")
+                    .and_then(|i| i.get("url").or(Some(i)))
+                    .and_then(|u| u.as_str())
+                    .map(convert_image_url),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// `data:` URL 转为 base64 source，其余作为 url source
+fn convert_image_url(url: &str) -> Value {
+    if let Some(rest) = url.strip_prefix("data:") {
+        if let Some((media_type, data)) = rest.split_once(";base64,") {
+            return json!({
+                "type": "image",
+                "source": {"type": "base64", "media_type": media_type, "data": data}
+            });
+        }
+    }
+    json!({"type": "image", "source": {"type": "url", "url": url}})
+}
+
+/// assistant 的 tool_call → tool_use block（arguments 非法时退化为空对象）
+fn convert_tool_call(call: &Value) -> Value {
+    let function = call.get("function");
+    let arguments = function
+        .and_then(|f| f.get("arguments"))
+        .and_then(|a| a.as_str())
+        .unwrap_or("");
+    let input = serde_json::from_str::<Value>(arguments)
+        .ok()
+        .filter(|v| v.is_object())
+        .unwrap_or_else(|| json!({}));
+    json!({
+        "type": "tool_use",
+        "id": call.get("id").and_then(|i| i.as_str()).unwrap_or(""),
+        "name": function.and_then(|f| f.get("name")).and_then(|n| n.as_str()).unwrap_or(""),
+        "input": input
+    })
+}
+
+/// Chat tool_choice → Anthropic tool_choice
+fn convert_tool_choice(choice: &Value) -> Option<Value> {
+    match choice {
+        Value::String(s) => match s.as_str() {
+            "auto" => Some(json!({"type": "auto"})),
+            "required" => Some(json!({"type": "any"})),
+            "none" => Some(json!({"type": "none"})),
+            _ => None,
+        },
+        Value::Object(obj) => obj
+            .get("function")
+            .and_then(|f| f.get("name"))
+            .and_then(|n| n.as_str())
+            .map(|name| json!({"type": "tool", "name": name})),
+        _ => None,
+    }
+}
+
+/// Anthropic stop_reason → Chat finish_reason
+pub fn map_stop_reason(stop_reason: &str) -> &'static str {
+    match stop_reason {
+        "tool_use" => "tool_calls",
+        "max_tokens" => "length",
+        "refusal" => "content_filter",
+        _ => "stop",
+    }
+}
+
+/// Anthropic usage → Chat usage（prompt_tokens 包含缓存读写部分）
+pub fn anthropic_usage_to_chat(usage: &Value) -> Value {
+    let get = |key: &str| usage.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+    let cache_read = get("cache_read_input_tokens");
+    let prompt_tokens = get("input_tokens") + cache_read + get("cache_creation_input_tokens");
+    let completion_tokens = get("output_tokens");
+
+    let mut result = json!({
+        "prompt_tokens": prompt_tokens,
+        "completion_tokens": completion_tokens,
+        "total_tokens": prompt_tokens + completion_tokens
+    });
+    if cache_read > 0 {
+        result["prompt_tokens_details"] = json!({ "cached_tokens": cache_read });
+    }
+    result
+}
+
+fn chat_completion_id(message_id: &str) -> String {
+    format!(
+        "chatcmpl-{}",
+        message_id.strip_prefix("msg_").unwrap_or(message_id)
+    )
+}
+
+/// Anthropic Messages 响应 → Chat Completions 响应
+pub fn anthropic_to_chat(body: Value) -> Result<Value, ProxyError> {
+    let content = body
+        .get("content")
+        .and_then(|c| c.as_array())
+        .ok_or_else(|| ProxyError::TransformError("No content in response".to_string()))?;
+
+    let mut text = String::new();
+    let mut reasoning = String::new();
+    let mut tool_calls = Vec::new();
+
+    for block in content {
+        match block.get("type").and_then(|t| t.as_str()) {
+            Some("text") => {
+                if let Some(t) = block.get("text").and_then(|t| t.as_str()) {
+                    text.push_str(t);
+                }
+            }
+            Some("thinking") => {
+                if let Some(t) = block.get("thinking").and_then(|t| t.as_str()) {
+                    reasoning.push_str(t);
+                }
+            }
+            Some("tool_use") => {
+                let input = block.get("input").cloned().unwrap_or_else(|| json!({}));
+                tool_calls.push(json!({
+                    "id": block.get("id").and_then(|i| i.as_str()).unwrap_or(""),
+                    "type": "function",
+                    "function": {
+                        "name": block.get("name").and_then(|n| n.as_str()).unwrap_or(""),
+                        "arguments": input.to_string()
+                    }
+                }));
+            }
+            _ => {}
+        }
+    }
+
+    let mut message = json!({
+        "role": "assistant",
+        "content": if text.is_empty() && !tool_calls.is_empty() { Value::Null } else { json!(text) }
+    });
+    if !reasoning.is_empty() {
+        message["reasoning_content"] = json!(reasoning);
+    }
+    if !tool_calls.is_empty() {
+        message["tool_calls"] = json!(tool_calls);
+    }
+
+    let finish_reason = body
+        .get("stop_reason")
+        .and_then(|r| r.as_str())
+        .map(map_stop_reason)
+        .unwrap_or("stop");
+
+    let mut result = json!({
+        "id": chat_completion_id(body.get("id").and_then(|i| i.as_str()).unwrap_or("")),
+        "object": "chat.completion",
+        "created": chrono::Utc::now().timestamp(),
+        "model": body.get("model").and_then(|m| m.as_str()).unwrap_or(""),
+        "choices": [{
+            "index": 0,
+            "message": message,
+            "finish_reason": finish_reason
+        }]
+    });
+    if let Some(usage) = body.get("usage") {
+        result["usage"] = anthropic_usage_to_chat(usage);
+    }
+
+    Ok(result)
+}
+
+/// Anthropic SSE → Chat Completions SSE 转换状态机
+///
+/// 每个 tool_use block 对应一个 tool_calls 下标；usage 在 `message_start`/`message_delta`
+/// 中分两次给出，合并后仅在客户端请求 `stream_options.include_usage` 时单独发送。
+#[derive(Debug)]
+pub struct ChatSseConverter {
+    id: String,
+    model: String,
+    created: i64,
+    include_usage: bool,
+    usage: Map<String, Value>,
+    /// Anthropic content block 下标 → tool_calls 下标
+    tool_indices: HashMap<u64, usize>,
+    finish_sent: bool,
+    done: bool,
+}
+
+impl ChatSseConverter {
+    pub fn new(include_usage: bool) -> Self {
+        Self {
+            id: String::new(),
+            model: String::new(),
+            created: chrono::Utc::now().timestamp(),
+            include_usage,
+            usage: Map::new(),
+            tool_indices: HashMap::new(),
+            finish_sent: false,
+            done: false,
+        }
+    }
+
+    fn chunk(&self, delta: Value, finish_reason: Option<&str>) -> String {
+        chat_sse(json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "created": self.created,
+            "model": self.model,
+            "choices": [{
+                "index": 0,
+                "delta": delta,
+                "finish_reason": finish_reason
+            }]
+        }))
+    }
+
+    fn merge_usage(&mut self, usage: Option<&Value>) {
+        if let Some(Value::Object(usage)) = usage {
+            for (key, value) in usage {
+                if !value.is_null() {
+                    self.usage.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+
+    /// 处理一条 Anthropic SSE `data:` 负载，返回需要发送的 Chat SSE 文本
+    pub fn process_data(&mut self, data: &str) -> Vec<String> {
+        let event: Value = match serde_json::from_str(data) {
+            Ok(event) => event,
+            Err(e) => {
+                log::debug!("[Claude/Chat] 跳过无法解析的 SSE 数据: {e}");
+                return Vec::new();
+            }
+        };
+
+        let mut out = Vec::new();
+        match event.get("type").and_then(|t| t.as_str()) {
+            Some("message_start") => {
+                let message = event.get("message");
+                if let Some(id) = message.and_then(|m| m.get("id")).and_then(|i| i.as_str()) {
+                    self.id = chat_completion_id(id);
+                }
+                if let Some(model) = message
+                    .and_then(|m| m.get("model"))
+                    .and_then(|m| m.as_str())
+                {
+                    self.model = model.to_string();
+                }
+                self.merge_usage(message.and_then(|m| m.get("usage")));
+                out.push(self.chunk(json!({"role": "assistant", "content": ""}), None));
+            }
+            Some("content_block_start") => {
+                let block = event.get("content_block");
+                if block.and_then(|b| b.get("type")).and_then(|t| t.as_str()) == Some("tool_use") {
+                    let block_index = event.get("index").and_then(|i| i.as_u64()).unwrap_or(0);
+                    let tool_index = self.tool_indices.len();
+                    self.tool_indices.insert(block_index, tool_index);
+                    out.push(self.chunk(
+                        json!({"tool_calls": [{
+                            "index": tool_index,
+                            "id": block.and_then(|b| b.get("id")).and_then(|i| i.as_str()).unwrap_or(""),
+                            "type": "function",
+                            "function": {
+                                "name": block.and_then(|b| b.get("name")).and_then(|n| n.as_str()).unwrap_or(""),
+                                "arguments": ""
+                            }
+                        }]}),
+                        None,
+                    ));
+                }
+            }
+            Some("content_block_delta") => {
+                let delta = event.get("delta");
+                let text_of = |key: &str| {
+                    delta
+                        .and_then(|d| d.get(key))
+                        .and_then(|t| t.as_str())
+                        .unwrap_or("")
+                        .to_string()
+                };
+                match delta.and_then(|d| d.get("type")).and_then(|t| t.as_str()) {
+                    Some("text_delta") => {
+                        out.push(self.chunk(json!({"content": text_of("text")}), None));
+                    }
+                    Some("thinking_delta") => {
+                        out.push(
+                            self.chunk(json!({"reasoning_content": text_of("thinking")}), None),
+                        );
+                    }
+                    Some("input_json_delta") => {
+                        let block_index = event.get("index").and_then(|i| i.as_u64()).unwrap_or(0);
+                        if let Some(&tool_index) = self.tool_indices.get(&block_index) {
+                            out.push(self.chunk(
+                                json!({"tool_calls": [{
+                                    "index": tool_index,
+                                    "function": {"arguments": text_of("partial_json")}
+                                }]}),
+                                None,
+                            ));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Some("message_delta") => {
+                self.merge_usage(event.get("usage"));
+                if let Some(reason) = event
+                    .get("delta")
+                    .and_then(|d| d.get("stop_reason"))
+                    .and_then(|r| r.as_str())
+                {
+                    self.finish_sent = true;
+                    out.push(self.chunk(json!({}), Some(map_stop_reason(reason))));
+                }
+            }
+            Some("message_stop") => out.extend(self.finish()),
+            Some("error") => {
+                out.push(chat_sse(json!({
+                    "error": event.get("error").cloned().unwrap_or_else(|| json!({}))
+                })));
+                out.extend(self.finish());
+            }
+            _ => {}
+        }
+
+        out
+    }
+
+    /// 流结束时补齐 finish_reason、usage chunk 与 `[DONE]`
+    pub fn finish(&mut self) -> Vec<String> {
+        if self.done {
+            return Vec::new();
+        }
+        self.done = true;
+
+        let mut out = Vec::new();
+        if !self.finish_sent {
+            self.finish_sent = true;
+            out.push(self.chunk(json!({}), Some("stop")));
+        }
+        if self.include_usage && !self.usage.is_empty() {
+            out.push(chat_sse(json!({
+                "id": self.id,
+                "object": "chat.completion.chunk",
+                "created": self.created,
+                "model": self.model,
+                "choices": [],
+                "usage": anthropic_usage_to_chat(&Value::Object(self.usage.clone()))
+            })));
+        }
+        out.push("data: [DONE]\n\n".to_string());
+        out
+    }
+}
+
+impl SseDataConverter for ChatSseConverter {
+    fn process_data(&mut self, data: &str) -> Vec<String> {
+        ChatSseConverter::process_data(self, data)
+    }
+
+    fn upstream_finished(&self) -> bool {
+        self.finish_sent || self.done
+    }
+
+    fn finish(&mut self) -> Vec<String> {
+        ChatSseConverter::finish(self)
+    }
+
+    fn error(&mut self, message: &str) -> Vec<String> {
+        self.done = true;
+        vec![chat_sse(json!({
+            "error": {"type": "stream_error", "message": message}
+        }))]
+    }
+}
+
+/// Chat Completions 的 SSE 只有 `data:` 行
+fn chat_sse(data: Value) -> String {
+    format!(
+        "data: {}\n\n",
+        serde_json::to_string(&data).unwrap_or_default()
+    )
+}
+
+/// 创建 Chat Completions SSE 流（Anthropic SSE → Chat SSE）
+pub fn create_chat_sse_stream(
+    stream: impl Stream<Item = Result<Bytes, std::io::Error>> + Send + 'static,
+    include_usage: bool,
+) -> impl Stream<Item = Result<Bytes, std::io::Error>> + Send {
+    convert_sse_stream(stream, "Claude/Chat", ChatSseConverter::new(include_usage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_claude_model() {
+        assert!(is_claude_model("claude-sonnet-4-5"));
+        assert!(is_claude_model("Claude-3-5-Haiku"));
+        assert!(!is_claude_model("anthropic/claude-opus-4"));
+        assert!(!is_claude_model("claudette"));
+        assert!(!is_claude_model("gpt-4o"));
+        assert!(!is_claude_model("deepseek-chat"));
+    }
+
+    #[test]
+    fn test_chat_to_anthropic_basic() {
+        let body = json!({
+            "model": "claude-sonnet-4-5",
+            "messages": [
+                {"role": "system", "content": "You are helpful"},
+                {"role": "developer", "content": [{"type": "text", "text": "Be brief"}]},
+                {"role": "user", "content": "Hello"}
+            ],
+            "max_tokens": 100,
+            "temperature": 0.2,
+            "stop": "END",
+            "stream": true,
+            "user": "u1"
+        });
+
+        let result = chat_to_anthropic(body).unwrap();
+        assert_eq!(result["system"], "You are helpful\n\nBe brief");
+        assert_eq!(result["messages"].as_array().unwrap().len(), 1);
+        assert_eq!(result["messages"][0]["content"][0]["text"], "Hello");
+        assert_eq!(result["max_tokens"], 100);
+        assert_eq!(result["temperature"], 0.2);
+        assert_eq!(result["stop_sequences"], json!(["END"]));
+        assert_eq!(result["stream"], true);
+        assert_eq!(result["metadata"]["user_id"], "u1");
+    }
+
+    #[test]
+    fn test_chat_to_anthropic_default_max_tokens() {
+        let result = chat_to_anthropic(json!({
+            "model": "claude-sonnet-4-5",
+            "messages": [{"role": "user", "content": "Hi"}],
+            "max_completion_tokens": 256
+        }))
+        .unwrap();
+        assert_eq!(result["max_tokens"], 256);
+
+        let result = chat_to_anthropic(json!({
+            "model": "claude-sonnet-4-5",
+            "messages": [{"role": "user", "content": "Hi"}]
+        }))
+        .unwrap();
+        assert_eq!(result["max_tokens"], DEFAULT_MAX_TOKENS);
+    }
+
+    #[test]
+    fn test_chat_to_anthropic_tool_round_trip() {
+        let body = json!({
+            "model": "claude-sonnet-4-5",
+            "messages": [
+                {"role": "user", "content": "Weather in Tokyo and Paris?"},
+                {"role": "assistant", "content": null, "tool_calls": [
+                    {"id": "call_1", "type": "function", "function": {"name": "get_weather", "arguments": "{\"city\":\"Tokyo\"}"}},
+                    {"id": "call_2", "type": "function", "function": {"name": "get_weather", "arguments": "not json"}}
+                ]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "Sunny"},
+                {"role": "tool", "tool_call_id": "call_2", "content": [{"type": "text", "text": "Rainy"}]}
+            ],
+            "tools": [{"type": "function", "function": {
+                "name": "get_weather",
+                "description": "Get weather",
+                "parameters": {"type": "object", "properties": {"city": {"type": "string"}}}
+            }}],
+            "tool_choice": {"type": "function", "function": {"name": "get_weather"}},
+            "parallel_tool_calls": false
+        });
+
+        let result = chat_to_anthropic(body).unwrap();
+        let messages = result["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+
+        let assistant = &messages[1]["content"];
+        assert_eq!(assistant[0]["type"], "tool_use");
+        assert_eq!(assistant[0]["id"], "call_1");
+        assert_eq!(assistant[0]["input"]["city"], "Tokyo");
+        assert_eq!(assistant[1]["input"], json!({}));
+
+        // 连续的 tool 消息合并为一条 user 消息
+        let results = messages[2]["content"].as_array().unwrap();
+        assert_eq!(messages[2]["role"], "user");
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["type"], "tool_result");
+        assert_eq!(results[0]["tool_use_id"], "call_1");
+        assert_eq!(results[1]["content"], "Rainy");
+
+        assert_eq!(result["tools"][0]["name"], "get_weather");
+        assert_eq!(result["tools"][0]["input_schema"]["type"], "object");
+        assert_eq!(result["tool_choice"]["type"], "tool");
+        assert_eq!(result["tool_choice"]["name"], "get_weather");
+        assert_eq!(result["tool_choice"]["disable_parallel_tool_use"], true);
+    }
+
+    #[test]
+    fn test_chat_to_anthropic_tool_choice_strings() {
+        assert_eq!(
+            convert_tool_choice(&json!("required")).unwrap()["type"],
+            "any"
+        );
+        assert_eq!(convert_tool_choice(&json!("none")).unwrap()["type"], "none");
+        assert_eq!(convert_tool_choice(&json!("auto")).unwrap()["type"], "auto");
+    }
+
+    #[test]
+    fn test_chat_to_anthropic_images() {
+        let body = json!({
+            "model": "claude-sonnet-4-5",
+            "messages": [{"role": "user", "content": [
+                {"type": "text", "text": "What is this?"},
+                {"type": "image_url", "image_url": {"url": "data:image/png;base64,AAAA"}},
+                {"type": "image_url", "image_url": {"url": "https://example.com/a.jpg"}}
+            ]}]
+        });
+
+        let result = chat_to_anthropic(body).unwrap();
+        let content = &result["messages"][0]["content"];
+        assert_eq!(content[1]["source"]["type"], "base64");
+        assert_eq!(content[1]["source"]["media_type"], "image/png");
+        assert_eq!(content[1]["source"]["data"], "AAAA");
+        assert_eq!(content[2]["source"]["type"], "url");
+        assert_eq!(content[2]["source"]["url"], "https://example.com/a.jpg");
+    }
+
+    #[test]
+    fn test_anthropic_to_chat_text_and_tools() {
+        let body = json!({
+            "id": "msg_123",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-sonnet-4-5",
+            "content": [
+                {"type": "thinking", "thinking": "Let me check", "signature": "s"},
+                {"type": "text", "text": "Checking."},
+                {"type": "tool_use", "id": "toolu_1", "name": "get_weather", "input": {"city": "Tokyo"}}
+            ],
+            "stop_reason": "tool_use",
+            "usage": {"input_tokens": 10, "output_tokens": 5, "cache_read_input_tokens": 20}
+        });
+
+        let result = anthropic_to_chat(body).unwrap();
+        assert_eq!(result["id"], "chatcmpl-123");
+        assert_eq!(result["object"], "chat.completion");
+        let choice = &result["choices"][0];
+        assert_eq!(choice["finish_reason"], "tool_calls");
+        assert_eq!(choice["message"]["content"], "Checking.");
+        assert_eq!(choice["message"]["reasoning_content"], "Let me check");
+        let call = &choice["message"]["tool_calls"][0];
+        assert_eq!(call["id"], "toolu_1");
+        assert_eq!(call["function"]["name"], "get_weather");
+        let args: Value =
+            serde_json::from_str(call["function"]["arguments"].as_str().unwrap()).unwrap();
+        assert_eq!(args["city"], "Tokyo");
+
+        assert_eq!(result["usage"]["prompt_tokens"], 30);
+        assert_eq!(result["usage"]["completion_tokens"], 5);
+        assert_eq!(result["usage"]["total_tokens"], 35);
+        assert_eq!(
+            result["usage"]["prompt_tokens_details"]["cached_tokens"],
+            20
+        );
+    }
+
+    #[test]
+    fn test_anthropic_to_chat_tool_only_has_null_content() {
+        let body = json!({
+            "id": "msg_1",
+            "model": "claude",
+            "content": [{"type": "tool_use", "id": "t", "name": "f", "input": {}}],
+            "stop_reason": "tool_use"
+        });
+        let result = anthropic_to_chat(body).unwrap();
+        assert!(result["choices"][0]["message"]["content"].is_null());
+    }
+
+    #[test]
+    fn test_map_stop_reason() {
+        assert_eq!(map_stop_reason("end_turn"), "stop");
+        assert_eq!(map_stop_reason("stop_sequence"), "stop");
+        assert_eq!(map_stop_reason("max_tokens"), "length");
+        assert_eq!(map_stop_reason("tool_use"), "tool_calls");
+    }
+
+    fn run(converter: &mut ChatSseConverter, events: &[Value]) -> Vec<String> {
+        let mut out = Vec::new();
+        for event in events {
+            out.extend(converter.process_data(&event.to_string()));
+        }
+        out.extend(converter.finish());
+        out
+    }
+
+    fn parse(chunks: &[String]) -> Vec<Value> {
+        chunks
+            .iter()
+            .map(|c| c.trim_start_matches("data: ").trim())
+            .filter(|c| *c != "[DONE]")
+            .map(|c| serde_json::from_str(c).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_stream_text_and_tool_calls() {
+        let mut converter = ChatSseConverter::new(true);
+        let out = run(
+            &mut converter,
+            &[
+                json!({"type": "message_start", "message": {"id": "msg_1", "model": "claude-sonnet-4-5", "usage": {"input_tokens": 12, "output_tokens": 1}}}),
+                json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+                json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hi"}}),
+                json!({"type": "content_block_stop", "index": 0}),
+                json!({"type": "content_block_start", "index": 1, "content_block": {"type": "tool_use", "id": "toolu_1", "name": "get_weather", "input": {}}}),
+                json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "{\"city\":"}}),
+                json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "\"Tokyo\"}"}}),
+                json!({"type": "content_block_stop", "index": 1}),
+                json!({"type": "message_delta", "delta": {"stop_reason": "tool_use"}, "usage": {"output_tokens": 7}}),
+                json!({"type": "message_stop"}),
+            ],
+        );
+
+        assert_eq!(out.last().unwrap(), "data: [DONE]\n\n");
+        let chunks = parse(&out);
+        assert!(chunks.iter().all(|c| c["id"] == "chatcmpl-1"));
+        assert_eq!(chunks[0]["choices"][0]["delta"]["role"], "assistant");
+        assert_eq!(chunks[1]["choices"][0]["delta"]["content"], "Hi");
+
+        let start = &chunks[2]["choices"][0]["delta"]["tool_calls"][0];
+        assert_eq!(start["index"], 0);
+        assert_eq!(start["id"], "toolu_1");
+        assert_eq!(start["function"]["name"], "get_weather");
+        let args: String = chunks[3..5]
+            .iter()
+            .map(|c| {
+                c["choices"][0]["delta"]["tool_calls"][0]["function"]["arguments"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(args, "{\"city\":\"Tokyo\"}");
+
+        assert_eq!(chunks[5]["choices"][0]["finish_reason"], "tool_calls");
+        let usage_chunk = chunks.last().unwrap();
+        assert!(usage_chunk["choices"].as_array().unwrap().is_empty());
+        assert_eq!(usage_chunk["usage"]["prompt_tokens"], 12);
+        assert_eq!(usage_chunk["usage"]["completion_tokens"], 7);
+    }
+
+    #[test]
+    fn test_stream_thinking_and_truncated_upstream() {
+        let mut converter = ChatSseConverter::new(false);
+        let out = run(
+            &mut converter,
+            &[
+                json!({"type": "message_start", "message": {"id": "msg_2", "model": "claude"}}),
+                json!({"type": "content_block_delta", "index": 0, "delta": {"type": "thinking_delta", "thinking": "Hmm"}}),
+            ],
+        );
+
+        let chunks = parse(&out);
+        assert_eq!(chunks[1]["choices"][0]["delta"]["reasoning_content"], "Hmm");
+        // 上游提前断开时补齐 finish_reason，且未请求 usage 时不发送 usage chunk
+        assert_eq!(
+            chunks.last().unwrap()["choices"][0]["finish_reason"],
+            "stop"
+        );
+        assert!(chunks.iter().all(|c| c.get("usage").is_none()));
+        assert_eq!(out.last().unwrap(), "data: [DONE]\n\n");
+        assert!(converter.finish().is_empty());
+    }
+}
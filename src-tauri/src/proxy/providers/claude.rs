@@ -1,16 +1,21 @@
 //! Claude (Anthropic) Provider Adapter
 //!
-//! 支持透传模式和 OpenRouter 兼容模式
+//! 支持透传模式、OpenAI 兼容模式和 OpenRouter 兼容模式
 //!
 //! ## 认证模式
 //! - **Claude**: Anthropic 官方 API (x-api-key + anthropic-version)
 //! - **ClaudeAuth**: 中转服务 (仅 Bearer 认证，无 x-api-key)
+//! - **OpenAICompat**: OpenAI Chat Completions 兼容上游（Bearer），
+//!   在 settings_config 中设置 `"api_format": "openai"` 启用
+//! - **GeminiCompat**: Gemini generateContent 上游（x-goog-api-key），
+//!   在 settings_config 中设置 `"api_format": "gemini"` 启用
 //! - **OpenRouter**: 已支持 Claude Code 兼容接口，默认透传（保留旧转换逻辑备用）
 
 use super::{AuthInfo, AuthStrategy, ProviderAdapter, ProviderType};
 use crate::provider::Provider;
 use crate::proxy::error::ProxyError;
 use reqwest::RequestBuilder;
+use serde_json::Value;
 
 /// Claude 适配器
 pub struct ClaudeAdapter;
@@ -22,11 +27,20 @@ impl ClaudeAdapter {
 
     /// 获取供应商类型
     ///
-    /// 根据 base_url 和 auth_mode 检测具体的供应商类型：
+    /// 根据 api_format、base_url 和 auth_mode 检测具体的供应商类型：
+    /// - OpenAICompat: api_format 为 openai / openai_chat
+    /// - GeminiCompat: api_format 为 gemini
     /// - OpenRouter: base_url 包含 openrouter.ai
     /// - ClaudeAuth: auth_mode 为 bearer_only
     /// - Claude: 默认 Anthropic 官方
     pub fn provider_type(&self, provider: &Provider) -> ProviderType {
+        // 显式配置的上游格式优先
+        match self.api_format(provider).as_deref() {
+            Some("openai" | "openai_chat") => return ProviderType::OpenAICompat,
+            Some("gemini") => return ProviderType::GeminiCompat,
+            _ => {}
+        }
+
         // 检测 OpenRouter
         if self.is_openrouter(provider) {
             return ProviderType::OpenRouter;
@@ -40,6 +54,16 @@ impl ClaudeAdapter {
         ProviderType::Claude
     }
 
+    /// 读取 settings_config 中显式配置的上游格式
+    fn api_format(&self, provider: &Provider) -> Option<String> {
+        provider
+            .settings_config
+            .get("api_format")
+            .or_else(|| provider.settings_config.get("apiFormat"))
+            .and_then(|v| v.as_str())
+            .map(|v| v.trim().to_lowercase())
+    }
+
     /// 检测是否使用 OpenRouter
     fn is_openrouter(&self, provider: &Provider) -> bool {
         if let Ok(base_url) = self.extract_base_url(provider) {
@@ -120,6 +144,15 @@ impl ClaudeAdapter {
                 log::debug!("[Claude] 使用 OPENROUTER_API_KEY");
                 return Some(key.to_string());
             }
+            // Gemini key (用于 Gemini 上游)
+            if let Some(key) = env
+                .get("GEMINI_API_KEY")
+                .and_then(|v| v.as_str())
+                .filter(|s| !s.is_empty())
+            {
+                log::debug!("[Claude] 使用 GEMINI_API_KEY");
+                return Some(key.to_string());
+            }
             // 备选 OpenAI key (用于 OpenRouter)
             if let Some(key) = env
                 .get("OPENAI_API_KEY")
@@ -200,7 +233,8 @@ impl ProviderAdapter for ClaudeAdapter {
     fn extract_auth(&self, provider: &Provider) -> Option<AuthInfo> {
         let provider_type = self.provider_type(provider);
         let strategy = match provider_type {
-            ProviderType::OpenRouter => AuthStrategy::Bearer,
+            ProviderType::OpenRouter | ProviderType::OpenAICompat => AuthStrategy::Bearer,
+            ProviderType::GeminiCompat => AuthStrategy::Google,
             ProviderType::ClaudeAuth => AuthStrategy::ClaudeAuth,
             _ => AuthStrategy::Anthropic,
         };
@@ -215,13 +249,21 @@ impl ProviderAdapter for ClaudeAdapter {
         // 映射到 `/v1/chat/completions`，并做 Anthropic ↔ OpenAI 的格式转换。
         //
         // 现在 OpenRouter 已推出 Claude Code 兼容接口，因此默认直接透传 endpoint。
-        // 如需回退旧逻辑，可在 forwarder 中根据 needs_transform 改写 endpoint。
+        // 需要转换时由 forwarder 根据 needs_transform 改写 endpoint。
+        //
+        // OpenAI 兼容服务的 base_url 常常已带 `/v1`（如 `https://api.deepseek.com/v1`），
+        // Gemini 则可能带 `/v1beta`，此时去掉 endpoint 中重复的版本前缀。
+        let base = base_url.trim_end_matches('/');
+        let mut path = endpoint.trim_start_matches('/');
+        for version in ["v1beta", "v1"] {
+            if base.ends_with(&format!("/{version}")) {
+                if let Some(rest) = path.strip_prefix(&format!("{version}/")) {
+                    path = rest;
+                }
+            }
+        }
 
-        format!(
-            "{}/{}",
-            base_url.trim_end_matches('/'),
-            endpoint.trim_start_matches('/')
-        )
+        format!("{base}/{path}")
     }
 
     fn add_auth_headers(&self, request: RequestBuilder, auth: &AuthInfo) -> RequestBuilder {
@@ -239,17 +281,37 @@ impl ProviderAdapter for ClaudeAdapter {
             AuthStrategy::Bearer => request
                 .header("Authorization", format!("Bearer {}", auth.api_key))
                 .header("anthropic-version", "2023-06-01"),
+            // Gemini 上游: x-goog-api-key
+            AuthStrategy::Google => request.header("x-goog-api-key", &auth.api_key),
             _ => request,
         }
     }
 
-    fn needs_transform(&self, _provider: &Provider) -> bool {
-        // NOTE:
-        // OpenRouter 已推出 Claude Code 兼容接口（可直接处理 `/v1/messages`），默认不再启用
-        // Anthropic ↔ OpenAI 的格式转换。
-        //
-        // 如果未来需要回退到旧的 OpenAI Chat Completions 方案，可恢复下面这行：
-        self.is_openrouter_compat_enabled(_provider)
+    fn upstream_endpoint(&self, endpoint: &str, provider: &Provider, body: &Value) -> String {
+        if endpoint != "/v1/messages" || !self.needs_transform(provider) {
+            return endpoint.to_string();
+        }
+
+        if self.provider_type(provider) == ProviderType::GeminiCompat {
+            // Gemini 的模型名在路径中，流式需要 alt=sse 才返回 SSE
+            let model = body.get("model").and_then(|m| m.as_str()).unwrap_or("");
+            let model = super::transform::get_model_from_provider(model, provider, body);
+            let model = model.trim_start_matches("models/");
+            return if body.get("stream").and_then(|s| s.as_bool()) == Some(true) {
+                format!("/v1beta/models/{model}:streamGenerateContent?alt=sse")
+            } else {
+                format!("/v1beta/models/{model}:generateContent")
+            };
+        }
+
+        "/v1/chat/completions".to_string()
+    }
+
+    fn needs_transform(&self, provider: &Provider) -> bool {
+        // OpenAI 兼容 / Gemini 上游始终需要格式转换；
+        // OpenRouter 由 openrouter_compat_mode 控制
+        self.provider_type(provider).needs_transform()
+            || self.is_openrouter_compat_enabled(provider)
     }
 
     fn transform_request(
@@ -257,7 +319,10 @@ impl ProviderAdapter for ClaudeAdapter {
         body: serde_json::Value,
         provider: &Provider,
     ) -> Result<serde_json::Value, ProxyError> {
-        super::transform::anthropic_to_openai(body, provider)
+        match self.provider_type(provider) {
+            ProviderType::GeminiCompat => super::gemini_transform::anthropic_to_gemini(body),
+            _ => super::transform::anthropic_to_openai(body, provider),
+        }
     }
 
     fn transform_response(&self, body: serde_json::Value) -> Result<serde_json::Value, ProxyError> {
@@ -411,6 +476,113 @@ mod tests {
             adapter.provider_type(&claude_auth),
             ProviderType::ClaudeAuth
         );
+
+        // OpenAI 兼容（优先于 bearer_only）
+        let openai_compat = create_provider(json!({
+            "env": {
+                "ANTHROPIC_BASE_URL": "https://api.deepseek.com",
+                "ANTHROPIC_AUTH_TOKEN": "sk-test"
+            },
+            "api_format": "openai",
+            "auth_mode": "bearer_only"
+        }));
+        assert_eq!(
+            adapter.provider_type(&openai_compat),
+            ProviderType::OpenAICompat
+        );
+    }
+
+    #[test]
+    fn test_extract_auth_openai_compat() {
+        let adapter = ClaudeAdapter::new();
+        let provider = create_provider(json!({
+            "env": {
+                "ANTHROPIC_BASE_URL": "http://localhost:8000/v1",
+                "ANTHROPIC_AUTH_TOKEN": "sk-local"
+            },
+            "api_format": "openai_chat"
+        }));
+
+        let auth = adapter.extract_auth(&provider).unwrap();
+        assert_eq!(auth.api_key, "sk-local");
+        assert_eq!(auth.strategy, AuthStrategy::Bearer);
+    }
+
+    #[test]
+    fn test_gemini_compat_endpoint_and_auth() {
+        let adapter = ClaudeAdapter::new();
+        let provider = create_provider(json!({
+            "env": {
+                "ANTHROPIC_BASE_URL": "https://generativelanguage.googleapis.com/v1beta",
+                "GEMINI_API_KEY": "AIza-test",
+                "ANTHROPIC_MODEL": "gemini-2.5-pro"
+            },
+            "api_format": "gemini"
+        }));
+
+        assert_eq!(adapter.provider_type(&provider), ProviderType::GeminiCompat);
+        assert!(adapter.needs_transform(&provider));
+
+        let auth = adapter.extract_auth(&provider).unwrap();
+        assert_eq!(auth.api_key, "AIza-test");
+        assert_eq!(auth.strategy, AuthStrategy::Google);
+
+        let body = json!({"model": "claude-sonnet-4", "stream": true});
+        let endpoint = adapter.upstream_endpoint("/v1/messages", &provider, &body);
+        assert_eq!(
+            endpoint,
+            "/v1beta/models/gemini-2.5-pro:streamGenerateContent?alt=sse"
+        );
+        let url = adapter.build_url(
+            "https://generativelanguage.googleapis.com/v1beta",
+            &endpoint,
+        );
+        assert_eq!(
+            url,
+            "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.5-pro:streamGenerateContent?alt=sse"
+        );
+
+        let body = json!({"model": "claude-sonnet-4"});
+        assert_eq!(
+            adapter.upstream_endpoint("/v1/messages", &provider, &body),
+            "/v1beta/models/gemini-2.5-pro:generateContent"
+        );
+        // count_tokens 等其他端点不改写
+        assert_eq!(
+            adapter.upstream_endpoint("/v1/messages/count_tokens", &provider, &body),
+            "/v1/messages/count_tokens"
+        );
+    }
+
+    #[test]
+    fn test_openai_compat_endpoint() {
+        let adapter = ClaudeAdapter::new();
+        let provider = create_provider(json!({
+            "env": {"ANTHROPIC_BASE_URL": "https://api.deepseek.com"},
+            "api_format": "openai"
+        }));
+        assert_eq!(
+            adapter.upstream_endpoint("/v1/messages", &provider, &json!({})),
+            "/v1/chat/completions"
+        );
+
+        let anthropic = create_provider(json!({
+            "env": {"ANTHROPIC_BASE_URL": "https://api.anthropic.com"}
+        }));
+        assert_eq!(
+            adapter.upstream_endpoint("/v1/messages", &anthropic, &json!({})),
+            "/v1/messages"
+        );
+    }
+
+    #[test]
+    fn test_build_url_dedupes_v1() {
+        let adapter = ClaudeAdapter::new();
+        let url = adapter.build_url("https://api.deepseek.com/v1", "/v1/chat/completions");
+        assert_eq!(url, "https://api.deepseek.com/v1/chat/completions");
+
+        let url = adapter.build_url("https://api.deepseek.com", "/v1/chat/completions");
+        assert_eq!(url, "https://api.deepseek.com/v1/chat/completions");
     }
 
     #[test]
@@ -452,5 +624,13 @@ mod tests {
             "openrouter_compat_mode": false
         }));
         assert!(!adapter.needs_transform(&openrouter_disabled));
+
+        let openai_compat = create_provider(json!({
+            "env": {
+                "ANTHROPIC_BASE_URL": "https://api.moonshot.cn/v1"
+            },
+            "api_format": "openai"
+        }));
+        assert!(adapter.needs_transform(&openai_compat));
     }
 }
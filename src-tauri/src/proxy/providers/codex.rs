@@ -1,15 +1,18 @@
 //! Codex (OpenAI) Provider Adapter
 //!
-//! 仅透传模式，支持直连 OpenAI API
+//! 默认透传模式，支持直连 OpenAI API；
+//! 在 settings_config 中设置 `"api_format": "openai_chat"` 时，
+//! 将 `/v1/responses` 桥接到仅支持 Chat Completions 的上游
 //!
 //! ## 客户端检测
 //! 支持检测官方 Codex 客户端 (codex_vscode, codex_cli_rs)
 
-use super::{AuthInfo, AuthStrategy, ProviderAdapter};
+use super::{AuthInfo, AuthStrategy, ProviderAdapter, ProviderType};
 use crate::provider::Provider;
 use crate::proxy::error::ProxyError;
 use regex::Regex;
 use reqwest::RequestBuilder;
+use serde_json::Value;
 use std::sync::LazyLock;
 
 /// 官方 Codex 客户端 User-Agent 正则
@@ -33,6 +36,23 @@ impl CodexAdapter {
         CODEX_CLIENT_REGEX.is_match(user_agent)
     }
 
+    /// 获取供应商类型
+    ///
+    /// - CodexChat: api_format 为 openai_chat / chat（上游仅支持 Chat Completions）
+    /// - Codex: 默认 Responses API 透传
+    pub fn provider_type(&self, provider: &Provider) -> ProviderType {
+        let api_format = provider
+            .settings_config
+            .get("api_format")
+            .or_else(|| provider.settings_config.get("apiFormat"))
+            .and_then(|v| v.as_str())
+            .map(|v| v.trim().to_lowercase());
+        match api_format.as_deref() {
+            Some("openai_chat" | "chat") => ProviderType::CodexChat,
+            _ => ProviderType::Codex,
+        }
+    }
+
     /// 从 Provider 配置中提取 API Key
     fn extract_key(&self, provider: &Provider) -> Option<String> {
         // 1. 尝试从 env 中获取
@@ -154,6 +174,29 @@ impl ProviderAdapter for CodexAdapter {
     fn add_auth_headers(&self, request: RequestBuilder, auth: &AuthInfo) -> RequestBuilder {
         request.header("Authorization", format!("Bearer {}", auth.api_key))
     }
+
+    fn upstream_endpoint(&self, endpoint: &str, provider: &Provider, _body: &Value) -> String {
+        if self.needs_transform(provider) && endpoint.ends_with("/responses") {
+            return "/v1/chat/completions".to_string();
+        }
+        endpoint.to_string()
+    }
+
+    fn needs_transform(&self, provider: &Provider) -> bool {
+        self.provider_type(provider) == ProviderType::CodexChat
+    }
+
+    fn transform_request(&self, body: Value, _provider: &Provider) -> Result<Value, ProxyError> {
+        // 客户端直接发来的 Chat Completions 请求无需桥接
+        if body.get("messages").is_some() && body.get("input").is_none() {
+            return Ok(body);
+        }
+        super::responses_bridge::responses_to_chat(body)
+    }
+
+    fn transform_response(&self, body: Value) -> Result<Value, ProxyError> {
+        super::responses_bridge::chat_to_responses(body)
+    }
 }
 
 #[cfg(test)]
@@ -223,6 +266,38 @@ mod tests {
         assert_eq!(url, "https://api.openai.com/v1/responses");
     }
 
+    #[test]
+    fn test_chat_bridge_detection_and_endpoint() {
+        let adapter = CodexAdapter::new();
+        let passthrough = create_provider(json!({
+            "base_url": "https://api.openai.com/v1"
+        }));
+        assert_eq!(adapter.provider_type(&passthrough), ProviderType::Codex);
+        assert!(!adapter.needs_transform(&passthrough));
+        assert_eq!(
+            adapter.upstream_endpoint("/v1/responses", &passthrough, &json!({})),
+            "/v1/responses"
+        );
+
+        let bridged = create_provider(json!({
+            "base_url": "https://relay.example.com/v1",
+            "api_format": "openai_chat"
+        }));
+        assert_eq!(adapter.provider_type(&bridged), ProviderType::CodexChat);
+        assert!(adapter.needs_transform(&bridged));
+        let endpoint = adapter.upstream_endpoint("/v1/responses", &bridged, &json!({}));
+        assert_eq!(endpoint, "/v1/chat/completions");
+        assert_eq!(
+            adapter.build_url("https://relay.example.com/v1", &endpoint),
+            "https://relay.example.com/v1/chat/completions"
+        );
+        // Chat Completions 请求本身不需要改写端点
+        assert_eq!(
+            adapter.upstream_endpoint("/v1/chat/completions", &bridged, &json!({})),
+            "/v1/chat/completions"
+        );
+    }
+
     #[test]
     fn test_build_url_dedup_v1() {
         let adapter = CodexAdapter::new();
@@ -0,0 +1,283 @@
+//! Gemini 流式响应转换模块
+//!
+//! 实现 Gemini streamGenerateContent（`alt=sse`）→ Anthropic SSE 格式转换
+
+use super::gemini_transform::{
+    gemini_usage_to_anthropic, map_gemini_finish_reason, new_tool_use_id,
+};
+use super::streaming::{convert_sse_stream, AnthropicEventWriter, BlockKind, SseDataConverter};
+use bytes::Bytes;
+use futures::stream::Stream;
+use serde_json::{json, Value};
+
+/// Gemini SSE → Anthropic SSE 转换状态机
+///
+/// Gemini 每个 chunk 都是完整的 GenerateContentResponse（parts 为增量内容），
+/// usageMetadata 为累计值，以最后一次为准；流没有 [DONE] 标记，收尾由 `finish` 完成。
+#[derive(Debug, Default)]
+pub struct GeminiSseConverter {
+    writer: AnthropicEventWriter,
+    has_tool_use: bool,
+}
+
+impl GeminiSseConverter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 处理一条 Gemini SSE `data:` 负载，返回需要发送的 Anthropic SSE 文本
+    pub fn process_data(&mut self, data: &str) -> Vec<String> {
+        let chunk: Value = match serde_json::from_str(data) {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                log::debug!("[Claude/Gemini] 跳过无法解析的 SSE 数据: {e}");
+                return Vec::new();
+            }
+        };
+
+        let mut events = Vec::new();
+        let writer = &mut self.writer;
+
+        if writer.message_id.is_none() {
+            if let Some(id) = chunk.get("responseId").and_then(|i| i.as_str()) {
+                writer.message_id = Some(format!("msg_{id}"));
+            }
+        }
+        if writer.model.is_none() {
+            if let Some(model) = chunk.get("modelVersion").and_then(|m| m.as_str()) {
+                writer.model = Some(model.to_string());
+            }
+        }
+        if let Some(usage) = chunk.get("usageMetadata") {
+            writer.usage = Some(gemini_usage_to_anthropic(usage));
+        }
+
+        let Some(candidate) = chunk
+            .get("candidates")
+            .and_then(|c| c.as_array())
+            .and_then(|c| c.first())
+        else {
+            return events;
+        };
+
+        writer.ensure_message_start(&mut events);
+
+        if let Some(parts) = candidate
+            .get("content")
+            .and_then(|c| c.get("parts"))
+            .and_then(|p| p.as_array())
+        {
+            for part in parts {
+                let signature = part
+                    .get("thoughtSignature")
+                    .and_then(|s| s.as_str())
+                    .filter(|s| !s.is_empty());
+
+                if let Some(call) = part.get("functionCall") {
+                    self.has_tool_use = true;
+                    // 签名放在独立的 thinking block 中，下一轮请求时回传
+                    if let Some(signature) = signature {
+                        writer.start_block(
+                            &mut events,
+                            BlockKind::Thinking,
+                            json!({"type": "thinking", "thinking": "", "signature": ""}),
+                        );
+                        writer.push_delta(
+                            &mut events,
+                            json!({"type": "signature_delta", "signature": signature}),
+                        );
+                    }
+                    let id = call
+                        .get("id")
+                        .and_then(|i| i.as_str())
+                        .map(|s| s.to_string())
+                        .unwrap_or_else(new_tool_use_id);
+                    // Gemini 一次性返回完整参数
+                    writer.start_block(
+                        &mut events,
+                        BlockKind::ToolUse,
+                        json!({
+                            "type": "tool_use",
+                            "id": id,
+                            "name": call.get("name").and_then(|n| n.as_str()).unwrap_or(""),
+                            "input": {}
+                        }),
+                    );
+                    let args = call.get("args").cloned().unwrap_or(json!({}));
+                    writer.push_delta(
+                        &mut events,
+                        json!({"type": "input_json_delta", "partial_json": args.to_string()}),
+                    );
+                    writer.close_block(&mut events);
+                    continue;
+                }
+
+                let text = part.get("text").and_then(|t| t.as_str()).unwrap_or("");
+                if part.get("thought").and_then(|t| t.as_bool()) == Some(true) {
+                    writer.switch_block(
+                        &mut events,
+                        BlockKind::Thinking,
+                        || json!({"type": "thinking", "thinking": "", "signature": ""}),
+                    );
+                    if !text.is_empty() {
+                        writer.push_delta(
+                            &mut events,
+                            json!({"type": "thinking_delta", "thinking": text}),
+                        );
+                    }
+                    if let Some(signature) = signature {
+                        writer.push_delta(
+                            &mut events,
+                            json!({"type": "signature_delta", "signature": signature}),
+                        );
+                    }
+                } else if !text.is_empty() {
+                    writer.switch_block(
+                        &mut events,
+                        BlockKind::Text,
+                        || json!({"type": "text", "text": ""}),
+                    );
+                    writer.push_delta(&mut events, json!({"type": "text_delta", "text": text}));
+                }
+            }
+        }
+
+        if let Some(finish_reason) = candidate.get("finishReason").and_then(|r| r.as_str()) {
+            writer.close_block(&mut events);
+            writer.pending_stop_reason =
+                Some(map_gemini_finish_reason(finish_reason, self.has_tool_use).to_string());
+        }
+
+        writer.flush_message_delta_if_ready(&mut events);
+
+        events
+    }
+
+    /// 流结束时补齐缺失的收尾事件
+    pub fn finish(&mut self) -> Vec<String> {
+        self.writer.finish()
+    }
+}
+
+impl SseDataConverter for GeminiSseConverter {
+    fn process_data(&mut self, data: &str) -> Vec<String> {
+        GeminiSseConverter::process_data(self, data)
+    }
+
+    fn upstream_finished(&self) -> bool {
+        self.writer.upstream_finished()
+    }
+
+    fn finish(&mut self) -> Vec<String> {
+        GeminiSseConverter::finish(self)
+    }
+}
+
+/// 创建 Anthropic SSE 流（Gemini SSE → Anthropic SSE）
+pub fn create_anthropic_sse_stream_from_gemini(
+    stream: impl Stream<Item = Result<Bytes, reqwest::Error>> + Send + 'static,
+) -> impl Stream<Item = Result<Bytes, std::io::Error>> + Send {
+    convert_sse_stream(stream, "Claude/Gemini", GeminiSseConverter::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(chunks: &[Value]) -> Vec<(String, Value)> {
+        let mut converter = GeminiSseConverter::new();
+        let mut out = Vec::new();
+        for chunk in chunks {
+            out.extend(converter.process_data(&chunk.to_string()));
+        }
+        out.extend(converter.finish());
+        out.iter()
+            .map(|e| {
+                let mut lines = e.lines();
+                let event = lines.next().unwrap().trim_start_matches("event: ");
+                let data = lines.next().unwrap().trim_start_matches("data: ");
+                (event.to_string(), serde_json::from_str(data).unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_thinking_and_text_stream() {
+        let events = run(&[
+            json!({"responseId": "r1", "modelVersion": "gemini-2.5-flash", "candidates": [{"content": {"parts": [{"text": "Hmm", "thought": true}]}}]}),
+            json!({"candidates": [{"content": {"parts": [{"text": "Hel"}]}}]}),
+            json!({"candidates": [{"content": {"parts": [{"text": "lo"}]}, "finishReason": "STOP"}],
+                   "usageMetadata": {"promptTokenCount": 10, "candidatesTokenCount": 2, "thoughtsTokenCount": 3}}),
+        ]);
+
+        let names: Vec<&str> = events.iter().map(|(e, _)| e.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "message_start",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_stop",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_delta",
+                "content_block_stop",
+                "message_delta",
+                "message_stop"
+            ]
+        );
+        assert_eq!(events[0].1["message"]["id"], "msg_r1");
+        assert_eq!(events[0].1["message"]["model"], "gemini-2.5-flash");
+        assert_eq!(events[1].1["content_block"]["type"], "thinking");
+        assert_eq!(events[4].1["content_block"]["type"], "text");
+        assert_eq!(events[4].1["index"], 1);
+        assert_eq!(events[8].1["delta"]["stop_reason"], "end_turn");
+        assert_eq!(events[8].1["usage"]["input_tokens"], 10);
+        assert_eq!(events[8].1["usage"]["output_tokens"], 5);
+    }
+
+    #[test]
+    fn test_function_call_stream() {
+        let events = run(&[json!({
+            "candidates": [{
+                "content": {"parts": [
+                    {"functionCall": {"name": "read_file", "args": {"path": "a.rs"}}, "thoughtSignature": "sig"}
+                ]},
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {"promptTokenCount": 5, "candidatesTokenCount": 1}
+        })]);
+
+        let starts: Vec<_> = events
+            .iter()
+            .filter(|(e, _)| e == "content_block_start")
+            .collect();
+        assert_eq!(starts.len(), 2);
+        assert_eq!(starts[0].1["content_block"]["type"], "thinking");
+        assert_eq!(starts[1].1["content_block"]["type"], "tool_use");
+        assert_eq!(starts[1].1["content_block"]["name"], "read_file");
+
+        let signature = events
+            .iter()
+            .find(|(_, d)| d["delta"]["type"] == "signature_delta")
+            .unwrap();
+        assert_eq!(signature.1["delta"]["signature"], "sig");
+
+        let args = events
+            .iter()
+            .find(|(_, d)| d["delta"]["type"] == "input_json_delta")
+            .unwrap();
+        let parsed: Value =
+            serde_json::from_str(args.1["delta"]["partial_json"].as_str().unwrap()).unwrap();
+        assert_eq!(parsed["path"], "a.rs");
+
+        let delta = events.iter().find(|(e, _)| e == "message_delta").unwrap();
+        assert_eq!(delta.1["delta"]["stop_reason"], "tool_use");
+    }
+
+    #[test]
+    fn test_stream_without_candidates_emits_nothing() {
+        let events = run(&[json!({"usageMetadata": {"promptTokenCount": 1}})]);
+        assert!(events.is_empty());
+    }
+}
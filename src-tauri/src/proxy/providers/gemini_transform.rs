@@ -0,0 +1,629 @@
+//! Gemini 格式转换模块
+//!
+//! 实现 Anthropic Messages ↔ Gemini generateContent 格式转换，
+//! 用于 Claude 应用使用 Gemini API Key 作为上游
+
+use super::transform::clean_schema;
+use crate::proxy::error::ProxyError;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// Gemini 不支持的 JSON Schema 字段
+const UNSUPPORTED_SCHEMA_KEYS: &[&str] = &[
+    "$schema",
+    "$id",
+    "$ref",
+    "$defs",
+    "definitions",
+    "additionalProperties",
+    "exclusiveMinimum",
+    "exclusiveMaximum",
+    "const",
+    "examples",
+    "default",
+    "propertyNames",
+    "patternProperties",
+];
+
+/// Anthropic 请求 → Gemini generateContent 请求
+///
+/// 模型名放在 URL 中（见 `ClaudeAdapter::upstream_endpoint`），请求体不包含 model 字段。
+pub fn anthropic_to_gemini(body: Value) -> Result<Value, ProxyError> {
+    let mut result = json!({});
+
+    // system → systemInstruction
+    if let Some(system) = body.get("system") {
+        let text = if let Some(text) = system.as_str() {
+            text.to_string()
+        } else if let Some(arr) = system.as_array() {
+            arr.iter()
+                .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        } else {
+            String::new()
+        };
+        if !text.is_empty() {
+            result["systemInstruction"] = json!({"parts": [{"text": text}]});
+        }
+    }
+
+    // messages → contents
+    let messages = body
+        .get("messages")
+        .and_then(|m| m.as_array())
+        .ok_or_else(|| ProxyError::TransformError("Missing messages".to_string()))?;
+
+    // tool_use id → 函数名（functionResponse 需要函数名而不是 id）
+    let mut tool_names: HashMap<String, String> = HashMap::new();
+    let mut contents: Vec<Value> = Vec::new();
+
+    for msg in messages {
+        let role = match msg.get("role").and_then(|r| r.as_str()) {
+            Some("assistant") => "model",
+            _ => "user",
+        };
+        let parts = convert_message_parts(msg.get("content"), &mut tool_names);
+        if parts.is_empty() {
+            continue;
+        }
+
+        // Gemini 要求 user/model 交替出现，合并相邻的同角色消息
+        if let Some(last) = contents.last_mut() {
+            if last.get("role").and_then(|r| r.as_str()) == Some(role) {
+                if let Some(existing) = last.get_mut("parts").and_then(|p| p.as_array_mut()) {
+                    existing.extend(parts);
+                    continue;
+                }
+            }
+        }
+        contents.push(json!({"role": role, "parts": parts}));
+    }
+    result["contents"] = json!(contents);
+
+    // generationConfig
+    let mut generation_config = Map::new();
+    if let Some(v) = body.get("max_tokens") {
+        generation_config.insert("maxOutputTokens".to_string(), v.clone());
+    }
+    if let Some(v) = body.get("temperature") {
+        generation_config.insert("temperature".to_string(), v.clone());
+    }
+    if let Some(v) = body.get("top_p") {
+        generation_config.insert("topP".to_string(), v.clone());
+    }
+    if let Some(v) = body.get("top_k") {
+        generation_config.insert("topK".to_string(), v.clone());
+    }
+    if let Some(v) = body.get("stop_sequences") {
+        generation_config.insert("stopSequences".to_string(), v.clone());
+    }
+    if let Some(thinking) = body.get("thinking") {
+        match thinking.get("type").and_then(|t| t.as_str()) {
+            Some("enabled") => {
+                let mut config = json!({"includeThoughts": true});
+                if let Some(budget) = thinking.get("budget_tokens") {
+                    config["thinkingBudget"] = budget.clone();
+                }
+                generation_config.insert("thinkingConfig".to_string(), config);
+            }
+            Some("disabled") => {
+                generation_config
+                    .insert("thinkingConfig".to_string(), json!({"thinkingBudget": 0}));
+            }
+            _ => {}
+        }
+    }
+    if !generation_config.is_empty() {
+        result["generationConfig"] = Value::Object(generation_config);
+    }
+
+    // tools → functionDeclarations
+    if let Some(tools) = body.get("tools").and_then(|t| t.as_array()) {
+        let declarations: Vec<Value> = tools
+            .iter()
+            .filter(|t| t.get("type").and_then(|v| v.as_str()) != Some("BatchTool"))
+            .map(|t| {
+                let mut declaration = json!({
+                    "name": t.get("name").and_then(|n| n.as_str()).unwrap_or(""),
+                    "description": t.get("description").and_then(|d| d.as_str()).unwrap_or("")
+                });
+                let schema = t.get("input_schema").cloned().unwrap_or(json!({}));
+                let schema = clean_gemini_schema(clean_schema(schema));
+                // 无参数的工具不能传空 properties 的 object
+                let has_properties = schema
+                    .get("properties")
+                    .and_then(|p| p.as_object())
+                    .is_some_and(|p| !p.is_empty());
+                if has_properties {
+                    declaration["parameters"] = schema;
+                }
+                declaration
+            })
+            .collect();
+
+        if !declarations.is_empty() {
+            result["tools"] = json!([{"functionDeclarations": declarations}]);
+        }
+    }
+
+    // tool_choice → toolConfig
+    if let Some(choice) = body.get("tool_choice") {
+        let config = match choice.get("type").and_then(|t| t.as_str()) {
+            Some("auto") => Some(json!({"mode": "AUTO"})),
+            Some("any") => Some(json!({"mode": "ANY"})),
+            Some("none") => Some(json!({"mode": "NONE"})),
+            Some("tool") => choice
+                .get("name")
+                .and_then(|n| n.as_str())
+                .map(|name| json!({"mode": "ANY", "allowedFunctionNames": [name]})),
+            _ => None,
+        };
+        if let Some(config) = config {
+            result["toolConfig"] = json!({"functionCallingConfig": config});
+        }
+    }
+
+    Ok(result)
+}
+
+/// 转换单条消息的 content 为 Gemini parts
+fn convert_message_parts(
+    content: Option<&Value>,
+    tool_names: &mut HashMap<String, String>,
+) -> Vec<Value> {
+    let mut parts = Vec::new();
+
+    let blocks = match content {
+        Some(Value::String(text)) => {
+            parts.push(json!({"text": text}));
+            return parts;
+        }
+        Some(Value::Array(blocks)) => blocks,
+        _ => return parts,
+    };
+
+    // 上一个 thinking block 的签名，回传给紧随其后的 functionCall
+    let mut thought_signature: Option<String> = None;
+
+    for block in blocks {
+        match block.get("type").and_then(|t| t.as_str()).unwrap_or("") {
+            "text" => {
+                if let Some(text) = block.get("text").and_then(|t| t.as_str()) {
+                    parts.push(json!({"text": text}));
+                }
+            }
+            "image" => {
+                if let Some(part) = block.get("source").and_then(convert_image_source) {
+                    parts.push(part);
+                }
+            }
+            "tool_use" => {
+                let id = block.get("id").and_then(|i| i.as_str()).unwrap_or("");
+                let name = block.get("name").and_then(|n| n.as_str()).unwrap_or("");
+                tool_names.insert(id.to_string(), name.to_string());
+                let mut part = json!({
+                    "functionCall": {
+                        "name": name,
+                        "args": block.get("input").cloned().unwrap_or(json!({}))
+                    }
+                });
+                if let Some(signature) = thought_signature.take() {
+                    part["thoughtSignature"] = json!(signature);
+                }
+                parts.push(part);
+            }
+            "tool_result" => {
+                let tool_use_id = block
+                    .get("tool_use_id")
+                    .and_then(|i| i.as_str())
+                    .unwrap_or("");
+                let name = tool_names
+                    .get(tool_use_id)
+                    .cloned()
+                    .unwrap_or_else(|| tool_use_id.to_string());
+
+                let mut images = Vec::new();
+                let text = match block.get("content") {
+                    Some(Value::String(s)) => s.clone(),
+                    Some(Value::Array(items)) => {
+                        let mut texts = Vec::new();
+                        for item in items {
+                            match item.get("type").and_then(|t| t.as_str()) {
+                                Some("text") => {
+                                    if let Some(t) = item.get("text").and_then(|t| t.as_str()) {
+                                        texts.push(t);
+                                    }
+                                }
+                                Some("image") => {
+                                    if let Some(part) =
+                                        item.get("source").and_then(convert_image_source)
+                                    {
+                                        images.push(part);
+                                    }
+                                }
+                                _ => {}
+                            }
+                        }
+                        texts.join("\n")
+                    }
+                    Some(v) => v.to_string(),
+                    None => String::new(),
+                };
+
+                let is_error = block
+                    .get("is_error")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+                let response = if is_error {
+                    json!({"error": text})
+                } else {
+                    json!({"content": text})
+                };
+                parts.push(json!({
+                    "functionResponse": {"name": name, "response": response}
+                }));
+                parts.extend(images);
+            }
+            "thinking" => {
+                // 推理内容不回传，只保留签名供 functionCall 使用
+                thought_signature = block
+                    .get("signature")
+                    .and_then(|s| s.as_str())
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    parts
+}
+
+/// Anthropic image source → Gemini inlineData / fileData
+fn convert_image_source(source: &Value) -> Option<Value> {
+    let media_type = source
+        .get("media_type")
+        .and_then(|m| m.as_str())
+        .unwrap_or("image/png");
+    match source.get("type").and_then(|t| t.as_str()) {
+        Some("url") => {
+            let url = source.get("url").and_then(|u| u.as_str())?;
+            Some(json!({"fileData": {"mimeType": media_type, "fileUri": url}}))
+        }
+        _ => {
+            let data = source.get("data").and_then(|d| d.as_str())?;
+            Some(json!({"inlineData": {"mimeType": media_type, "data": data}}))
+        }
+    }
+}
+
+/// 在 `clean_schema` 基础上移除 Gemini 不支持的 schema 字段
+fn clean_gemini_schema(mut schema: Value) -> Value {
+    if let Some(obj) = schema.as_object_mut() {
+        for key in UNSUPPORTED_SCHEMA_KEYS {
+            obj.remove(*key);
+        }
+
+        if let Some(properties) = obj.get_mut("properties").and_then(|v| v.as_object_mut()) {
+            for (_, value) in properties.iter_mut() {
+                *value = clean_gemini_schema(value.take());
+            }
+        }
+
+        if let Some(items) = obj.get_mut("items") {
+            *items = clean_gemini_schema(items.take());
+        }
+
+        for key in ["anyOf", "oneOf", "allOf"] {
+            if let Some(variants) = obj.get_mut(key).and_then(|v| v.as_array_mut()) {
+                for variant in variants.iter_mut() {
+                    *variant = clean_gemini_schema(variant.take());
+                }
+            }
+        }
+    }
+    schema
+}
+
+/// Gemini finishReason → Anthropic stop_reason
+pub fn map_gemini_finish_reason(finish_reason: &str, has_tool_use: bool) -> &'static str {
+    if has_tool_use {
+        return "tool_use";
+    }
+    match finish_reason {
+        "MAX_TOKENS" => "max_tokens",
+        "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII" => "refusal",
+        _ => "end_turn",
+    }
+}
+
+/// Gemini usageMetadata → Anthropic usage
+///
+/// thoughtsTokenCount 按输出计费，合并到 output_tokens；
+/// cachedContentTokenCount 映射为 cache_read_input_tokens 并从 input_tokens 中扣除。
+pub fn gemini_usage_to_anthropic(usage: &Value) -> Value {
+    let get = |key: &str| usage.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+    let prompt_tokens = get("promptTokenCount");
+    let cached_tokens = get("cachedContentTokenCount");
+    let output_tokens = get("candidatesTokenCount") + get("thoughtsTokenCount");
+
+    let mut result = json!({
+        "input_tokens": prompt_tokens.saturating_sub(cached_tokens),
+        "output_tokens": output_tokens
+    });
+    if cached_tokens > 0 {
+        result["cache_read_input_tokens"] = json!(cached_tokens);
+    }
+    result
+}
+
+/// 生成 tool_use id（Gemini 的 functionCall 不一定带 id）
+pub fn new_tool_use_id() -> String {
+    format!("toolu_{}", uuid::Uuid::new_v4().simple())
+}
+
+/// Gemini generateContent 响应 → Anthropic 响应
+pub fn gemini_to_anthropic(body: Value) -> Result<Value, ProxyError> {
+    let candidate = body
+        .get("candidates")
+        .and_then(|c| c.as_array())
+        .and_then(|c| c.first())
+        .ok_or_else(|| {
+            // 被安全策略拦截时没有 candidates，只有 promptFeedback
+            let reason = body
+                .get("promptFeedback")
+                .and_then(|f| f.get("blockReason"))
+                .and_then(|r| r.as_str())
+                .unwrap_or("unknown");
+            ProxyError::TransformError(format!("No candidates in response (blockReason: {reason})"))
+        })?;
+
+    let mut content = Vec::new();
+    let mut has_tool_use = false;
+
+    if let Some(parts) = candidate
+        .get("content")
+        .and_then(|c| c.get("parts"))
+        .and_then(|p| p.as_array())
+    {
+        for part in parts {
+            let signature = part
+                .get("thoughtSignature")
+                .and_then(|s| s.as_str())
+                .unwrap_or("");
+
+            if let Some(call) = part.get("functionCall") {
+                has_tool_use = true;
+                let id = call
+                    .get("id")
+                    .and_then(|i| i.as_str())
+                    .map(|s| s.to_string())
+                    .unwrap_or_else(new_tool_use_id);
+                // 保留签名，下一轮请求时回传给 Gemini
+                if !signature.is_empty() {
+                    content
+                        .push(json!({"type": "thinking", "thinking": "", "signature": signature}));
+                }
+                content.push(json!({
+                    "type": "tool_use",
+                    "id": id,
+                    "name": call.get("name").and_then(|n| n.as_str()).unwrap_or(""),
+                    "input": call.get("args").cloned().unwrap_or(json!({}))
+                }));
+            } else if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
+                if part.get("thought").and_then(|t| t.as_bool()) == Some(true) {
+                    content.push(
+                        json!({"type": "thinking", "thinking": text, "signature": signature}),
+                    );
+                } else {
+                    content.push(json!({"type": "text", "text": text}));
+                }
+            }
+        }
+    }
+
+    let stop_reason = candidate
+        .get("finishReason")
+        .and_then(|r| r.as_str())
+        .map(|r| map_gemini_finish_reason(r, has_tool_use));
+
+    let usage = gemini_usage_to_anthropic(body.get("usageMetadata").unwrap_or(&json!({})));
+
+    let id = body
+        .get("responseId")
+        .and_then(|i| i.as_str())
+        .map(|s| format!("msg_{s}"))
+        .unwrap_or_else(|| format!("msg_{}", uuid::Uuid::new_v4().simple()));
+
+    Ok(json!({
+        "id": id,
+        "type": "message",
+        "role": "assistant",
+        "content": content,
+        "model": body.get("modelVersion").and_then(|m| m.as_str()).unwrap_or(""),
+        "stop_reason": stop_reason,
+        "stop_sequence": null,
+        "usage": usage
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anthropic_to_gemini_basic() {
+        let input = json!({
+            "model": "claude-sonnet-4",
+            "max_tokens": 1024,
+            "temperature": 0.5,
+            "stop_sequences": ["END"],
+            "system": [{"type": "text", "text": "Be brief."}],
+            "messages": [
+                {"role": "user", "content": "Hello"},
+                {"role": "assistant", "content": [{"type": "text", "text": "Hi"}]},
+                {"role": "user", "content": [
+                    {"type": "text", "text": "Look"},
+                    {"type": "image", "source": {"type": "base64", "media_type": "image/jpeg", "data": "AAAA"}}
+                ]}
+            ]
+        });
+
+        let result = anthropic_to_gemini(input).unwrap();
+        assert!(result.get("model").is_none());
+        assert_eq!(result["systemInstruction"]["parts"][0]["text"], "Be brief.");
+        assert_eq!(result["contents"][0]["role"], "user");
+        assert_eq!(result["contents"][0]["parts"][0]["text"], "Hello");
+        assert_eq!(result["contents"][1]["role"], "model");
+        assert_eq!(
+            result["contents"][2]["parts"][1]["inlineData"]["data"],
+            "AAAA"
+        );
+        assert_eq!(result["generationConfig"]["maxOutputTokens"], 1024);
+        assert_eq!(result["generationConfig"]["temperature"], 0.5);
+        assert_eq!(result["generationConfig"]["stopSequences"][0], "END");
+    }
+
+    #[test]
+    fn test_anthropic_to_gemini_tools_and_results() {
+        let input = json!({
+            "max_tokens": 1024,
+            "tools": [{
+                "name": "get_weather",
+                "description": "Get weather",
+                "input_schema": {
+                    "$schema": "http://json-schema.org/draft-07/schema#",
+                    "type": "object",
+                    "additionalProperties": false,
+                    "properties": {
+                        "city": {"type": "string", "format": "uri"},
+                        "days": {"type": "integer", "exclusiveMinimum": 0}
+                    }
+                }
+            }, {
+                "name": "now",
+                "input_schema": {"type": "object", "properties": {}}
+            }],
+            "tool_choice": {"type": "tool", "name": "get_weather"},
+            "messages": [
+                {"role": "user", "content": "Weather?"},
+                {"role": "assistant", "content": [
+                    {"type": "thinking", "thinking": "", "signature": "sig-1"},
+                    {"type": "tool_use", "id": "toolu_1", "name": "get_weather", "input": {"city": "Paris"}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": [{"type": "text", "text": "Sunny"}]},
+                    {"type": "text", "text": "Thanks"}
+                ]}
+            ]
+        });
+
+        let result = anthropic_to_gemini(input).unwrap();
+        let declarations = &result["tools"][0]["functionDeclarations"];
+        let params = &declarations[0]["parameters"];
+        assert!(params.get("$schema").is_none());
+        assert!(params.get("additionalProperties").is_none());
+        assert!(params["properties"]["city"].get("format").is_none());
+        assert!(params["properties"]["days"]
+            .get("exclusiveMinimum")
+            .is_none());
+        assert!(declarations[1].get("parameters").is_none());
+
+        assert_eq!(
+            result["toolConfig"]["functionCallingConfig"],
+            json!({"mode": "ANY", "allowedFunctionNames": ["get_weather"]})
+        );
+
+        let call = &result["contents"][1]["parts"][0];
+        assert_eq!(call["functionCall"]["name"], "get_weather");
+        assert_eq!(call["functionCall"]["args"]["city"], "Paris");
+        assert_eq!(call["thoughtSignature"], "sig-1");
+
+        let response = &result["contents"][2]["parts"][0]["functionResponse"];
+        assert_eq!(response["name"], "get_weather");
+        assert_eq!(response["response"]["content"], "Sunny");
+        assert_eq!(result["contents"][2]["parts"][1]["text"], "Thanks");
+    }
+
+    #[test]
+    fn test_anthropic_to_gemini_thinking_config() {
+        let input = json!({
+            "max_tokens": 4096,
+            "thinking": {"type": "enabled", "budget_tokens": 2048},
+            "messages": [{"role": "user", "content": "Think"}]
+        });
+        let result = anthropic_to_gemini(input).unwrap();
+        assert_eq!(
+            result["generationConfig"]["thinkingConfig"],
+            json!({"includeThoughts": true, "thinkingBudget": 2048})
+        );
+    }
+
+    #[test]
+    fn test_anthropic_to_gemini_merges_consecutive_roles() {
+        let input = json!({
+            "messages": [
+                {"role": "user", "content": "a"},
+                {"role": "user", "content": "b"}
+            ]
+        });
+        let result = anthropic_to_gemini(input).unwrap();
+        let contents = result["contents"].as_array().unwrap();
+        assert_eq!(contents.len(), 1);
+        assert_eq!(contents[0]["parts"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_gemini_to_anthropic() {
+        let input = json!({
+            "responseId": "abc",
+            "modelVersion": "gemini-2.5-pro",
+            "candidates": [{
+                "content": {"role": "model", "parts": [
+                    {"text": "Planning", "thought": true},
+                    {"text": "Calling tool"},
+                    {"functionCall": {"name": "get_weather", "args": {"city": "Paris"}}, "thoughtSignature": "sig"}
+                ]},
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {
+                "promptTokenCount": 100,
+                "candidatesTokenCount": 20,
+                "thoughtsTokenCount": 10,
+                "cachedContentTokenCount": 40
+            }
+        });
+
+        let result = gemini_to_anthropic(input).unwrap();
+        assert_eq!(result["id"], "msg_abc");
+        assert_eq!(result["model"], "gemini-2.5-pro");
+        let content = result["content"].as_array().unwrap();
+        assert_eq!(content[0]["type"], "thinking");
+        assert_eq!(content[0]["thinking"], "Planning");
+        assert_eq!(content[1]["type"], "text");
+        assert_eq!(content[2]["type"], "thinking");
+        assert_eq!(content[2]["signature"], "sig");
+        assert_eq!(content[3]["type"], "tool_use");
+        assert_eq!(content[3]["input"]["city"], "Paris");
+        assert!(content[3]["id"].as_str().unwrap().starts_with("toolu_"));
+        assert_eq!(result["stop_reason"], "tool_use");
+        assert_eq!(result["usage"]["input_tokens"], 60);
+        assert_eq!(result["usage"]["cache_read_input_tokens"], 40);
+        assert_eq!(result["usage"]["output_tokens"], 30);
+    }
+
+    #[test]
+    fn test_gemini_to_anthropic_blocked_prompt() {
+        let input = json!({"promptFeedback": {"blockReason": "SAFETY"}});
+        assert!(gemini_to_anthropic(input).is_err());
+    }
+
+    #[test]
+    fn test_map_gemini_finish_reason() {
+        assert_eq!(map_gemini_finish_reason("STOP", false), "end_turn");
+        assert_eq!(map_gemini_finish_reason("STOP", true), "tool_use");
+        assert_eq!(map_gemini_finish_reason("MAX_TOKENS", false), "max_tokens");
+        assert_eq!(map_gemini_finish_reason("SAFETY", false), "refusal");
+    }
+}
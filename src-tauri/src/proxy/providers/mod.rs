@@ -5,18 +5,25 @@
 //! ## 模块结构
 //! - `adapter`: 定义 `ProviderAdapter` trait
 //! - `auth`: 认证类型和策略
+//! - `chat_bridge`: Chat Completions 客户端 ⇄ Anthropic 上游桥接
 //! - `claude`: Claude (Anthropic) 适配器
 //! - `codex`: Codex (OpenAI) 适配器
 //! - `gemini`: Gemini (Google) 适配器
+//! - `gemini_transform` / `gemini_streaming`: Anthropic ↔ Gemini 格式转换
 //! - `models`: API 数据模型
+//! - `responses_bridge`: Responses API ⇄ Chat Completions 桥接
 //! - `transform`: 格式转换
 
 mod adapter;
 mod auth;
+pub mod chat_bridge;
 mod claude;
 mod codex;
 mod gemini;
+pub mod gemini_streaming;
+pub mod gemini_transform;
 pub mod models;
+pub mod responses_bridge;
 pub mod streaming;
 pub mod transform;
 
@@ -44,12 +51,21 @@ pub enum ProviderType {
     ClaudeAuth,
     /// OpenAI Codex Response API
     Codex,
+    /// Codex 使用仅支持 Chat Completions 的上游，需要 Responses ⇄ Chat 桥接
+    CodexChat,
     /// Google Gemini API (x-goog-api-key)
     Gemini,
     /// Google Gemini CLI (OAuth Bearer)
     GeminiCli,
     /// OpenRouter（已支持 Claude Code 兼容接口，默认透传；保留旧转换逻辑备用）
     OpenRouter,
+    /// OpenAI Chat Completions 兼容上游（DeepSeek、vLLM、Kimi、本地服务等），
+    /// 需要 Anthropic ↔ OpenAI 格式转换
+    #[serde(rename = "openai_compat")]
+    OpenAICompat,
+    /// Claude 应用使用 Gemini generateContent 上游（x-goog-api-key），
+    /// 需要 Anthropic ↔ Gemini 格式转换
+    GeminiCompat,
 }
 
 impl ProviderType {
@@ -57,9 +73,13 @@ impl ProviderType {
     ///
     /// 过去 OpenRouter 需要将 Anthropic 格式转换为 OpenAI 格式；
     /// 现在默认关闭转换（因为 OpenRouter 已支持 Claude Code 兼容接口）。
+    /// OpenAI 兼容上游始终需要转换。
     #[allow(dead_code)]
     pub fn needs_transform(&self) -> bool {
         match self {
+            ProviderType::OpenAICompat | ProviderType::GeminiCompat | ProviderType::CodexChat => {
+                true
+            }
             ProviderType::OpenRouter => false,
             _ => false,
         }
@@ -70,8 +90,10 @@ impl ProviderType {
     pub fn default_endpoint(&self) -> &'static str {
         match self {
             ProviderType::Claude | ProviderType::ClaudeAuth => "https://api.anthropic.com",
-            ProviderType::Codex => "https://api.openai.com",
-            ProviderType::Gemini | ProviderType::GeminiCli => {
+            ProviderType::Codex | ProviderType::CodexChat | ProviderType::OpenAICompat => {
+                "https://api.openai.com"
+            }
+            ProviderType::Gemini | ProviderType::GeminiCli | ProviderType::GeminiCompat => {
                 "https://generativelanguage.googleapis.com"
             }
             ProviderType::OpenRouter => "https://openrouter.ai/api",
@@ -81,41 +103,11 @@ impl ProviderType {
     /// 从 AppType 和 Provider 配置推断供应商类型
     ///
     /// 根据配置中的 base_url、auth_mode、api_key 格式等信息推断具体的供应商类型
-    #[allow(dead_code)]
     pub fn from_app_type_and_config(app_type: &AppType, provider: &Provider) -> Self {
         match app_type {
-            AppType::Claude => {
-                // 检测是否为 OpenRouter
-                let adapter = ClaudeAdapter::new();
-                if let Ok(base_url) = adapter.extract_base_url(provider) {
-                    if base_url.contains("openrouter.ai") {
-                        return ProviderType::OpenRouter;
-                    }
-                }
-                // 检测是否为中转服务（仅 Bearer 认证）
-                // 注意：ProviderMeta 没有直接的 auth_mode 字段，
-                // 我们通过检查 settings_config 中的配置来判断
-                // 检查 settings_config 中的 auth_mode
-                if let Some(auth_mode) = provider
-                    .settings_config
-                    .get("auth_mode")
-                    .and_then(|v| v.as_str())
-                {
-                    if auth_mode == "bearer_only" {
-                        return ProviderType::ClaudeAuth;
-                    }
-                }
-                // 检查 env 中的 auth_mode
-                if let Some(env) = provider.settings_config.get("env") {
-                    if let Some(auth_mode) = env.get("AUTH_MODE").and_then(|v| v.as_str()) {
-                        if auth_mode == "bearer_only" {
-                            return ProviderType::ClaudeAuth;
-                        }
-                    }
-                }
-                ProviderType::Claude
-            }
-            AppType::Codex => ProviderType::Codex,
+            // OpenAI 兼容 / OpenRouter / 仅 Bearer 认证的检测逻辑统一由适配器负责
+            AppType::Claude => ClaudeAdapter::new().provider_type(provider),
+            AppType::Codex => CodexAdapter::new().provider_type(provider),
             AppType::Gemini => {
                 // 检测是否为 CLI 模式（OAuth）
                 let adapter = GeminiAdapter::new();
@@ -141,9 +133,12 @@ impl ProviderType {
             ProviderType::Claude => "claude",
             ProviderType::ClaudeAuth => "claude_auth",
             ProviderType::Codex => "codex",
+            ProviderType::CodexChat => "codex_chat",
             ProviderType::Gemini => "gemini",
             ProviderType::GeminiCli => "gemini_cli",
             ProviderType::OpenRouter => "openrouter",
+            ProviderType::OpenAICompat => "openai_compat",
+            ProviderType::GeminiCompat => "gemini_compat",
         }
     }
 }
@@ -162,9 +157,12 @@ impl std::str::FromStr for ProviderType {
             "claude" => Ok(ProviderType::Claude),
             "claude_auth" | "claude-auth" => Ok(ProviderType::ClaudeAuth),
             "codex" => Ok(ProviderType::Codex),
+            "codex_chat" | "codex-chat" => Ok(ProviderType::CodexChat),
             "gemini" => Ok(ProviderType::Gemini),
             "gemini_cli" | "gemini-cli" => Ok(ProviderType::GeminiCli),
             "openrouter" => Ok(ProviderType::OpenRouter),
+            "openai_compat" | "openai-compat" | "openai" => Ok(ProviderType::OpenAICompat),
+            "gemini_compat" | "gemini-compat" => Ok(ProviderType::GeminiCompat),
             _ => Err(format!("Invalid provider type: {s}")),
         }
     }
@@ -183,10 +181,12 @@ pub fn get_adapter(app_type: &AppType) -> Box<dyn ProviderAdapter> {
 #[allow(dead_code)]
 pub fn get_adapter_for_provider_type(provider_type: &ProviderType) -> Box<dyn ProviderAdapter> {
     match provider_type {
-        ProviderType::Claude | ProviderType::ClaudeAuth | ProviderType::OpenRouter => {
-            Box::new(ClaudeAdapter::new())
-        }
-        ProviderType::Codex => Box::new(CodexAdapter::new()),
+        ProviderType::Claude
+        | ProviderType::ClaudeAuth
+        | ProviderType::OpenRouter
+        | ProviderType::OpenAICompat
+        | ProviderType::GeminiCompat => Box::new(ClaudeAdapter::new()),
+        ProviderType::Codex | ProviderType::CodexChat => Box::new(CodexAdapter::new()),
         ProviderType::Gemini | ProviderType::GeminiCli => Box::new(GeminiAdapter::new()),
     }
 }
@@ -221,6 +221,9 @@ mod tests {
         assert!(!ProviderType::Gemini.needs_transform());
         assert!(!ProviderType::GeminiCli.needs_transform());
         assert!(!ProviderType::OpenRouter.needs_transform());
+        assert!(ProviderType::OpenAICompat.needs_transform());
+        assert!(ProviderType::GeminiCompat.needs_transform());
+        assert!(ProviderType::CodexChat.needs_transform());
     }
 
     #[test]
@@ -249,6 +252,14 @@ mod tests {
             ProviderType::OpenRouter.default_endpoint(),
             "https://openrouter.ai/api"
         );
+        assert_eq!(
+            ProviderType::OpenAICompat.default_endpoint(),
+            "https://api.openai.com"
+        );
+        assert_eq!(
+            ProviderType::GeminiCompat.default_endpoint(),
+            "https://generativelanguage.googleapis.com"
+        );
     }
 
     #[test]
@@ -285,6 +296,22 @@ mod tests {
             "openrouter".parse::<ProviderType>().unwrap(),
             ProviderType::OpenRouter
         );
+        assert_eq!(
+            "openai_compat".parse::<ProviderType>().unwrap(),
+            ProviderType::OpenAICompat
+        );
+        assert_eq!(
+            "openai-compat".parse::<ProviderType>().unwrap(),
+            ProviderType::OpenAICompat
+        );
+        assert_eq!(
+            "gemini_compat".parse::<ProviderType>().unwrap(),
+            ProviderType::GeminiCompat
+        );
+        assert_eq!(
+            "codex_chat".parse::<ProviderType>().unwrap(),
+            ProviderType::CodexChat
+        );
         assert!("invalid".parse::<ProviderType>().is_err());
     }
 
@@ -296,6 +323,9 @@ mod tests {
         assert_eq!(ProviderType::Gemini.as_str(), "gemini");
         assert_eq!(ProviderType::GeminiCli.as_str(), "gemini_cli");
         assert_eq!(ProviderType::OpenRouter.as_str(), "openrouter");
+        assert_eq!(ProviderType::OpenAICompat.as_str(), "openai_compat");
+        assert_eq!(ProviderType::GeminiCompat.as_str(), "gemini_compat");
+        assert_eq!(ProviderType::CodexChat.as_str(), "codex_chat");
     }
 
     #[test]
@@ -315,6 +345,9 @@ mod tests {
 
         let deserialized: ProviderType = serde_json::from_str("\"gemini_cli\"").unwrap();
         assert_eq!(deserialized, ProviderType::GeminiCli);
+
+        let serialized = serde_json::to_string(&ProviderType::OpenAICompat).unwrap();
+        assert_eq!(serialized, "\"openai_compat\"");
     }
 
     #[test]
@@ -357,6 +390,34 @@ mod tests {
         assert_eq!(provider_type, ProviderType::ClaudeAuth);
     }
 
+    #[test]
+    fn test_from_app_type_claude_openai_compat() {
+        let provider = create_provider(json!({
+            "env": {
+                "ANTHROPIC_BASE_URL": "https://api.deepseek.com",
+                "ANTHROPIC_AUTH_TOKEN": "sk-test"
+            },
+            "api_format": "openai"
+        }));
+
+        let provider_type = ProviderType::from_app_type_and_config(&AppType::Claude, &provider);
+        assert_eq!(provider_type, ProviderType::OpenAICompat);
+    }
+
+    #[test]
+    fn test_from_app_type_claude_gemini_compat() {
+        let provider = create_provider(json!({
+            "env": {
+                "ANTHROPIC_BASE_URL": "https://generativelanguage.googleapis.com",
+                "GEMINI_API_KEY": "AIza-test"
+            },
+            "api_format": "gemini"
+        }));
+
+        let provider_type = ProviderType::from_app_type_and_config(&AppType::Claude, &provider);
+        assert_eq!(provider_type, ProviderType::GeminiCompat);
+    }
+
     #[test]
     fn test_from_app_type_codex() {
         let provider = create_provider(json!({
@@ -369,6 +430,19 @@ mod tests {
         assert_eq!(provider_type, ProviderType::Codex);
     }
 
+    #[test]
+    fn test_from_app_type_codex_chat() {
+        let provider = create_provider(json!({
+            "env": {
+                "OPENAI_API_KEY": "sk-test"
+            },
+            "api_format": "openai_chat"
+        }));
+
+        let provider_type = ProviderType::from_app_type_and_config(&AppType::Codex, &provider);
+        assert_eq!(provider_type, ProviderType::CodexChat);
+    }
+
     #[test]
     fn test_from_app_type_gemini_api_key() {
         let provider = create_provider(json!({
@@ -0,0 +1,1058 @@
+//! Responses API ⇄ Chat Completions 桥接模块
+//!
+//! Codex CLI 只会调用 `/v1/responses`，而很多中转站只实现了 `/v1/chat/completions`。
+//! 本模块负责：
+//! - Responses 请求（input items / tools / reasoning）→ Chat Completions 请求
+//! - Chat Completions 响应 → Responses 响应
+//! - Chat Completions SSE chunk → Responses `response.*` 流式事件
+
+use super::streaming::{convert_sse_stream, sse, SseDataConverter};
+use crate::proxy::error::ProxyError;
+use bytes::Bytes;
+use futures::stream::Stream;
+use serde_json::{json, Value};
+
+/// Responses 请求 → Chat Completions 请求
+pub fn responses_to_chat(body: Value) -> Result<Value, ProxyError> {
+    let mut messages: Vec<Value> = Vec::new();
+
+    // instructions → system
+    if let Some(instructions) = body
+        .get("instructions")
+        .and_then(|i| i.as_str())
+        .filter(|i| !i.is_empty())
+    {
+        messages.push(json!({"role": "system", "content": instructions}));
+    }
+
+    match body.get("input") {
+        Some(Value::String(text)) => {
+            messages.push(json!({"role": "user", "content": text}));
+        }
+        Some(Value::Array(items)) => {
+            for item in items {
+                convert_input_item(item, &mut messages);
+            }
+        }
+        _ => {
+            return Err(ProxyError::TransformError(
+                "Missing input in Responses request".to_string(),
+            ))
+        }
+    }
+
+    let mut result = json!({
+        "model": body.get("model").cloned().unwrap_or(json!("")),
+        "messages": messages
+    });
+
+    if let Some(v) = body.get("max_output_tokens") {
+        result["max_tokens"] = v.clone();
+    }
+    if let Some(v) = body.get("temperature") {
+        result["temperature"] = v.clone();
+    }
+    if let Some(v) = body.get("top_p") {
+        result["top_p"] = v.clone();
+    }
+    if let Some(v) = body.get("parallel_tool_calls") {
+        result["parallel_tool_calls"] = v.clone();
+    }
+    if let Some(v) = body.get("stream") {
+        result["stream"] = v.clone();
+        if v.as_bool() == Some(true) {
+            result["stream_options"] = json!({"include_usage": true});
+        }
+    }
+    if let Some(effort) = body
+        .get("reasoning")
+        .and_then(|r| r.get("effort"))
+        .and_then(|e| e.as_str())
+    {
+        result["reasoning_effort"] = json!(effort);
+    }
+
+    // text.format → response_format
+    if let Some(format) = body.get("text").and_then(|t| t.get("format")) {
+        match format.get("type").and_then(|t| t.as_str()) {
+            Some("json_schema") => {
+                result["response_format"] = json!({
+                    "type": "json_schema",
+                    "json_schema": {
+                        "name": format.get("name").cloned().unwrap_or(json!("output")),
+                        "schema": format.get("schema").cloned().unwrap_or(json!({})),
+                        "strict": format.get("strict").cloned().unwrap_or(json!(false))
+                    }
+                });
+            }
+            Some("json_object") => {
+                result["response_format"] = json!({"type": "json_object"});
+            }
+            _ => {}
+        }
+    }
+
+    // tools：只有 function 工具能映射到 Chat Completions
+    if let Some(tools) = body.get("tools").and_then(|t| t.as_array()) {
+        let functions: Vec<Value> = tools
+            .iter()
+            .filter_map(|tool| {
+                if tool.get("type").and_then(|t| t.as_str()) != Some("function") {
+                    log::debug!(
+                        "[Codex/Chat] 跳过 Chat Completions 不支持的工具类型: {:?}",
+                        tool.get("type")
+                    );
+                    return None;
+                }
+                let mut function = json!({
+                    "name": tool.get("name").cloned().unwrap_or(json!("")),
+                    "parameters": tool.get("parameters").cloned().unwrap_or(json!({"type": "object", "properties": {}}))
+                });
+                if let Some(description) = tool.get("description") {
+                    function["description"] = description.clone();
+                }
+                if let Some(strict) = tool.get("strict") {
+                    function["strict"] = strict.clone();
+                }
+                Some(json!({"type": "function", "function": function}))
+            })
+            .collect();
+        if !functions.is_empty() {
+            result["tools"] = json!(functions);
+        }
+    }
+
+    if let Some(choice) = body.get("tool_choice") {
+        result["tool_choice"] = match choice {
+            Value::String(_) => choice.clone(),
+            _ => match choice.get("name") {
+                Some(name) => json!({"type": "function", "function": {"name": name}}),
+                None => json!("auto"),
+            },
+        };
+    }
+
+    Ok(result)
+}
+
+/// 转换单个 Responses input item，追加到 Chat messages
+fn convert_input_item(item: &Value, messages: &mut Vec<Value>) {
+    let item_type = item
+        .get("type")
+        .and_then(|t| t.as_str())
+        // 简写形式 {"role": "user", "content": "..."} 没有 type
+        .unwrap_or("message");
+
+    match item_type {
+        "message" => {
+            let role = match item.get("role").and_then(|r| r.as_str()) {
+                // 不少中转站不认识 developer 角色
+                Some("developer") | Some("system") => "system",
+                Some("assistant") => "assistant",
+                _ => "user",
+            };
+            let content = convert_message_content(item.get("content"), role);
+            messages.push(json!({"role": role, "content": content}));
+        }
+        "function_call" => {
+            let tool_call = json!({
+                "id": item.get("call_id").cloned().unwrap_or(json!("")),
+                "type": "function",
+                "function": {
+                    "name": item.get("name").cloned().unwrap_or(json!("")),
+                    "arguments": item.get("arguments").cloned().unwrap_or(json!("{}"))
+                }
+            });
+            // 连续的 function_call 合并到同一条 assistant 消息
+            if let Some(last) = messages.last_mut() {
+                if last.get("role").and_then(|r| r.as_str()) == Some("assistant") {
+                    if let Some(calls) = last.get_mut("tool_calls").and_then(|c| c.as_array_mut()) {
+                        calls.push(tool_call);
+                        return;
+                    }
+                    if last.get("tool_calls").is_none() {
+                        last["tool_calls"] = json!([tool_call]);
+                        return;
+                    }
+                }
+            }
+            messages.push(json!({
+                "role": "assistant",
+                "content": null,
+                "tool_calls": [tool_call]
+            }));
+        }
+        "function_call_output" => {
+            let output = match item.get("output") {
+                Some(Value::String(s)) => s.clone(),
+                Some(Value::Array(parts)) => parts
+                    .iter()
+                    .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                Some(v) => v.to_string(),
+                None => String::new(),
+            };
+            messages.push(json!({
+                "role": "tool",
+                "tool_call_id": item.get("call_id").cloned().unwrap_or(json!("")),
+                "content": output
+            }));
+        }
+        "reasoning" => {
+            // 推理内容不回传给 Chat Completions 上游
+        }
+        other => {
+            log::debug!("[Codex/Chat] 跳过不支持的 input item: {other}");
+        }
+    }
+}
+
+/// Responses message content → Chat message content
+fn convert_message_content(content: Option<&Value>, role: &str) -> Value {
+    let parts = match content {
+        Some(Value::String(text)) => return json!(text),
+        Some(Value::Array(parts)) => parts,
+        _ => return json!(""),
+    };
+
+    let has_image = parts
+        .iter()
+        .any(|p| p.get("type").and_then(|t| t.as_str()) == Some("input_image"));
+
+    // assistant / system 以及纯文本消息使用字符串，兼容性最好
+    if role != "user" || !has_image {
+        let text = parts
+            .iter()
+            .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        return json!(text);
+    }
+
+    let converted: Vec<Value> = parts
+        .iter()
+        .filter_map(|part| match part.get("type").and_then(|t| t.as_str()) {
+            Some("input_text") | Some("output_text") | Some("text") => Some(
+                json!({"type": "text", "text": part.get("text").cloned().unwrap_or(json!(""))}),
+            ),
+            Some("input_image") => {
+                let url = part.get("image_url").and_then(|u| {
+                    u.as_str()
+                        .map(|s| s.to_string())
+                        .or_else(|| u.get("url").and_then(|s| s.as_str()).map(|s| s.to_string()))
+                })?;
+                Some(json!({"type": "image_url", "image_url": {"url": url}}))
+            }
+            _ => None,
+        })
+        .collect();
+    json!(converted)
+}
+
+/// Chat usage → Responses usage
+pub fn chat_usage_to_responses(usage: &Value) -> Value {
+    let get = |v: Option<&Value>| v.and_then(|v| v.as_u64()).unwrap_or(0);
+    let input_tokens = get(usage.get("prompt_tokens"));
+    let output_tokens = get(usage.get("completion_tokens"));
+    let cached_tokens = get(usage
+        .get("prompt_tokens_details")
+        .and_then(|d| d.get("cached_tokens")));
+    let reasoning_tokens = get(usage
+        .get("completion_tokens_details")
+        .and_then(|d| d.get("reasoning_tokens")));
+    json!({
+        "input_tokens": input_tokens,
+        "input_tokens_details": {"cached_tokens": cached_tokens},
+        "output_tokens": output_tokens,
+        "output_tokens_details": {"reasoning_tokens": reasoning_tokens},
+        "total_tokens": input_tokens + output_tokens
+    })
+}
+
+fn new_item_id(prefix: &str) -> String {
+    format!("{prefix}_{}", uuid::Uuid::new_v4().simple())
+}
+
+fn now_secs() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+/// 根据 finish_reason 得到 Responses 的 status 与 incomplete_details
+fn response_status(finish_reason: Option<&str>) -> (&'static str, Value) {
+    match finish_reason {
+        Some("length") => ("incomplete", json!({"reason": "max_output_tokens"})),
+        Some("content_filter") => ("incomplete", json!({"reason": "content_filter"})),
+        _ => ("completed", Value::Null),
+    }
+}
+
+fn reasoning_item(id: &str, text: &str) -> Value {
+    json!({
+        "type": "reasoning",
+        "id": id,
+        "summary": [{"type": "summary_text", "text": text}]
+    })
+}
+
+fn message_item(id: &str, text: &str, status: &str) -> Value {
+    json!({
+        "type": "message",
+        "id": id,
+        "role": "assistant",
+        "status": status,
+        "content": [{"type": "output_text", "text": text, "annotations": []}]
+    })
+}
+
+fn function_call_item(id: &str, call_id: &str, name: &str, arguments: &str, status: &str) -> Value {
+    json!({
+        "type": "function_call",
+        "id": id,
+        "call_id": call_id,
+        "name": name,
+        "arguments": arguments,
+        "status": status
+    })
+}
+
+/// Chat Completions 响应 → Responses 响应
+pub fn chat_to_responses(body: Value) -> Result<Value, ProxyError> {
+    let choice = body
+        .get("choices")
+        .and_then(|c| c.as_array())
+        .and_then(|c| c.first())
+        .ok_or_else(|| ProxyError::TransformError("No choices in response".to_string()))?;
+    let message = choice
+        .get("message")
+        .ok_or_else(|| ProxyError::TransformError("No message in choice".to_string()))?;
+
+    let mut output = Vec::new();
+
+    if let Some(reasoning) = message
+        .get("reasoning_content")
+        .or_else(|| message.get("reasoning"))
+        .and_then(|r| r.as_str())
+        .filter(|r| !r.is_empty())
+    {
+        output.push(reasoning_item(&new_item_id("rs"), reasoning));
+    }
+
+    if let Some(text) = message
+        .get("content")
+        .and_then(|c| c.as_str())
+        .filter(|c| !c.is_empty())
+    {
+        output.push(message_item(&new_item_id("msg"), text, "completed"));
+    }
+
+    if let Some(tool_calls) = message.get("tool_calls").and_then(|t| t.as_array()) {
+        for call in tool_calls {
+            let function = call.get("function");
+            output.push(function_call_item(
+                &new_item_id("fc"),
+                call.get("id").and_then(|i| i.as_str()).unwrap_or(""),
+                function
+                    .and_then(|f| f.get("name"))
+                    .and_then(|n| n.as_str())
+                    .unwrap_or(""),
+                function
+                    .and_then(|f| f.get("arguments"))
+                    .and_then(|a| a.as_str())
+                    .unwrap_or("{}"),
+                "completed",
+            ));
+        }
+    }
+
+    let (status, incomplete_details) =
+        response_status(choice.get("finish_reason").and_then(|r| r.as_str()));
+
+    let id = body
+        .get("id")
+        .and_then(|i| i.as_str())
+        .map(|i| format!("resp_{i}"))
+        .unwrap_or_else(|| new_item_id("resp"));
+
+    Ok(json!({
+        "id": id,
+        "object": "response",
+        "created_at": body.get("created").and_then(|c| c.as_i64()).unwrap_or_else(now_secs),
+        "model": body.get("model").cloned().unwrap_or(json!("")),
+        "status": status,
+        "incomplete_details": incomplete_details,
+        "output": output,
+        "usage": chat_usage_to_responses(body.get("usage").unwrap_or(&json!({})))
+    }))
+}
+
+/// 当前正在输出的 Responses item（推理或文本）
+#[derive(Debug)]
+enum OpenItem {
+    Reasoning { id: String, text: String },
+    Message { id: String, text: String },
+}
+
+/// 正在接收参数的 function_call item
+///
+/// 并行工具调用的参数分片可能交错到达，按 Chat `tool_calls[].index` 路由到对应的 item
+#[derive(Debug)]
+struct OpenFunctionCall {
+    index: u64,
+    output_index: usize,
+    id: String,
+    call_id: String,
+    name: String,
+    arguments: String,
+}
+
+/// Chat Completions SSE → Responses SSE 转换状态机
+#[derive(Debug, Default)]
+pub struct ResponsesSseConverter {
+    response_id: Option<String>,
+    model: String,
+    created_at: i64,
+    started: bool,
+    sequence_number: u64,
+    current: Option<OpenItem>,
+    /// 按 output_index 排列的未结束 function_call
+    function_calls: Vec<OpenFunctionCall>,
+    output: Vec<Value>,
+    finish_reason: Option<String>,
+    usage: Option<Value>,
+    completed: bool,
+}
+
+impl ResponsesSseConverter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 处理一条 Chat Completions SSE `data:` 负载，返回需要发送的 Responses SSE 文本
+    pub fn process_data(&mut self, data: &str) -> Vec<String> {
+        if data.trim() == "[DONE]" {
+            return self.finish();
+        }
+
+        let chunk: Value = match serde_json::from_str(data) {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                log::debug!("[Codex/Chat] 跳过无法解析的 SSE 数据: {e}");
+                return Vec::new();
+            }
+        };
+
+        let mut events = Vec::new();
+
+        if let Some(usage) = chunk.get("usage").filter(|u| !u.is_null()) {
+            self.usage = Some(chat_usage_to_responses(usage));
+        }
+
+        let Some(choice) = chunk
+            .get("choices")
+            .and_then(|c| c.as_array())
+            .and_then(|c| c.first())
+        else {
+            return events;
+        };
+
+        if !self.started {
+            self.response_id = chunk
+                .get("id")
+                .and_then(|i| i.as_str())
+                .map(|i| format!("resp_{i}"));
+            self.model = chunk
+                .get("model")
+                .and_then(|m| m.as_str())
+                .unwrap_or_default()
+                .to_string();
+            self.created_at = chunk
+                .get("created")
+                .and_then(|c| c.as_i64())
+                .unwrap_or_else(now_secs);
+            self.start(&mut events);
+        }
+
+        let delta = choice.get("delta").cloned().unwrap_or(json!({}));
+
+        if let Some(reasoning) = delta
+            .get("reasoning_content")
+            .or_else(|| delta.get("reasoning"))
+            .and_then(|r| r.as_str())
+            .filter(|r| !r.is_empty())
+        {
+            self.append_reasoning(&mut events, reasoning);
+        }
+
+        if let Some(text) = delta
+            .get("content")
+            .and_then(|c| c.as_str())
+            .filter(|c| !c.is_empty())
+        {
+            self.append_text(&mut events, text);
+        }
+
+        if let Some(tool_calls) = delta.get("tool_calls").and_then(|t| t.as_array()) {
+            for call in tool_calls {
+                self.append_function_call(&mut events, call);
+            }
+        }
+
+        if let Some(finish_reason) = choice.get("finish_reason").and_then(|r| r.as_str()) {
+            self.close_item(&mut events);
+            self.finish_reason = Some(finish_reason.to_string());
+        }
+
+        events
+    }
+
+    /// 流结束时发送 response.completed
+    pub fn finish(&mut self) -> Vec<String> {
+        let mut events = Vec::new();
+        if self.completed || !self.started {
+            return events;
+        }
+        self.close_item(&mut events);
+        self.completed = true;
+
+        let (status, incomplete_details) = response_status(self.finish_reason.as_deref());
+        let mut response = self.response_snapshot(status);
+        response["incomplete_details"] = incomplete_details;
+        response["output"] = json!(self.output);
+        response["usage"] = self.usage.clone().unwrap_or(Value::Null);
+        events.push(self.event("response.completed", json!({"response": response})));
+        events
+    }
+
+    fn response_snapshot(&self, status: &str) -> Value {
+        json!({
+            "id": self.response_id.clone().unwrap_or_default(),
+            "object": "response",
+            "created_at": self.created_at,
+            "model": self.model,
+            "status": status,
+            "output": []
+        })
+    }
+
+    fn start(&mut self, events: &mut Vec<String>) {
+        self.started = true;
+        if self.response_id.is_none() {
+            self.response_id = Some(new_item_id("resp"));
+        }
+        let response = self.response_snapshot("in_progress");
+        events.push(self.event("response.created", json!({"response": response.clone()})));
+        events.push(self.event("response.in_progress", json!({"response": response})));
+    }
+
+    fn append_reasoning(&mut self, events: &mut Vec<String>, delta: &str) {
+        if !matches!(self.current, Some(OpenItem::Reasoning { .. })) {
+            self.close_item(events);
+            let id = new_item_id("rs");
+            let output_index = self.output.len();
+            events.push(self.event(
+                "response.output_item.added",
+                json!({
+                    "output_index": output_index,
+                    "item": {"type": "reasoning", "id": id, "summary": []}
+                }),
+            ));
+            events.push(self.event(
+                "response.reasoning_summary_part.added",
+                json!({
+                    "item_id": id,
+                    "output_index": output_index,
+                    "summary_index": 0,
+                    "part": {"type": "summary_text", "text": ""}
+                }),
+            ));
+            self.current = Some(OpenItem::Reasoning {
+                id,
+                text: String::new(),
+            });
+        }
+        let output_index = self.output.len();
+        if let Some(OpenItem::Reasoning { id, text }) = &mut self.current {
+            text.push_str(delta);
+            let item_id = id.clone();
+            events.push(self.event(
+                "response.reasoning_summary_text.delta",
+                json!({
+                    "item_id": item_id,
+                    "output_index": output_index,
+                    "summary_index": 0,
+                    "delta": delta
+                }),
+            ));
+        }
+    }
+
+    fn append_text(&mut self, events: &mut Vec<String>, delta: &str) {
+        if !matches!(self.current, Some(OpenItem::Message { .. })) {
+            self.close_item(events);
+            let id = new_item_id("msg");
+            let output_index = self.output.len();
+            events.push(self.event(
+                "response.output_item.added",
+                json!({
+                    "output_index": output_index,
+                    "item": {
+                        "type": "message",
+                        "id": id,
+                        "role": "assistant",
+                        "status": "in_progress",
+                        "content": []
+                    }
+                }),
+            ));
+            events.push(self.event(
+                "response.content_part.added",
+                json!({
+                    "item_id": id,
+                    "output_index": output_index,
+                    "content_index": 0,
+                    "part": {"type": "output_text", "text": "", "annotations": []}
+                }),
+            ));
+            self.current = Some(OpenItem::Message {
+                id,
+                text: String::new(),
+            });
+        }
+        let output_index = self.output.len();
+        if let Some(OpenItem::Message { id, text }) = &mut self.current {
+            text.push_str(delta);
+            let item_id = id.clone();
+            events.push(self.event(
+                "response.output_text.delta",
+                json!({
+                    "item_id": item_id,
+                    "output_index": output_index,
+                    "content_index": 0,
+                    "delta": delta
+                }),
+            ));
+        }
+    }
+
+    /// 处理一条 Chat 工具调用分片
+    ///
+    /// 按 `index` 路由到已打开的 function_call（部分供应商会在后续分片中重复携带 id），
+    /// 同一 index 出现新的 id 或尚未打开时，带 id / name 的分片开启新的 item
+    fn append_function_call(&mut self, events: &mut Vec<String>, call: &Value) {
+        let index = call.get("index").and_then(|i| i.as_u64()).unwrap_or(0);
+        let function = call.get("function");
+        let name = function
+            .and_then(|f| f.get("name"))
+            .and_then(|n| n.as_str());
+        let call_id = call.get("id").and_then(|i| i.as_str());
+
+        let position = self
+            .function_calls
+            .iter()
+            .rposition(|c| c.index == index && call_id.is_none_or(|id| id == c.call_id));
+        let position = match position {
+            Some(position) => position,
+            None if call_id.is_some() || name.is_some() => {
+                self.close_current(events);
+                let output_index = self.output.len() + self.function_calls.len();
+                let id = new_item_id("fc");
+                let call_id = call_id.unwrap_or_default().to_string();
+                let name = name.unwrap_or_default().to_string();
+                events.push(self.event(
+                    "response.output_item.added",
+                    json!({
+                        "output_index": output_index,
+                        "item": function_call_item(&id, &call_id, &name, "", "in_progress")
+                    }),
+                ));
+                self.function_calls.push(OpenFunctionCall {
+                    index,
+                    output_index,
+                    id,
+                    call_id,
+                    name,
+                    arguments: String::new(),
+                });
+                self.function_calls.len() - 1
+            }
+            None => return,
+        };
+
+        if let Some(args) = function
+            .and_then(|f| f.get("arguments"))
+            .and_then(|a| a.as_str())
+            .filter(|a| !a.is_empty())
+        {
+            let call = &mut self.function_calls[position];
+            call.arguments.push_str(args);
+            let data =
+                json!({"item_id": call.id, "output_index": call.output_index, "delta": args});
+            events.push(self.event("response.function_call_arguments.delta", data));
+        }
+    }
+
+    /// 关闭所有未结束的 item，发送对应的 *.done 事件并记录到 output
+    fn close_item(&mut self, events: &mut Vec<String>) {
+        self.close_current(events);
+        for call in std::mem::take(&mut self.function_calls) {
+            events.push(self.event(
+                "response.function_call_arguments.done",
+                json!({"item_id": call.id, "output_index": call.output_index, "arguments": call.arguments}),
+            ));
+            let done_item = function_call_item(
+                &call.id,
+                &call.call_id,
+                &call.name,
+                &call.arguments,
+                "completed",
+            );
+            events.push(self.event(
+                "response.output_item.done",
+                json!({"output_index": call.output_index, "item": done_item}),
+            ));
+            self.output.push(done_item);
+        }
+    }
+
+    /// 关闭当前的推理 / 文本 item
+    fn close_current(&mut self, events: &mut Vec<String>) {
+        let Some(item) = self.current.take() else {
+            return;
+        };
+        let output_index = self.output.len();
+        let done_item = match item {
+            OpenItem::Reasoning { id, text } => {
+                events.push(self.event(
+                    "response.reasoning_summary_text.done",
+                    json!({"item_id": id, "output_index": output_index, "summary_index": 0, "text": text}),
+                ));
+                events.push(self.event(
+                    "response.reasoning_summary_part.done",
+                    json!({
+                        "item_id": id,
+                        "output_index": output_index,
+                        "summary_index": 0,
+                        "part": {"type": "summary_text", "text": text}
+                    }),
+                ));
+                reasoning_item(&id, &text)
+            }
+            OpenItem::Message { id, text } => {
+                events.push(self.event(
+                    "response.output_text.done",
+                    json!({"item_id": id, "output_index": output_index, "content_index": 0, "text": text}),
+                ));
+                events.push(self.event(
+                    "response.content_part.done",
+                    json!({
+                        "item_id": id,
+                        "output_index": output_index,
+                        "content_index": 0,
+                        "part": {"type": "output_text", "text": text, "annotations": []}
+                    }),
+                ));
+                message_item(&id, &text, "completed")
+            }
+        };
+        events.push(self.event(
+            "response.output_item.done",
+            json!({"output_index": output_index, "item": done_item}),
+        ));
+        self.output.push(done_item);
+    }
+
+    /// 构造带 type 与 sequence_number 的 Responses 事件
+    fn event(&mut self, event_type: &str, mut data: Value) -> String {
+        data["type"] = json!(event_type);
+        data["sequence_number"] = json!(self.sequence_number);
+        self.sequence_number += 1;
+        sse(event_type, data)
+    }
+}
+
+impl SseDataConverter for ResponsesSseConverter {
+    fn process_data(&mut self, data: &str) -> Vec<String> {
+        ResponsesSseConverter::process_data(self, data)
+    }
+
+    fn upstream_finished(&self) -> bool {
+        self.completed || self.finish_reason.is_some()
+    }
+
+    fn finish(&mut self) -> Vec<String> {
+        ResponsesSseConverter::finish(self)
+    }
+
+    fn error(&mut self, message: &str) -> Vec<String> {
+        self.completed = true;
+        let mut response = self.response_snapshot("failed");
+        response["output"] = json!(self.output);
+        response["error"] = json!({"code": "stream_error", "message": message});
+        vec![self.event("response.failed", json!({"response": response}))]
+    }
+}
+
+/// 创建 Responses SSE 流（Chat Completions SSE → Responses SSE）
+pub fn create_responses_sse_stream<E: std::fmt::Display + Send + 'static>(
+    stream: impl Stream<Item = Result<Bytes, E>> + Send + 'static,
+) -> impl Stream<Item = Result<Bytes, std::io::Error>> + Send {
+    convert_sse_stream(stream, "Codex/Chat", ResponsesSseConverter::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_responses_to_chat_messages() {
+        let input = json!({
+            "model": "gpt-5-codex",
+            "instructions": "You are Codex.",
+            "stream": true,
+            "max_output_tokens": 2048,
+            "reasoning": {"effort": "high", "summary": "auto"},
+            "input": [
+                {"type": "message", "role": "developer", "content": [{"type": "input_text", "text": "Use tools."}]},
+                {"type": "message", "role": "user", "content": [
+                    {"type": "input_text", "text": "What is this?"},
+                    {"type": "input_image", "image_url": "data:image/png;base64,AAAA"}
+                ]},
+                {"type": "reasoning", "id": "rs_1", "summary": [{"type": "summary_text", "text": "thinking"}]},
+                {"type": "function_call", "call_id": "call_1", "name": "shell", "arguments": "{\"cmd\":\"ls\"}"},
+                {"type": "function_call", "call_id": "call_2", "name": "shell", "arguments": "{\"cmd\":\"pwd\"}"},
+                {"type": "function_call_output", "call_id": "call_1", "output": "a.rs"},
+                {"type": "function_call_output", "call_id": "call_2", "output": "/tmp"},
+                {"type": "message", "role": "assistant", "content": [{"type": "output_text", "text": "Done."}]}
+            ]
+        });
+
+        let result = responses_to_chat(input).unwrap();
+        assert_eq!(result["model"], "gpt-5-codex");
+        assert_eq!(result["max_tokens"], 2048);
+        assert_eq!(result["reasoning_effort"], "high");
+        assert_eq!(result["stream_options"]["include_usage"], true);
+
+        let messages = result["messages"].as_array().unwrap();
+        assert_eq!(
+            messages[0],
+            json!({"role": "system", "content": "You are Codex."})
+        );
+        assert_eq!(
+            messages[1],
+            json!({"role": "system", "content": "Use tools."})
+        );
+        assert_eq!(
+            messages[2]["content"][1]["image_url"]["url"],
+            "data:image/png;base64,AAAA"
+        );
+        assert_eq!(messages[3]["role"], "assistant");
+        assert_eq!(messages[3]["tool_calls"].as_array().unwrap().len(), 2);
+        assert_eq!(messages[3]["tool_calls"][1]["id"], "call_2");
+        assert_eq!(
+            messages[4],
+            json!({"role": "tool", "tool_call_id": "call_1", "content": "a.rs"})
+        );
+        assert_eq!(messages[5]["tool_call_id"], "call_2");
+        assert_eq!(
+            messages[6],
+            json!({"role": "assistant", "content": "Done."})
+        );
+    }
+
+    #[test]
+    fn test_responses_to_chat_tools() {
+        let input = json!({
+            "model": "m",
+            "input": "hi",
+            "tools": [
+                {"type": "function", "name": "shell", "description": "Run", "parameters": {"type": "object", "properties": {"cmd": {"type": "string"}}}, "strict": false},
+                {"type": "web_search"}
+            ],
+            "tool_choice": {"type": "function", "name": "shell"}
+        });
+
+        let result = responses_to_chat(input).unwrap();
+        assert_eq!(
+            result["messages"][0],
+            json!({"role": "user", "content": "hi"})
+        );
+        let tools = result["tools"].as_array().unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0]["function"]["name"], "shell");
+        assert_eq!(
+            tools[0]["function"]["parameters"]["properties"]["cmd"]["type"],
+            "string"
+        );
+        assert_eq!(
+            result["tool_choice"],
+            json!({"type": "function", "function": {"name": "shell"}})
+        );
+    }
+
+    #[test]
+    fn test_chat_to_responses() {
+        let input = json!({
+            "id": "chatcmpl-1",
+            "created": 1700000000,
+            "model": "deepseek-chat",
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "reasoning_content": "hmm",
+                    "content": "Running",
+                    "tool_calls": [{"id": "call_1", "type": "function", "function": {"name": "shell", "arguments": "{}"}}]
+                },
+                "finish_reason": "tool_calls"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "prompt_tokens_details": {"cached_tokens": 4}}
+        });
+
+        let result = chat_to_responses(input).unwrap();
+        assert_eq!(result["id"], "resp_chatcmpl-1");
+        assert_eq!(result["status"], "completed");
+        let output = result["output"].as_array().unwrap();
+        assert_eq!(output[0]["type"], "reasoning");
+        assert_eq!(output[1]["type"], "message");
+        assert_eq!(output[1]["content"][0]["text"], "Running");
+        assert_eq!(output[2]["type"], "function_call");
+        assert_eq!(output[2]["call_id"], "call_1");
+        assert_eq!(result["usage"]["input_tokens"], 10);
+        assert_eq!(result["usage"]["input_tokens_details"]["cached_tokens"], 4);
+        assert_eq!(result["usage"]["total_tokens"], 15);
+    }
+
+    fn run(chunks: &[Value]) -> Vec<(String, Value)> {
+        let mut converter = ResponsesSseConverter::new();
+        let mut out = Vec::new();
+        for chunk in chunks {
+            out.extend(converter.process_data(&chunk.to_string()));
+        }
+        out.extend(converter.process_data("[DONE]"));
+        out.iter()
+            .map(|e| {
+                let mut lines = e.lines();
+                let event = lines.next().unwrap().trim_start_matches("event: ");
+                let data = lines.next().unwrap().trim_start_matches("data: ");
+                (event.to_string(), serde_json::from_str(data).unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_stream_text_and_tool_call() {
+        let events = run(&[
+            json!({"id": "c1", "model": "m", "choices": [{"delta": {"reasoning_content": "think"}}]}),
+            json!({"id": "c1", "model": "m", "choices": [{"delta": {"content": "Hel"}}]}),
+            json!({"id": "c1", "model": "m", "choices": [{"delta": {"content": "lo"}}]}),
+            json!({"id": "c1", "model": "m", "choices": [{"delta": {"tool_calls": [{"index": 0, "id": "call_1", "function": {"name": "shell", "arguments": ""}}]}}]}),
+            json!({"id": "c1", "model": "m", "choices": [{"delta": {"tool_calls": [{"index": 0, "function": {"arguments": "{\"cmd\":\"ls\"}"}}]}}]}),
+            json!({"id": "c1", "model": "m", "choices": [{"delta": {}, "finish_reason": "tool_calls"}]}),
+            json!({"id": "c1", "model": "m", "choices": [], "usage": {"prompt_tokens": 7, "completion_tokens": 3}}),
+        ]);
+
+        assert_eq!(events[0].0, "response.created");
+        assert_eq!(events[0].1["response"]["id"], "resp_c1");
+        assert_eq!(events[1].0, "response.in_progress");
+
+        // sequence_number 连续递增
+        for (i, (_, data)) in events.iter().enumerate() {
+            assert_eq!(data["sequence_number"], i as u64);
+        }
+
+        let text: String = events
+            .iter()
+            .filter(|(e, _)| e == "response.output_text.delta")
+            .map(|(_, d)| d["delta"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(text, "Hello");
+
+        let done_items: Vec<&Value> = events
+            .iter()
+            .filter(|(e, _)| e == "response.output_item.done")
+            .map(|(_, d)| &d["item"])
+            .collect();
+        assert_eq!(done_items.len(), 3);
+        assert_eq!(done_items[0]["type"], "reasoning");
+        assert_eq!(done_items[0]["summary"][0]["text"], "think");
+        assert_eq!(done_items[1]["content"][0]["text"], "Hello");
+        assert_eq!(done_items[2]["type"], "function_call");
+        assert_eq!(done_items[2]["call_id"], "call_1");
+        assert_eq!(done_items[2]["arguments"], "{\"cmd\":\"ls\"}");
+
+        let (event, completed) = events.last().unwrap();
+        assert_eq!(event, "response.completed");
+        assert_eq!(completed["response"]["status"], "completed");
+        assert_eq!(completed["response"]["output"].as_array().unwrap().len(), 3);
+        assert_eq!(completed["response"]["usage"]["input_tokens"], 7);
+        assert_eq!(completed["response"]["usage"]["output_tokens"], 3);
+    }
+
+    #[test]
+    fn test_stream_interleaved_parallel_tool_calls() {
+        let call = |call: Value| json!({"id": "c3", "model": "m", "choices": [{"delta": {"tool_calls": [call]}}]});
+        let events = run(&[
+            json!({"id": "c3", "model": "m", "choices": [{"delta": {"content": "Running"}}]}),
+            call(
+                json!({"index": 0, "id": "call_a", "function": {"name": "shell", "arguments": ""}}),
+            ),
+            call(
+                json!({"index": 1, "id": "call_b", "function": {"name": "read", "arguments": ""}}),
+            ),
+            call(json!({"index": 0, "function": {"arguments": "{\"cmd\":"}})),
+            call(json!({"index": 1, "function": {"arguments": "{\"path\":"}})),
+            // 部分供应商在后续分片中重复携带 id
+            call(json!({"index": 0, "id": "call_a", "function": {"arguments": "\"ls\"}"}})),
+            call(json!({"index": 1, "function": {"arguments": "\"a.rs\"}"}})),
+            json!({"id": "c3", "model": "m", "choices": [{"delta": {}, "finish_reason": "tool_calls"}]}),
+        ]);
+
+        let added: Vec<(u64, &str)> = events
+            .iter()
+            .filter(|(e, d)| {
+                e == "response.output_item.added" && d["item"]["type"] == "function_call"
+            })
+            .map(|(_, d)| {
+                (
+                    d["output_index"].as_u64().unwrap(),
+                    d["item"]["call_id"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(added, vec![(1, "call_a"), (2, "call_b")]);
+
+        let args = |output_index: u64| -> String {
+            events
+                .iter()
+                .filter(|(e, d)| {
+                    e == "response.function_call_arguments.delta"
+                        && d["output_index"] == output_index
+                })
+                .map(|(_, d)| d["delta"].as_str().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(args(1), "{\"cmd\":\"ls\"}");
+        assert_eq!(args(2), "{\"path\":\"a.rs\"}");
+
+        let (_, completed) = events.last().unwrap();
+        let output = completed["response"]["output"].as_array().unwrap();
+        assert_eq!(output.len(), 3);
+        assert_eq!(output[1]["call_id"], "call_a");
+        assert_eq!(output[1]["arguments"], "{\"cmd\":\"ls\"}");
+        assert_eq!(output[2]["call_id"], "call_b");
+        assert_eq!(output[2]["arguments"], "{\"path\":\"a.rs\"}");
+    }
+
+    #[test]
+    fn test_stream_length_is_incomplete() {
+        let events = run(&[
+            json!({"id": "c2", "model": "m", "choices": [{"delta": {"content": "cut"}, "finish_reason": "length"}]}),
+        ]);
+        let (_, completed) = events.last().unwrap();
+        assert_eq!(completed["response"]["status"], "incomplete");
+        assert_eq!(
+            completed["response"]["incomplete_details"]["reason"],
+            "max_output_tokens"
+        );
+    }
+}
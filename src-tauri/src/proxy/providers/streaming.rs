@@ -1,41 +1,51 @@
 //! 流式响应转换模块
 //!
-//! 实现 OpenAI SSE → Anthropic SSE 格式转换
+//! 实现 OpenAI SSE → Anthropic SSE 格式转换，
+//! 并提供 Anthropic 事件构造（`AnthropicEventWriter`）和 SSE 分帧（`convert_sse_stream`）等公共部件
 
+use super::transform::{map_finish_reason, openai_usage_to_anthropic};
 use bytes::Bytes;
 use futures::stream::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use serde_json::{json, Value};
+use std::collections::HashMap;
 
 /// OpenAI 流式响应数据结构
 #[derive(Debug, Deserialize)]
 struct OpenAIStreamChunk {
+    #[serde(default)]
     id: String,
+    #[serde(default)]
     model: String,
+    #[serde(default)]
     choices: Vec<StreamChoice>,
     #[serde(default)]
-    usage: Option<Usage>,
+    usage: Option<Value>,
 }
 
 #[derive(Debug, Deserialize)]
 struct StreamChoice {
+    #[serde(default)]
     delta: Delta,
     #[serde(default)]
     finish_reason: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 struct Delta {
     #[serde(default)]
     content: Option<String>,
     #[serde(default)]
     reasoning: Option<String>, // OpenRouter 的推理内容
     #[serde(default)]
+    reasoning_content: Option<String>, // DeepSeek / Kimi / vLLM 的推理内容
+    #[serde(default)]
     tool_calls: Option<Vec<DeltaToolCall>>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 struct DeltaToolCall {
+    #[serde(default)]
     index: usize,
     #[serde(default)]
     id: Option<String>,
@@ -53,286 +63,731 @@ struct DeltaFunction {
     arguments: Option<String>,
 }
 
-/// OpenAI 流式响应的 usage 信息（完整版）
-#[derive(Debug, Deserialize)]
-struct Usage {
-    #[serde(default)]
-    prompt_tokens: u32,
-    #[serde(default)]
-    completion_tokens: u32,
+/// 当前打开的 content block 类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum BlockKind {
+    Thinking,
+    Text,
+    ToolUse,
 }
 
-/// 创建 Anthropic SSE 流
-pub fn create_anthropic_sse_stream(
-    stream: impl Stream<Item = Result<Bytes, reqwest::Error>> + Send + 'static,
+/// 上游 SSE `data:` 负载 → Anthropic SSE 事件的转换器
+pub(super) trait SseDataConverter: Send + 'static {
+    /// 处理一条 `data:` 负载，返回需要发送的 Anthropic SSE 文本
+    fn process_data(&mut self, data: &str) -> Vec<String>;
+    /// 上游是否已发出结束信号（结束原因或结束标记），未发出即断开视为流被截断
+    fn upstream_finished(&self) -> bool;
+    /// 流结束时补齐缺失的收尾事件
+    fn finish(&mut self) -> Vec<String>;
+    /// 上游出错或提前断开时发给客户端的错误事件，不伪造正常结束
+    fn error(&mut self, message: &str) -> Vec<String> {
+        vec![sse(
+            "error",
+            json!({
+                "type": "error",
+                "error": {"type": "stream_error", "message": message}
+            }),
+        )]
+    }
+}
+
+/// Anthropic SSE 事件构造器
+///
+/// 维护 message/content block 的开闭状态，保证输出的事件序列符合 Anthropic 流式协议。
+/// `message_delta` 会延迟到拿到 usage 或流结束时才发出，保证 Claude Code 能拿到完整的 token 统计。
+#[derive(Debug, Default)]
+pub(super) struct AnthropicEventWriter {
+    pub message_id: Option<String>,
+    pub model: Option<String>,
+    pub pending_stop_reason: Option<String>,
+    pub usage: Option<Value>,
+    message_started: bool,
+    current_block: Option<BlockKind>,
+    content_index: usize,
+    /// 当前 block 之外仍在接收参数的 tool_use block（并行工具调用的参数分片可能交错到达）
+    open_tool_blocks: Vec<usize>,
+    message_delta_sent: bool,
+    message_stopped: bool,
+}
+
+impl AnthropicEventWriter {
+    pub fn ensure_message_start(&mut self, events: &mut Vec<String>) {
+        if self.message_started {
+            return;
+        }
+        self.message_started = true;
+        events.push(sse(
+            "message_start",
+            json!({
+                "type": "message_start",
+                "message": {
+                    "id": self.message_id.clone().unwrap_or_default(),
+                    "type": "message",
+                    "role": "assistant",
+                    "model": self.model.clone().unwrap_or_default(),
+                    "content": [],
+                    "stop_reason": null,
+                    "stop_sequence": null,
+                    "usage": {
+                        "input_tokens": 0,
+                        "output_tokens": 0
+                    }
+                }
+            }),
+        ));
+    }
+
+    /// 切换到指定类型的 block（相同类型则继续沿用当前 block）
+    pub fn switch_block(
+        &mut self,
+        events: &mut Vec<String>,
+        kind: BlockKind,
+        content_block: impl FnOnce() -> Value,
+    ) {
+        if self.current_block == Some(kind) {
+            return;
+        }
+        self.start_block(events, kind, content_block());
+    }
+
+    /// 总是开启一个新的 block（tool_use 每次调用都是独立 block）
+    pub fn start_block(&mut self, events: &mut Vec<String>, kind: BlockKind, content_block: Value) {
+        self.close_block(events);
+        self.open_block(events, kind, content_block);
+    }
+
+    /// 开启一个新的 tool_use block 并返回其索引
+    ///
+    /// 当前 block 也是 tool_use 时保持其打开，直到 `close_block` 时一并结束，
+    /// 以便并行工具调用交错到达的参数分片仍能写入对应的 block。
+    pub fn start_tool_block(&mut self, events: &mut Vec<String>, content_block: Value) -> usize {
+        if self.current_block == Some(BlockKind::ToolUse) {
+            self.open_tool_blocks.push(self.content_index);
+            self.content_index += 1;
+        } else {
+            self.close_block(events);
+        }
+        self.open_block(events, BlockKind::ToolUse, content_block);
+        self.content_index
+    }
+
+    /// 指定索引的 block 是否仍可写入 delta
+    pub fn is_block_open(&self, index: usize) -> bool {
+        (self.current_block.is_some() && index == self.content_index)
+            || self.open_tool_blocks.contains(&index)
+    }
+
+    fn open_block(&mut self, events: &mut Vec<String>, kind: BlockKind, content_block: Value) {
+        self.current_block = Some(kind);
+        events.push(sse(
+            "content_block_start",
+            json!({
+                "type": "content_block_start",
+                "index": self.content_index,
+                "content_block": content_block
+            }),
+        ));
+    }
+
+    /// 向当前 block 追加 delta
+    pub fn push_delta(&self, events: &mut Vec<String>, delta: Value) {
+        self.push_delta_at(events, self.content_index, delta);
+    }
+
+    /// 向指定索引的 block 追加 delta（调用方保证该 block 仍打开）
+    pub fn push_delta_at(&self, events: &mut Vec<String>, index: usize, delta: Value) {
+        events.push(sse(
+            "content_block_delta",
+            json!({
+                "type": "content_block_delta",
+                "index": index,
+                "delta": delta
+            }),
+        ));
+    }
+
+    /// 结束当前 block（以及仍打开的其他 tool_use block）
+    pub fn close_block(&mut self, events: &mut Vec<String>) {
+        for index in self.open_tool_blocks.drain(..) {
+            events.push(sse(
+                "content_block_stop",
+                json!({"type": "content_block_stop", "index": index}),
+            ));
+        }
+        if self.current_block.take().is_some() {
+            events.push(sse(
+                "content_block_stop",
+                json!({"type": "content_block_stop", "index": self.content_index}),
+            ));
+            self.content_index += 1;
+        }
+    }
+
+    /// stop_reason 与 usage 都已到齐时发出 message_delta
+    pub fn flush_message_delta_if_ready(&mut self, events: &mut Vec<String>) {
+        if self.pending_stop_reason.is_some() && self.usage.is_some() {
+            self.emit_message_delta(events);
+        }
+    }
+
+    pub fn emit_message_delta(&mut self, events: &mut Vec<String>) {
+        if self.message_delta_sent {
+            return;
+        }
+        self.message_delta_sent = true;
+        let stop_reason = self
+            .pending_stop_reason
+            .clone()
+            .unwrap_or_else(|| "end_turn".to_string());
+        let usage = self
+            .usage
+            .clone()
+            .unwrap_or_else(|| json!({"input_tokens": 0, "output_tokens": 0}));
+        events.push(sse(
+            "message_delta",
+            json!({
+                "type": "message_delta",
+                "delta": {
+                    "stop_reason": stop_reason,
+                    "stop_sequence": null
+                },
+                "usage": usage
+            }),
+        ));
+    }
+
+    /// 上游是否已给出结束原因（或已发出 message_stop）
+    pub fn upstream_finished(&self) -> bool {
+        self.message_stopped || self.pending_stop_reason.is_some()
+    }
+
+    /// 流结束时补齐缺失的收尾事件
+    pub fn finish(&mut self) -> Vec<String> {
+        let mut events = Vec::new();
+        if self.message_stopped {
+            return events;
+        }
+        if !self.message_started {
+            // 上游没有返回任何内容，不伪造空消息
+            return events;
+        }
+        self.close_block(&mut events);
+        self.emit_message_delta(&mut events);
+        events.push(sse("message_stop", json!({"type": "message_stop"})));
+        self.message_stopped = true;
+        events
+    }
+}
+
+/// OpenAI SSE → Anthropic SSE 转换状态机
+///
+/// 每个 OpenAI chunk 可能产生零到多个 Anthropic 事件。
+/// usage 通常在 finish_reason 之后的独立 chunk 中返回。
+#[derive(Debug, Default)]
+pub struct AnthropicSseConverter {
+    writer: AnthropicEventWriter,
+    /// OpenAI `tool_calls[].index` → (调用 ID, Anthropic content block 索引)
+    tool_blocks: HashMap<usize, (Option<String>, usize)>,
+}
+
+impl AnthropicSseConverter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 处理一条 OpenAI SSE `data:` 负载，返回需要发送的 Anthropic SSE 文本
+    pub fn process_data(&mut self, data: &str) -> Vec<String> {
+        if data.trim() == "[DONE]" {
+            return self.finish();
+        }
+
+        let chunk = match serde_json::from_str::<OpenAIStreamChunk>(data) {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                log::debug!("[Claude/OpenAI] 跳过无法解析的 SSE 数据: {e}");
+                return Vec::new();
+            }
+        };
+
+        let mut events = Vec::new();
+        let writer = &mut self.writer;
+
+        if writer.message_id.is_none() && !chunk.id.is_empty() {
+            writer.message_id = Some(chunk.id.clone());
+        }
+        if writer.model.is_none() && !chunk.model.is_empty() {
+            writer.model = Some(chunk.model.clone());
+        }
+        if let Some(usage) = chunk.usage.as_ref().filter(|u| !u.is_null()) {
+            writer.usage = Some(openai_usage_to_anthropic(usage));
+        }
+
+        if let Some(choice) = chunk.choices.first() {
+            writer.ensure_message_start(&mut events);
+
+            // 处理 reasoning（thinking）
+            let reasoning = choice
+                .delta
+                .reasoning_content
+                .as_deref()
+                .or(choice.delta.reasoning.as_deref())
+                .filter(|r| !r.is_empty());
+            if let Some(reasoning) = reasoning {
+                writer.switch_block(
+                    &mut events,
+                    BlockKind::Thinking,
+                    || json!({"type": "thinking", "thinking": "", "signature": ""}),
+                );
+                writer.push_delta(
+                    &mut events,
+                    json!({"type": "thinking_delta", "thinking": reasoning}),
+                );
+            }
+
+            // 处理文本内容
+            if let Some(content) = choice.delta.content.as_deref().filter(|c| !c.is_empty()) {
+                writer.switch_block(
+                    &mut events,
+                    BlockKind::Text,
+                    || json!({"type": "text", "text": ""}),
+                );
+                writer.push_delta(&mut events, json!({"type": "text_delta", "text": content}));
+            }
+
+            // 处理工具调用：按 tool_calls[].index 路由分片（并行调用的参数分片可能交错到达，
+            // 部分供应商会在后续分片中重复携带 id）；同一 index 出现新的 id 时视为新的调用
+            if let Some(tool_calls) = &choice.delta.tool_calls {
+                for tool_call in tool_calls {
+                    let name = tool_call.function.as_ref().and_then(|f| f.name.as_deref());
+                    let known = self
+                        .tool_blocks
+                        .get(&tool_call.index)
+                        .filter(|(id, block)| {
+                            writer.is_block_open(*block)
+                                && (tool_call.id.is_none() || tool_call.id == *id)
+                        })
+                        .map(|(_, block)| *block);
+                    let block = match known {
+                        Some(block) => Some(block),
+                        None if tool_call.id.is_some() || name.is_some() => {
+                            let block = writer.start_tool_block(
+                                &mut events,
+                                json!({
+                                    "type": "tool_use",
+                                    "id": tool_call.id.clone().unwrap_or_else(|| format!("toolu_{}", tool_call.index)),
+                                    "name": name.unwrap_or_default(),
+                                    "input": {}
+                                }),
+                            );
+                            self.tool_blocks
+                                .insert(tool_call.index, (tool_call.id.clone(), block));
+                            Some(block)
+                        }
+                        None => None,
+                    };
+
+                    let args = tool_call
+                        .function
+                        .as_ref()
+                        .and_then(|f| f.arguments.as_deref())
+                        .filter(|a| !a.is_empty());
+                    if let (Some(block), Some(args)) = (block, args) {
+                        writer.push_delta_at(
+                            &mut events,
+                            block,
+                            json!({"type": "input_json_delta", "partial_json": args}),
+                        );
+                    }
+                }
+            }
+
+            // 处理 finish_reason：先关闭 block，message_delta 等 usage 到齐后再发
+            if let Some(finish_reason) = &choice.finish_reason {
+                writer.close_block(&mut events);
+                writer.pending_stop_reason = Some(map_finish_reason(finish_reason).to_string());
+            }
+        }
+
+        // usage 已到齐（同一 chunk 或 finish 之后的独立 chunk）
+        writer.flush_message_delta_if_ready(&mut events);
+
+        events
+    }
+
+    /// 流结束（[DONE] 或上游断开）时补齐缺失的收尾事件
+    pub fn finish(&mut self) -> Vec<String> {
+        self.writer.finish()
+    }
+}
+
+impl SseDataConverter for AnthropicSseConverter {
+    fn process_data(&mut self, data: &str) -> Vec<String> {
+        AnthropicSseConverter::process_data(self, data)
+    }
+
+    fn upstream_finished(&self) -> bool {
+        self.writer.upstream_finished()
+    }
+
+    fn finish(&mut self) -> Vec<String> {
+        AnthropicSseConverter::finish(self)
+    }
+}
+
+/// 构造单条 SSE 事件文本
+pub(super) fn sse(event: &str, data: Value) -> String {
+    format!(
+        "event: {event}\ndata: {}\n\n",
+        serde_json::to_string(&data).unwrap_or_default()
+    )
+}
+
+/// 按 SSE 分帧读取上游流，逐条交给转换器处理
+///
+/// 字节先缓存到完整的行再解码，避免多字节 UTF-8 字符跨数据块时被替换成乱码；
+/// 上游出错或未发出结束信号就断开时，向客户端发送错误事件而不是伪造正常结束。
+pub(super) fn convert_sse_stream<C: SseDataConverter, E: std::fmt::Display + Send + 'static>(
+    stream: impl Stream<Item = Result<Bytes, E>> + Send + 'static,
+    tag: &'static str,
+    mut converter: C,
 ) -> impl Stream<Item = Result<Bytes, std::io::Error>> + Send {
     async_stream::stream! {
+        let mut pending: Vec<u8> = Vec::new();
         let mut buffer = String::new();
-        let mut message_id = None;
-        let mut current_model = None;
-        let mut content_index = 0;
-        let mut has_sent_message_start = false;
-        let mut current_block_type: Option<String> = None;
-        let mut tool_call_id = None;
 
-        log::info!("[Claude/OpenRouter] ====== 开始流式响应转换 ======");
+        log::info!("[{tag}] ====== 开始流式响应转换 ======");
 
         tokio::pin!(stream);
 
         while let Some(chunk) = stream.next().await {
             match chunk {
                 Ok(bytes) => {
-                    let text = String::from_utf8_lossy(&bytes);
-                    buffer.push_str(&text);
+                    pending.extend_from_slice(&bytes);
+                    // 只解码到最后一个换行符，剩余的半行留到下个数据块
+                    let Some(line_end) = pending.iter().rposition(|&b| b == b'\n') else {
+                        continue;
+                    };
+                    let complete: Vec<u8> = pending.drain(..=line_end).collect();
+                    buffer.push_str(&String::from_utf8_lossy(&complete).replace("\r\n", "\n"));
 
                     while let Some(pos) = buffer.find("\n\n") {
-                        let line = buffer[..pos].to_string();
+                        let block = buffer[..pos].to_string();
                         buffer = buffer[pos + 2..].to_string();
 
-                        if line.trim().is_empty() {
+                        if block.trim().is_empty() {
                             continue;
                         }
 
-                        for l in line.lines() {
-                            if let Some(data) = l.strip_prefix("data: ") {
-                                if data.trim() == "[DONE]" {
-                                    log::info!("[Claude/OpenRouter] <<< OpenAI SSE: [DONE]");
-                                    let event = json!({"type": "message_stop"});
-                                    let sse_data = format!("event: message_stop\ndata: {}\n\n",
-                                        serde_json::to_string(&event).unwrap_or_default());
-                                    log::info!("[Claude/OpenRouter] >>> Anthropic SSE: message_stop");
-                                    yield Ok(Bytes::from(sse_data));
-                                    continue;
-                                }
-
-                                if let Ok(chunk) = serde_json::from_str::<OpenAIStreamChunk>(data) {
-                                    // 记录原始 OpenAI 事件（格式化显示）
-                                    if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(data) {
-                                        log::info!(
-                                            "[Claude/OpenRouter] <<< OpenAI SSE 事件:\n{}",
-                                            serde_json::to_string_pretty(&json_value).unwrap_or_else(|_| data.to_string())
-                                        );
-                                    } else {
-                                        log::info!("[Claude/OpenRouter] <<< OpenAI SSE 数据: {data}");
-                                    }
-
-                                    if message_id.is_none() {
-                                        message_id = Some(chunk.id.clone());
-                                    }
-                                    if current_model.is_none() {
-                                        current_model = Some(chunk.model.clone());
-                                    }
-
-                                    if let Some(choice) = chunk.choices.first() {
-                                        if !has_sent_message_start {
-                                            let event = json!({
-                                                "type": "message_start",
-                                                "message": {
-                                                    "id": message_id.clone().unwrap_or_default(),
-                                                    "type": "message",
-                                                    "role": "assistant",
-                                                    "model": current_model.clone().unwrap_or_default(),
-                                                    "usage": {
-                                                        "input_tokens": 0,
-                                                        "output_tokens": 0
-                                                    }
-                                                }
-                                            });
-                                            let sse_data = format!("event: message_start\ndata: {}\n\n",
-                                                serde_json::to_string(&event).unwrap_or_default());
-                                            yield Ok(Bytes::from(sse_data));
-                                            has_sent_message_start = true;
-                                        }
-
-                                        // 处理 reasoning（thinking）
-                                        if let Some(reasoning) = &choice.delta.reasoning {
-                                            if current_block_type.is_none() {
-                                                let event = json!({
-                                                    "type": "content_block_start",
-                                                    "index": content_index,
-                                                    "content_block": {
-                                                        "type": "thinking",
-                                                        "thinking": ""
-                                                    }
-                                                });
-                                                let sse_data = format!("event: content_block_start\ndata: {}\n\n",
-                                                    serde_json::to_string(&event).unwrap_or_default());
-                                                yield Ok(Bytes::from(sse_data));
-                                                current_block_type = Some("thinking".to_string());
-                                            }
-
-                                            let event = json!({
-                                                "type": "content_block_delta",
-                                                "index": content_index,
-                                                "delta": {
-                                                    "type": "thinking_delta",
-                                                    "thinking": reasoning
-                                                }
-                                            });
-                                            let sse_data = format!("event: content_block_delta\ndata: {}\n\n",
-                                                serde_json::to_string(&event).unwrap_or_default());
-                                            yield Ok(Bytes::from(sse_data));
-                                        }
-
-                                        // 处理文本内容
-                                        if let Some(content) = &choice.delta.content {
-                                            if !content.is_empty() {
-                                                if current_block_type.as_deref() != Some("text") {
-                                                    if current_block_type.is_some() {
-                                                        let event = json!({
-                                                            "type": "content_block_stop",
-                                                            "index": content_index
-                                                        });
-                                                        let sse_data = format!("event: content_block_stop\ndata: {}\n\n",
-                                                            serde_json::to_string(&event).unwrap_or_default());
-                                                        yield Ok(Bytes::from(sse_data));
-                                                        content_index += 1;
-                                                    }
-
-                                                    let event = json!({
-                                                        "type": "content_block_start",
-                                                        "index": content_index,
-                                                        "content_block": {
-                                                            "type": "text",
-                                                            "text": ""
-                                                        }
-                                                    });
-                                                    let sse_data = format!("event: content_block_start\ndata: {}\n\n",
-                                                        serde_json::to_string(&event).unwrap_or_default());
-                                                    yield Ok(Bytes::from(sse_data));
-                                                    current_block_type = Some("text".to_string());
-                                                }
-
-                                                let event = json!({
-                                                    "type": "content_block_delta",
-                                                    "index": content_index,
-                                                    "delta": {
-                                                        "type": "text_delta",
-                                                        "text": content
-                                                    }
-                                                });
-                                                let sse_data = format!("event: content_block_delta\ndata: {}\n\n",
-                                                    serde_json::to_string(&event).unwrap_or_default());
-                                                yield Ok(Bytes::from(sse_data));
-                                            }
-                                        }
-
-                                        // 处理工具调用
-                                        if let Some(tool_calls) = &choice.delta.tool_calls {
-                                            for tool_call in tool_calls {
-                                                if let Some(id) = &tool_call.id {
-                                                    if current_block_type.is_some() {
-                                                        let event = json!({
-                                                            "type": "content_block_stop",
-                                                            "index": content_index
-                                                        });
-                                                        let sse_data = format!("event: content_block_stop\ndata: {}\n\n",
-                                                            serde_json::to_string(&event).unwrap_or_default());
-                                                        yield Ok(Bytes::from(sse_data));
-                                                        content_index += 1;
-                                                    }
-
-                                                    tool_call_id = Some(id.clone());
-                                                }
-
-                                                if let Some(function) = &tool_call.function {
-                                                    if let Some(name) = &function.name {
-                                                        let event = json!({
-                                                            "type": "content_block_start",
-                                                            "index": content_index,
-                                                            "content_block": {
-                                                                "type": "tool_use",
-                                                                "id": tool_call_id.clone().unwrap_or_default(),
-                                                                "name": name
-                                                            }
-                                                        });
-                                                        let sse_data = format!("event: content_block_start\ndata: {}\n\n",
-                                                            serde_json::to_string(&event).unwrap_or_default());
-                                                        yield Ok(Bytes::from(sse_data));
-                                                        current_block_type = Some("tool_use".to_string());
-                                                    }
-
-                                                    if let Some(args) = &function.arguments {
-                                                        let event = json!({
-                                                            "type": "content_block_delta",
-                                                            "index": content_index,
-                                                            "delta": {
-                                                                "type": "input_json_delta",
-                                                                "partial_json": args
-                                                            }
-                                                        });
-                                                        let sse_data = format!("event: content_block_delta\ndata: {}\n\n",
-                                                            serde_json::to_string(&event).unwrap_or_default());
-                                                        yield Ok(Bytes::from(sse_data));
-                                                    }
-                                                }
-                                            }
-                                        }
-
-                                        // 处理 finish_reason
-                                        if let Some(finish_reason) = &choice.finish_reason {
-                                            if current_block_type.is_some() {
-                                                let event = json!({
-                                                    "type": "content_block_stop",
-                                                    "index": content_index
-                                                });
-                                                let sse_data = format!("event: content_block_stop\ndata: {}\n\n",
-                                                    serde_json::to_string(&event).unwrap_or_default());
-                                                yield Ok(Bytes::from(sse_data));
-                                            }
-
-                                            let stop_reason = map_stop_reason(Some(finish_reason));
-                                            // 构建 usage 信息，包含 input_tokens 和 output_tokens
-                                            let usage_json = chunk.usage.as_ref().map(|u| json!({
-                                                "input_tokens": u.prompt_tokens,
-                                                "output_tokens": u.completion_tokens
-                                            }));
-                                            let event = json!({
-                                                "type": "message_delta",
-                                                "delta": {
-                                                    "stop_reason": stop_reason,
-                                                    "stop_sequence": null
-                                                },
-                                                "usage": usage_json
-                                            });
-                                            let sse_data = format!("event: message_delta\ndata: {}\n\n",
-                                                serde_json::to_string(&event).unwrap_or_default());
-                                            yield Ok(Bytes::from(sse_data));
-                                        }
-                                    }
-                                }
+                        for l in block.lines() {
+                            let data = match l.strip_prefix("data:") {
+                                Some(data) => data.trim_start(),
+                                None => continue,
+                            };
+                            log::debug!("[{tag}] <<< 上游 SSE 数据: {data}");
+                            for event in converter.process_data(data) {
+                                yield Ok(Bytes::from(event));
                             }
                         }
                     }
                 }
                 Err(e) => {
-                    log::error!("Stream error: {e}");
-                    let error_event = json!({
-                        "type": "error",
-                        "error": {
-                            "type": "stream_error",
-                            "message": format!("Stream error: {e}")
-                        }
-                    });
-                    let sse_data = format!("event: error\ndata: {}\n\n",
-                        serde_json::to_string(&error_event).unwrap_or_default());
-                    yield Ok(Bytes::from(sse_data));
-                    break;
+                    log::error!("[{tag}] Stream error: {e}");
+                    for event in converter.error(&format!("Stream error: {e}")) {
+                        yield Ok(Bytes::from(event));
+                    }
+                    return;
                 }
             }
         }
+
+        if converter.upstream_finished() {
+            // 上游已给出结束原因但未发送结束标记（或缺少 usage）时补齐收尾事件
+            for event in converter.finish() {
+                yield Ok(Bytes::from(event));
+            }
+        } else {
+            log::warn!("[{tag}] 上游流在结束前断开");
+            for event in converter.error("Upstream stream ended unexpectedly") {
+                yield Ok(Bytes::from(event));
+            }
+        }
     }
 }
 
-/// 映射停止原因
-fn map_stop_reason(finish_reason: Option<&str>) -> Option<String> {
-    finish_reason.map(|r| {
-        match r {
-            "tool_calls" => "tool_use",
-            "stop" => "end_turn",
-            "length" => "max_tokens",
-            _ => "end_turn",
+/// 创建 Anthropic SSE 流（OpenAI SSE → Anthropic SSE）
+pub fn create_anthropic_sse_stream(
+    stream: impl Stream<Item = Result<Bytes, reqwest::Error>> + Send + 'static,
+) -> impl Stream<Item = Result<Bytes, std::io::Error>> + Send {
+    convert_sse_stream(stream, "Claude/OpenAI", AnthropicSseConverter::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 把转换输出解析回 (event, data) 列表，便于断言
+    fn parse(events: &[String]) -> Vec<(String, Value)> {
+        events
+            .iter()
+            .map(|e| {
+                let mut lines = e.lines();
+                let event = lines
+                    .next()
+                    .and_then(|l| l.strip_prefix("event: "))
+                    .unwrap()
+                    .to_string();
+                let data = lines.next().and_then(|l| l.strip_prefix("data: ")).unwrap();
+                (event, serde_json::from_str(data).unwrap())
+            })
+            .collect()
+    }
+
+    fn run(chunks: &[Value]) -> Vec<(String, Value)> {
+        let mut converter = AnthropicSseConverter::new();
+        let mut out = Vec::new();
+        for chunk in chunks {
+            out.extend(converter.process_data(&chunk.to_string()));
         }
-        .to_string()
-    })
+        out.extend(converter.process_data("[DONE]"));
+        parse(&out)
+    }
+
+    fn names(events: &[(String, Value)]) -> Vec<&str> {
+        events.iter().map(|(e, _)| e.as_str()).collect()
+    }
+
+    #[test]
+    fn test_text_stream_with_trailing_usage_chunk() {
+        let events = run(&[
+            json!({"id": "c1", "model": "deepseek-chat", "choices": [{"delta": {"role": "assistant", "content": "Hel"}}]}),
+            json!({"id": "c1", "model": "deepseek-chat", "choices": [{"delta": {"content": "lo"}}]}),
+            json!({"id": "c1", "model": "deepseek-chat", "choices": [{"delta": {}, "finish_reason": "stop"}]}),
+            json!({"id": "c1", "model": "deepseek-chat", "choices": [], "usage": {"prompt_tokens": 12, "completion_tokens": 3}}),
+        ]);
+
+        assert_eq!(
+            names(&events),
+            vec![
+                "message_start",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_delta",
+                "content_block_stop",
+                "message_delta",
+                "message_stop"
+            ]
+        );
+        assert_eq!(events[0].1["message"]["model"], "deepseek-chat");
+        assert_eq!(events[1].1["content_block"]["type"], "text");
+        assert_eq!(events[3].1["delta"]["text"], "lo");
+        assert_eq!(events[5].1["delta"]["stop_reason"], "end_turn");
+        assert_eq!(events[5].1["usage"]["input_tokens"], 12);
+        assert_eq!(events[5].1["usage"]["output_tokens"], 3);
+    }
+
+    #[test]
+    fn test_reasoning_content_becomes_thinking_block() {
+        let events = run(&[
+            json!({"id": "c2", "model": "deepseek-reasoner", "choices": [{"delta": {"reasoning_content": "Let me think"}}]}),
+            json!({"id": "c2", "model": "deepseek-reasoner", "choices": [{"delta": {"content": "Answer"}}]}),
+            json!({"id": "c2", "model": "deepseek-reasoner", "choices": [{"delta": {}, "finish_reason": "stop"}], "usage": {"prompt_tokens": 5, "completion_tokens": 9}}),
+        ]);
+
+        assert_eq!(events[1].1["content_block"]["type"], "thinking");
+        assert_eq!(events[1].1["index"], 0);
+        assert_eq!(events[2].1["delta"]["type"], "thinking_delta");
+        assert_eq!(events[2].1["delta"]["thinking"], "Let me think");
+        assert_eq!(events[3].0, "content_block_stop");
+        assert_eq!(events[4].1["content_block"]["type"], "text");
+        assert_eq!(events[4].1["index"], 1);
+    }
+
+    #[test]
+    fn test_tool_call_stream() {
+        let events = run(&[
+            json!({"id": "c3", "model": "m", "choices": [{"delta": {"content": "Checking"}}]}),
+            json!({"id": "c3", "model": "m", "choices": [{"delta": {"tool_calls": [{"index": 0, "id": "call_1", "type": "function", "function": {"name": "get_weather", "arguments": ""}}]}}]}),
+            json!({"id": "c3", "model": "m", "choices": [{"delta": {"tool_calls": [{"index": 0, "function": {"arguments": "{\"city\":"}}]}}]}),
+            json!({"id": "c3", "model": "m", "choices": [{"delta": {"tool_calls": [{"index": 0, "function": {"arguments": "\"Tokyo\"}"}}]}}]}),
+            json!({"id": "c3", "model": "m", "choices": [{"delta": {}, "finish_reason": "tool_calls"}]}),
+        ]);
+
+        let starts: Vec<_> = events
+            .iter()
+            .filter(|(e, _)| e == "content_block_start")
+            .collect();
+        assert_eq!(starts.len(), 2);
+        assert_eq!(starts[1].1["index"], 1);
+        assert_eq!(starts[1].1["content_block"]["type"], "tool_use");
+        assert_eq!(starts[1].1["content_block"]["id"], "call_1");
+        assert_eq!(starts[1].1["content_block"]["name"], "get_weather");
+
+        let partial: String = events
+            .iter()
+            .filter(|(_, d)| d["delta"]["type"] == "input_json_delta")
+            .map(|(_, d)| d["delta"]["partial_json"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(partial, "{\"city\":\"Tokyo\"}");
+
+        let delta = events.iter().find(|(e, _)| e == "message_delta").unwrap();
+        assert_eq!(delta.1["delta"]["stop_reason"], "tool_use");
+        assert_eq!(events.last().unwrap().0, "message_stop");
+    }
+
+    #[test]
+    fn test_interleaved_parallel_tool_calls() {
+        let tool = |index: u32, id: Option<&str>, name: Option<&str>, args: &str| {
+            let mut call = json!({"index": index, "function": {"arguments": args}});
+            if let Some(id) = id {
+                call["id"] = json!(id);
+            }
+            if let Some(name) = name {
+                call["function"]["name"] = json!(name);
+            }
+            json!({"id": "c9", "model": "m", "choices": [{"delta": {"tool_calls": [call]}}]})
+        };
+        let events = run(&[
+            tool(0, Some("call_a"), Some("read"), ""),
+            tool(1, Some("call_b"), Some("grep"), ""),
+            tool(0, None, None, "{\"path\":"),
+            tool(1, None, None, "{\"q\":"),
+            // 部分供应商在后续分片中重复携带 id
+            tool(0, Some("call_a"), None, "\"a.rs\"}"),
+            tool(1, None, None, "\"x\"}"),
+            json!({"id": "c9", "model": "m", "choices": [{"delta": {}, "finish_reason": "tool_calls"}]}),
+        ]);
+
+        let starts: Vec<_> = events
+            .iter()
+            .filter(|(e, _)| e == "content_block_start")
+            .map(|(_, d)| (d["index"].clone(), d["content_block"]["id"].clone()))
+            .collect();
+        assert_eq!(
+            starts,
+            vec![(json!(0), json!("call_a")), (json!(1), json!("call_b"))]
+        );
+
+        let args = |index: u64| -> String {
+            events
+                .iter()
+                .filter(|(e, d)| e == "content_block_delta" && d["index"] == index)
+                .map(|(_, d)| d["delta"]["partial_json"].as_str().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(args(0), "{\"path\":\"a.rs\"}");
+        assert_eq!(args(1), "{\"q\":\"x\"}");
+
+        // 每个 block 恰好结束一次，且结束后不再有 delta
+        for index in [0u64, 1] {
+            let stop = events
+                .iter()
+                .position(|(e, d)| e == "content_block_stop" && d["index"] == index)
+                .unwrap();
+            assert!(!events[stop..]
+                .iter()
+                .any(|(e, d)| e == "content_block_delta" && d["index"] == index));
+        }
+        assert_eq!(
+            names(&events)
+                .iter()
+                .filter(|e| **e == "content_block_stop")
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_cached_tokens_mapped_to_cache_read() {
+        let events = run(&[
+            json!({"id": "c4", "model": "m", "choices": [{"delta": {"content": "x"}, "finish_reason": "length"}], "usage": {"prompt_tokens": 100, "completion_tokens": 7, "prompt_tokens_details": {"cached_tokens": 60}}}),
+        ]);
+
+        let delta = events.iter().find(|(e, _)| e == "message_delta").unwrap();
+        assert_eq!(delta.1["delta"]["stop_reason"], "max_tokens");
+        assert_eq!(delta.1["usage"]["input_tokens"], 40);
+        assert_eq!(delta.1["usage"]["cache_read_input_tokens"], 60);
+        assert_eq!(delta.1["usage"]["output_tokens"], 7);
+    }
+
+    #[test]
+    fn test_finish_without_done_closes_message() {
+        let mut converter = AnthropicSseConverter::new();
+        converter.process_data(
+            &json!({"id": "c5", "model": "m", "choices": [{"delta": {"content": "partial"}}]})
+                .to_string(),
+        );
+        let events = parse(&converter.finish());
+        assert_eq!(
+            names(&events),
+            vec!["content_block_stop", "message_delta", "message_stop"]
+        );
+        // 重复调用不会再产生事件
+        assert!(converter.finish().is_empty());
+    }
+
+    async fn collect_stream(chunks: Vec<Result<Bytes, std::io::Error>>) -> Vec<(String, Value)> {
+        let stream = convert_sse_stream(
+            futures::stream::iter(chunks),
+            "test",
+            AnthropicSseConverter::new(),
+        );
+        let out: Vec<String> = stream
+            .map(|b| String::from_utf8(b.unwrap().to_vec()).unwrap())
+            .collect()
+            .await;
+        parse(&out)
+    }
+
+    #[tokio::test]
+    async fn test_stream_decodes_utf8_split_across_chunks() {
+        let line = format!(
+            "data: {}\n\n",
+            json!({"id": "c6", "model": "m", "choices": [{"delta": {"content": "你好"}, "finish_reason": "stop"}]})
+        );
+        let bytes = line.as_bytes();
+        // 在“你”的三个字节中间切开
+        let split = line.find('你').unwrap() + 1;
+        let events = collect_stream(vec![
+            Ok(Bytes::copy_from_slice(&bytes[..split])),
+            Ok(Bytes::copy_from_slice(&bytes[split..])),
+            Ok(Bytes::from_static(b"data: [DONE]\n\n")),
+        ])
+        .await;
+
+        let delta = events
+            .iter()
+            .find(|(e, _)| e == "content_block_delta")
+            .unwrap();
+        assert_eq!(delta.1["delta"]["text"], "你好");
+        assert_eq!(events.last().unwrap().0, "message_stop");
+    }
+
+    #[tokio::test]
+    async fn test_stream_truncated_emits_error_instead_of_stop() {
+        let partial = format!(
+            "data: {}\n\n",
+            json!({"id": "c7", "model": "m", "choices": [{"delta": {"content": "partial"}}]})
+        );
+        let events = collect_stream(vec![Ok(Bytes::from(partial.clone()))]).await;
+        assert_eq!(events.last().unwrap().0, "error");
+        assert!(!names(&events).contains(&"message_stop"));
+
+        let events = collect_stream(vec![
+            Ok(Bytes::from(partial)),
+            Err(std::io::Error::other("connection reset")),
+        ])
+        .await;
+        assert_eq!(events.last().unwrap().0, "error");
+        assert!(!names(&events).contains(&"message_delta"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_finish_reason_without_done_completes() {
+        let events = collect_stream(vec![Ok(Bytes::from(format!(
+            "data: {}\n\n",
+            json!({"id": "c8", "model": "m", "choices": [{"delta": {"content": "x"}, "finish_reason": "stop"}]})
+        )))])
+        .await;
+        assert_eq!(events.last().unwrap().0, "message_stop");
+    }
 }
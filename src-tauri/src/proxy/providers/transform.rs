@@ -1,6 +1,6 @@
 //! 格式转换模块
 //!
-//! 实现 Anthropic ↔ OpenAI 格式转换，用于 OpenRouter 支持
+//! 实现 Anthropic ↔ OpenAI 格式转换，用于 OpenAI 兼容上游（DeepSeek、vLLM、Kimi 等）和 OpenRouter
 //! 参考: anthropic-proxy-rs
 
 use crate::provider::Provider;
@@ -8,7 +8,7 @@ use crate::proxy::error::ProxyError;
 use serde_json::{json, Value};
 
 /// 从 Provider 配置中获取模型映射
-fn get_model_from_provider(model: &str, provider: &Provider, body: &Value) -> String {
+pub(super) fn get_model_from_provider(model: &str, provider: &Provider, body: &Value) -> String {
     let env = provider.settings_config.get("env");
     let model_lower = model.to_lowercase();
 
@@ -84,11 +84,15 @@ pub fn anthropic_to_openai(body: Value, provider: &Provider) -> Result<Value, Pr
             // 单个字符串
             messages.push(json!({"role": "system", "content": text}));
         } else if let Some(arr) = system.as_array() {
-            // 多个 system message
-            for msg in arr {
-                if let Some(text) = msg.get("text").and_then(|t| t.as_str()) {
-                    messages.push(json!({"role": "system", "content": text}));
-                }
+            // 多段 system（含 cache_control 等）合并为一条，
+            // 部分 OpenAI 兼容服务（vLLM 等的 chat template）只接受一条 system 消息
+            let text = arr
+                .iter()
+                .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            if !text.is_empty() {
+                messages.push(json!({"role": "system", "content": text}));
             }
         }
     }
@@ -120,6 +124,17 @@ pub fn anthropic_to_openai(body: Value, provider: &Provider) -> Result<Value, Pr
     }
     if let Some(v) = body.get("stream") {
         result["stream"] = v.clone();
+        // 要求上游在流末尾返回 usage，否则无法统计 token
+        if v.as_bool() == Some(true) {
+            result["stream_options"] = json!({"include_usage": true});
+        }
+    }
+    if let Some(user_id) = body
+        .get("metadata")
+        .and_then(|m| m.get("user_id"))
+        .and_then(|u| u.as_str())
+    {
+        result["user"] = json!(user_id);
     }
 
     // 转换 tools (过滤 BatchTool)
@@ -145,12 +160,67 @@ pub fn anthropic_to_openai(body: Value, provider: &Provider) -> Result<Value, Pr
     }
 
     if let Some(v) = body.get("tool_choice") {
-        result["tool_choice"] = v.clone();
+        let (tool_choice, parallel) = convert_tool_choice(v);
+        if let Some(tool_choice) = tool_choice {
+            result["tool_choice"] = tool_choice;
+        }
+        if let Some(parallel) = parallel {
+            result["parallel_tool_calls"] = json!(parallel);
+        }
     }
 
     Ok(result)
 }
 
+/// Anthropic tool_choice → OpenAI tool_choice
+///
+/// 返回 (tool_choice, parallel_tool_calls)：
+/// - `{"type":"auto"}` → `"auto"`
+/// - `{"type":"any"}` → `"required"`
+/// - `{"type":"tool","name":X}` → `{"type":"function","function":{"name":X}}`
+/// - `{"type":"none"}` → `"none"`
+/// - `disable_parallel_tool_use: true` → `parallel_tool_calls: false`
+fn convert_tool_choice(choice: &Value) -> (Option<Value>, Option<bool>) {
+    // 已经是 OpenAI 格式的字符串，直接透传
+    if let Some(s) = choice.as_str() {
+        return (Some(json!(s)), None);
+    }
+
+    let parallel = choice
+        .get("disable_parallel_tool_use")
+        .and_then(|v| v.as_bool())
+        .map(|disabled| !disabled);
+
+    let converted = match choice.get("type").and_then(|t| t.as_str()) {
+        Some("auto") => Some(json!("auto")),
+        Some("any") => Some(json!("required")),
+        Some("none") => Some(json!("none")),
+        Some("tool") => choice
+            .get("name")
+            .and_then(|n| n.as_str())
+            .map(|name| json!({"type": "function", "function": {"name": name}})),
+        _ => None,
+    };
+
+    (converted, parallel)
+}
+
+/// Anthropic image source → OpenAI image_url
+fn convert_image_source(source: &Value) -> Option<Value> {
+    let url = match source.get("type").and_then(|t| t.as_str()) {
+        Some("url") => source.get("url").and_then(|u| u.as_str())?.to_string(),
+        _ => {
+            let media_type = source
+                .get("media_type")
+                .and_then(|m| m.as_str())
+                .unwrap_or("image/png");
+            let data = source.get("data").and_then(|d| d.as_str()).unwrap_or("");
+            format!("data:{media_type};base64,{data}")
+        }
+    };
+    Some(json!({"type": "image_url", "image_url": {"url": url}}))
+}
+
 /// 转换单条消息到 OpenAI 格式（可能产生多条消息）
 fn convert_message_to_openai(
     role: &str,
@@ -187,16 +257,8 @@ fn convert_message_to_openai(
                     }
                 }
                 "image" => {
-                    if let Some(source) = block.get("source") {
-                        let media_type = source
-                            .get("media_type")
-                            .and_then(|m| m.as_str())
-                            .unwrap_or("image/png");
-                        let data = source.get("data").and_then(|d| d.as_str()).unwrap_or("");
-                        content_parts.push(json!({
-                            "type": "image_url",
-                            "image_url": {"url": format!("data:{};base64,{}", media_type, data)}
-                        }));
+                    if let Some(part) = block.get("source").and_then(convert_image_source) {
+                        content_parts.push(part);
                     }
                 }
                 "tool_use" => {
@@ -219,19 +281,56 @@ fn convert_message_to_openai(
                         .and_then(|i| i.as_str())
                         .unwrap_or("");
                     let content_val = block.get("content");
+                    let mut images = Vec::new();
                     let content_str = match content_val {
                         Some(Value::String(s)) => s.clone(),
+                        // 数组内容：拼接文本，图片无法放进 tool 消息，单独追加一条 user 消息
+                        Some(Value::Array(parts)) => {
+                            let mut texts = Vec::new();
+                            for part in parts {
+                                match part.get("type").and_then(|t| t.as_str()) {
+                                    Some("text") => {
+                                        if let Some(text) =
+                                            part.get("text").and_then(|t| t.as_str())
+                                        {
+                                            texts.push(text);
+                                        }
+                                    }
+                                    Some("image") => {
+                                        if let Some(image) =
+                                            part.get("source").and_then(convert_image_source)
+                                        {
+                                            images.push(image);
+                                        }
+                                    }
+                                    _ => {}
+                                }
+                            }
+                            texts.join("\n")
+                        }
                         Some(v) => serde_json::to_string(v).unwrap_or_default(),
                         None => String::new(),
                     };
+                    let is_error = block
+                        .get("is_error")
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(false);
+                    let content_str = if is_error && !content_str.starts_with("Error") {
+                        format!("Error: {content_str}")
+                    } else {
+                        content_str
+                    };
                     result.push(json!({
                         "role": "tool",
                         "tool_call_id": tool_use_id,
                         "content": content_str
                     }));
+                    if !images.is_empty() {
+                        result.push(json!({"role": "user", "content": images}));
+                    }
                 }
-                "thinking" => {
-                    // 跳过 thinking blocks
+                "thinking" | "redacted_thinking" => {
+                    // 跳过 thinking blocks（OpenAI 兼容服务不接受回传推理内容）
                 }
                 _ => {}
             }
@@ -271,7 +370,7 @@ fn convert_message_to_openai(
 }
 
 /// 清理 JSON schema（移除不支持的 format）
-fn clean_schema(mut schema: Value) -> Value {
+pub(super) fn clean_schema(mut schema: Value) -> Value {
     if let Some(obj) = schema.as_object_mut() {
         // 移除 "format": "uri"
         if obj.get("format").and_then(|v| v.as_str()) == Some("uri") {
@@ -309,6 +408,16 @@ pub fn openai_to_anthropic(body: Value) -> Result<Value, ProxyError> {
 
     let mut content = Vec::new();
 
+    // 推理内容（DeepSeek/Kimi 等使用 reasoning_content，OpenRouter 使用 reasoning）
+    if let Some(reasoning) = message
+        .get("reasoning_content")
+        .or_else(|| message.get("reasoning"))
+        .and_then(|r| r.as_str())
+        .filter(|r| !r.is_empty())
+    {
+        content.push(json!({"type": "thinking", "thinking": reasoning, "signature": ""}));
+    }
+
     // 文本内容
     if let Some(text) = message.get("content").and_then(|c| c.as_str()) {
         if !text.is_empty() {
@@ -342,23 +451,10 @@ pub fn openai_to_anthropic(body: Value) -> Result<Value, ProxyError> {
     let stop_reason = choice
         .get("finish_reason")
         .and_then(|r| r.as_str())
-        .map(|r| match r {
-            "stop" => "end_turn",
-            "length" => "max_tokens",
-            "tool_calls" => "tool_use",
-            other => other,
-        });
+        .map(map_finish_reason);
 
     // usage
-    let usage = body.get("usage").cloned().unwrap_or(json!({}));
-    let input_tokens = usage
-        .get("prompt_tokens")
-        .and_then(|v| v.as_u64())
-        .unwrap_or(0) as u32;
-    let output_tokens = usage
-        .get("completion_tokens")
-        .and_then(|v| v.as_u64())
-        .unwrap_or(0) as u32;
+    let usage = openai_usage_to_anthropic(body.get("usage").unwrap_or(&json!({})));
 
     let result = json!({
         "id": body.get("id").and_then(|i| i.as_str()).unwrap_or(""),
@@ -368,15 +464,52 @@ pub fn openai_to_anthropic(body: Value) -> Result<Value, ProxyError> {
         "model": body.get("model").and_then(|m| m.as_str()).unwrap_or(""),
         "stop_reason": stop_reason,
         "stop_sequence": null,
-        "usage": {
-            "input_tokens": input_tokens,
-            "output_tokens": output_tokens
-        }
+        "usage": usage
     });
 
     Ok(result)
 }
 
+/// OpenAI finish_reason → Anthropic stop_reason
+pub fn map_finish_reason(finish_reason: &str) -> &'static str {
+    match finish_reason {
+        "length" => "max_tokens",
+        "tool_calls" | "function_call" => "tool_use",
+        _ => "end_turn",
+    }
+}
+
+/// OpenAI usage → Anthropic usage
+///
+/// 缓存命中的 token（`prompt_tokens_details.cached_tokens`，DeepSeek 为
+/// `prompt_cache_hit_tokens`）映射为 `cache_read_input_tokens`，并从 input_tokens 中扣除，
+/// 与 Anthropic 的计费口径保持一致。
+pub fn openai_usage_to_anthropic(usage: &Value) -> Value {
+    let prompt_tokens = usage
+        .get("prompt_tokens")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+    let output_tokens = usage
+        .get("completion_tokens")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+    let cached_tokens = usage
+        .get("prompt_tokens_details")
+        .and_then(|d| d.get("cached_tokens"))
+        .or_else(|| usage.get("prompt_cache_hit_tokens"))
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+
+    let mut result = json!({
+        "input_tokens": prompt_tokens.saturating_sub(cached_tokens),
+        "output_tokens": output_tokens
+    });
+    if cached_tokens > 0 {
+        result["cache_read_input_tokens"] = json!(cached_tokens);
+    }
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -637,4 +770,208 @@ mod tests {
         // 应该使用普通模型
         assert_eq!(result["model"], "anthropic/claude-sonnet-4.5");
     }
+
+    #[test]
+    fn test_anthropic_to_openai_merges_system_blocks() {
+        let provider = create_openrouter_provider();
+        let input = json!({
+            "model": "claude-3-opus",
+            "max_tokens": 1024,
+            "system": [
+                {"type": "text", "text": "You are Claude."},
+                {"type": "text", "text": "Be concise.", "cache_control": {"type": "ephemeral"}}
+            ],
+            "messages": [{"role": "user", "content": "Hello"}]
+        });
+
+        let result = anthropic_to_openai(input, &provider).unwrap();
+        let messages = result["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0]["role"], "system");
+        assert_eq!(messages[0]["content"], "You are Claude.\n\nBe concise.");
+    }
+
+    #[test]
+    fn test_anthropic_to_openai_images() {
+        let provider = create_openrouter_provider();
+        let input = json!({
+            "model": "claude-3-opus",
+            "max_tokens": 1024,
+            "messages": [{
+                "role": "user",
+                "content": [
+                    {"type": "text", "text": "Compare"},
+                    {"type": "image", "source": {"type": "base64", "media_type": "image/jpeg", "data": "AAAA"}},
+                    {"type": "image", "source": {"type": "url", "url": "https://example.com/a.png"}}
+                ]
+            }]
+        });
+
+        let result = anthropic_to_openai(input, &provider).unwrap();
+        let parts = result["messages"][0]["content"].as_array().unwrap();
+        assert_eq!(parts[0]["type"], "text");
+        assert_eq!(parts[1]["image_url"]["url"], "data:image/jpeg;base64,AAAA");
+        assert_eq!(parts[2]["image_url"]["url"], "https://example.com/a.png");
+    }
+
+    #[test]
+    fn test_anthropic_to_openai_tool_choice() {
+        let provider = create_openrouter_provider();
+        let cases = [
+            (json!({"type": "auto"}), json!("auto")),
+            (json!({"type": "any"}), json!("required")),
+            (json!({"type": "none"}), json!("none")),
+            (
+                json!({"type": "tool", "name": "get_weather"}),
+                json!({"type": "function", "function": {"name": "get_weather"}}),
+            ),
+        ];
+        for (choice, expected) in cases {
+            let input = json!({
+                "model": "claude-3-opus",
+                "max_tokens": 1024,
+                "tool_choice": choice,
+                "messages": [{"role": "user", "content": "Hi"}]
+            });
+            let result = anthropic_to_openai(input, &provider).unwrap();
+            assert_eq!(result["tool_choice"], expected);
+            assert!(result.get("parallel_tool_calls").is_none());
+        }
+
+        let input = json!({
+            "model": "claude-3-opus",
+            "max_tokens": 1024,
+            "tool_choice": {"type": "auto", "disable_parallel_tool_use": true},
+            "messages": [{"role": "user", "content": "Hi"}]
+        });
+        let result = anthropic_to_openai(input, &provider).unwrap();
+        assert_eq!(result["tool_choice"], "auto");
+        assert_eq!(result["parallel_tool_calls"], false);
+    }
+
+    #[test]
+    fn test_anthropic_to_openai_stream_requests_usage() {
+        let provider = create_openrouter_provider();
+        let input = json!({
+            "model": "claude-3-opus",
+            "max_tokens": 1024,
+            "stream": true,
+            "messages": [{"role": "user", "content": "Hi"}]
+        });
+
+        let result = anthropic_to_openai(input, &provider).unwrap();
+        assert_eq!(result["stream"], true);
+        assert_eq!(result["stream_options"]["include_usage"], true);
+    }
+
+    #[test]
+    fn test_anthropic_to_openai_tool_result_blocks() {
+        let provider = create_openrouter_provider();
+        let input = json!({
+            "model": "claude-3-opus",
+            "max_tokens": 1024,
+            "messages": [{
+                "role": "user",
+                "content": [{
+                    "type": "tool_result",
+                    "tool_use_id": "call_1",
+                    "content": [
+                        {"type": "text", "text": "line 1"},
+                        {"type": "text", "text": "line 2"},
+                        {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "BBBB"}}
+                    ]
+                }]
+            }]
+        });
+
+        let result = anthropic_to_openai(input, &provider).unwrap();
+        let messages = result["messages"].as_array().unwrap();
+        assert_eq!(messages[0]["role"], "tool");
+        assert_eq!(messages[0]["tool_call_id"], "call_1");
+        assert_eq!(messages[0]["content"], "line 1\nline 2");
+        assert_eq!(messages[1]["role"], "user");
+        assert_eq!(
+            messages[1]["content"][0]["image_url"]["url"],
+            "data:image/png;base64,BBBB"
+        );
+    }
+
+    #[test]
+    fn test_anthropic_to_openai_skips_thinking_blocks() {
+        let provider = create_openrouter_provider();
+        let input = json!({
+            "model": "claude-3-opus",
+            "max_tokens": 1024,
+            "messages": [{
+                "role": "assistant",
+                "content": [
+                    {"type": "thinking", "thinking": "hmm", "signature": "sig"},
+                    {"type": "redacted_thinking", "data": "xxx"},
+                    {"type": "text", "text": "Answer"}
+                ]
+            }]
+        });
+
+        let result = anthropic_to_openai(input, &provider).unwrap();
+        let msg = &result["messages"][0];
+        assert_eq!(msg["role"], "assistant");
+        assert_eq!(msg["content"], "Answer");
+    }
+
+    #[test]
+    fn test_openai_to_anthropic_reasoning_and_usage() {
+        let input = json!({
+            "id": "chatcmpl-1",
+            "model": "deepseek-reasoner",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "reasoning_content": "Let me think",
+                    "content": "42"
+                },
+                "finish_reason": "length"
+            }],
+            "usage": {
+                "prompt_tokens": 100,
+                "completion_tokens": 20,
+                "prompt_tokens_details": {"cached_tokens": 60}
+            }
+        });
+
+        let result = openai_to_anthropic(input).unwrap();
+        assert_eq!(result["content"][0]["type"], "thinking");
+        assert_eq!(result["content"][0]["thinking"], "Let me think");
+        assert_eq!(result["content"][1]["type"], "text");
+        assert_eq!(result["content"][1]["text"], "42");
+        assert_eq!(result["stop_reason"], "max_tokens");
+        assert_eq!(result["usage"]["input_tokens"], 40);
+        assert_eq!(result["usage"]["cache_read_input_tokens"], 60);
+        assert_eq!(result["usage"]["output_tokens"], 20);
+    }
+
+    #[test]
+    fn test_openai_usage_to_anthropic_deepseek_cache_hit() {
+        let usage = openai_usage_to_anthropic(&json!({
+            "prompt_tokens": 50,
+            "completion_tokens": 5,
+            "prompt_cache_hit_tokens": 30
+        }));
+        assert_eq!(usage["input_tokens"], 20);
+        assert_eq!(usage["cache_read_input_tokens"], 30);
+
+        let usage = openai_usage_to_anthropic(&json!({"prompt_tokens": 10}));
+        assert_eq!(usage["input_tokens"], 10);
+        assert_eq!(usage["output_tokens"], 0);
+        assert!(usage.get("cache_read_input_tokens").is_none());
+    }
+
+    #[test]
+    fn test_map_finish_reason() {
+        assert_eq!(map_finish_reason("stop"), "end_turn");
+        assert_eq!(map_finish_reason("length"), "max_tokens");
+        assert_eq!(map_finish_reason("tool_calls"), "tool_use");
+        assert_eq!(map_finish_reason("function_call"), "tool_use");
+        assert_eq!(map_finish_reason("content_filter"), "end_turn");
+    }
 }
@@ -0,0 +1,393 @@
+//! 供应商级限流
+//!
+//! 按供应商 meta 中配置的每分钟请求数（RPM）、每分钟输入 Token 数（TPM）和最大并发数限流。
+//! RPM/TPM 使用令牌桶（容量为一分钟的额度，按秒匀速回填），并发数在响应体读取完毕时释放。
+//! 额度不足时请求进入有界等待队列；队列已满或预计等待超过截止时间时拒绝，
+//! 由转发器溢出到故障转移队列中的下一个供应商。
+
+use crate::provider::Provider;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// 默认等待队列长度
+const DEFAULT_QUEUE_SIZE: u32 = 32;
+
+/// 默认最长等待时间（毫秒）
+const DEFAULT_MAX_WAIT_MS: u64 = 10_000;
+
+/// 供应商限流配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimits {
+    pub requests_per_minute: Option<u32>,
+    pub tokens_per_minute: Option<u32>,
+    pub max_concurrency: Option<u32>,
+    pub queue_size: u32,
+    pub max_wait: Duration,
+}
+
+impl RateLimits {
+    /// 从供应商 meta 读取限流配置；未配置任何限额（或均为 0）时返回 None
+    pub fn from_provider(provider: &Provider) -> Option<Self> {
+        let meta = provider.meta.as_ref()?;
+        let positive = |v: Option<u32>| v.filter(|v| *v > 0);
+
+        let limits = Self {
+            requests_per_minute: positive(meta.rate_limit_rpm),
+            tokens_per_minute: positive(meta.rate_limit_tpm),
+            max_concurrency: positive(meta.max_concurrency),
+            queue_size: meta.rate_limit_queue_size.unwrap_or(DEFAULT_QUEUE_SIZE),
+            max_wait: Duration::from_millis(
+                meta.rate_limit_max_wait_ms.unwrap_or(DEFAULT_MAX_WAIT_MS),
+            ),
+        };
+
+        (limits.requests_per_minute.is_some()
+            || limits.tokens_per_minute.is_some()
+            || limits.max_concurrency.is_some())
+        .then_some(limits)
+    }
+}
+
+/// 粗略估算请求的输入 Token 数（按请求体 JSON 长度 / 4）
+pub fn estimate_input_tokens(body: &serde_json::Value) -> u32 {
+    (body.to_string().len() / 4).max(1) as u32
+}
+
+/// 限流拒绝原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RateLimitRejection {
+    /// 等待队列已满
+    QueueFull,
+    /// 预计等待时间超过截止时间
+    WaitExceeded(Duration),
+}
+
+impl std::fmt::Display for RateLimitRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::QueueFull => write!(f, "等待队列已满"),
+            Self::WaitExceeded(wait) => {
+                write!(f, "预计等待 {}ms 超过上限", wait.as_millis())
+            }
+        }
+    }
+}
+
+/// 令牌桶（容量为一分钟的额度）
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    available: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(per_minute: u32, now: Instant) -> Self {
+        Self {
+            capacity: per_minute as f64,
+            available: per_minute as f64,
+            last_refill: now,
+        }
+    }
+
+    /// 额度变更时保留已用部分
+    fn set_capacity(&mut self, per_minute: u32) {
+        let capacity = per_minute as f64;
+        if capacity != self.capacity {
+            self.available = (self.available + capacity - self.capacity).clamp(0.0, capacity);
+            self.capacity = capacity;
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.available = (self.available + elapsed * self.capacity / 60.0).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// 获取 `amount` 额度还需等待的时间（超过容量的请求按容量计算，避免永远无法放行）
+    fn wait_time(&self, amount: f64) -> Duration {
+        let amount = amount.min(self.capacity);
+        if self.available >= amount {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64((amount - self.available) * 60.0 / self.capacity)
+    }
+
+    fn consume(&mut self, amount: f64) {
+        self.available -= amount.min(self.capacity);
+    }
+}
+
+/// 单个供应商的限流状态
+#[derive(Debug, Default)]
+struct ProviderLimitState {
+    requests: Option<TokenBucket>,
+    tokens: Option<TokenBucket>,
+    in_flight: u32,
+    waiting: u32,
+}
+
+impl ProviderLimitState {
+    /// 同步最新配置（供应商 meta 可能在运行期间被修改）
+    fn sync_limits(&mut self, limits: &RateLimits, now: Instant) {
+        fn sync(bucket: &mut Option<TokenBucket>, limit: Option<u32>, now: Instant) {
+            match (bucket.as_mut(), limit) {
+                (Some(b), Some(limit)) => b.set_capacity(limit),
+                (None, Some(limit)) => *bucket = Some(TokenBucket::new(limit, now)),
+                (_, None) => *bucket = None,
+            }
+        }
+        sync(&mut self.requests, limits.requests_per_minute, now);
+        sync(&mut self.tokens, limits.tokens_per_minute, now);
+    }
+
+    /// 尝试放行；需要等待时返回等待时长（并发已满时返回 None，等待释放通知）
+    fn try_acquire(
+        &mut self,
+        limits: &RateLimits,
+        tokens: u32,
+        now: Instant,
+    ) -> Result<(), Option<Duration>> {
+        if let Some(max) = limits.max_concurrency {
+            if self.in_flight >= max {
+                return Err(None);
+            }
+        }
+
+        let mut wait = Duration::ZERO;
+        if let Some(bucket) = self.requests.as_mut() {
+            bucket.refill(now);
+            wait = wait.max(bucket.wait_time(1.0));
+        }
+        if let Some(bucket) = self.tokens.as_mut() {
+            bucket.refill(now);
+            wait = wait.max(bucket.wait_time(tokens as f64));
+        }
+        if !wait.is_zero() {
+            return Err(Some(wait));
+        }
+
+        if let Some(bucket) = self.requests.as_mut() {
+            bucket.consume(1.0);
+        }
+        if let Some(bucket) = self.tokens.as_mut() {
+            bucket.consume(tokens as f64);
+        }
+        self.in_flight += 1;
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct LimiterInner {
+    states: Mutex<HashMap<String, ProviderLimitState>>,
+    notify: Notify,
+}
+
+impl LimiterInner {
+    fn with_state<T>(&self, key: &str, f: impl FnOnce(&mut ProviderLimitState) -> T) -> T {
+        let mut states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+        f(states.entry(key.to_string()).or_default())
+    }
+}
+
+/// 限流放行许可
+///
+/// drop 时释放并发名额并唤醒等待者；需要持有到响应体读取完毕（流式响应即流结束）。
+pub struct RateLimitPermit {
+    inner: Arc<LimiterInner>,
+    key: String,
+}
+
+impl Drop for RateLimitPermit {
+    fn drop(&mut self) {
+        self.inner.with_state(&self.key, |state| {
+            state.in_flight = state.in_flight.saturating_sub(1);
+        });
+        self.inner.notify.notify_waiters();
+    }
+}
+
+/// 等待队列占位，离开队列（放行、拒绝或请求被取消）时自动递减
+struct QueueSlot<'a> {
+    inner: &'a LimiterInner,
+    key: &'a str,
+}
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.inner.with_state(self.key, |state| {
+            state.waiting = state.waiting.saturating_sub(1);
+        });
+    }
+}
+
+/// 单次放行检查的结果
+enum AcquireOutcome {
+    Acquired,
+    QueueFull,
+    /// 需要等待：`Some` 为令牌桶回填时间，`None` 为等待并发名额释放
+    Wait(Option<Duration>),
+}
+
+/// 供应商限流器（由 `ProviderRouter` 持有，所有请求共享）
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+    inner: Arc<LimiterInner>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 获取放行许可，额度不足时在队列中等待，直到放行或超过截止时间
+    pub async fn acquire(
+        &self,
+        key: &str,
+        limits: &RateLimits,
+        tokens: u32,
+    ) -> Result<RateLimitPermit, RateLimitRejection> {
+        let deadline = Instant::now() + limits.max_wait;
+        let mut slot: Option<QueueSlot> = None;
+
+        loop {
+            // 先注册通知再检查状态，避免错过检查与等待之间的释放
+            let notified = self.inner.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            let now = Instant::now();
+            let queued = slot.is_some();
+            let outcome = self.inner.with_state(key, |state| {
+                state.sync_limits(limits, now);
+                match state.try_acquire(limits, tokens, now) {
+                    Ok(()) => AcquireOutcome::Acquired,
+                    Err(_) if !queued && state.waiting >= limits.queue_size => {
+                        AcquireOutcome::QueueFull
+                    }
+                    Err(wait) => {
+                        if !queued {
+                            state.waiting += 1;
+                        }
+                        AcquireOutcome::Wait(wait)
+                    }
+                }
+            });
+
+            let wait = match outcome {
+                AcquireOutcome::Acquired => {
+                    return Ok(RateLimitPermit {
+                        inner: self.inner.clone(),
+                        key: key.to_string(),
+                    })
+                }
+                AcquireOutcome::QueueFull => return Err(RateLimitRejection::QueueFull),
+                AcquireOutcome::Wait(wait) => wait,
+            };
+            if !queued {
+                slot = Some(QueueSlot {
+                    inner: &self.inner,
+                    key,
+                });
+            }
+
+            let remaining = deadline.saturating_duration_since(now);
+            match wait {
+                Some(wait) if wait > remaining => {
+                    return Err(RateLimitRejection::WaitExceeded(wait));
+                }
+                Some(wait) => tokio::time::sleep(wait).await,
+                None if remaining.is_zero() => {
+                    return Err(RateLimitRejection::WaitExceeded(limits.max_wait));
+                }
+                None => {
+                    if tokio::time::timeout(remaining, notified).await.is_err() {
+                        return Err(RateLimitRejection::WaitExceeded(limits.max_wait));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(rpm: Option<u32>, tpm: Option<u32>, concurrency: Option<u32>) -> RateLimits {
+        RateLimits {
+            requests_per_minute: rpm,
+            tokens_per_minute: tpm,
+            max_concurrency: concurrency,
+            queue_size: 1,
+            max_wait: Duration::from_millis(200),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rpm_rejects_when_wait_exceeds_deadline() {
+        let limiter = RateLimiter::new();
+        let limits = limits(Some(2), None, None);
+
+        let _a = limiter.acquire("claude:p1", &limits, 1).await.unwrap();
+        let _b = limiter.acquire("claude:p1", &limits, 1).await.unwrap();
+        // 第三个请求需要等待约 30 秒回填，超过 200ms 截止时间
+        assert!(matches!(
+            limiter.acquire("claude:p1", &limits, 1).await,
+            Err(RateLimitRejection::WaitExceeded(_))
+        ));
+        // 其他供应商不受影响
+        assert!(limiter.acquire("claude:p2", &limits, 1).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_tpm_counts_estimated_tokens() {
+        let limiter = RateLimiter::new();
+        let limits = limits(None, Some(1000), None);
+
+        let _a = limiter.acquire("claude:p1", &limits, 800).await.unwrap();
+        assert!(limiter.acquire("claude:p1", &limits, 800).await.is_err());
+        assert!(limiter.acquire("claude:p1", &limits, 100).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_waits_for_release() {
+        let limiter = RateLimiter::new();
+        let limits = limits(None, None, Some(1));
+
+        let first = limiter.acquire("claude:p1", &limits, 1).await.unwrap();
+        let waiter = {
+            let limiter = limiter.clone();
+            let limits = limits.clone();
+            tokio::spawn(async move { limiter.acquire("claude:p1", &limits, 1).await.is_ok() })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        // 队列长度为 1，第三个请求直接被拒绝
+        assert_eq!(
+            limiter.acquire("claude:p1", &limits, 1).await.err(),
+            Some(RateLimitRejection::QueueFull)
+        );
+
+        drop(first);
+        assert!(waiter.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_times_out() {
+        let limiter = RateLimiter::new();
+        let limits = limits(None, None, Some(1));
+
+        let _first = limiter.acquire("claude:p1", &limits, 1).await.unwrap();
+        assert!(matches!(
+            limiter.acquire("claude:p1", &limits, 1).await,
+            Err(RateLimitRejection::WaitExceeded(_))
+        ));
+    }
+}
@@ -0,0 +1,312 @@
+//! 本地响应缓存
+//!
+//! CI 等场景会反复发送相同的确定性 prompt，命中缓存时直接返回此前的上游响应，不再请求上游。
+//! 仅缓存 temperature 为 0 的请求；缓存键由应用类型、供应商、模型和规范化后的请求体计算得到。
+
+use crate::database::Database;
+use bytes::Bytes;
+use futures::stream::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+/// 不参与缓存键计算的易变字段（如 Claude Code 在 metadata.user_id 中携带会话 ID）
+const VOLATILE_FIELDS: &[&str] = &["metadata", "user"];
+
+/// 缓存命中时附加的响应头
+pub const CACHE_HIT_HEADER: &str = "x-cc-switch-cache";
+
+/// 缓存的上游响应
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    pub provider_id: String,
+    pub status_code: u16,
+    pub content_type: String,
+    pub body: Vec<u8>,
+    pub is_streaming: bool,
+}
+
+/// 缓存条目摘要（不含响应体）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseCacheEntry {
+    pub cache_key: String,
+    pub app_type: String,
+    pub provider_id: String,
+    pub model: String,
+    pub is_streaming: bool,
+    pub status_code: u16,
+    pub size_bytes: u64,
+    pub hit_count: u64,
+    pub created_at: i64,
+    pub expires_at: i64,
+    pub last_hit_at: Option<i64>,
+}
+
+/// 缓存统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseCacheStats {
+    pub entries: u64,
+    pub total_bytes: u64,
+    pub total_hits: u64,
+}
+
+/// 请求是否为确定性请求（temperature 为 0）
+///
+/// Gemini 的 temperature 位于 `generationConfig` 中
+pub fn is_deterministic(body: &Value) -> bool {
+    body.get("temperature")
+        .or_else(|| body.pointer("/generationConfig/temperature"))
+        .and_then(|t| t.as_f64())
+        == Some(0.0)
+}
+
+/// 规范化请求体：对象键递归排序，并去除顶层的易变字段
+pub fn canonicalize(body: &Value) -> Value {
+    fn sort(value: &Value) -> Value {
+        match value {
+            Value::Object(map) => {
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+                let mut sorted = Map::new();
+                for key in keys {
+                    sorted.insert(key.clone(), sort(&map[key]));
+                }
+                Value::Object(sorted)
+            }
+            Value::Array(items) => Value::Array(items.iter().map(sort).collect()),
+            other => other.clone(),
+        }
+    }
+
+    let mut canonical = sort(body);
+    if let Some(map) = canonical.as_object_mut() {
+        for field in VOLATILE_FIELDS {
+            map.remove(*field);
+        }
+    }
+    canonical
+}
+
+/// 计算缓存键；非确定性请求返回 None
+///
+/// `endpoint` 参与计算（Gemini 的流式与非流式请求体相同，仅端点不同）。
+/// 键为 SHA-256 摘要的十六进制串：跨版本稳定，持久化到数据库的缓存升级后仍可命中。
+/// 每段输入前写入其字节长度，避免不同字段拼接后产生相同的输入。
+pub fn cache_key(
+    app_type: &str,
+    provider_id: &str,
+    model: &str,
+    endpoint: &str,
+    body: &Value,
+) -> Option<String> {
+    if !is_deterministic(body) {
+        return None;
+    }
+
+    let canonical = canonicalize(body).to_string();
+    let mut hasher = Sha256::new();
+    for part in [app_type, provider_id, model, endpoint, canonical.as_str()] {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+
+    Some(
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect(),
+    )
+}
+
+/// SSE 原文是否包含正常结束的标记
+///
+/// - Claude: `message_stop` 事件
+/// - OpenAI Chat: `data: [DONE]`
+/// - Responses: `response.completed` 事件
+/// - Gemini: 带 `finishReason` 的数据块
+pub fn has_stream_end(sse: &[u8]) -> bool {
+    String::from_utf8_lossy(sse).lines().any(|line| {
+        let line = line.trim_end();
+        matches!(
+            line,
+            "event: message_stop" | "event: response.completed" | "data: [DONE]"
+        ) || line.strip_prefix("data:").is_some_and(|data| {
+            data.contains("\"type\":\"message_stop\"") || data.contains("\"finishReason\"")
+        })
+    })
+}
+
+/// 响应缓存写入器
+///
+/// 由 `RequestContext::response_cache_writer` 创建，持有写入缓存所需的全部上下文，
+/// 便于在流式响应结束时（脱离请求上下文）写入缓存。
+#[derive(Clone)]
+pub struct ResponseCacheWriter {
+    pub db: Arc<Database>,
+    pub cache_key: String,
+    pub app_type: String,
+    pub provider_id: String,
+    pub model: String,
+    pub ttl_seconds: u64,
+    pub max_bytes: u64,
+}
+
+impl ResponseCacheWriter {
+    /// 写入缓存，失败只记录日志
+    pub fn write(&self, status_code: u16, content_type: String, body: Vec<u8>, is_streaming: bool) {
+        let response = CachedResponse {
+            provider_id: self.provider_id.clone(),
+            status_code,
+            content_type,
+            body,
+            is_streaming,
+        };
+        match self.db.put_cached_response(
+            &self.cache_key,
+            &self.app_type,
+            &self.model,
+            &response,
+            self.ttl_seconds,
+            self.max_bytes,
+        ) {
+            Ok(()) => log::debug!(
+                "[ResponseCache] 已缓存 {} 响应 ({} bytes)",
+                self.app_type,
+                response.body.len()
+            ),
+            Err(e) => log::warn!("[ResponseCache] 写入缓存失败: {e}"),
+        }
+    }
+
+    /// 透传流式响应，完整结束后把 SSE 原文写入缓存
+    ///
+    /// 流中途出错、被客户端中断、超出容量上限或缺少结束事件（上游提前断开）时不写入
+    pub fn tee_stream(
+        self,
+        stream: impl Stream<Item = Result<Bytes, std::io::Error>> + Send + 'static,
+        status_code: u16,
+        content_type: String,
+    ) -> impl Stream<Item = Result<Bytes, std::io::Error>> + Send {
+        async_stream::stream! {
+            let mut buffer: Vec<u8> = Vec::new();
+            let mut complete = true;
+
+            tokio::pin!(stream);
+            while let Some(item) = stream.next().await {
+                match &item {
+                    Ok(bytes) if complete => {
+                        if (buffer.len() + bytes.len()) as u64 <= self.max_bytes {
+                            buffer.extend_from_slice(bytes);
+                        } else {
+                            complete = false;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(_) => complete = false,
+                }
+                yield item;
+            }
+
+            if complete && has_stream_end(&buffer) {
+                self.write(status_code, content_type, buffer, true);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_only_temperature_zero_is_cacheable() {
+        let body = json!({"model": "m", "temperature": 0, "messages": []});
+        assert!(cache_key("claude", "p1", "m", "/v1/messages", &body).is_some());
+
+        let body = json!({"model": "m", "temperature": 0.7, "messages": []});
+        assert!(cache_key("claude", "p1", "m", "/v1/messages", &body).is_none());
+
+        let body = json!({"model": "m", "messages": []});
+        assert!(cache_key("claude", "p1", "m", "/v1/messages", &body).is_none());
+
+        let body = json!({"contents": [], "generationConfig": {"temperature": 0.0}});
+        let generate = "/v1beta/models/gemini-2.5-pro:generateContent";
+        let stream = "/v1beta/models/gemini-2.5-pro:streamGenerateContent?alt=sse";
+        let key = cache_key("gemini", "p1", "gemini-2.5-pro", generate, &body);
+        assert!(key.is_some());
+        assert_ne!(
+            key,
+            cache_key("gemini", "p1", "gemini-2.5-pro", stream, &body)
+        );
+    }
+
+    #[test]
+    fn test_key_ignores_key_order_and_volatile_fields() {
+        let a = json!({
+            "model": "m",
+            "temperature": 0,
+            "messages": [{"role": "user", "content": "hi"}],
+            "metadata": {"user_id": "session-1"}
+        });
+        let b = json!({
+            "metadata": {"user_id": "session-2"},
+            "messages": [{"content": "hi", "role": "user"}],
+            "temperature": 0,
+            "model": "m"
+        });
+        assert_eq!(
+            cache_key("claude", "p1", "m", "/v1/messages", &a),
+            cache_key("claude", "p1", "m", "/v1/messages", &b)
+        );
+    }
+
+    #[test]
+    fn test_key_depends_on_provider_model_and_body() {
+        let body = json!({"model": "m", "temperature": 0, "messages": []});
+        let base = cache_key("claude", "p1", "m", "/v1/messages", &body);
+        assert_ne!(base, cache_key("claude", "p2", "m", "/v1/messages", &body));
+        assert_ne!(base, cache_key("claude", "p1", "m2", "/v1/messages", &body));
+        assert_ne!(base, cache_key("codex", "p1", "m", "/v1/messages", &body));
+
+        let streaming = json!({"model": "m", "temperature": 0, "messages": [], "stream": true});
+        assert_ne!(
+            base,
+            cache_key("claude", "p1", "m", "/v1/messages", &streaming)
+        );
+    }
+
+    #[test]
+    fn test_key_is_stable_sha256() {
+        // 固定摘要值：缓存键持久化在数据库中，不能随构建变化
+        let body = json!({"temperature": 0, "model": "m", "messages": []});
+        assert_eq!(
+            cache_key("claude", "p1", "m", "/v1/messages", &body).as_deref(),
+            Some("7e06f7ddc5afea0ae601889e2afe7d7821963cd76839416b2f8cadc238825be0")
+        );
+    }
+
+    #[test]
+    fn test_stream_end_markers() {
+        let claude = b"event: content_block_delta\ndata: {\"type\":\"content_block_delta\"}\n\nevent: message_stop\ndata: {\"type\":\"message_stop\"}\n\n";
+        assert!(has_stream_end(claude));
+        assert!(has_stream_end(
+            b"data: {\"choices\":[]}\n\ndata: [DONE]\n\n"
+        ));
+        assert!(has_stream_end(
+            b"event: response.completed\ndata: {\"type\":\"response.completed\"}\n\n"
+        ));
+        assert!(has_stream_end(
+            b"data: {\"candidates\":[{\"finishReason\":\"STOP\"}]}\r\n\r\n"
+        ));
+
+        // 上游提前断开：只有中间事件
+        let truncated = b"event: message_start\ndata: {\"type\":\"message_start\"}\n\nevent: content_block_delta\ndata: {\"delta\":{\"text\":\"message_stop\"}}\n\n";
+        assert!(!has_stream_end(truncated));
+        assert!(!has_stream_end(b""));
+    }
+}
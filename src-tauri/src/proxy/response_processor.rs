@@ -5,7 +5,9 @@
 use super::{
     handler_config::UsageParserConfig,
     handler_context::{RequestContext, StreamingTimeoutConfig},
+    payload_capture::PayloadCapture,
     server::ProxyState,
+    stream_recovery::create_recoverable_stream,
     usage::parser::TokenUsage,
     ProxyError,
 };
@@ -56,22 +58,49 @@ pub async fn handle_streaming(
         builder = builder.header(key, value);
     }
 
+    let capture = ctx.capture_with_response(&response);
+
     // 创建字节流
     let stream = response
         .bytes_stream()
         .map(|chunk| chunk.map_err(|e| std::io::Error::other(e.to_string())));
 
     // 创建使用量收集器
-    let usage_collector = create_usage_collector(ctx, state, status.as_u16(), parser_config);
+    let usage_collector =
+        create_usage_collector(ctx, state, status.as_u16(), parser_config, capture);
 
     // 获取流式超时配置
-    let timeout_config = ctx.streaming_timeout_config();
+    let mut timeout_config = ctx.streaming_timeout_config();
+
+    // 启用流式中断恢复时由恢复流按段处理超时
+    let stream: std::pin::Pin<Box<dyn Stream<Item = Result<Bytes, std::io::Error>> + Send>> =
+        match ctx.stream_recovery(state).filter(|_| status.is_success()) {
+            Some(recovery) => {
+                timeout_config = StreamingTimeoutConfig {
+                    first_byte_timeout: 0,
+                    idle_timeout: 0,
+                };
+                Box::pin(create_recoverable_stream(stream, recovery))
+            }
+            None => Box::pin(stream),
+        };
 
     // 创建带日志和超时的透传流
     let logged_stream =
         create_logged_passthrough_stream(stream, ctx.tag, Some(usage_collector), timeout_config);
 
-    let body = axum::body::Body::from_stream(logged_stream);
+    // 可缓存的成功响应在完整结束后写入本地响应缓存
+    let body = match ctx
+        .response_cache_writer(state)
+        .filter(|_| status.is_success())
+    {
+        Some(writer) => axum::body::Body::from_stream(writer.tee_stream(
+            logged_stream,
+            status.as_u16(),
+            "text/event-stream".to_string(),
+        )),
+        None => axum::body::Body::from_stream(logged_stream),
+    };
     builder.body(body).unwrap()
 }
 
@@ -84,6 +113,7 @@ pub async fn handle_non_streaming(
 ) -> Result<Response, ProxyError> {
     let response_headers = response.headers().clone();
     let status = response.status();
+    let capture = ctx.capture_with_response(&response);
 
     // 读取响应体
     let body_bytes = response.bytes().await.map_err(|e| {
@@ -127,6 +157,26 @@ pub async fn handle_non_streaming(
 
     log::info!("[{}] ====== 请求结束 ======", ctx.tag);
 
+    if let Some(capture) = capture {
+        capture.record(
+            &ctx.provider.id,
+            status.as_u16(),
+            Some(String::from_utf8_lossy(&body_bytes).to_string()),
+            false,
+        );
+    }
+
+    if status.is_success() {
+        if let Some(writer) = ctx.response_cache_writer(state) {
+            let content_type = response_headers
+                .get("content-type")
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            writer.write(status.as_u16(), content_type, body_bytes.to_vec(), false);
+        }
+    }
+
     // 构建响应
     let mut builder = axum::response::Response::builder().status(status);
     for (key, value) in response_headers.iter() {
@@ -229,14 +279,21 @@ impl SseUsageCollector {
 // ============================================================================
 
 /// 创建使用量收集器
-fn create_usage_collector(
+///
+/// 传入载荷捕获器时，流结束后同时保存收集到的 SSE 事件
+pub fn create_usage_collector(
     ctx: &RequestContext,
     state: &ProxyState,
     status_code: u16,
     parser_config: &UsageParserConfig,
+    capture: Option<PayloadCapture>,
 ) -> SseUsageCollector {
     let state = state.clone();
     let provider_id = ctx.provider.id.clone();
+    let request_id = ctx.request_id.clone();
+    let session_id = ctx.session_id.clone();
+    let api_key = ctx.api_key.clone();
+    let stream_recoveries = ctx.stream_recoveries.clone();
     let request_model = ctx.request_model.clone();
     let app_type_str = parser_config.app_type_str;
     let tag = ctx.tag;
@@ -245,16 +302,27 @@ fn create_usage_collector(
     let model_extractor = parser_config.model_extractor;
 
     SseUsageCollector::new(start_time, move |events, first_token_ms| {
+        if let Some(capture) = &capture {
+            capture.record_stream_events(&provider_id, status_code, &events);
+        }
+
         if let Some(usage) = stream_parser(&events) {
             let model = model_extractor(&events, &request_model);
             let latency_ms = start_time.elapsed().as_millis() as u64;
 
             let state = state.clone();
             let provider_id = provider_id.clone();
+            let request_id = request_id.clone();
+            let session_id = session_id.clone();
+            let api_key = api_key.clone();
+            let stream_recoveries = stream_recoveries.load(Ordering::Relaxed);
 
             tokio::spawn(async move {
                 log_usage_internal(
                     &state,
+                    request_id,
+                    session_id,
+                    api_key,
                     &provider_id,
                     app_type_str,
                     &model,
@@ -263,6 +331,7 @@ fn create_usage_collector(
                     first_token_ms,
                     true, // is_streaming
                     status_code,
+                    stream_recoveries,
                 )
                 .await;
             });
@@ -282,6 +351,9 @@ fn spawn_log_usage(
     is_streaming: bool,
 ) {
     let state = state.clone();
+    let request_id = ctx.request_id.clone();
+    let session_id = ctx.session_id.clone();
+    let api_key = ctx.api_key.clone();
     let provider_id = ctx.provider.id.clone();
     let app_type_str = ctx.app_type_str.to_string();
     let model = model.to_string();
@@ -290,6 +362,9 @@ fn spawn_log_usage(
     tokio::spawn(async move {
         log_usage_internal(
             &state,
+            request_id,
+            session_id,
+            api_key,
             &provider_id,
             &app_type_str,
             &model,
@@ -298,6 +373,7 @@ fn spawn_log_usage(
             None,
             is_streaming,
             status_code,
+            0,
         )
         .await;
     });
@@ -307,6 +383,9 @@ fn spawn_log_usage(
 #[allow(clippy::too_many_arguments)]
 async fn log_usage_internal(
     state: &ProxyState,
+    request_id: String,
+    session_id: Option<String>,
+    api_key: Option<String>,
     provider_id: &str,
     app_type: &str,
     model: &str,
@@ -315,10 +394,14 @@ async fn log_usage_internal(
     first_token_ms: Option<u64>,
     is_streaming: bool,
     status_code: u16,
+    stream_recoveries: u32,
 ) {
     use super::usage::logger::UsageLogger;
 
-    let logger = UsageLogger::new(&state.db);
+    let logger = UsageLogger::new(&state.db)
+        .with_metrics(&state.metrics)
+        .with_api_key(api_key.as_deref())
+        .with_stream_recoveries(stream_recoveries);
 
     // 获取 provider 的 cost_multiplier
     let multiplier = match state.db.get_provider_by_id(provider_id, app_type) {
@@ -336,8 +419,6 @@ async fn log_usage_internal(
         _ => Decimal::from(1),
     };
 
-    let request_id = uuid::Uuid::new_v4().to_string();
-
     if let Err(e) = logger.log_with_calculation(
         request_id,
         provider_id.to_string(),
@@ -348,7 +429,7 @@ async fn log_usage_internal(
         latency_ms,
         first_token_ms,
         status_code,
-        None,
+        session_id,
         None, // provider_type
         is_streaming,
     ) {
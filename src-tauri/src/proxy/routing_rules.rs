@@ -0,0 +1,269 @@
+//! 模型路由规则
+//!
+//! 根据请求中的模型名称（以及是否启用 thinking）把请求路由到指定的供应商链，
+//! 例如 `*haiku*` 走便宜的中转站、`*opus*` 走官方 API。
+//! 规则按 sort_index 顺序匹配，首条命中的规则生效。
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{LazyLock, RwLock};
+
+/// 编译缓存上限，超出后整体清空，避免规则频繁修改时无界增长
+const PATTERN_CACHE_CAPACITY: usize = 256;
+
+/// 已编译的匹配模式 - key: (匹配方式, 模式)
+///
+/// 规则每次请求都会从数据库重新加载，按模式文本缓存可避免逐请求重复编译正则。
+static PATTERN_CACHE: LazyLock<RwLock<HashMap<(RuleMatchType, String), Regex>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// 模型匹配方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleMatchType {
+    /// 通配符匹配（`*` 任意字符，`?` 单个字符），不区分大小写
+    #[default]
+    Glob,
+    /// 正则表达式匹配
+    Regex,
+}
+
+impl RuleMatchType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleMatchType::Glob => "glob",
+            RuleMatchType::Regex => "regex",
+        }
+    }
+}
+
+impl std::str::FromStr for RuleMatchType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "glob" => Ok(RuleMatchType::Glob),
+            "regex" => Ok(RuleMatchType::Regex),
+            other => Err(format!("未知的匹配方式: {other}")),
+        }
+    }
+}
+
+/// 模型路由规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutingRule {
+    /// 规则 ID（为空时由后端生成）
+    #[serde(default)]
+    pub id: String,
+    /// 应用类型 (claude/codex/gemini)
+    pub app_type: String,
+    /// 规则名称
+    #[serde(default)]
+    pub name: String,
+    /// 模型匹配模式
+    pub model_pattern: String,
+    /// 匹配方式
+    #[serde(default)]
+    pub match_type: RuleMatchType,
+    /// thinking 条件：None 表示不限，Some(true) 仅匹配启用 thinking 的请求
+    #[serde(default)]
+    pub thinking: Option<bool>,
+    /// 该规则的供应商链（按顺序故障转移）
+    #[serde(default)]
+    pub provider_ids: Vec<String>,
+    /// 是否启用
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// 匹配顺序（越小越先匹配）
+    #[serde(default)]
+    pub sort_index: i64,
+    /// 创建时间（Unix 秒）
+    #[serde(default)]
+    pub created_at: i64,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl RoutingRule {
+    /// 编译模型匹配模式（命中缓存时直接复用）
+    pub fn compile(&self) -> Result<Regex, String> {
+        compile_pattern(self.match_type, &self.model_pattern)
+    }
+
+    /// 校验规则是否可用
+    pub fn validate(&self) -> Result<(), String> {
+        if self.model_pattern.trim().is_empty() {
+            return Err("模型匹配模式不能为空".to_string());
+        }
+        if self.provider_ids.is_empty() {
+            return Err("路由规则至少需要一个供应商".to_string());
+        }
+        self.compile().map(|_| ())
+    }
+
+    /// 判断规则是否命中该请求
+    pub fn matches(&self, model: &str, thinking: bool) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        if let Some(expected) = self.thinking {
+            if expected != thinking {
+                return false;
+            }
+        }
+        match self.compile() {
+            Ok(re) => re.is_match(model),
+            Err(e) => {
+                log::warn!("[RoutingRule] 规则 {} 无法编译，已跳过: {e}", self.id);
+                false
+            }
+        }
+    }
+}
+
+/// 编译匹配模式，结果按 (匹配方式, 模式) 缓存；编译失败不缓存
+pub fn compile_pattern(match_type: RuleMatchType, pattern: &str) -> Result<Regex, String> {
+    let key = (match_type, pattern.to_string());
+    if let Some(re) = PATTERN_CACHE
+        .read()
+        .ok()
+        .and_then(|cache| cache.get(&key).cloned())
+    {
+        return Ok(re);
+    }
+
+    let re = match match_type {
+        RuleMatchType::Glob => compile_glob(pattern)?,
+        RuleMatchType::Regex => {
+            Regex::new(pattern).map_err(|e| format!("无效的正则表达式 '{pattern}': {e}"))?
+        }
+    };
+
+    if let Ok(mut cache) = PATTERN_CACHE.write() {
+        if cache.len() >= PATTERN_CACHE_CAPACITY {
+            cache.clear();
+        }
+        cache.insert(key, re.clone());
+    }
+    Ok(re)
+}
+
+/// 编译通配符模式（`*` 任意字符，`?` 单个字符，不区分大小写）
+pub fn compile_glob(pattern: &str) -> Result<Regex, String> {
+    let escaped = regex::escape(pattern)
+        .replace("\\*", ".*")
+        .replace("\\?", ".");
+    RegexBuilder::new(&format!("^{escaped}$"))
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| format!("无效的通配符模式 '{pattern}': {e}"))
+}
+
+/// 在规则列表中查找首条命中的规则（调用方保证按 sort_index 排序）
+pub fn find_matching_rule<'a>(
+    rules: &'a [RoutingRule],
+    model: &str,
+    thinking: bool,
+) -> Option<&'a RoutingRule> {
+    rules.iter().find(|rule| rule.matches(model, thinking))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, match_type: RuleMatchType, thinking: Option<bool>) -> RoutingRule {
+        RoutingRule {
+            id: pattern.to_string(),
+            app_type: "claude".to_string(),
+            name: String::new(),
+            model_pattern: pattern.to_string(),
+            match_type,
+            thinking,
+            provider_ids: vec!["p".to_string()],
+            enabled: true,
+            sort_index: 0,
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_glob_matching_is_case_insensitive() {
+        let r = rule("*haiku*", RuleMatchType::Glob, None);
+        assert!(r.matches("claude-3-5-haiku-20241022", false));
+        assert!(r.matches("Claude-Haiku-4", false));
+        assert!(!r.matches("claude-sonnet-4", false));
+
+        let r = rule("claude-?-opus", RuleMatchType::Glob, None);
+        assert!(r.matches("claude-3-opus", false));
+        assert!(!r.matches("claude-35-opus", false));
+    }
+
+    #[test]
+    fn test_glob_escapes_regex_metacharacters() {
+        let r = rule("gpt-4.1", RuleMatchType::Glob, None);
+        assert!(r.matches("gpt-4.1", false));
+        assert!(!r.matches("gpt-4x1", false));
+    }
+
+    #[test]
+    fn test_regex_matching() {
+        let r = rule(r"^claude-(opus|sonnet)-4", RuleMatchType::Regex, None);
+        assert!(r.matches("claude-opus-4-20250514", false));
+        assert!(r.matches("claude-sonnet-4-5", false));
+        assert!(!r.matches("claude-3-5-haiku", false));
+    }
+
+    #[test]
+    fn test_thinking_predicate() {
+        let r = rule("*", RuleMatchType::Glob, Some(true));
+        assert!(r.matches("any", true));
+        assert!(!r.matches("any", false));
+
+        let r = rule("*", RuleMatchType::Glob, Some(false));
+        assert!(!r.matches("any", true));
+        assert!(r.matches("any", false));
+    }
+
+    #[test]
+    fn test_disabled_and_invalid_rules_never_match() {
+        let mut r = rule("*", RuleMatchType::Glob, None);
+        r.enabled = false;
+        assert!(!r.matches("any", false));
+
+        let r = rule("(", RuleMatchType::Regex, None);
+        assert!(r.validate().is_err());
+        assert!(!r.matches("(", false));
+    }
+
+    #[test]
+    fn test_find_matching_rule_uses_first_hit() {
+        let rules = vec![
+            rule("*opus*", RuleMatchType::Glob, Some(true)),
+            rule("*opus*", RuleMatchType::Glob, None),
+            rule("*", RuleMatchType::Glob, None),
+        ];
+        let hit = find_matching_rule(&rules, "claude-opus-4", false).unwrap();
+        assert_eq!(hit.model_pattern, "*opus*");
+        assert_eq!(hit.thinking, None);
+
+        let hit = find_matching_rule(&rules, "claude-sonnet-4", false).unwrap();
+        assert_eq!(hit.model_pattern, "*");
+    }
+
+    #[test]
+    fn test_compiled_patterns_are_cached() {
+        let r = rule("*cache-probe*", RuleMatchType::Glob, None);
+        assert!(r.matches("x-cache-probe-y", false));
+
+        let key = (RuleMatchType::Glob, "*cache-probe*".to_string());
+        assert!(PATTERN_CACHE.read().unwrap().contains_key(&key));
+        // 同一模式文本在不同匹配方式下互不影响
+        let key = (RuleMatchType::Regex, "*cache-probe*".to_string());
+        assert!(!PATTERN_CACHE.read().unwrap().contains_key(&key));
+    }
+}
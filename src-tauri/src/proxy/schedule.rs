@@ -0,0 +1,551 @@
+//! 供应商定时切换
+//!
+//! 按 cron 规则（`分 时 日 月 周`，本地时间）在指定时刻切换当前供应商或重排故障转移队列，
+//! 例如工作日 9:00 切到官方 API、19:00 切回便宜的中转站。
+//! 某一时刻生效的规则为该时刻之前最近一次触发的规则（最多回溯 `LOOKBACK_MINUTES`）。
+
+use crate::database::Database;
+use crate::proxy::failover_switch::FailoverSwitchManager;
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Local, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// 规则回溯/前瞻的最长时间（32 天，覆盖按月触发的规则）
+const LOOKBACK_MINUTES: i64 = 32 * 24 * 60;
+
+/// 调度检查周期
+const SCHEDULE_TICK: Duration = Duration::from_secs(30);
+
+/// 定时规则触发时执行的动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleAction {
+    /// 切换当前供应商为 provider_ids 中的第一个
+    #[default]
+    Switch,
+    /// 把 provider_ids 按顺序排到故障转移队列最前面
+    ReorderQueue,
+}
+
+impl ScheduleAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScheduleAction::Switch => "switch",
+            ScheduleAction::ReorderQueue => "reorder_queue",
+        }
+    }
+}
+
+impl std::str::FromStr for ScheduleAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "switch" => Ok(ScheduleAction::Switch),
+            "reorder_queue" => Ok(ScheduleAction::ReorderQueue),
+            other => Err(format!("未知的定时动作: {other}")),
+        }
+    }
+}
+
+/// 供应商定时切换规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderSchedule {
+    /// 规则 ID（为空时由后端生成）
+    #[serde(default)]
+    pub id: String,
+    /// 应用类型 (claude/codex/gemini)
+    pub app_type: String,
+    /// 规则名称
+    #[serde(default)]
+    pub name: String,
+    /// cron 表达式（分 时 日 月 周），如 `0 9 * * 1-5`
+    pub cron: String,
+    /// 触发动作
+    #[serde(default)]
+    pub action: ScheduleAction,
+    /// 目标供应商（切换时取第一个；重排队列时按顺序排到最前）
+    #[serde(default)]
+    pub provider_ids: Vec<String>,
+    /// 是否启用
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// 排序（同一时刻触发时靠前的规则生效）
+    #[serde(default)]
+    pub sort_index: i64,
+    /// 创建时间（Unix 秒）
+    #[serde(default)]
+    pub created_at: i64,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl ProviderSchedule {
+    /// 校验规则是否可用
+    pub fn validate(&self) -> Result<(), String> {
+        if self.provider_ids.is_empty() {
+            return Err("定时规则至少需要一个供应商".to_string());
+        }
+        self.cron.parse::<CronExpr>().map(|_| ())
+    }
+}
+
+/// 某一时刻的定时规则预览
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchedulePreview {
+    /// 预览时刻（Unix 秒）
+    pub at: i64,
+    /// 该时刻生效的规则，None 表示没有规则生效（保持手动设置）
+    pub active: Option<ProviderSchedule>,
+    /// 生效规则最近一次触发的时间（Unix 秒）
+    pub triggered_at: Option<i64>,
+    /// 下一次有规则触发的时间（Unix 秒）
+    pub next_change_at: Option<i64>,
+    /// 下一次触发的规则 ID
+    pub next_schedule_id: Option<String>,
+}
+
+/// 解析后的 cron 表达式（每个字段为允许取值的位图）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronExpr {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// 日、周字段是否为 `*`（两者都受限时任一命中即可，与标准 cron 一致）
+    days_any: bool,
+    weekdays_any: bool,
+}
+
+impl std::str::FromStr for CronExpr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("cron 表达式需要 5 个字段（分 时 日 月 周）: '{s}'"));
+        }
+
+        let mut weekdays = parse_field(fields[4], 0, 7, "周")?;
+        // 7 与 0 都表示周日
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            minutes: parse_field(fields[0], 0, 59, "分")?,
+            hours: parse_field(fields[1], 0, 23, "时")?,
+            days: parse_field(fields[2], 1, 31, "日")?,
+            months: parse_field(fields[3], 1, 12, "月")?,
+            weekdays,
+            days_any: fields[2] == "*",
+            weekdays_any: fields[4] == "*",
+        })
+    }
+}
+
+/// 解析单个字段：支持 `*`、`a`、`a-b`、`*/n`、`a-b/n` 及逗号分隔的列表
+fn parse_field(field: &str, min: u32, max: u32, name: &str) -> Result<u64, String> {
+    let invalid = || format!("cron 的{name}字段无效: '{field}'");
+    let mut mask = 0u64;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(invalid());
+        }
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (
+                a.parse().map_err(|_| invalid())?,
+                b.parse().map_err(|_| invalid())?,
+            )
+        } else {
+            let value: u32 = range.parse().map_err(|_| invalid())?;
+            // `a/n` 表示从 a 开始到最大值
+            (value, if part.contains('/') { max } else { value })
+        };
+        if start < min || end > max || start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+
+    Ok(mask)
+}
+
+impl CronExpr {
+    /// 判断某一分钟是否触发
+    pub fn matches<Tz: TimeZone>(&self, t: &DateTime<Tz>) -> bool {
+        let hit = |mask: u64, value: u32| mask & (1 << value) != 0;
+        if !hit(self.minutes, t.minute())
+            || !hit(self.hours, t.hour())
+            || !hit(self.months, t.month())
+        {
+            return false;
+        }
+        let day = hit(self.days, t.day());
+        let weekday = hit(self.weekdays, t.weekday().num_days_from_sunday());
+        match (self.days_any, self.weekdays_any) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        }
+    }
+
+    /// `at` 之前（含当前分钟）最近一次触发时间
+    pub fn last_trigger<Tz: TimeZone>(&self, at: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let start = truncate_to_minute(at);
+        (0..=LOOKBACK_MINUTES)
+            .map(|i| start.clone() - ChronoDuration::minutes(i))
+            .find(|t| self.matches(t))
+    }
+
+    /// `at` 之后（不含当前分钟）下一次触发时间
+    pub fn next_trigger<Tz: TimeZone>(&self, at: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let start = truncate_to_minute(at);
+        (1..=LOOKBACK_MINUTES)
+            .map(|i| start.clone() + ChronoDuration::minutes(i))
+            .find(|t| self.matches(t))
+    }
+}
+
+fn truncate_to_minute<Tz: TimeZone>(t: &DateTime<Tz>) -> DateTime<Tz> {
+    t.clone()
+        - ChronoDuration::seconds(t.second() as i64)
+        - ChronoDuration::nanoseconds(t.nanosecond() as i64)
+}
+
+/// 计算 `at` 时刻生效的规则（调用方保证按 sort_index 排序）
+pub fn preview_schedules<Tz: TimeZone>(
+    schedules: &[ProviderSchedule],
+    at: &DateTime<Tz>,
+) -> SchedulePreview {
+    let mut active: Option<(&ProviderSchedule, DateTime<Tz>)> = None;
+    let mut next: Option<(&ProviderSchedule, DateTime<Tz>)> = None;
+
+    for schedule in schedules.iter().filter(|s| s.enabled) {
+        let cron = match schedule.cron.parse::<CronExpr>() {
+            Ok(cron) => cron,
+            Err(e) => {
+                log::warn!("[Schedule] 规则 {} 无法解析，已跳过: {e}", schedule.id);
+                continue;
+            }
+        };
+        if let Some(t) = cron.last_trigger(at) {
+            if active.as_ref().is_none_or(|(_, best)| t > *best) {
+                active = Some((schedule, t));
+            }
+        }
+        if let Some(t) = cron.next_trigger(at) {
+            if next.as_ref().is_none_or(|(_, best)| t < *best) {
+                next = Some((schedule, t));
+            }
+        }
+    }
+
+    SchedulePreview {
+        at: at.timestamp(),
+        triggered_at: active.as_ref().map(|(_, t)| t.timestamp()),
+        active: active.map(|(s, _)| s.clone()),
+        next_change_at: next.as_ref().map(|(_, t)| t.timestamp()),
+        next_schedule_id: next.map(|(s, _)| s.id.clone()),
+    }
+}
+
+/// 定时切换执行器（随代理服务器启动，仅对已接管的应用生效）
+pub struct ProviderScheduler {
+    db: Arc<Database>,
+    failover_manager: Arc<FailoverSwitchManager>,
+    app_handle: Option<tauri::AppHandle>,
+    /// 每个应用最近一次已处理的触发（规则 ID, 触发时间），持久化记录的内存缓存
+    applied: HashMap<String, (String, i64)>,
+    /// 调度器启动时间（Unix 秒，按分钟取整）
+    started_at: i64,
+}
+
+impl ProviderScheduler {
+    pub fn new(
+        db: Arc<Database>,
+        failover_manager: Arc<FailoverSwitchManager>,
+        app_handle: Option<tauri::AppHandle>,
+    ) -> Self {
+        Self {
+            db,
+            failover_manager,
+            app_handle,
+            applied: HashMap::new(),
+            started_at: Local::now().timestamp() / 60 * 60,
+        }
+    }
+
+    /// 启动后台调度任务（代理停止时 abort 返回的句柄）
+    ///
+    /// 每次触发只执行一次（已处理的触发持久化在 settings 表），重启代理或重新接管不会重复执行，
+    /// 因此不会覆盖用户在触发之后手动选择的供应商。
+    pub fn spawn(mut self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(SCHEDULE_TICK);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                for app_type in ["claude", "codex", "gemini"] {
+                    self.run_app(app_type).await;
+                }
+            }
+        })
+    }
+
+    async fn run_app(&mut self, app_type: &str) {
+        // 未接管的应用不切换（Live 配置未指向代理，仅改数据库会导致不一致）
+        let taken_over = matches!(
+            self.db.get_proxy_config_for_app(app_type).await,
+            Ok(config) if config.enabled
+        );
+
+        let schedules = match self.db.get_provider_schedules(app_type) {
+            Ok(schedules) => schedules,
+            Err(e) => {
+                log::warn!("[Schedule] 读取 {app_type} 的定时规则失败: {e}");
+                return;
+            }
+        };
+        let preview = preview_schedules(&schedules, &Local::now());
+        let (Some(schedule), Some(triggered_at)) = (preview.active, preview.triggered_at) else {
+            return;
+        };
+
+        let mark = (schedule.id.clone(), triggered_at);
+        let last = self.last_applied(app_type);
+        if last.as_ref() == Some(&mark) {
+            return;
+        }
+
+        if !should_apply(last.is_some(), triggered_at, self.started_at, taken_over) {
+            self.mark_applied(app_type, mark);
+            return;
+        }
+
+        match self.apply(&schedule).await {
+            Ok(()) => self.mark_applied(app_type, mark),
+            Err(e) => log::warn!("[Schedule] 执行规则 '{}' 失败: {e}", schedule.name),
+        }
+    }
+
+    fn last_applied(&mut self, app_type: &str) -> Option<(String, i64)> {
+        if let Some(mark) = self.applied.get(app_type) {
+            return Some(mark.clone());
+        }
+        let mark = match self.db.get_schedule_last_applied(app_type) {
+            Ok(mark) => mark?,
+            Err(e) => {
+                log::warn!("[Schedule] 读取 {app_type} 的触发记录失败: {e}");
+                return None;
+            }
+        };
+        self.applied.insert(app_type.to_string(), mark.clone());
+        Some(mark)
+    }
+
+    fn mark_applied(&mut self, app_type: &str, mark: (String, i64)) {
+        if let Err(e) = self.db.set_schedule_last_applied(app_type, &mark) {
+            log::warn!("[Schedule] 保存 {app_type} 的触发记录失败: {e}");
+        }
+        self.applied.insert(app_type.to_string(), mark);
+    }
+
+    async fn apply(&self, schedule: &ProviderSchedule) -> Result<(), String> {
+        let app_type = schedule.app_type.as_str();
+        match schedule.action {
+            ScheduleAction::Switch => {
+                let provider = schedule
+                    .provider_ids
+                    .iter()
+                    .find_map(|id| self.db.get_provider_by_id(id, app_type).ok().flatten())
+                    .ok_or_else(|| "规则中的供应商均不存在".to_string())?;
+
+                let current = self
+                    .db
+                    .get_current_provider(app_type)
+                    .map_err(|e| e.to_string())?;
+                if current.as_deref() == Some(provider.id.as_str()) {
+                    return Ok(());
+                }
+
+                log::info!(
+                    "[Schedule] 规则 '{}' 触发，切换 {app_type} -> {} ({})",
+                    schedule.name,
+                    provider.name,
+                    provider.id
+                );
+                self.failover_manager
+                    .try_switch_from(
+                        self.app_handle.as_ref(),
+                        app_type,
+                        &provider.id,
+                        &provider.name,
+                        "schedule",
+                    )
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| e.to_string())
+            }
+            ScheduleAction::ReorderQueue => {
+                let changed = self
+                    .db
+                    .reorder_failover_queue(app_type, &schedule.provider_ids)
+                    .map_err(|e| e.to_string())?;
+                if changed {
+                    log::info!(
+                        "[Schedule] 规则 '{}' 触发，重排 {app_type} 故障转移队列: {:?}",
+                        schedule.name,
+                        schedule.provider_ids
+                    );
+                    self.failover_manager
+                        .notify_providers_changed(self.app_handle.as_ref(), app_type, "schedule")
+                        .await;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// 新的触发是否需要执行（不执行的触发也会记为已处理）
+///
+/// - 未接管时只记录，重新接管后不补执行
+/// - 没有任何触发记录（首次运行）时，只执行调度器启动之后的触发
+fn should_apply(has_record: bool, triggered_at: i64, started_at: i64, taken_over: bool) -> bool {
+    taken_over && (has_record || triggered_at >= started_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn schedule(id: &str, cron: &str, sort_index: i64) -> ProviderSchedule {
+        ProviderSchedule {
+            id: id.to_string(),
+            app_type: "claude".to_string(),
+            name: id.to_string(),
+            cron: cron.to_string(),
+            action: ScheduleAction::Switch,
+            provider_ids: vec![id.to_string()],
+            enabled: true,
+            sort_index,
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_parse_cron_fields() {
+        let cron: CronExpr = "*/15 9-17 * * 1-5".parse().unwrap();
+        // 2024-01-01 是周一
+        assert!(cron.matches(&at("2024-01-01T09:30:00Z")));
+        assert!(!cron.matches(&at("2024-01-01T09:31:00Z")));
+        assert!(!cron.matches(&at("2024-01-01T18:00:00Z")));
+        assert!(!cron.matches(&at("2024-01-06T10:00:00Z")));
+
+        let sunday: CronExpr = "0 0 * * 7".parse().unwrap();
+        assert!(sunday.matches(&at("2024-01-07T00:00:00Z")));
+
+        assert!("0 9 * *".parse::<CronExpr>().is_err());
+        assert!("60 * * * *".parse::<CronExpr>().is_err());
+        assert!("*/0 * * * *".parse::<CronExpr>().is_err());
+        assert!("0 5-3 * * *".parse::<CronExpr>().is_err());
+    }
+
+    #[test]
+    fn test_day_and_weekday_are_ored_when_both_restricted() {
+        let cron: CronExpr = "0 0 1 * 1".parse().unwrap();
+        assert!(cron.matches(&at("2024-02-01T00:00:00Z"))); // 1 号（周四）
+        assert!(cron.matches(&at("2024-02-05T00:00:00Z"))); // 周一
+        assert!(!cron.matches(&at("2024-02-06T00:00:00Z")));
+    }
+
+    #[test]
+    fn test_preview_picks_latest_trigger() {
+        let schedules = vec![
+            schedule("office", "0 9 * * 1-5", 0),
+            schedule("offpeak", "0 19 * * *", 1),
+        ];
+
+        // 周一 10:00：9 点的规则最近触发
+        let preview = preview_schedules(&schedules, &at("2024-01-01T10:00:00Z"));
+        assert_eq!(preview.active.unwrap().id, "office");
+        assert_eq!(
+            preview.triggered_at,
+            Some(at("2024-01-01T09:00:00Z").timestamp())
+        );
+        assert_eq!(
+            preview.next_change_at,
+            Some(at("2024-01-01T19:00:00Z").timestamp())
+        );
+        assert_eq!(preview.next_schedule_id.as_deref(), Some("offpeak"));
+
+        // 周六 10:00：工作日规则不触发，沿用周五 19 点的规则
+        let preview = preview_schedules(&schedules, &at("2024-01-06T10:00:00Z"));
+        assert_eq!(preview.active.unwrap().id, "offpeak");
+
+        // 触发的那一分钟内即生效
+        let preview = preview_schedules(&schedules, &at("2024-01-01T19:00:30Z"));
+        assert_eq!(preview.active.unwrap().id, "offpeak");
+    }
+
+    #[test]
+    fn test_preview_ties_and_disabled_rules() {
+        let mut schedules = vec![
+            schedule("first", "0 9 * * *", 0),
+            schedule("second", "0 9 * * *", 1),
+        ];
+        let now = at("2024-01-01T10:00:00Z");
+        assert_eq!(
+            preview_schedules(&schedules, &now).active.unwrap().id,
+            "first"
+        );
+
+        schedules[0].enabled = false;
+        assert_eq!(
+            preview_schedules(&schedules, &now).active.unwrap().id,
+            "second"
+        );
+
+        schedules[1].enabled = false;
+        assert!(preview_schedules(&schedules, &now).active.is_none());
+    }
+
+    #[test]
+    fn test_old_triggers_are_not_replayed() {
+        let started_at = at("2024-01-01T10:00:00Z").timestamp();
+        let before_start = at("2024-01-01T09:00:00Z").timestamp();
+        let after_start = at("2024-01-01T19:00:00Z").timestamp();
+
+        // 首次运行：启动前的触发只记录不执行
+        assert!(!should_apply(false, before_start, started_at, true));
+        assert!(should_apply(false, after_start, started_at, true));
+        // 已有记录：新的触发照常执行
+        assert!(should_apply(true, before_start, started_at, true));
+        // 未接管：只记录，重新接管后不补执行
+        assert!(!should_apply(true, after_start, started_at, false));
+    }
+}
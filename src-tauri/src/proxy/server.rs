@@ -3,15 +3,26 @@
 //! 基于Axum的HTTP服务器，处理代理请求
 
 use super::{
-    failover_switch::FailoverSwitchManager, handlers, provider_router::ProviderRouter, types::*,
+    failover_switch::FailoverSwitchManager,
+    handlers,
+    health::HealthChecker,
+    inbound_auth,
+    key_pool::PoolKeyStatus,
+    listener::{resolve_unix_socket_path, ProxyListener},
+    metrics::ProxyMetrics,
+    provider_router::ProviderRouter,
+    providers::AuthInfo,
+    schedule::ProviderScheduler,
+    types::*,
     ProxyError,
 };
 use crate::database::Database;
+use crate::provider::Provider;
 use axum::{
+    middleware,
     routing::{get, post},
     Router,
 };
-use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::{oneshot, RwLock};
 use tokio::task::JoinHandle;
@@ -32,6 +43,8 @@ pub struct ProxyState {
     pub app_handle: Option<tauri::AppHandle>,
     /// 故障转移切换管理器
     pub failover_manager: Arc<FailoverSwitchManager>,
+    /// Prometheus 指标（`/metrics`）
+    pub metrics: Arc<ProxyMetrics>,
 }
 
 /// 代理HTTP服务器
@@ -41,6 +54,10 @@ pub struct ProxyServer {
     shutdown_tx: Arc<RwLock<Option<oneshot::Sender<()>>>>,
     /// 服务器任务句柄，用于等待服务器实际关闭
     server_handle: Arc<RwLock<Option<JoinHandle<()>>>>,
+    /// 后台健康探测任务句柄
+    health_handle: Arc<RwLock<Option<JoinHandle<()>>>>,
+    /// 定时切换任务句柄
+    schedule_handle: Arc<RwLock<Option<JoinHandle<()>>>>,
 }
 
 impl ProxyServer {
@@ -50,7 +67,8 @@ impl ProxyServer {
         app_handle: Option<tauri::AppHandle>,
     ) -> Self {
         // 创建共享的 ProviderRouter（熔断器状态将跨所有请求保持）
-        let provider_router = Arc::new(ProviderRouter::new(db.clone()));
+        let provider_router =
+            Arc::new(ProviderRouter::new(db.clone()).with_app_handle(app_handle.clone()));
         // 创建故障转移切换管理器
         let failover_manager = Arc::new(FailoverSwitchManager::new(db.clone()));
 
@@ -63,6 +81,7 @@ impl ProxyServer {
             provider_router,
             app_handle,
             failover_manager,
+            metrics: Arc::new(ProxyMetrics::new()),
         };
 
         Self {
@@ -70,6 +89,8 @@ impl ProxyServer {
             state,
             shutdown_tx: Arc::new(RwLock::new(None)),
             server_handle: Arc::new(RwLock::new(None)),
+            health_handle: Arc::new(RwLock::new(None)),
+            schedule_handle: Arc::new(RwLock::new(None)),
         }
     }
 
@@ -79,23 +100,37 @@ impl ProxyServer {
             return Err(ProxyError::AlreadyRunning);
         }
 
-        let addr: SocketAddr =
-            format!("{}:{}", self.config.listen_address, self.config.listen_port)
-                .parse()
-                .map_err(|e| ProxyError::BindFailed(format!("无效的地址: {e}")))?;
-
         // 创建关闭通道
         let (shutdown_tx, shutdown_rx) = oneshot::channel();
 
         // 构建路由
         let app = self.build_router();
 
-        // 绑定监听器
-        let listener = tokio::net::TcpListener::bind(&addr)
-            .await
-            .map_err(|e| ProxyError::BindFailed(e.to_string()))?;
+        // 绑定监听器（TCP / TLS / Unix 域套接字）
+        let listener = ProxyListener::bind(&self.config).await?;
+
+        log::info!("代理服务器启动于 {}", listener.describe());
+
+        if self.config.listen_mode != ListenMode::Unix
+            && !inbound_auth::is_loopback_address(&self.config.listen_address)
+        {
+            let auth_enabled = self
+                .state
+                .db
+                .get_global_proxy_config()
+                .await
+                .map(|config| config.auth_enabled)
+                .unwrap_or(false);
+            if !auth_enabled {
+                log::warn!(
+                    "代理监听在非回环地址 {} 且未启用入站认证，局域网内的客户端可直接使用已配置的 API Key",
+                    self.config.listen_address
+                );
+            }
+        }
 
-        log::info!("代理服务器启动于 {addr}");
+        // 恢复上次运行时的熔断器状态
+        self.state.provider_router.restore_circuit_breakers().await;
 
         // 保存关闭句柄
         *self.shutdown_tx.write().await = Some(shutdown_tx);
@@ -103,8 +138,9 @@ impl ProxyServer {
         // 更新状态
         let mut status = self.state.status.write().await;
         status.running = true;
-        status.address = self.config.listen_address.clone();
-        status.port = self.config.listen_port;
+        let (address, port) = self.listen_endpoint();
+        status.address = address;
+        status.port = port;
         drop(status);
 
         // 记录启动时间
@@ -113,12 +149,7 @@ impl ProxyServer {
         // 启动服务器
         let state = self.state.clone();
         let handle = tokio::spawn(async move {
-            axum::serve(listener, app)
-                .with_graceful_shutdown(async {
-                    shutdown_rx.await.ok();
-                })
-                .await
-                .ok();
+            listener.serve(app, shutdown_rx).await;
 
             // 服务器停止后更新状态
             state.status.write().await.running = false;
@@ -128,13 +159,39 @@ impl ProxyServer {
         // 保存服务器任务句柄
         *self.server_handle.write().await = Some(handle);
 
+        // 启动后台健康探测（按应用配置决定是否探测）
+        let checker = HealthChecker::new(self.state.db.clone(), self.state.provider_router.clone());
+        *self.health_handle.write().await = Some(checker.spawn());
+
+        // 启动定时切换（仅对已接管的应用生效）
+        let scheduler = ProviderScheduler::new(
+            self.state.db.clone(),
+            self.state.failover_manager.clone(),
+            self.state.app_handle.clone(),
+        );
+        *self.schedule_handle.write().await = Some(scheduler.spawn());
+
+        let (address, port) = self.listen_endpoint();
         Ok(ProxyServerInfo {
-            address: self.config.listen_address.clone(),
-            port: self.config.listen_port,
+            address,
+            port,
             started_at: chrono::Utc::now().to_rfc3339(),
         })
     }
 
+    /// 对外展示的监听位置：Unix 域套接字模式下为套接字路径，端口为 0
+    fn listen_endpoint(&self) -> (String, u16) {
+        match self.config.listen_mode {
+            ListenMode::Unix => (
+                resolve_unix_socket_path(self.config.unix_socket_path.as_deref())
+                    .display()
+                    .to_string(),
+                0,
+            ),
+            _ => (self.config.listen_address.clone(), self.config.listen_port),
+        }
+    }
+
     pub async fn stop(&self) -> Result<(), ProxyError> {
         // 1. 发送关闭信号
         if let Some(tx) = self.shutdown_tx.write().await.take() {
@@ -142,6 +199,12 @@ impl ProxyServer {
         } else {
             return Err(ProxyError::NotRunning);
         }
+        if let Some(handle) = self.health_handle.write().await.take() {
+            handle.abort();
+        }
+        if let Some(handle) = self.schedule_handle.write().await.take() {
+            handle.abort();
+        }
 
         // 2. 等待服务器任务结束（带 5 秒超时保护）
         if let Some(handle) = self.server_handle.write().await.take() {
@@ -173,6 +236,9 @@ impl ProxyServer {
                 provider_name: provider_name.clone(),
             })
             .collect();
+        drop(current_providers);
+
+        status.cooldowns = self.state.provider_router.list_cooldowns().await;
 
         status
     }
@@ -187,9 +253,18 @@ impl ProxyServer {
             // 健康检查
             .route("/health", get(handlers::health_check))
             .route("/status", get(handlers::get_status))
+            .route("/metrics", get(handlers::metrics))
             // Claude API (支持带前缀和不带前缀两种格式)
             .route("/v1/messages", post(handlers::handle_messages))
             .route("/claude/v1/messages", post(handlers::handle_messages))
+            .route(
+                "/v1/messages/count_tokens",
+                post(handlers::handle_count_tokens),
+            )
+            .route(
+                "/claude/v1/messages/count_tokens",
+                post(handlers::handle_count_tokens),
+            )
             // OpenAI Chat Completions API (Codex CLI，支持带前缀和不带前缀)
             .route("/chat/completions", post(handlers::handle_chat_completions))
             .route(
@@ -204,6 +279,11 @@ impl ProxyServer {
                 "/codex/v1/chat/completions",
                 post(handlers::handle_chat_completions),
             )
+            // OpenAI Chat Completions 客户端 → 当前 Claude 供应商
+            .route(
+                "/claude/v1/chat/completions",
+                post(handlers::handle_claude_chat_completions),
+            )
             // OpenAI Responses API (Codex CLI，支持带前缀和不带前缀)
             .route("/responses", post(handlers::handle_responses))
             .route("/v1/responses", post(handlers::handle_responses))
@@ -212,6 +292,11 @@ impl ProxyServer {
             // Gemini API (支持带前缀和不带前缀)
             .route("/v1beta/*path", post(handlers::handle_gemini))
             .route("/gemini/v1beta/*path", post(handlers::handle_gemini))
+            // 入站认证（令牌 + 来源 CIDR），位于 CORS 之内，预检请求不受影响
+            .layer(middleware::from_fn_with_state(
+                self.state.clone(),
+                inbound_auth::require_client_auth,
+            ))
             .layer(cors)
             .with_state(self.state.clone())
     }
@@ -238,4 +323,37 @@ impl ProxyServer {
             .reset_provider_breaker(provider_id, app_type)
             .await;
     }
+
+    /// 获取指定 Provider 的 Key 池运行状态
+    pub fn key_pool_status(
+        &self,
+        provider: &Provider,
+        app_type: &str,
+        auth: Option<&AuthInfo>,
+    ) -> Vec<PoolKeyStatus> {
+        self.state
+            .provider_router
+            .key_pools()
+            .status(provider, app_type, auth)
+    }
+
+    /// 重新启用指定 Provider 被停用的 Key
+    pub fn reset_key_pool(&self, provider_id: &str, app_type: &str) {
+        self.state
+            .provider_router
+            .key_pools()
+            .reset(provider_id, app_type);
+    }
+
+    /// 获取指定 Provider 的熔断器统计
+    pub async fn get_circuit_breaker_stats(
+        &self,
+        provider_id: &str,
+        app_type: &str,
+    ) -> Option<super::circuit_breaker::CircuitBreakerStats> {
+        self.state
+            .provider_router
+            .get_circuit_breaker_stats(provider_id, app_type)
+            .await
+    }
 }
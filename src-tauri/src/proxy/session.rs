@@ -176,6 +176,43 @@ impl ProxySession {
     }
 }
 
+/// 从请求体中提取客户端会话 ID
+///
+/// - Claude Code：`metadata.user_id` 形如 `user_<hash>_account_<uuid>_session_<uuid>`
+/// - Codex CLI：`prompt_cache_key` 为会话 ID
+pub fn extract_session_id(body: &serde_json::Value) -> Option<String> {
+    if let Some(user_id) = body.pointer("/metadata/user_id").and_then(|v| v.as_str()) {
+        if let Some((_, session)) = user_id.rsplit_once("_session_") {
+            if !session.is_empty() {
+                return Some(session.to_string());
+            }
+        }
+    }
+
+    body.get("prompt_cache_key")
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+}
+
+/// 从请求头提取客户端会话 ID（`session_id` / `x-session-id`）
+pub fn session_id_from_headers(headers: &axum::http::HeaderMap) -> Option<String> {
+    ["session_id", "x-session-id"]
+        .iter()
+        .find_map(|name| headers.get(*name))
+        .and_then(|v| v.to_str().ok())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+}
+
+/// 提取客户端会话 ID：优先请求体，其次请求头
+pub fn request_session_id(
+    body: &serde_json::Value,
+    headers: &axum::http::HeaderMap,
+) -> Option<String> {
+    extract_session_id(body).or_else(|| session_id_from_headers(headers))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -295,4 +332,21 @@ mod tests {
         assert_eq!(ClientFormat::GeminiCli.as_str(), "gemini_cli");
         assert_eq!(ClientFormat::Unknown.as_str(), "unknown");
     }
+
+    #[test]
+    fn test_extract_session_id() {
+        let body = json!({
+            "metadata": {"user_id": "user_abc123_account_11111111-2222_session_33333333-4444"}
+        });
+        assert_eq!(extract_session_id(&body).as_deref(), Some("33333333-4444"));
+
+        let body = json!({"prompt_cache_key": "codex-session-1"});
+        assert_eq!(
+            extract_session_id(&body).as_deref(),
+            Some("codex-session-1")
+        );
+
+        let body = json!({"metadata": {"user_id": "user_abc123"}});
+        assert!(extract_session_id(&body).is_none());
+    }
 }
@@ -0,0 +1,179 @@
+//! 消费限额
+//!
+//! 供应商级限额来自 meta 的 `limitDailyUsd` / `limitMonthlyUsd`：hard 模式下超出后路由器不再选择该供应商，
+//! soft 模式仅告警。应用级与全局上限来自代理配置，超出后直接拒绝请求。
+//! 消费按 UTC 自然日 / 自然月统计（与 `check_provider_limits` 一致），
+//! 同一限额在同一统计周期内只发出一次 `provider-limit-exceeded` 事件。
+
+use crate::services::usage_stats::SpendTotals;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::Emitter;
+
+/// 超出消费限额时发往前端的事件名
+pub const LIMIT_EXCEEDED_EVENT: &str = "provider-limit-exceeded";
+
+/// 限额作用范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpendScope {
+    Provider,
+    App,
+    Global,
+}
+
+/// 统计周期
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpendPeriod {
+    Daily,
+    Monthly,
+}
+
+impl SpendPeriod {
+    fn label(self) -> &'static str {
+        match self {
+            SpendPeriod::Daily => "今日",
+            SpendPeriod::Monthly => "本月",
+        }
+    }
+
+    /// 当前统计周期的标识（UTC），用于告警去重
+    fn current_id(self) -> String {
+        let now = chrono::Utc::now();
+        match self {
+            SpendPeriod::Daily => now.format("%Y-%m-%d").to_string(),
+            SpendPeriod::Monthly => now.format("%Y-%m").to_string(),
+        }
+    }
+}
+
+/// 超出的消费限额（同时作为 `provider-limit-exceeded` 事件的负载）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpendLimitExceeded {
+    pub scope: SpendScope,
+    pub app_type: Option<String>,
+    pub provider_id: Option<String>,
+    pub provider_name: Option<String>,
+    pub period: SpendPeriod,
+    pub usage_usd: f64,
+    pub limit_usd: f64,
+    /// 是否仅告警（供应商 soft 模式）
+    pub soft: bool,
+}
+
+impl SpendLimitExceeded {
+    /// 面向客户端 / 日志的说明
+    pub fn message(&self) -> String {
+        let subject = match self.scope {
+            SpendScope::Provider => format!(
+                "供应商 {}",
+                self.provider_name
+                    .as_deref()
+                    .or(self.provider_id.as_deref())
+                    .unwrap_or_default()
+            ),
+            SpendScope::App => format!("{} 应用", self.app_type.as_deref().unwrap_or_default()),
+            SpendScope::Global => "全局".to_string(),
+        };
+        format!(
+            "{subject}{}消费 ${:.2} 已达上限 ${:.2}",
+            self.period.label(),
+            self.usage_usd,
+            self.limit_usd
+        )
+    }
+
+    fn alert_key(&self) -> String {
+        format!(
+            "{:?}:{}:{}:{:?}",
+            self.scope,
+            self.app_type.as_deref().unwrap_or_default(),
+            self.provider_id.as_deref().unwrap_or_default(),
+            self.period
+        )
+    }
+}
+
+/// 解析 USD 限额字符串；空串、非法值或非正数视为未设置
+pub fn parse_limit(value: Option<&str>) -> Option<f64> {
+    value
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .filter(|v| v.is_finite() && *v > 0.0)
+}
+
+/// 检查消费是否超出限额，返回 (周期, 用量, 限额)；日限额优先
+pub fn exceeded_period(
+    totals: SpendTotals,
+    daily_limit: Option<f64>,
+    monthly_limit: Option<f64>,
+) -> Option<(SpendPeriod, f64, f64)> {
+    if let Some(limit) = daily_limit.filter(|limit| totals.daily >= *limit) {
+        return Some((SpendPeriod::Daily, totals.daily, limit));
+    }
+    monthly_limit
+        .filter(|limit| totals.monthly >= *limit)
+        .map(|limit| (SpendPeriod::Monthly, totals.monthly, limit))
+}
+
+/// 消费限额告警器
+///
+/// 记录每个限额最近一次告警所在的统计周期，同一周期内只记录一次警告日志并发射一次事件。
+#[derive(Default)]
+pub struct SpendAlerts {
+    notified: Mutex<HashMap<String, String>>,
+}
+
+impl SpendAlerts {
+    pub fn notify(&self, app_handle: Option<&tauri::AppHandle>, exceeded: &SpendLimitExceeded) {
+        let period_id = exceeded.period.current_id();
+        {
+            let mut notified = self.notified.lock().unwrap_or_else(|e| e.into_inner());
+            let key = exceeded.alert_key();
+            if notified.get(&key) == Some(&period_id) {
+                return;
+            }
+            notified.insert(key, period_id);
+        }
+
+        log::warn!("[SpendLimit] {}", exceeded.message());
+        if let Some(app) = app_handle {
+            if let Err(e) = app.emit(LIMIT_EXCEEDED_EVENT, exceeded) {
+                log::error!("[SpendLimit] 发射限额事件失败: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_limit() {
+        assert_eq!(parse_limit(Some(" 12.5 ")), Some(12.5));
+        assert_eq!(parse_limit(Some("")), None);
+        assert_eq!(parse_limit(Some("0")), None);
+        assert_eq!(parse_limit(Some("abc")), None);
+        assert_eq!(parse_limit(None), None);
+    }
+
+    #[test]
+    fn test_exceeded_period_prefers_daily() {
+        let totals = SpendTotals {
+            daily: 5.0,
+            monthly: 50.0,
+        };
+        assert_eq!(
+            exceeded_period(totals, Some(5.0), Some(40.0)),
+            Some((SpendPeriod::Daily, 5.0, 5.0))
+        );
+        assert_eq!(
+            exceeded_period(totals, Some(10.0), Some(40.0)),
+            Some((SpendPeriod::Monthly, 50.0, 40.0))
+        );
+        assert_eq!(exceeded_period(totals, Some(10.0), None), None);
+    }
+}
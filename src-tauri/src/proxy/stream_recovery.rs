@@ -0,0 +1,490 @@
+//! 流式响应中断恢复
+//!
+//! 上游输出部分内容后中断流式响应（连接断开、超时、`error` 事件、未收到 `message_stop` 就结束）时，
+//! 把已输出的文本作为 assistant 预填充追加到原请求，重新发给同一供应商（失败时按故障转移链切换），
+//! 并把续写的事件拼接进同一个客户端流：续写的首个文本块并入中断时未结束的文本块，其余内容块的索引顺延。
+//!
+//! 仅用于 Claude 原生 SSE 透传；已输出工具调用、思考等非文本块时无法安全续写，按原样结束。
+//! 续写请求的用量单独计费，请求日志中的 token 统计只包含首段的输入与最后一段的输出。
+
+use super::{forwarder::RequestForwarder, handler_context::StreamingTimeoutConfig};
+use crate::app_config::AppType;
+use crate::provider::Provider;
+use bytes::Bytes;
+use futures::stream::{Stream, StreamExt};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::pin::Pin;
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+use std::time::Duration;
+
+type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes, std::io::Error>> + Send>>;
+
+/// 续写恢复所需的请求信息
+pub struct StreamRecovery {
+    pub forwarder: RequestForwarder,
+    /// 原始请求体
+    pub body: Value,
+    pub headers: axum::http::HeaderMap,
+    /// 续写时的供应商链（中断的供应商在前）
+    pub providers: Vec<Provider>,
+    /// 单个请求最多续写次数
+    pub max_attempts: u32,
+    /// 实际续写次数（写入请求日志）
+    pub recoveries: Arc<AtomicU32>,
+    /// 每段上游流的超时配置
+    pub timeout: StreamingTimeoutConfig,
+    pub tag: &'static str,
+}
+
+/// 上游流中断的方式
+enum Abort {
+    /// 出错（连接断开、超时、error 事件）
+    Error(String),
+    /// 未收到 message_stop 就正常结束
+    Ended,
+}
+
+/// 已输出的内容块（None 表示非文本块）
+#[derive(Debug, Default)]
+struct StreamState {
+    started: bool,
+    finished: bool,
+    blocks: BTreeMap<u64, Option<String>>,
+    open: Option<u64>,
+}
+
+impl StreamState {
+    fn observe(&mut self, event: &Value) {
+        let index = event.get("index").and_then(|i| i.as_u64());
+        match event.get("type").and_then(|t| t.as_str()) {
+            Some("message_start") => self.started = true,
+            Some("message_stop") => self.finished = true,
+            Some("content_block_start") => {
+                let Some(index) = index else { return };
+                let block = event.get("content_block");
+                let text = (block.and_then(|b| b.get("type")).and_then(|t| t.as_str())
+                    == Some("text"))
+                .then(|| {
+                    block
+                        .and_then(|b| b.get("text"))
+                        .and_then(|t| t.as_str())
+                        .unwrap_or_default()
+                        .to_string()
+                });
+                self.blocks.insert(index, text);
+                self.open = Some(index);
+            }
+            Some("content_block_delta") => {
+                let Some(block) = index.and_then(|i| self.blocks.get_mut(&i)) else {
+                    return;
+                };
+                let delta = event.get("delta");
+                match delta.and_then(|d| d.get("type")).and_then(|t| t.as_str()) {
+                    Some("text_delta") => {
+                        if let (Some(text), Some(chunk)) = (
+                            block.as_mut(),
+                            delta.and_then(|d| d.get("text")).and_then(|t| t.as_str()),
+                        ) {
+                            text.push_str(chunk);
+                        }
+                    }
+                    _ => *block = None,
+                }
+            }
+            Some("content_block_stop") if self.open == index => self.open = None,
+            _ => {}
+        }
+    }
+
+    /// 已输出内容且全部为文本块时才能续写
+    fn can_recover(&self) -> bool {
+        self.started
+            && !self.finished
+            && self.blocks.values().all(Option::is_some)
+            && self
+                .blocks
+                .values()
+                .flatten()
+                .any(|text| !text.trim().is_empty())
+    }
+
+    fn next_index(&self) -> u64 {
+        self.blocks.keys().next_back().map_or(0, |i| i + 1)
+    }
+
+    /// 构建续写请求：已输出的文本块追加为 assistant 预填充
+    fn continuation_body(&self, body: &Value) -> Option<Value> {
+        let mut texts: Vec<String> = self.blocks.values().flatten().cloned().collect();
+        // 上游拒绝以空白结尾的 assistant 预填充
+        if let Some(last) = texts.last_mut() {
+            last.truncate(last.trim_end().len());
+        }
+        let blocks: Vec<Value> = texts
+            .into_iter()
+            .filter(|text| !text.is_empty())
+            .map(|text| json!({ "type": "text", "text": text }))
+            .collect();
+        if blocks.is_empty() {
+            return None;
+        }
+
+        let mut body = body.clone();
+        let messages = body.get_mut("messages")?.as_array_mut()?;
+        match messages.last_mut() {
+            // 客户端自带预填充时接在其后
+            Some(last) if last.get("role").and_then(|r| r.as_str()) == Some("assistant") => {
+                let content = match last.get_mut("content")?.take() {
+                    Value::String(text) if !text.is_empty() => {
+                        vec![json!({ "type": "text", "text": text })]
+                    }
+                    Value::Array(content) => content,
+                    _ => Vec::new(),
+                };
+                last["content"] = Value::Array(content.into_iter().chain(blocks).collect());
+            }
+            _ => messages.push(json!({ "role": "assistant", "content": blocks })),
+        }
+        Some(body)
+    }
+}
+
+/// 把续写流的事件拼接到中断的流之后
+#[derive(Debug)]
+struct Splice {
+    /// 中断时未结束的文本块
+    open: Option<u64>,
+    next_index: u64,
+    /// 续写内容块索引的偏移量（收到首个内容块时确定）
+    base: Option<u64>,
+    /// 未结束的文本块是否已关闭（续写首块不是文本时补发 content_block_stop）
+    open_closed: bool,
+}
+
+impl Splice {
+    fn new(state: &StreamState) -> Self {
+        Self {
+            open: state.open,
+            next_index: state.next_index(),
+            base: None,
+            open_closed: state.open.is_none(),
+        }
+    }
+
+    fn close_open(&mut self) -> Option<Value> {
+        if self.open_closed {
+            return None;
+        }
+        self.open_closed = true;
+        self.open
+            .map(|index| json!({ "type": "content_block_stop", "index": index }))
+    }
+
+    /// 改写续写流的事件，返回需要发给客户端的事件
+    fn rewrite(&mut self, mut event: Value) -> Vec<Value> {
+        let event_type = event
+            .get("type")
+            .and_then(|t| t.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let index = event.get("index").and_then(|i| i.as_u64());
+        let mut events = Vec::new();
+
+        match event_type.as_str() {
+            // 客户端已收到过 message_start
+            "message_start" => return events,
+            "content_block_start" if self.base.is_none() => {
+                let is_text = event
+                    .get("content_block")
+                    .and_then(|b| b.get("type"))
+                    .and_then(|t| t.as_str())
+                    == Some("text");
+                match self.open {
+                    Some(open) if index == Some(0) && is_text && !self.open_closed => {
+                        // 首个文本块并入未结束的文本块，省略其 content_block_start，由续写的 stop 关闭
+                        self.base = Some(open);
+                        self.open_closed = true;
+                        return events;
+                    }
+                    _ => {
+                        events.extend(self.close_open());
+                        self.base = Some(self.next_index);
+                    }
+                }
+            }
+            "message_delta" | "message_stop" => events.extend(self.close_open()),
+            _ => {}
+        }
+
+        if let (Some(index), Some(base)) = (index, self.base) {
+            event["index"] = json!(base + index);
+        }
+        events.push(event);
+        events
+    }
+}
+
+fn sse_event(event: &Value) -> Bytes {
+    let event_type = event
+        .get("type")
+        .and_then(|t| t.as_str())
+        .unwrap_or("message");
+    Bytes::from(format!("event: {event_type}\ndata: {event}\n\n"))
+}
+
+fn event_data(event_text: &str) -> Option<Value> {
+    event_text
+        .lines()
+        .find_map(|line| line.strip_prefix("data:"))
+        .and_then(|data| serde_json::from_str(data.trim()).ok())
+}
+
+fn timeout_duration(secs: u64) -> Option<Duration> {
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// 创建可在中断后续写恢复的 SSE 流
+///
+/// 超时由本流按段处理（续写的首字节等待不计入上一段的静默期），外层透传流不应再设置超时。
+pub fn create_recoverable_stream(
+    stream: impl Stream<Item = Result<Bytes, std::io::Error>> + Send + 'static,
+    recovery: StreamRecovery,
+) -> impl Stream<Item = Result<Bytes, std::io::Error>> + Send {
+    async_stream::stream! {
+        let tag = recovery.tag;
+        let first_byte_timeout = timeout_duration(recovery.timeout.first_byte_timeout);
+        let idle_timeout = timeout_duration(recovery.timeout.idle_timeout);
+        let mut upstream: ByteStream = Box::pin(stream);
+        let mut state = StreamState::default();
+        let mut splice: Option<Splice> = None;
+        let mut attempts = 0;
+
+        loop {
+            let mut buffer: Vec<u8> = Vec::new();
+            let mut is_first_chunk = true;
+
+            let abort = 'segment: loop {
+                let timeout = if is_first_chunk { first_byte_timeout } else { idle_timeout };
+                let next = match timeout {
+                    Some(duration) => match tokio::time::timeout(duration, upstream.next()).await {
+                        Ok(next) => next,
+                        Err(_) => {
+                            let timeout_type = if is_first_chunk { "首字节" } else { "静默期" };
+                            log::error!("[{tag}] 流式响应{timeout_type}超时 ({}秒)", duration.as_secs());
+                            break Abort::Error(format!("流式响应{timeout_type}超时"));
+                        }
+                    },
+                    None => upstream.next().await,
+                };
+
+                let bytes = match next {
+                    Some(Ok(bytes)) => bytes,
+                    Some(Err(e)) => {
+                        log::error!("[{tag}] 流错误: {e}");
+                        break Abort::Error(e.to_string());
+                    }
+                    None if state.finished || !state.started => return,
+                    None => break Abort::Ended,
+                };
+                is_first_chunk = false;
+                buffer.extend_from_slice(&bytes);
+
+                while let Some(pos) = buffer.windows(2).position(|w| w == b"\n\n") {
+                    let raw: Vec<u8> = buffer.drain(..pos + 2).collect();
+                    let event_text = String::from_utf8_lossy(&raw[..pos]).to_string();
+                    if event_text.trim().is_empty() {
+                        continue;
+                    }
+                    let data = event_data(&event_text);
+
+                    // 可恢复时不把 error 事件转发给客户端
+                    if let Some(error) = data
+                        .as_ref()
+                        .filter(|d| d.get("type").and_then(|t| t.as_str()) == Some("error"))
+                    {
+                        if attempts < recovery.max_attempts && state.can_recover() {
+                            break 'segment Abort::Error(error.to_string());
+                        }
+                    }
+
+                    match (splice.as_mut(), data) {
+                        (Some(splice), Some(data)) => {
+                            for event in splice.rewrite(data) {
+                                state.observe(&event);
+                                yield Ok(sse_event(&event));
+                            }
+                        }
+                        (_, data) => {
+                            if let Some(data) = &data {
+                                state.observe(data);
+                            }
+                            yield Ok(Bytes::from(raw));
+                        }
+                    }
+                }
+            };
+
+            if attempts >= recovery.max_attempts || !state.can_recover() {
+                if let Abort::Error(message) = abort {
+                    yield Err(std::io::Error::other(message));
+                }
+                return;
+            }
+            let Some(body) = state.continuation_body(&recovery.body) else {
+                return;
+            };
+
+            attempts += 1;
+            let reason = match &abort {
+                Abort::Error(message) => message.as_str(),
+                Abort::Ended => "未收到 message_stop",
+            };
+            log::warn!("[{tag}] 流式响应中断（{reason}），第 {attempts} 次续写恢复");
+
+            match recovery
+                .forwarder
+                .forward_with_retry(
+                    &AppType::Claude,
+                    "/v1/messages",
+                    body,
+                    recovery.headers.clone(),
+                    recovery.providers.clone(),
+                )
+                .await
+            {
+                Ok(result) => {
+                    log::info!("[{tag}] 续写请求已由 {} 接手", result.provider.name);
+                    recovery.recoveries.fetch_add(1, Ordering::Relaxed);
+                    upstream = Box::pin(
+                        result
+                            .response
+                            .bytes_stream()
+                            .map(|chunk| chunk.map_err(|e| std::io::Error::other(e.to_string()))),
+                    );
+                    splice = Some(Splice::new(&state));
+                }
+                Err(err) => {
+                    log::error!("[{tag}] 续写恢复失败: {}", err.error);
+                    if let Abort::Error(message) = abort {
+                        yield Err(std::io::Error::other(message));
+                    }
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn observe_all(state: &mut StreamState, events: &[Value]) {
+        for event in events {
+            state.observe(event);
+        }
+    }
+
+    fn interrupted_state() -> StreamState {
+        let mut state = StreamState::default();
+        observe_all(
+            &mut state,
+            &[
+                json!({"type": "message_start", "message": {"id": "msg_1"}}),
+                json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+                json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "First. "}}),
+                json!({"type": "content_block_stop", "index": 0}),
+                json!({"type": "content_block_start", "index": 1, "content_block": {"type": "text", "text": ""}}),
+                json!({"type": "content_block_delta", "index": 1, "delta": {"type": "text_delta", "text": "Second half "}}),
+            ],
+        );
+        state
+    }
+
+    #[test]
+    fn test_continuation_body_appends_prefill() {
+        let state = interrupted_state();
+        assert!(state.can_recover());
+
+        let body = json!({"model": "claude", "messages": [{"role": "user", "content": "hi"}]});
+        let continued = state.continuation_body(&body).unwrap();
+        assert_eq!(
+            continued["messages"][1],
+            json!({"role": "assistant", "content": [
+                {"type": "text", "text": "First. "},
+                {"type": "text", "text": "Second half"}
+            ]})
+        );
+
+        // 客户端自带预填充时接在其后
+        let body = json!({"messages": [
+            {"role": "user", "content": "hi"},
+            {"role": "assistant", "content": "Sure:"}
+        ]});
+        let continued = state.continuation_body(&body).unwrap();
+        assert_eq!(continued["messages"].as_array().unwrap().len(), 2);
+        assert_eq!(
+            continued["messages"][1]["content"]
+                .as_array()
+                .unwrap()
+                .len(),
+            3
+        );
+
+        // 已输出工具调用时不续写
+        let mut tool_state = interrupted_state();
+        tool_state.observe(&json!({"type": "content_block_start", "index": 2, "content_block": {"type": "tool_use"}}));
+        assert!(!tool_state.can_recover());
+    }
+
+    #[test]
+    fn test_splice_merges_open_text_block_and_shifts_indices() {
+        let mut state = interrupted_state();
+        let mut splice = Splice::new(&state);
+
+        let continuation = [
+            json!({"type": "message_start", "message": {"id": "msg_2"}}),
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "continues."}}),
+            json!({"type": "content_block_stop", "index": 0}),
+            json!({"type": "content_block_start", "index": 1, "content_block": {"type": "tool_use"}}),
+            json!({"type": "content_block_stop", "index": 1}),
+            json!({"type": "message_stop"}),
+        ];
+        let out: Vec<Value> = continuation
+            .into_iter()
+            .flat_map(|event| splice.rewrite(event))
+            .collect();
+        observe_all(&mut state, &out);
+
+        let indices: Vec<(String, Option<u64>)> = out
+            .iter()
+            .map(|e| (e["type"].as_str().unwrap().to_string(), e["index"].as_u64()))
+            .collect();
+        assert_eq!(
+            indices,
+            [
+                ("content_block_delta".to_string(), Some(1)),
+                ("content_block_stop".to_string(), Some(1)),
+                ("content_block_start".to_string(), Some(2)),
+                ("content_block_stop".to_string(), Some(2)),
+                ("message_stop".to_string(), None),
+            ]
+        );
+        assert_eq!(state.blocks[&1].as_deref(), Some("Second half continues."));
+        assert!(state.finished);
+
+        // 中断时没有未结束的块：续写块顺延，且不会补发 content_block_stop
+        let mut state = interrupted_state();
+        state.observe(&json!({"type": "content_block_stop", "index": 1}));
+        let mut splice = Splice::new(&state);
+        let out = splice.rewrite(
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text"}}),
+        );
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0]["index"], 2);
+    }
+}
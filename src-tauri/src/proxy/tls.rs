@@ -0,0 +1,202 @@
+//! 代理 HTTPS 证书
+//!
+//! 用户配置了证书与私钥路径时直接加载；否则在 `~/.cc-switch/proxy-tls/` 下生成一个本地自签名 CA，
+//! 并用它签发 `localhost` / `127.0.0.1` / `::1` 的服务端证书。客户端信任 `ca.pem` 后即可校验代理证书。
+//! 已生成的文件会被复用，CA 不会在每次启动时变化。
+
+use super::ProxyError;
+use rcgen::{
+    BasicConstraints, CertificateParams, DistinguishedName, DnType, ExtendedKeyUsagePurpose, IsCa,
+    KeyPair, KeyUsagePurpose,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::TlsAcceptor;
+
+const CA_CERT_FILE: &str = "ca.pem";
+const CA_KEY_FILE: &str = "ca-key.pem";
+const SERVER_CERT_FILE: &str = "server.pem";
+const SERVER_KEY_FILE: &str = "server-key.pem";
+
+/// 自动生成证书的存放目录
+pub fn tls_dir() -> PathBuf {
+    crate::config::get_app_config_dir().join("proxy-tls")
+}
+
+/// 自签名 CA 证书路径（供用户导入系统信任或通过 `NODE_EXTRA_CA_CERTS` 等方式信任）
+pub fn ca_cert_path() -> PathBuf {
+    tls_dir().join(CA_CERT_FILE)
+}
+
+/// 根据配置构建 TLS 接受器
+///
+/// `cert_path` / `key_path` 需要同时提供；都为空时使用自动生成的证书。
+pub fn build_acceptor(
+    cert_path: Option<&str>,
+    key_path: Option<&str>,
+) -> Result<TlsAcceptor, ProxyError> {
+    fn non_empty(value: Option<&str>) -> Option<&str> {
+        value.map(str::trim).filter(|v| !v.is_empty())
+    }
+    let (cert_path, key_path) = match (non_empty(cert_path), non_empty(key_path)) {
+        (Some(cert), Some(key)) => (PathBuf::from(cert), PathBuf::from(key)),
+        (None, None) => ensure_self_signed(&tls_dir())?,
+        _ => {
+            return Err(ProxyError::ConfigError(
+                "TLS 证书与私钥路径需要同时配置".to_string(),
+            ))
+        }
+    };
+
+    let certs = CertificateDer::pem_file_iter(&cert_path)
+        .and_then(|iter| iter.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| {
+            ProxyError::ConfigError(format!("读取 TLS 证书失败 {}: {e}", cert_path.display()))
+        })?;
+    if certs.is_empty() {
+        return Err(ProxyError::ConfigError(format!(
+            "TLS 证书文件中没有证书: {}",
+            cert_path.display()
+        )));
+    }
+    let key = PrivateKeyDer::from_pem_file(&key_path).map_err(|e| {
+        ProxyError::ConfigError(format!("读取 TLS 私钥失败 {}: {e}", key_path.display()))
+    })?;
+
+    let mut config = ServerConfig::builder_with_provider(Arc::new(
+        tokio_rustls::rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .map_err(|e| ProxyError::ConfigError(format!("TLS 配置失败: {e}")))?
+    .with_no_client_auth()
+    .with_single_cert(certs, key)
+    .map_err(|e| ProxyError::ConfigError(format!("TLS 证书与私钥不匹配: {e}")))?;
+    // 服务端只实现了 HTTP/1.1
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// 确保自签名 CA 与服务端证书存在，返回 (服务端证书路径, 服务端私钥路径)
+fn ensure_self_signed(dir: &Path) -> Result<(PathBuf, PathBuf), ProxyError> {
+    let server_cert = dir.join(SERVER_CERT_FILE);
+    let server_key = dir.join(SERVER_KEY_FILE);
+    if server_cert.exists() && server_key.exists() {
+        return Ok((server_cert, server_key));
+    }
+
+    let tls_error = |e: rcgen::Error| ProxyError::ConfigError(format!("生成 TLS 证书失败: {e}"));
+
+    // 复用已有的 CA 私钥：CA 参数固定，重新自签后签发的证书仍能被已信任的 ca.pem 校验
+    let ca_key_path = dir.join(CA_KEY_FILE);
+    let ca_cert_path = dir.join(CA_CERT_FILE);
+    let existing_ca_key = if ca_cert_path.exists() {
+        fs::read_to_string(&ca_key_path)
+            .ok()
+            .and_then(|pem| KeyPair::from_pem(&pem).ok())
+    } else {
+        None
+    };
+    let (ca_key, new_ca) = match existing_ca_key {
+        Some(key) => (key, false),
+        None => (KeyPair::generate().map_err(tls_error)?, true),
+    };
+    let ca = ca_params().self_signed(&ca_key).map_err(tls_error)?;
+
+    let mut params = CertificateParams::new(vec![
+        "localhost".to_string(),
+        "127.0.0.1".to_string(),
+        "::1".to_string(),
+    ])
+    .map_err(tls_error)?;
+    params
+        .distinguished_name
+        .push(DnType::CommonName, "CC Switch Proxy");
+    params.use_authority_key_identifier_extension = true;
+    params.key_usages = vec![
+        KeyUsagePurpose::DigitalSignature,
+        KeyUsagePurpose::KeyEncipherment,
+    ];
+    params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+    let key = KeyPair::generate().map_err(tls_error)?;
+    let cert = params.signed_by(&key, &ca, &ca_key).map_err(tls_error)?;
+
+    if new_ca {
+        write_file(&ca_cert_path, &ca.pem(), false)?;
+        write_file(&ca_key_path, &ca_key.serialize_pem(), true)?;
+        log::info!("[TLS] 已生成本地 CA 证书: {}", ca_cert_path.display());
+    }
+    write_file(&server_cert, &cert.pem(), false)?;
+    write_file(&server_key, &key.serialize_pem(), true)?;
+    log::info!("[TLS] 已签发代理服务端证书: {}", server_cert.display());
+
+    Ok((server_cert, server_key))
+}
+
+fn ca_params() -> CertificateParams {
+    let mut params = CertificateParams::default();
+    let mut name = DistinguishedName::new();
+    name.push(DnType::CommonName, "CC Switch Local CA");
+    name.push(DnType::OrganizationName, "CC Switch");
+    params.distinguished_name = name;
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    params.key_usages = vec![
+        KeyUsagePurpose::KeyCertSign,
+        KeyUsagePurpose::CrlSign,
+        KeyUsagePurpose::DigitalSignature,
+    ];
+    params.not_before = rcgen::date_time_ymd(2024, 1, 1);
+    params.not_after = rcgen::date_time_ymd(2049, 12, 31);
+    params
+}
+
+/// 写入 PEM 文件；私钥文件在 Unix 上设置为 600
+fn write_file(path: &Path, content: &str, private: bool) -> Result<(), ProxyError> {
+    crate::config::write_text_file(path, content)
+        .map_err(|e| ProxyError::ConfigError(e.to_string()))?;
+
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+            .map_err(|e| ProxyError::ConfigError(format!("设置私钥权限失败: {e}")))?;
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_signed_certificates_are_generated_and_reused() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let (cert, key) = ensure_self_signed(dir.path()).expect("generate");
+        let ca = fs::read_to_string(dir.path().join(CA_CERT_FILE)).expect("ca");
+        assert!(ca.contains("BEGIN CERTIFICATE"));
+
+        // 已存在时复用
+        let first = fs::read_to_string(&cert).unwrap();
+        ensure_self_signed(dir.path()).expect("reuse");
+        assert_eq!(fs::read_to_string(&cert).unwrap(), first);
+
+        // 服务端证书丢失时用原有 CA 重新签发，CA 保持不变
+        fs::remove_file(&cert).unwrap();
+        ensure_self_signed(dir.path()).expect("reissue");
+        assert_eq!(
+            fs::read_to_string(dir.path().join(CA_CERT_FILE)).unwrap(),
+            ca
+        );
+
+        let acceptor = build_acceptor(Some(cert.to_str().unwrap()), Some(key.to_str().unwrap()));
+        assert!(acceptor.is_ok());
+        assert!(build_acceptor(Some(cert.to_str().unwrap()), None).is_err());
+    }
+}
@@ -25,6 +25,18 @@ pub struct ProxyConfig {
     /// 非流式总超时（秒）- 非流式请求的总超时时间
     #[serde(default = "default_non_streaming_timeout")]
     pub non_streaming_timeout: u64,
+    /// 监听方式
+    #[serde(default)]
+    pub listen_mode: ListenMode,
+    /// TLS 证书路径（PEM），为空时使用自动生成的自签名证书
+    #[serde(default)]
+    pub tls_cert_path: Option<String>,
+    /// TLS 私钥路径（PEM）
+    #[serde(default)]
+    pub tls_key_path: Option<String>,
+    /// Unix 域套接字路径
+    #[serde(default)]
+    pub unix_socket_path: Option<String>,
 }
 
 fn default_streaming_first_byte_timeout() -> u64 {
@@ -51,6 +63,58 @@ impl Default for ProxyConfig {
             streaming_first_byte_timeout: 30,
             streaming_idle_timeout: 60,
             non_streaming_timeout: 600,
+            listen_mode: ListenMode::default(),
+            tls_cert_path: None,
+            tls_key_path: None,
+            unix_socket_path: None,
+        }
+    }
+}
+
+impl ProxyConfig {
+    /// 监听相关配置是否一致（不一致时需要重启代理服务器）
+    pub fn same_listener(&self, other: &ProxyConfig) -> bool {
+        self.listen_address == other.listen_address
+            && self.listen_port == other.listen_port
+            && self.listen_mode == other.listen_mode
+            && self.tls_cert_path == other.tls_cert_path
+            && self.tls_key_path == other.tls_key_path
+            && self.unix_socket_path == other.unix_socket_path
+    }
+}
+
+/// 代理监听方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListenMode {
+    /// 明文 HTTP（TCP）
+    #[default]
+    Tcp,
+    /// HTTPS（用户提供的证书或自动生成的自签名 CA 签发的证书）
+    Tls,
+    /// Unix 域套接字（仅限本机单用户使用，不占用 TCP 端口）
+    Unix,
+}
+
+impl ListenMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ListenMode::Tcp => "tcp",
+            ListenMode::Tls => "tls",
+            ListenMode::Unix => "unix",
+        }
+    }
+}
+
+impl std::str::FromStr for ListenMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tcp" => Ok(ListenMode::Tcp),
+            "tls" => Ok(ListenMode::Tls),
+            "unix" => Ok(ListenMode::Unix),
+            other => Err(format!("未知的监听方式: {other}")),
         }
     }
 }
@@ -89,6 +153,9 @@ pub struct ProxyStatus {
     /// 当前活跃的代理目标列表
     #[serde(default)]
     pub active_targets: Vec<ActiveTarget>,
+    /// 因上游限流处于冷却中的供应商
+    #[serde(default)]
+    pub cooldowns: Vec<super::cooldown::ProviderCooldown>,
 }
 
 /// 活跃的代理目标信息
@@ -160,6 +227,33 @@ pub struct GlobalProxyConfig {
     pub listen_port: u16,
     /// 是否启用日志
     pub enable_logging: bool,
+    /// 全局每日消费上限（USD，所有应用合计），None 表示不限
+    #[serde(default)]
+    pub spend_limit_daily_usd: Option<String>,
+    /// 全局每月消费上限（USD，所有应用合计）
+    #[serde(default)]
+    pub spend_limit_monthly_usd: Option<String>,
+    /// 是否要求客户端携带本地访问令牌
+    #[serde(default)]
+    pub auth_enabled: bool,
+    /// 本地访问令牌（接管时写入各应用 Live 配置）
+    #[serde(default)]
+    pub access_token: Option<String>,
+    /// 允许访问代理的来源地址（CIDR 或单个 IP），为空表示不限；回环地址始终允许
+    #[serde(default)]
+    pub allowed_cidrs: Vec<String>,
+    /// 监听方式（tcp / tls / unix）
+    #[serde(default)]
+    pub listen_mode: ListenMode,
+    /// TLS 证书路径（PEM），为空时使用自动生成的自签名证书
+    #[serde(default)]
+    pub tls_cert_path: Option<String>,
+    /// TLS 私钥路径（PEM）
+    #[serde(default)]
+    pub tls_key_path: Option<String>,
+    /// Unix 域套接字路径，为空时使用应用配置目录下的 `proxy.sock`
+    #[serde(default)]
+    pub unix_socket_path: Option<String>,
 }
 
 /// 应用级代理配置（每个 app 独立）
@@ -190,4 +284,141 @@ pub struct AppProxyConfig {
     pub circuit_error_rate_threshold: f64,
     /// 计算错误率的最小请求数
     pub circuit_min_requests: u32,
+    /// 负载均衡策略（仅在自动故障转移开启时生效）
+    #[serde(default)]
+    pub load_balance_strategy: LoadBalanceStrategy,
+    /// 对冲请求开关（仅流式请求、自动故障转移开启时生效）
+    #[serde(default)]
+    pub hedge_enabled: bool,
+    /// 对冲延迟（毫秒）：首个供应商超过该时间仍未返回首字节时，并发请求下一个供应商
+    #[serde(default = "default_hedge_delay_ms")]
+    pub hedge_delay_ms: u32,
+    /// 本地响应缓存开关（仅缓存 temperature 为 0 的请求）
+    #[serde(default)]
+    pub response_cache_enabled: bool,
+    /// 响应缓存有效期（秒）
+    #[serde(default = "default_response_cache_ttl_seconds")]
+    pub response_cache_ttl_seconds: u32,
+    /// 响应缓存容量上限（MB），超出时淘汰最久未使用的条目
+    #[serde(default = "default_response_cache_max_mb")]
+    pub response_cache_max_mb: u32,
+    /// 请求/响应载荷捕获开关（调试用，请求头中的密钥会被遮蔽）
+    #[serde(default)]
+    pub payload_capture_enabled: bool,
+    /// 载荷保留天数，0 表示不按时间清理
+    #[serde(default = "default_payload_retention_days")]
+    pub payload_retention_days: u32,
+    /// 单个请求体/响应体的最大保存大小（KB），超出部分截断
+    #[serde(default = "default_payload_max_body_kb")]
+    pub payload_max_body_kb: u32,
+    /// 应用每日消费上限（USD），None 表示不限
+    #[serde(default)]
+    pub spend_limit_daily_usd: Option<String>,
+    /// 应用每月消费上限（USD）
+    #[serde(default)]
+    pub spend_limit_monthly_usd: Option<String>,
+    /// 流式中断恢复开关（仅 Claude 原生 SSE 透传）：已输出部分内容后上游中断时，带上已输出内容续写
+    #[serde(default)]
+    pub stream_recovery_enabled: bool,
+    /// 单个请求最多续写次数
+    #[serde(default = "default_stream_recovery_max_attempts")]
+    pub stream_recovery_max_attempts: u32,
+    /// 后台健康探测开关（仅自动故障转移开启时探测队列中的供应商）
+    #[serde(default)]
+    pub health_probe_enabled: bool,
+    /// 健康供应商的探测间隔（秒）；熔断中的供应商在冷却结束后立即探测
+    #[serde(default = "default_health_probe_interval_secs")]
+    pub health_probe_interval_secs: u32,
+    /// 每日最多探测次数，0 表示不限
+    #[serde(default = "default_health_probe_daily_limit")]
+    pub health_probe_daily_limit: u32,
+    /// 每日探测费用上限（USD），按探测日志中记录的估算费用累计，None 表示不限
+    #[serde(default)]
+    pub health_probe_daily_cost_usd: Option<String>,
+    /// 探测的默认模型：仅用于未配置自身模型的供应商，None 时沿用流式健康检查的模型
+    #[serde(default)]
+    pub health_probe_model: Option<String>,
+}
+
+fn default_stream_recovery_max_attempts() -> u32 {
+    1
+}
+
+fn default_health_probe_interval_secs() -> u32 {
+    300
+}
+
+fn default_health_probe_daily_limit() -> u32 {
+    200
+}
+
+fn default_hedge_delay_ms() -> u32 {
+    3000
+}
+
+fn default_response_cache_ttl_seconds() -> u32 {
+    3600
+}
+
+fn default_response_cache_max_mb() -> u32 {
+    50
+}
+
+fn default_payload_retention_days() -> u32 {
+    7
+}
+
+fn default_payload_max_body_kb() -> u32 {
+    1024
+}
+
+/// 负载均衡策略
+///
+/// 决定故障转移队列中各供应商的尝试顺序，熔断器仍会过滤掉不可用的供应商。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadBalanceStrategy {
+    /// 按队列顺序（sort_index），首个可用供应商承担全部流量
+    #[default]
+    Priority,
+    /// 按供应商权重随机
+    WeightedRandom,
+    /// 轮询
+    RoundRobin,
+    /// 最近平均延迟最低优先
+    LeastLatency,
+    /// 在途请求最少优先
+    LeastInFlight,
+}
+
+impl LoadBalanceStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoadBalanceStrategy::Priority => "priority",
+            LoadBalanceStrategy::WeightedRandom => "weighted_random",
+            LoadBalanceStrategy::RoundRobin => "round_robin",
+            LoadBalanceStrategy::LeastLatency => "least_latency",
+            LoadBalanceStrategy::LeastInFlight => "least_in_flight",
+        }
+    }
+
+    /// 是否会打乱队列顺序（非 priority 策略）
+    pub fn is_balanced(&self) -> bool {
+        !matches!(self, LoadBalanceStrategy::Priority)
+    }
+}
+
+impl std::str::FromStr for LoadBalanceStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "priority" => Ok(LoadBalanceStrategy::Priority),
+            "weighted_random" => Ok(LoadBalanceStrategy::WeightedRandom),
+            "round_robin" => Ok(LoadBalanceStrategy::RoundRobin),
+            "least_latency" => Ok(LoadBalanceStrategy::LeastLatency),
+            "least_in_flight" => Ok(LoadBalanceStrategy::LeastInFlight),
+            other => Err(format!("未知的负载均衡策略: {other}")),
+        }
+    }
 }
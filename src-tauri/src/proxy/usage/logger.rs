@@ -4,6 +4,7 @@ use super::calculator::{CostBreakdown, CostCalculator, ModelPricing};
 use super::parser::TokenUsage;
 use crate::database::Database;
 use crate::error::AppError;
+use crate::proxy::metrics::ProxyMetrics;
 use crate::services::usage_stats::find_model_pricing_row;
 use rust_decimal::Decimal;
 use std::time::SystemTime;
@@ -28,20 +29,52 @@ pub struct RequestLog {
     pub is_streaming: bool,
     /// 成本倍数
     pub cost_multiplier: String,
+    /// 是否命中本地响应缓存
+    pub cache_hit: bool,
 }
 
 /// 使用量记录器
 pub struct UsageLogger<'a> {
     db: &'a Database,
+    metrics: Option<&'a ProxyMetrics>,
+    api_key: Option<&'a str>,
+    stream_recoveries: u32,
 }
 
 impl<'a> UsageLogger<'a> {
     pub fn new(db: &'a Database) -> Self {
-        Self { db }
+        Self {
+            db,
+            metrics: None,
+            api_key: None,
+            stream_recoveries: 0,
+        }
+    }
+
+    /// 同时把请求累加到 Prometheus 指标
+    pub fn with_metrics(mut self, metrics: &'a ProxyMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// 记录请求使用的 API Key（已遮蔽），用于 Key 池的用量归属
+    pub fn with_api_key(mut self, api_key: Option<&'a str>) -> Self {
+        self.api_key = api_key;
+        self
+    }
+
+    /// 记录流式中断后的续写次数
+    pub fn with_stream_recoveries(mut self, stream_recoveries: u32) -> Self {
+        self.stream_recoveries = stream_recoveries;
+        self
     }
 
     /// 记录成功的请求
     pub fn log_request(&self, log: &RequestLog) -> Result<(), AppError> {
+        if let Some(metrics) = self.metrics {
+            metrics.observe_request(log);
+        }
+
         let conn = crate::database::lock_conn!(self.db.conn);
 
         let (input_cost, output_cost, cache_read_cost, cache_creation_cost, total_cost) =
@@ -74,8 +107,9 @@ impl<'a> UsageLogger<'a> {
                 input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens,
                 input_cost_usd, output_cost_usd, cache_read_cost_usd, cache_creation_cost_usd, total_cost_usd,
                 latency_ms, first_token_ms, status_code, error_message, session_id,
-                provider_type, is_streaming, cost_multiplier, created_at
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22)",
+                provider_type, is_streaming, cost_multiplier, created_at, cache_hit, api_key,
+                stream_recoveries
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25)",
             rusqlite::params![
                 log.request_id,
                 log.provider_id,
@@ -99,6 +133,9 @@ impl<'a> UsageLogger<'a> {
                 log.is_streaming as i64,
                 log.cost_multiplier,
                 created_at,
+                log.cache_hit as i64,
+                self.api_key,
+                self.stream_recoveries,
             ],
         )
         .map_err(|e| AppError::Database(format!("记录请求日志失败: {e}")))?;
@@ -135,6 +172,7 @@ impl<'a> UsageLogger<'a> {
             provider_type: None,
             is_streaming: false,
             cost_multiplier: "1.0".to_string(),
+            cache_hit: false,
         };
 
         self.log_request(&log)
@@ -172,6 +210,43 @@ impl<'a> UsageLogger<'a> {
             provider_type,
             is_streaming,
             cost_multiplier: "1.0".to_string(),
+            cache_hit: false,
+        };
+
+        self.log_request(&log)
+    }
+
+    /// 记录命中本地响应缓存的请求
+    ///
+    /// 未请求上游，不计 token 与费用
+    #[allow(clippy::too_many_arguments)]
+    pub fn log_cache_hit(
+        &self,
+        request_id: String,
+        provider_id: String,
+        app_type: String,
+        model: String,
+        status_code: u16,
+        latency_ms: u64,
+        is_streaming: bool,
+        session_id: Option<String>,
+    ) -> Result<(), AppError> {
+        let log = RequestLog {
+            request_id,
+            provider_id,
+            app_type,
+            model,
+            usage: TokenUsage::default(),
+            cost: None,
+            latency_ms,
+            first_token_ms: None,
+            status_code,
+            error_message: None,
+            session_id,
+            provider_type: None,
+            is_streaming,
+            cost_multiplier: "1.0".to_string(),
+            cache_hit: true,
         };
 
         self.log_request(&log)
@@ -231,6 +306,7 @@ impl<'a> UsageLogger<'a> {
             provider_type,
             is_streaming,
             cost_multiplier: cost_multiplier.to_string(),
+            cache_hit: false,
         };
 
         self.log_request(&log)
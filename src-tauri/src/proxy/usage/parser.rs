@@ -109,6 +109,15 @@ impl TokenUsage {
                                     usage.input_tokens = input as u32;
                                 }
                             }
+                            // OpenAI 兼容上游的缓存命中同样在末尾的 usage 中返回
+                            if usage.cache_read_tokens == 0 {
+                                if let Some(cache_read) = delta_usage
+                                    .get("cache_read_input_tokens")
+                                    .and_then(|v| v.as_u64())
+                                {
+                                    usage.cache_read_tokens = cache_read as u32;
+                                }
+                            }
                         }
                     }
                     _ => {}
@@ -445,6 +454,33 @@ mod tests {
         assert_eq!(usage.model, None);
     }
 
+    #[test]
+    fn test_claude_stream_parsing_usage_in_delta() {
+        // OpenAI 兼容上游转换后的流：usage 全部在 message_delta 中
+        let events = vec![
+            json!({
+                "type": "message_start",
+                "message": {
+                    "model": "deepseek-chat",
+                    "usage": {"input_tokens": 0, "output_tokens": 0}
+                }
+            }),
+            json!({
+                "type": "message_delta",
+                "usage": {
+                    "input_tokens": 40,
+                    "cache_read_input_tokens": 60,
+                    "output_tokens": 20
+                }
+            }),
+        ];
+
+        let usage = TokenUsage::from_claude_stream_events(&events).unwrap();
+        assert_eq!(usage.input_tokens, 40);
+        assert_eq!(usage.cache_read_tokens, 60);
+        assert_eq!(usage.output_tokens, 20);
+    }
+
     #[test]
     fn test_openrouter_response_parsing() {
         let response = json!({
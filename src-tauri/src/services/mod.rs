@@ -5,6 +5,7 @@ pub mod mcp;
 pub mod prompt;
 pub mod provider;
 pub mod proxy;
+pub mod replay;
 pub mod skill;
 pub mod speedtest;
 pub mod stream_check;
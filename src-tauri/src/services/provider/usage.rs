@@ -4,7 +4,7 @@
 
 use crate::app_config::AppType;
 use crate::error::AppError;
-use crate::provider::{UsageData, UsageResult, UsageScript};
+use crate::provider::{OutboundConfig, UsageData, UsageResult, UsageScript};
 use crate::settings;
 use crate::store::AppState;
 use crate::usage_script;
@@ -17,6 +17,7 @@ pub(crate) async fn execute_and_format_usage_result(
     timeout: u64,
     access_token: Option<&str>,
     user_id: Option<&str>,
+    outbound: Option<&OutboundConfig>,
 ) -> Result<UsageResult, AppError> {
     match usage_script::execute_usage_script(
         script_code,
@@ -25,6 +26,7 @@ pub(crate) async fn execute_and_format_usage_result(
         timeout,
         access_token,
         user_id,
+        outbound,
     )
     .await
     {
@@ -113,7 +115,7 @@ pub async fn query_usage(
     app_type: AppType,
     provider_id: &str,
 ) -> Result<UsageResult, AppError> {
-    let (script_code, timeout, api_key, base_url, access_token, user_id) = {
+    let (script_code, timeout, api_key, base_url, access_token, user_id, outbound) = {
         let providers = state.db.get_all_providers(app_type.as_str())?;
         let provider = providers.get(provider_id).ok_or_else(|| {
             AppError::localized(
@@ -164,6 +166,7 @@ pub async fn query_usage(
             base_url,
             usage_script.access_token.clone(),
             usage_script.user_id.clone(),
+            provider.outbound().cloned(),
         )
     };
 
@@ -174,6 +177,7 @@ pub async fn query_usage(
         timeout,
         access_token.as_deref(),
         user_id.as_deref(),
+        outbound.as_ref(),
     )
     .await
 }
@@ -181,9 +185,9 @@ pub async fn query_usage(
 /// Test usage script (using temporary script content, not saved)
 #[allow(clippy::too_many_arguments)]
 pub async fn test_usage_script(
-    _state: &AppState,
-    _app_type: AppType,
-    _provider_id: &str,
+    state: &AppState,
+    app_type: AppType,
+    provider_id: &str,
     script_code: &str,
     timeout: u64,
     api_key: Option<&str>,
@@ -191,7 +195,13 @@ pub async fn test_usage_script(
     access_token: Option<&str>,
     user_id: Option<&str>,
 ) -> Result<UsageResult, AppError> {
-    // Use provided credential parameters directly for testing
+    // Use provided credential parameters directly for testing;
+    // outbound network settings come from the saved provider (if any)
+    let outbound = state
+        .db
+        .get_provider_by_id(provider_id, app_type.as_str())?
+        .and_then(|provider| provider.outbound().cloned());
+
     execute_and_format_usage_result(
         script_code,
         api_key.unwrap_or(""),
@@ -199,6 +209,7 @@ pub async fn test_usage_script(
         timeout,
         access_token,
         user_id,
+        outbound.as_ref(),
     )
     .await
 }
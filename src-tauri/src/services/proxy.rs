@@ -6,6 +6,9 @@ use crate::app_config::AppType;
 use crate::config::{get_claude_settings_path, read_json_file, write_json_file};
 use crate::database::Database;
 use crate::provider::Provider;
+use crate::proxy::inbound_auth::{generate_access_token, ACCESS_TOKEN_PREFIX};
+use crate::proxy::key_pool::{KeyPoolManager, PoolKeyStatus};
+use crate::proxy::providers::get_adapter;
 use crate::proxy::server::ProxyServer;
 use crate::proxy::types::*;
 use crate::services::provider::write_live_snapshot;
@@ -15,8 +18,22 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 
 /// 用于接管 Live 配置时的占位符（避免客户端提示缺少 key，同时不泄露真实 Token）
+///
+/// 启用入站认证时改为写入本地访问令牌（见 `live_proxy_token`）。
 const PROXY_TOKEN_PLACEHOLDER: &str = "PROXY_MANAGED";
 
+/// 早期版本在 Unix 域套接字监听时写入 Live 配置的地址前缀，清理残留接管配置时仍需识别
+const UNIX_PROXY_SCHEME: &str = "http+unix://";
+
+/// Claude Code / Codex / Gemini CLI 只能通过 HTTP(S) 地址连接代理，Unix 套接字模式下无法接管
+const UNIX_TAKEOVER_UNSUPPORTED: &str =
+    "Unix 套接字监听模式下客户端无法通过地址连接代理，不支持接管应用配置，请改用 TCP 或 TLS 监听";
+
+/// 是否为接管时由代理写入的令牌（占位符或本地访问令牌），这类令牌不能同步回供应商配置
+fn is_proxy_managed_token(token: &str) -> bool {
+    token == PROXY_TOKEN_PLACEHOLDER || token.starts_with(ACCESS_TOKEN_PREFIX)
+}
+
 #[derive(Clone)]
 pub struct ProxyService {
     db: Arc<Database>,
@@ -93,6 +110,8 @@ impl ProxyService {
 
     /// 启动代理服务器（带 Live 配置接管）
     pub async fn start_with_takeover(&self) -> Result<ProxyServerInfo, String> {
+        self.ensure_takeover_supported().await?;
+
         // 1. 备份各应用的 Live 配置
         self.backup_live_configs().await?;
 
@@ -188,6 +207,8 @@ impl ProxyService {
         let app_type_str = app.as_str();
 
         if enabled {
+            self.ensure_takeover_supported().await?;
+
             // 1) 代理服务未运行则自动启动
             if !self.is_running().await {
                 self.start().await?;
@@ -349,7 +370,7 @@ impl ProxyService {
                                     .map(|s| (key, s.trim()))
                             })
                             .filter(|(_, token)| {
-                                !token.is_empty() && *token != PROXY_TOKEN_PLACEHOLDER
+                                !token.is_empty() && !is_proxy_managed_token(token)
                             });
 
                             if let Some((token_key, token)) = token_pair {
@@ -422,7 +443,7 @@ impl ProxyService {
                             .and_then(|v| v.get("OPENAI_API_KEY"))
                             .and_then(|v| v.as_str())
                             .map(|s| s.trim())
-                            .filter(|s| !s.is_empty() && *s != PROXY_TOKEN_PLACEHOLDER)
+                            .filter(|s| !s.is_empty() && !is_proxy_managed_token(s))
                         {
                             if let Some(auth_obj) = provider
                                 .settings_config
@@ -463,7 +484,7 @@ impl ProxyService {
                             .and_then(|v| v.get("GEMINI_API_KEY"))
                             .and_then(|v| v.as_str())
                             .map(|s| s.trim())
-                            .filter(|s| !s.is_empty() && *s != PROXY_TOKEN_PLACEHOLDER)
+                            .filter(|s| !s.is_empty() && !is_proxy_managed_token(s))
                         {
                             if let Some(env_obj) = provider
                                 .settings_config
@@ -618,11 +639,7 @@ impl ProxyService {
             .await
             .map_err(|e| format!("删除备份失败: {e}"))?;
 
-        // 5. 重置健康状态
-        self.db
-            .clear_all_provider_health()
-            .await
-            .map_err(|e| format!("重置健康状态失败: {e}"))?;
+        // 注意：不清除健康状态，熔断器状态在下次启动代理时恢复
 
         log::info!("代理已停止，Live 配置已恢复（保留代理状态，下次启动将自动恢复）");
         Ok(())
@@ -703,13 +720,30 @@ impl ProxyService {
             connect_host
         };
 
-        let proxy_origin = format!("http://{}:{}", connect_host_for_url, config.listen_port);
+        let proxy_origin = match config.listen_mode {
+            ListenMode::Tcp => format!("http://{}:{}", connect_host_for_url, config.listen_port),
+            ListenMode::Tls => format!("https://{}:{}", connect_host_for_url, config.listen_port),
+            ListenMode::Unix => return Err(UNIX_TAKEOVER_UNSUPPORTED.to_string()),
+        };
         let proxy_url = proxy_origin.clone();
         let proxy_codex_base_url = format!("{}/v1", proxy_origin.trim_end_matches('/'));
 
         Ok((proxy_url, proxy_codex_base_url))
     }
 
+    /// 当前监听方式是否支持接管 Live 配置（Unix 套接字模式不支持）
+    async fn ensure_takeover_supported(&self) -> Result<(), String> {
+        let config = self
+            .db
+            .get_global_proxy_config()
+            .await
+            .map_err(|e| format!("获取全局代理配置失败: {e}"))?;
+        if config.listen_mode == ListenMode::Unix {
+            return Err(UNIX_TAKEOVER_UNSUPPORTED.to_string());
+        }
+        Ok(())
+    }
+
     /// 接管各应用的 Live 配置（写入代理地址）
     ///
     /// 代理服务器的路由已经根据 API 端点自动区分应用类型：
@@ -720,6 +754,7 @@ impl ProxyService {
     /// 因此不需要在 URL 中添加应用前缀。
     async fn takeover_live_configs(&self) -> Result<(), String> {
         let (proxy_url, proxy_codex_base_url) = self.build_proxy_urls().await?;
+        let live_token = self.live_proxy_token().await;
 
         // Claude: 修改 ANTHROPIC_BASE_URL，使用占位符替代真实 Token（代理会注入真实 Token）
         if let Ok(mut live_config) = self.read_claude_live() {
@@ -737,21 +772,18 @@ impl ProxyService {
                 let mut replaced_any = false;
                 for key in token_keys {
                     if env.contains_key(key) {
-                        env.insert(key.to_string(), json!(PROXY_TOKEN_PLACEHOLDER));
+                        env.insert(key.to_string(), json!(&live_token));
                         replaced_any = true;
                     }
                 }
 
                 if !replaced_any {
-                    env.insert(
-                        "ANTHROPIC_AUTH_TOKEN".to_string(),
-                        json!(PROXY_TOKEN_PLACEHOLDER),
-                    );
+                    env.insert("ANTHROPIC_AUTH_TOKEN".to_string(), json!(&live_token));
                 }
             } else {
                 live_config["env"] = json!({
                     "ANTHROPIC_BASE_URL": &proxy_url,
-                    "ANTHROPIC_AUTH_TOKEN": PROXY_TOKEN_PLACEHOLDER
+                    "ANTHROPIC_AUTH_TOKEN": &live_token
                 });
             }
             self.write_claude_live(&live_config)?;
@@ -762,7 +794,7 @@ impl ProxyService {
         if let Ok(mut live_config) = self.read_codex_live() {
             // 1. 修改 auth.json 中的 OPENAI_API_KEY（使用占位符）
             if let Some(auth) = live_config.get_mut("auth").and_then(|v| v.as_object_mut()) {
-                auth.insert("OPENAI_API_KEY".to_string(), json!(PROXY_TOKEN_PLACEHOLDER));
+                auth.insert("OPENAI_API_KEY".to_string(), json!(&live_token));
             }
 
             // 2. 修改 config.toml 中的 base_url
@@ -782,11 +814,11 @@ impl ProxyService {
             if let Some(env) = live_config.get_mut("env").and_then(|v| v.as_object_mut()) {
                 env.insert("GOOGLE_GEMINI_BASE_URL".to_string(), json!(&proxy_url));
                 // 使用占位符，避免显示缺少 key 的警告
-                env.insert("GEMINI_API_KEY".to_string(), json!(PROXY_TOKEN_PLACEHOLDER));
+                env.insert("GEMINI_API_KEY".to_string(), json!(&live_token));
             } else {
                 live_config["env"] = json!({
                     "GOOGLE_GEMINI_BASE_URL": &proxy_url,
-                    "GEMINI_API_KEY": PROXY_TOKEN_PLACEHOLDER
+                    "GEMINI_API_KEY": &live_token
                 });
             }
             self.write_gemini_live(&live_config)?;
@@ -799,6 +831,7 @@ impl ProxyService {
     /// 接管指定应用的 Live 配置（严格模式：目标配置不存在则返回错误）
     async fn takeover_live_config_strict(&self, app_type: &AppType) -> Result<(), String> {
         let (proxy_url, proxy_codex_base_url) = self.build_proxy_urls().await?;
+        let live_token = self.live_proxy_token().await;
 
         match app_type {
             AppType::Claude => {
@@ -816,21 +849,18 @@ impl ProxyService {
                     let mut replaced_any = false;
                     for key in token_keys {
                         if env.contains_key(key) {
-                            env.insert(key.to_string(), json!(PROXY_TOKEN_PLACEHOLDER));
+                            env.insert(key.to_string(), json!(&live_token));
                             replaced_any = true;
                         }
                     }
 
                     if !replaced_any {
-                        env.insert(
-                            "ANTHROPIC_AUTH_TOKEN".to_string(),
-                            json!(PROXY_TOKEN_PLACEHOLDER),
-                        );
+                        env.insert("ANTHROPIC_AUTH_TOKEN".to_string(), json!(&live_token));
                     }
                 } else {
                     live_config["env"] = json!({
                         "ANTHROPIC_BASE_URL": &proxy_url,
-                        "ANTHROPIC_AUTH_TOKEN": PROXY_TOKEN_PLACEHOLDER
+                        "ANTHROPIC_AUTH_TOKEN": &live_token
                     });
                 }
 
@@ -841,7 +871,7 @@ impl ProxyService {
                 let mut live_config = self.read_codex_live()?;
 
                 if let Some(auth) = live_config.get_mut("auth").and_then(|v| v.as_object_mut()) {
-                    auth.insert("OPENAI_API_KEY".to_string(), json!(PROXY_TOKEN_PLACEHOLDER));
+                    auth.insert("OPENAI_API_KEY".to_string(), json!(&live_token));
                 }
 
                 let config_str = live_config
@@ -859,11 +889,11 @@ impl ProxyService {
 
                 if let Some(env) = live_config.get_mut("env").and_then(|v| v.as_object_mut()) {
                     env.insert("GOOGLE_GEMINI_BASE_URL".to_string(), json!(&proxy_url));
-                    env.insert("GEMINI_API_KEY".to_string(), json!(PROXY_TOKEN_PLACEHOLDER));
+                    env.insert("GEMINI_API_KEY".to_string(), json!(&live_token));
                 } else {
                     live_config["env"] = json!({
                         "GOOGLE_GEMINI_BASE_URL": &proxy_url,
-                        "GEMINI_API_KEY": PROXY_TOKEN_PLACEHOLDER
+                        "GEMINI_API_KEY": &live_token
                     });
                 }
 
@@ -878,6 +908,7 @@ impl ProxyService {
     /// 接管指定应用的 Live 配置（尽力而为：配置不存在/读取失败则跳过）
     async fn takeover_live_config_best_effort(&self, app_type: &AppType) -> Result<(), String> {
         let (proxy_url, proxy_codex_base_url) = self.build_proxy_urls().await?;
+        let live_token = self.live_proxy_token().await;
 
         match app_type {
             AppType::Claude => {
@@ -895,21 +926,18 @@ impl ProxyService {
                         let mut replaced_any = false;
                         for key in token_keys {
                             if env.contains_key(key) {
-                                env.insert(key.to_string(), json!(PROXY_TOKEN_PLACEHOLDER));
+                                env.insert(key.to_string(), json!(&live_token));
                                 replaced_any = true;
                             }
                         }
 
                         if !replaced_any {
-                            env.insert(
-                                "ANTHROPIC_AUTH_TOKEN".to_string(),
-                                json!(PROXY_TOKEN_PLACEHOLDER),
-                            );
+                            env.insert("ANTHROPIC_AUTH_TOKEN".to_string(), json!(&live_token));
                         }
                     } else {
                         live_config["env"] = json!({
                             "ANTHROPIC_BASE_URL": &proxy_url,
-                            "ANTHROPIC_AUTH_TOKEN": PROXY_TOKEN_PLACEHOLDER
+                            "ANTHROPIC_AUTH_TOKEN": &live_token
                         });
                     }
 
@@ -920,7 +948,7 @@ impl ProxyService {
                 if let Ok(mut live_config) = self.read_codex_live() {
                     if let Some(auth) = live_config.get_mut("auth").and_then(|v| v.as_object_mut())
                     {
-                        auth.insert("OPENAI_API_KEY".to_string(), json!(PROXY_TOKEN_PLACEHOLDER));
+                        auth.insert("OPENAI_API_KEY".to_string(), json!(&live_token));
                     }
 
                     let config_str = live_config
@@ -938,11 +966,11 @@ impl ProxyService {
                 if let Ok(mut live_config) = self.read_gemini_live() {
                     if let Some(env) = live_config.get_mut("env").and_then(|v| v.as_object_mut()) {
                         env.insert("GOOGLE_GEMINI_BASE_URL".to_string(), json!(&proxy_url));
-                        env.insert("GEMINI_API_KEY".to_string(), json!(PROXY_TOKEN_PLACEHOLDER));
+                        env.insert("GEMINI_API_KEY".to_string(), json!(&live_token));
                     } else {
                         live_config["env"] = json!({
                             "GOOGLE_GEMINI_BASE_URL": &proxy_url,
-                            "GEMINI_API_KEY": PROXY_TOKEN_PLACEHOLDER
+                            "GEMINI_API_KEY": &live_token
                         });
                     }
 
@@ -1121,10 +1149,15 @@ impl ProxyService {
 
     fn is_local_proxy_url(url: &str) -> bool {
         let url = url.trim();
-        if !url.starts_with("http://") {
-            return false;
+        if url.starts_with(UNIX_PROXY_SCHEME) {
+            return true;
         }
-        let rest = &url["http://".len()..];
+        let Some(rest) = url
+            .strip_prefix("http://")
+            .or_else(|| url.strip_prefix("https://"))
+        else {
+            return false;
+        };
         rest.starts_with("127.0.0.1")
             || rest.starts_with("localhost")
             || rest.starts_with("0.0.0.0")
@@ -1147,7 +1180,11 @@ impl ProxyService {
             "OPENROUTER_API_KEY",
             "OPENAI_API_KEY",
         ] {
-            if env.get(key).and_then(|v| v.as_str()) == Some(PROXY_TOKEN_PLACEHOLDER) {
+            if env
+                .get(key)
+                .and_then(|v| v.as_str())
+                .is_some_and(is_proxy_managed_token)
+            {
                 env.remove(key);
             }
         }
@@ -1169,7 +1206,10 @@ impl ProxyService {
         let mut config = self.read_codex_live()?;
 
         if let Some(auth) = config.get_mut("auth").and_then(|v| v.as_object_mut()) {
-            if auth.get("OPENAI_API_KEY").and_then(|v| v.as_str()) == Some(PROXY_TOKEN_PLACEHOLDER)
+            if auth
+                .get("OPENAI_API_KEY")
+                .and_then(|v| v.as_str())
+                .is_some_and(is_proxy_managed_token)
             {
                 auth.remove("OPENAI_API_KEY");
             }
@@ -1238,7 +1278,11 @@ impl ProxyService {
             return Ok(());
         };
 
-        if env.get("GEMINI_API_KEY").and_then(|v| v.as_str()) == Some(PROXY_TOKEN_PLACEHOLDER) {
+        if env
+            .get("GEMINI_API_KEY")
+            .and_then(|v| v.as_str())
+            .is_some_and(is_proxy_managed_token)
+        {
             env.remove("GEMINI_API_KEY");
         }
 
@@ -1325,7 +1369,11 @@ impl ProxyService {
             "OPENROUTER_API_KEY",
             "OPENAI_API_KEY",
         ] {
-            if env.get(key).and_then(|v| v.as_str()) == Some(PROXY_TOKEN_PLACEHOLDER) {
+            if env
+                .get(key)
+                .and_then(|v| v.as_str())
+                .is_some_and(is_proxy_managed_token)
+            {
                 return true;
             }
         }
@@ -1338,7 +1386,9 @@ impl ProxyService {
             Some(auth) => auth,
             None => return false,
         };
-        auth.get("OPENAI_API_KEY").and_then(|v| v.as_str()) == Some(PROXY_TOKEN_PLACEHOLDER)
+        auth.get("OPENAI_API_KEY")
+            .and_then(|v| v.as_str())
+            .is_some_and(is_proxy_managed_token)
     }
 
     fn is_gemini_live_taken_over(config: &Value) -> bool {
@@ -1346,7 +1396,9 @@ impl ProxyService {
             Some(env) => env,
             None => return false,
         };
-        env.get("GEMINI_API_KEY").and_then(|v| v.as_str()) == Some(PROXY_TOKEN_PLACEHOLDER)
+        env.get("GEMINI_API_KEY")
+            .and_then(|v| v.as_str())
+            .is_some_and(is_proxy_managed_token)
     }
 
     /// 从供应商配置更新 Live 备份（用于代理模式下的热切换）
@@ -1565,6 +1617,113 @@ impl ProxyService {
             .map_err(|e| format!("获取代理配置失败: {e}"))
     }
 
+    /// 更新全局代理配置
+    ///
+    /// 开启入站认证而尚无访问令牌时自动生成；认证设置变化后同步更新已接管应用 Live 配置中的令牌。
+    pub async fn update_global_config(&self, mut config: GlobalProxyConfig) -> Result<(), String> {
+        let previous = self
+            .db
+            .get_global_proxy_config()
+            .await
+            .map_err(|e| format!("获取全局代理配置失败: {e}"))?;
+
+        if config.listen_mode == ListenMode::Unix {
+            let takeover = self.get_takeover_status().await?;
+            if takeover.claude || takeover.codex || takeover.gemini {
+                return Err(format!("{UNIX_TAKEOVER_UNSUPPORTED}（请先关闭接管）"));
+            }
+        }
+
+        if config.auth_enabled && config.access_token.as_deref().unwrap_or("").is_empty() {
+            config.access_token = Some(generate_access_token());
+        }
+        let auth_changed = config.auth_enabled != previous.auth_enabled
+            || (config.auth_enabled && config.access_token != previous.access_token);
+        let listener_changed = config.listen_address != previous.listen_address
+            || config.listen_port != previous.listen_port
+            || config.listen_mode != previous.listen_mode
+            || config.tls_cert_path != previous.tls_cert_path
+            || config.tls_key_path != previous.tls_key_path
+            || config.unix_socket_path != previous.unix_socket_path;
+
+        self.db
+            .update_global_proxy_config(config)
+            .await
+            .map_err(|e| format!("更新全局代理配置失败: {e}"))?;
+
+        if listener_changed && self.is_running().await {
+            // 重启时会一并刷新 Live 配置（含访问令牌）
+            let runtime_config = self
+                .db
+                .get_proxy_config()
+                .await
+                .map_err(|e| format!("获取代理配置失败: {e}"))?;
+            return self.restart_server(runtime_config).await;
+        }
+
+        if (auth_changed || listener_changed) && self.refresh_taken_over_live_configs().await? {
+            log::info!("已同步更新 Live 配置中的代理地址与本地访问令牌");
+        }
+        Ok(())
+    }
+
+    /// 重新生成本地访问令牌，旧令牌立即失效
+    pub async fn regenerate_access_token(&self) -> Result<String, String> {
+        let mut config = self
+            .db
+            .get_global_proxy_config()
+            .await
+            .map_err(|e| format!("获取全局代理配置失败: {e}"))?;
+
+        let token = generate_access_token();
+        config.access_token = Some(token.clone());
+        self.db
+            .update_global_proxy_config(config.clone())
+            .await
+            .map_err(|e| format!("保存访问令牌失败: {e}"))?;
+
+        if config.auth_enabled && self.refresh_taken_over_live_configs().await? {
+            log::info!("已同步更新 Live 配置中的本地访问令牌");
+        }
+        Ok(token)
+    }
+
+    /// 接管 Live 时写入的令牌：启用入站认证时为本地访问令牌，否则为占位符
+    async fn live_proxy_token(&self) -> String {
+        match self.db.get_global_proxy_config().await {
+            Ok(config) if config.auth_enabled => config
+                .access_token
+                .filter(|token| !token.is_empty())
+                .unwrap_or_else(|| PROXY_TOKEN_PLACEHOLDER.to_string()),
+            _ => PROXY_TOKEN_PLACEHOLDER.to_string(),
+        }
+    }
+
+    /// 重新写入所有已接管应用的 Live 配置（代理地址与令牌），返回是否有应用被更新
+    async fn refresh_taken_over_live_configs(&self) -> Result<bool, String> {
+        let Ok(takeover) = self.get_takeover_status().await else {
+            return Ok(false);
+        };
+
+        let mut updated_any = false;
+        if takeover.claude {
+            self.takeover_live_config_best_effort(&AppType::Claude)
+                .await?;
+            updated_any = true;
+        }
+        if takeover.codex {
+            self.takeover_live_config_best_effort(&AppType::Codex)
+                .await?;
+            updated_any = true;
+        }
+        if takeover.gemini {
+            self.takeover_live_config_best_effort(&AppType::Gemini)
+                .await?;
+            updated_any = true;
+        }
+        Ok(updated_any)
+    }
+
     /// 更新代理配置
     pub async fn update_config(&self, config: &ProxyConfig) -> Result<(), String> {
         // 记录旧配置用于判定是否需要重启
@@ -1575,8 +1734,13 @@ impl ProxyService {
             .map_err(|e| format!("获取代理配置失败: {e}"))?;
 
         // 保存到数据库（保持 live_takeover_active 状态不变）
+        // 监听方式与证书、套接字路径属于全局配置，由 update_global_config 维护，这里沿用已保存的值
         let mut new_config = config.clone();
         new_config.live_takeover_active = previous.live_takeover_active;
+        new_config.listen_mode = previous.listen_mode;
+        new_config.tls_cert_path = previous.tls_cert_path.clone();
+        new_config.tls_key_path = previous.tls_key_path.clone();
+        new_config.unix_socket_path = previous.unix_socket_path.clone();
 
         self.db
             .update_proxy_config(new_config.clone())
@@ -1584,63 +1748,48 @@ impl ProxyService {
             .map_err(|e| format!("保存代理配置失败: {e}"))?;
 
         // 检查服务器当前状态
-        let mut server_guard = self.server.write().await;
+        let server_guard = self.server.write().await;
         if server_guard.is_none() {
             return Ok(());
         }
 
         // 判断是否需要重启（地址或端口变更）
-        let require_restart = new_config.listen_address != previous.listen_address
-            || new_config.listen_port != previous.listen_port;
-
-        if require_restart {
-            if let Some(server) = server_guard.take() {
-                server
-                    .stop()
-                    .await
-                    .map_err(|e| format!("重启前停止代理服务器失败: {e}"))?;
-            }
-
-            let app_handle = self.app_handle.read().await.clone();
-            let new_server = ProxyServer::new(new_config, self.db.clone(), app_handle);
-            new_server
-                .start()
-                .await
-                .map_err(|e| format!("重启代理服务器失败: {e}"))?;
+        if !new_config.same_listener(&previous) {
+            drop(server_guard);
+            return self.restart_server(new_config).await;
+        } else if let Some(server) = server_guard.as_ref() {
+            server.apply_runtime_config(&new_config).await;
+            log::info!("代理配置已实时应用，无需重启代理服务器");
+        }
 
-            *server_guard = Some(new_server);
-            log::info!("代理配置已更新，服务器已自动重启应用最新配置");
+        Ok(())
+    }
 
-            // 如果当前存在任意 app 的 Live 接管，需要同步更新 Live 中的代理地址（否则客户端仍指向旧端口）
-            drop(server_guard);
-            if let Ok(takeover) = self.get_takeover_status().await {
-                let mut updated_any = false;
+    /// 使用新的监听配置重启正在运行的代理服务器，并同步 Live 配置中的代理地址
+    async fn restart_server(&self, config: ProxyConfig) -> Result<(), String> {
+        let mut server_guard = self.server.write().await;
+        let Some(server) = server_guard.take() else {
+            return Ok(());
+        };
+        server
+            .stop()
+            .await
+            .map_err(|e| format!("重启前停止代理服务器失败: {e}"))?;
 
-                if takeover.claude {
-                    self.takeover_live_config_best_effort(&AppType::Claude)
-                        .await?;
-                    updated_any = true;
-                }
-                if takeover.codex {
-                    self.takeover_live_config_best_effort(&AppType::Codex)
-                        .await?;
-                    updated_any = true;
-                }
-                if takeover.gemini {
-                    self.takeover_live_config_best_effort(&AppType::Gemini)
-                        .await?;
-                    updated_any = true;
-                }
+        let app_handle = self.app_handle.read().await.clone();
+        let new_server = ProxyServer::new(config, self.db.clone(), app_handle);
+        new_server
+            .start()
+            .await
+            .map_err(|e| format!("重启代理服务器失败: {e}"))?;
 
-                if updated_any {
-                    log::info!("已同步更新 Live 配置中的代理地址");
-                }
-            }
+        *server_guard = Some(new_server);
+        log::info!("代理配置已更新，服务器已自动重启应用最新配置");
 
-            return Ok(());
-        } else if let Some(server) = server_guard.as_ref() {
-            server.apply_runtime_config(&new_config).await;
-            log::info!("代理配置已实时应用，无需重启代理服务器");
+        // 如果当前存在任意 app 的 Live 接管，需要同步更新 Live 中的代理地址（否则客户端仍指向旧端口）
+        drop(server_guard);
+        if self.refresh_taken_over_live_configs().await? {
+            log::info!("已同步更新 Live 配置中的代理地址");
         }
 
         Ok(())
@@ -1683,6 +1832,52 @@ impl ProxyService {
         }
         Ok(())
     }
+
+    /// 获取指定 Provider 的熔断器统计（含限流冷却剩余时间）
+    ///
+    /// 代理服务器未运行或该 Provider 尚未处理过请求时返回 None
+    pub async fn get_circuit_breaker_stats(
+        &self,
+        provider_id: &str,
+        app_type: &str,
+    ) -> Option<crate::proxy::CircuitBreakerStats> {
+        let server = self.server.read().await;
+        server
+            .as_ref()?
+            .get_circuit_breaker_stats(provider_id, app_type)
+            .await
+    }
+
+    /// 获取指定 Provider 的 Key 池状态
+    ///
+    /// 代理服务器未运行时仅返回配置中的 Key（无停用与选中统计）
+    pub async fn get_key_pool_status(
+        &self,
+        provider_id: &str,
+        app_type: &str,
+    ) -> Result<Vec<PoolKeyStatus>, String> {
+        let app = AppType::from_str(app_type).map_err(|e| format!("无效的应用类型: {e}"))?;
+        let provider = self
+            .db
+            .get_provider_by_id(provider_id, app_type)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("供应商不存在: {provider_id}"))?;
+        let auth = get_adapter(&app).extract_auth(&provider);
+
+        let server = self.server.read().await;
+        Ok(match server.as_ref() {
+            Some(server) => server.key_pool_status(&provider, app_type, auth.as_ref()),
+            None => KeyPoolManager::new().status(&provider, app_type, auth.as_ref()),
+        })
+    }
+
+    /// 重新启用指定 Provider 被停用的 Key（仅当代理服务器运行时有状态需要重置）
+    pub async fn reset_key_pool(&self, provider_id: &str, app_type: &str) {
+        if let Some(server) = self.server.read().await.as_ref() {
+            server.reset_key_pool(provider_id, app_type);
+            log::info!("已重置 Provider {provider_id} (app: {app_type}) 的 Key 池");
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1903,4 +2098,41 @@ model = "gpt-5.1-codex"
             "should not add ANTHROPIC_AUTH_TOKEN when absent"
         );
     }
+
+    #[tokio::test]
+    #[serial]
+    async fn build_proxy_urls_follows_listen_mode() {
+        let _home = TempHome::new();
+        let db = Arc::new(Database::memory().expect("init db"));
+        let service = ProxyService::new(db.clone());
+
+        let mut config = db.get_global_proxy_config().await.expect("global config");
+        config.listen_address = "0.0.0.0".to_string();
+        config.listen_port = 15721;
+        config.listen_mode = ListenMode::Tls;
+        db.update_global_proxy_config(config.clone())
+            .await
+            .expect("save tls config");
+        let (url, codex_url) = service.build_proxy_urls().await.expect("tls urls");
+        assert_eq!(url, "https://127.0.0.1:15721");
+        assert_eq!(codex_url, "https://127.0.0.1:15721/v1");
+        assert!(ProxyService::is_local_proxy_url(&url));
+
+        config.listen_mode = ListenMode::Unix;
+        config.unix_socket_path = Some("/tmp/cc switch/proxy.sock".to_string());
+        db.update_global_proxy_config(config)
+            .await
+            .expect("save unix config");
+        // 客户端无法连接 Unix 套接字地址，拒绝接管
+        let err = service.build_proxy_urls().await.expect_err("unix urls");
+        assert_eq!(err, UNIX_TAKEOVER_UNSUPPORTED);
+        assert!(service.ensure_takeover_supported().await.is_err());
+        // 仍能识别早期版本写入的套接字地址，便于清理残留配置
+        assert!(ProxyService::is_local_proxy_url(
+            "http+unix://%2Ftmp%2Fcc%20switch%2Fproxy.sock"
+        ));
+        assert!(!ProxyService::is_local_proxy_url(
+            "https://api.anthropic.com"
+        ));
+    }
 }
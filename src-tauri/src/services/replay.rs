@@ -0,0 +1,458 @@
+//! 请求重放服务
+//!
+//! 把已捕获载荷的请求原样发送到指定供应商，经过与代理相同的适配器、模型映射和格式转换，
+//! 返回原始响应与重放响应（含耗时、Token 与成本）以便对比。
+//! 重放不切换当前供应商、不影响熔断器，也不写入请求日志。
+
+use futures::StreamExt;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::app_config::AppType;
+use crate::database::Database;
+use crate::error::AppError;
+use crate::provider::Provider;
+use crate::proxy::error_mapper::{get_error_message, map_proxy_error_to_status};
+use crate::proxy::handler_config::{
+    UsageParserConfig, CLAUDE_PARSER_CONFIG, CODEX_PARSER_CONFIG, GEMINI_PARSER_CONFIG,
+    OPENAI_PARSER_CONFIG,
+};
+use crate::proxy::http_client::{self, ClientOptions};
+use crate::proxy::payload_capture::{is_sensitive_header, strip_secret_params, RequestPayload};
+use crate::proxy::providers::gemini_streaming::create_anthropic_sse_stream_from_gemini;
+use crate::proxy::providers::streaming::create_anthropic_sse_stream;
+use crate::proxy::providers::{
+    gemini_transform, get_adapter, responses_bridge, transform, ClaudeAdapter, CodexAdapter,
+    ProviderAdapter, ProviderType,
+};
+use crate::proxy::usage::{CostCalculator, TokenUsage, UsageLogger};
+use crate::proxy::{send_upstream_request, UpstreamOptions};
+
+/// 重放请求的默认超时（代理配置中非流式超时为 0 时使用）
+const DEFAULT_TIMEOUT_SECS: u64 = 300;
+
+/// 单侧响应（原始请求或重放请求）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayResponse {
+    pub provider_id: String,
+    pub provider_name: Option<String>,
+    pub status_code: u16,
+    pub latency_ms: Option<u64>,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub cache_read_tokens: u32,
+    pub cache_creation_tokens: u32,
+    pub total_cost_usd: Option<String>,
+    /// 响应体（客户端格式；流式响应为 SSE 事件 JSON 数组，与载荷捕获格式一致）
+    pub body: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// 重放结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayResult {
+    pub request_id: String,
+    pub app_type: String,
+    pub endpoint: String,
+    pub model: String,
+    pub is_streaming: bool,
+    pub original: ReplayResponse,
+    pub replay: ReplayResponse,
+}
+
+/// 上游响应到客户端格式的转换方式（与代理处理器保持一致）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ResponseConversion {
+    /// 透传
+    None,
+    /// OpenAI Chat Completions → Anthropic Messages
+    OpenAIToAnthropic,
+    /// Gemini generateContent → Anthropic Messages
+    GeminiToAnthropic,
+    /// Chat Completions → Responses
+    ChatToResponses,
+}
+
+/// 请求重放服务
+pub struct ReplayService;
+
+impl ReplayService {
+    /// 将已捕获的请求重放到指定供应商
+    pub async fn replay(
+        db: &Database,
+        request_id: &str,
+        provider_id: &str,
+    ) -> Result<ReplayResult, AppError> {
+        let payload = db.get_request_payload(request_id)?.ok_or_else(|| {
+            AppError::Message(format!(
+                "请求 {request_id} 没有捕获的载荷，请先在代理配置中启用载荷捕获"
+            ))
+        })?;
+        if payload.truncated {
+            return Err(AppError::Message(
+                "请求载荷超出大小上限已被截断，无法重放".to_string(),
+            ));
+        }
+
+        let body: Value = serde_json::from_str(&payload.request_body)
+            .map_err(|e| AppError::Message(format!("解析请求体失败: {e}")))?;
+        let app_type = AppType::from_str(&payload.app_type)?;
+        let provider = db
+            .get_provider_by_id(provider_id, &payload.app_type)?
+            .ok_or_else(|| AppError::Message(format!("供应商 {provider_id} 不存在")))?;
+        let config = db.get_proxy_config_for_app(&payload.app_type).await?;
+        let timeout_secs = if config.non_streaming_timeout > 0 {
+            config.non_streaming_timeout as u64
+        } else {
+            DEFAULT_TIMEOUT_SECS
+        };
+
+        let endpoint = strip_secret_params(&payload.endpoint);
+        let is_streaming = body
+            .get("stream")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+            || endpoint.contains("streamGenerateContent");
+
+        log::info!(
+            "[Replay] 重放请求 {request_id} ({}) 到供应商 {}",
+            payload.app_type,
+            provider.name
+        );
+
+        let original = Self::original_response(db, &payload)?;
+        let replay = Self::send(
+            db,
+            &app_type,
+            &provider,
+            &endpoint,
+            &body,
+            &payload,
+            timeout_secs,
+        )
+        .await;
+
+        Ok(ReplayResult {
+            request_id: payload.request_id,
+            app_type: payload.app_type,
+            endpoint,
+            model: payload.model,
+            is_streaming,
+            original,
+            replay,
+        })
+    }
+
+    /// 从载荷与请求日志还原原始响应
+    fn original_response(
+        db: &Database,
+        payload: &RequestPayload,
+    ) -> Result<ReplayResponse, AppError> {
+        let provider_name = db
+            .get_provider_by_id(&payload.provider_id, &payload.app_type)?
+            .map(|p| p.name);
+
+        let mut original = ReplayResponse {
+            provider_id: payload.provider_id.clone(),
+            provider_name,
+            status_code: payload.status_code,
+            body: payload.response_body.clone(),
+            ..Default::default()
+        };
+
+        if let Some(detail) = db.get_request_detail(&payload.request_id)? {
+            original.latency_ms = Some(detail.latency_ms);
+            original.input_tokens = detail.input_tokens;
+            original.output_tokens = detail.output_tokens;
+            original.cache_read_tokens = detail.cache_read_tokens;
+            original.cache_creation_tokens = detail.cache_creation_tokens;
+            original.total_cost_usd = Some(detail.total_cost_usd);
+            original.error_message = detail.error_message;
+        }
+
+        Ok(original)
+    }
+
+    /// 发送重放请求并解析响应
+    async fn send(
+        db: &Database,
+        app_type: &AppType,
+        provider: &Provider,
+        endpoint: &str,
+        body: &Value,
+        payload: &RequestPayload,
+        timeout_secs: u64,
+    ) -> ReplayResponse {
+        let mut result = ReplayResponse {
+            provider_id: provider.id.clone(),
+            provider_name: Some(provider.name.clone()),
+            ..Default::default()
+        };
+
+        let client = match http_client::client_for(
+            ClientOptions::with_timeout(Duration::from_secs(timeout_secs)),
+            provider.outbound(),
+        ) {
+            Ok(client) => client,
+            Err(e) => {
+                result.error_message = Some(e);
+                return result;
+            }
+        };
+
+        let adapter = get_adapter(app_type);
+        let headers = Self::rebuild_headers(&payload.request_headers);
+        let conversion = Self::conversion(app_type, provider, endpoint, adapter.as_ref());
+        // 与代理转发一致：去掉该供应商已知不支持的字段
+        let stripped = db
+            .get_incompatible_fields(app_type.as_str(), &provider.id)
+            .unwrap_or_default();
+        let start = Instant::now();
+
+        let response = match send_upstream_request(
+            &client,
+            provider,
+            endpoint,
+            body,
+            &headers,
+            adapter.as_ref(),
+            &UpstreamOptions {
+                stripped_fields: &stripped,
+                ..Default::default()
+            },
+        )
+        .await
+        {
+            Ok(response) => response,
+            Err(e) => {
+                result.latency_ms = Some(start.elapsed().as_millis() as u64);
+                result.status_code = map_proxy_error_to_status(&e);
+                result.error_message = Some(get_error_message(&e));
+                return result;
+            }
+        };
+
+        result.status_code = response.status().as_u16();
+        let is_sse = response
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|ct| ct.contains("text/event-stream"));
+        let parser = Self::parser_config(app_type, endpoint);
+
+        let usage = if is_sse {
+            match Self::collect_stream(response, conversion).await {
+                Ok(events) => {
+                    result.body = Some(serde_json::to_string(&events).unwrap_or_default());
+                    (parser.stream_parser)(&events)
+                }
+                Err(e) => {
+                    result.error_message = Some(e);
+                    None
+                }
+            }
+        } else {
+            match Self::read_body(response, conversion).await {
+                Ok(json) => {
+                    let usage = (parser.response_parser)(&json);
+                    result.body = Some(json.to_string());
+                    usage
+                }
+                Err(e) => {
+                    result.error_message = Some(e);
+                    None
+                }
+            }
+        };
+        result.latency_ms = Some(start.elapsed().as_millis() as u64);
+
+        if let Some(usage) = usage {
+            let model = usage.model.clone().unwrap_or_else(|| payload.model.clone());
+            result.total_cost_usd = Self::calculate_cost(db, provider, &model, &usage);
+            result.input_tokens = usage.input_tokens;
+            result.output_tokens = usage.output_tokens;
+            result.cache_read_tokens = usage.cache_read_tokens;
+            result.cache_creation_tokens = usage.cache_creation_tokens;
+        }
+
+        result
+    }
+
+    /// 从捕获的请求头还原请求头（已遮蔽的敏感头不参与重放，认证由适配器添加）
+    fn rebuild_headers(captured: &Value) -> axum::http::HeaderMap {
+        let mut headers = axum::http::HeaderMap::new();
+        let Some(map) = captured.as_object() else {
+            return headers;
+        };
+        for (name, value) in map {
+            if is_sensitive_header(name) {
+                continue;
+            }
+            let (Ok(name), Some(Ok(value))) = (
+                axum::http::HeaderName::from_str(name),
+                value.as_str().map(axum::http::HeaderValue::from_str),
+            ) else {
+                continue;
+            };
+            headers.insert(name, value);
+        }
+        headers
+    }
+
+    /// 判断上游响应需要的格式转换
+    fn conversion(
+        app_type: &AppType,
+        provider: &Provider,
+        endpoint: &str,
+        adapter: &dyn ProviderAdapter,
+    ) -> ResponseConversion {
+        if !adapter.needs_transform(provider) {
+            return ResponseConversion::None;
+        }
+        match app_type {
+            AppType::Claude => {
+                if ClaudeAdapter::new().provider_type(provider) == ProviderType::GeminiCompat {
+                    ResponseConversion::GeminiToAnthropic
+                } else {
+                    ResponseConversion::OpenAIToAnthropic
+                }
+            }
+            AppType::Codex
+                if endpoint.ends_with("/responses")
+                    && CodexAdapter::new().needs_transform(provider) =>
+            {
+                ResponseConversion::ChatToResponses
+            }
+            _ => ResponseConversion::None,
+        }
+    }
+
+    /// 按客户端端点选择 usage 解析配置
+    fn parser_config(app_type: &AppType, endpoint: &str) -> &'static UsageParserConfig {
+        match app_type {
+            AppType::Claude => &CLAUDE_PARSER_CONFIG,
+            AppType::Codex if endpoint.contains("/chat/completions") => &OPENAI_PARSER_CONFIG,
+            AppType::Codex => &CODEX_PARSER_CONFIG,
+            AppType::Gemini => &GEMINI_PARSER_CONFIG,
+        }
+    }
+
+    /// 读取非流式响应并转换为客户端格式
+    async fn read_body(
+        response: reqwest::Response,
+        conversion: ResponseConversion,
+    ) -> Result<Value, String> {
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| format!("读取响应失败: {e}"))?;
+        let json: Value =
+            serde_json::from_slice(&bytes).map_err(|e| format!("解析响应失败: {e}"))?;
+
+        let converted = match conversion {
+            ResponseConversion::None => Ok(json),
+            ResponseConversion::OpenAIToAnthropic => transform::openai_to_anthropic(json),
+            ResponseConversion::GeminiToAnthropic => gemini_transform::gemini_to_anthropic(json),
+            ResponseConversion::ChatToResponses => responses_bridge::chat_to_responses(json),
+        };
+        converted.map_err(|e| format!("转换响应失败: {e}"))
+    }
+
+    /// 读取流式响应，转换为客户端格式后收集全部 SSE 事件
+    async fn collect_stream(
+        response: reqwest::Response,
+        conversion: ResponseConversion,
+    ) -> Result<Vec<Value>, String> {
+        let upstream = response.bytes_stream();
+        let mut stream: std::pin::Pin<
+            Box<dyn futures::Stream<Item = Result<bytes::Bytes, std::io::Error>> + Send>,
+        > = match conversion {
+            ResponseConversion::None => Box::pin(
+                upstream.map(|chunk| chunk.map_err(|e| std::io::Error::other(e.to_string()))),
+            ),
+            ResponseConversion::OpenAIToAnthropic => {
+                Box::pin(create_anthropic_sse_stream(upstream))
+            }
+            ResponseConversion::GeminiToAnthropic => {
+                Box::pin(create_anthropic_sse_stream_from_gemini(upstream))
+            }
+            ResponseConversion::ChatToResponses => {
+                Box::pin(responses_bridge::create_responses_sse_stream(upstream))
+            }
+        };
+
+        let mut text = String::new();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| format!("读取流式响应失败: {e}"))?;
+            text.push_str(&String::from_utf8_lossy(&chunk));
+        }
+
+        Ok(parse_sse_events(&text))
+    }
+
+    /// 按当前定价与供应商成本倍数计算成本
+    fn calculate_cost(
+        db: &Database,
+        provider: &Provider,
+        model: &str,
+        usage: &TokenUsage,
+    ) -> Option<String> {
+        let multiplier = provider
+            .meta
+            .as_ref()
+            .and_then(|meta| meta.cost_multiplier.as_deref())
+            .and_then(|cm| Decimal::from_str(cm).ok())
+            .unwrap_or(Decimal::from(1));
+        let pricing = UsageLogger::new(db).get_model_pricing(model).ok()??;
+        CostCalculator::try_calculate(usage, Some(&pricing), multiplier)
+            .map(|cost| cost.total_cost.to_string())
+    }
+}
+
+/// 解析 SSE 文本中的 JSON 事件（忽略 `[DONE]` 等非 JSON 数据）
+fn parse_sse_events(text: &str) -> Vec<Value> {
+    text.lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .filter_map(|data| serde_json::from_str::<Value>(data.trim()).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_sse_events_skips_done_and_event_lines() {
+        let text = "event: message_start\ndata: {\"type\":\"message_start\"}\n\n\
+                    data: {\"type\":\"message_stop\"}\n\ndata: [DONE]\n\n";
+        let events = parse_sse_events(text);
+        assert_eq!(
+            events,
+            vec![
+                json!({"type": "message_start"}),
+                json!({"type": "message_stop"})
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rebuild_headers_drops_masked_secrets() {
+        let captured = json!({
+            "x-api-key": "sk-a...1234",
+            "authorization": "Bearer sk-1...cdef",
+            "user-agent": "claude-cli/1.0",
+            "anthropic-version": "2023-06-01"
+        });
+        let headers = ReplayService::rebuild_headers(&captured);
+        assert!(headers.get("x-api-key").is_none());
+        assert!(headers.get("authorization").is_none());
+        assert_eq!(headers.get("user-agent").unwrap(), "claude-cli/1.0");
+        assert_eq!(headers.len(), 2);
+    }
+}
@@ -4,6 +4,8 @@ use serde::Serialize;
 use std::time::{Duration, Instant};
 
 use crate::error::AppError;
+use crate::provider::OutboundConfig;
+use crate::proxy::http_client::{self, ClientOptions};
 
 const DEFAULT_TIMEOUT_SECS: u64 = 8;
 const MAX_TIMEOUT_SECS: u64 = 30;
@@ -23,9 +25,12 @@ pub struct SpeedtestService;
 
 impl SpeedtestService {
     /// 测试一组端点的响应延迟。
+    ///
+    /// `outbound` 为所属供应商的出站设置，测速与实际转发走同一条网络路径。
     pub async fn test_endpoints(
         urls: Vec<String>,
         timeout_secs: Option<u64>,
+        outbound: Option<&OutboundConfig>,
     ) -> Result<Vec<EndpointLatency>, AppError> {
         if urls.is_empty() {
             return Ok(vec![]);
@@ -65,7 +70,7 @@ impl SpeedtestService {
         }
 
         let timeout = Self::sanitize_timeout(timeout_secs);
-        let client = Self::build_client(timeout)?;
+        let client = Self::build_client(timeout, outbound)?;
 
         let tasks = valid_targets.into_iter().map(|(idx, trimmed, parsed_url)| {
             let client = client.clone();
@@ -112,19 +117,25 @@ impl SpeedtestService {
         Ok(results.into_iter().flatten().collect::<Vec<_>>())
     }
 
-    fn build_client(timeout_secs: u64) -> Result<Client, AppError> {
-        Client::builder()
-            .timeout(Duration::from_secs(timeout_secs))
-            .redirect(reqwest::redirect::Policy::limited(5))
-            .user_agent("cc-switch-speedtest/1.0")
-            .build()
-            .map_err(|e| {
-                AppError::localized(
-                    "speedtest.client_create_failed",
-                    format!("创建 HTTP 客户端失败: {e}"),
-                    format!("Failed to create HTTP client: {e}"),
-                )
-            })
+    fn build_client(
+        timeout_secs: u64,
+        outbound: Option<&OutboundConfig>,
+    ) -> Result<Client, AppError> {
+        http_client::client_for(
+            ClientOptions {
+                timeout: Some(Duration::from_secs(timeout_secs)),
+                user_agent: Some("cc-switch-speedtest/1.0"),
+                max_redirects: Some(5),
+            },
+            outbound,
+        )
+        .map_err(|e| {
+            AppError::localized(
+                "speedtest.client_create_failed",
+                e.clone(),
+                format!("Failed to create HTTP client: {e}"),
+            )
+        })
     }
 
     fn sanitize_timeout(timeout_secs: Option<u64>) -> u64 {
@@ -159,9 +170,12 @@ mod tests {
 
     #[test]
     fn test_endpoints_handles_empty_list() {
-        let result =
-            tauri::async_runtime::block_on(SpeedtestService::test_endpoints(Vec::new(), Some(5)))
-                .expect("empty list should succeed");
+        let result = tauri::async_runtime::block_on(SpeedtestService::test_endpoints(
+            Vec::new(),
+            Some(5),
+            None,
+        ))
+        .expect("empty list should succeed");
         assert!(result.is_empty());
     }
 
@@ -170,6 +184,7 @@ mod tests {
         let result = tauri::async_runtime::block_on(SpeedtestService::test_endpoints(
             vec!["not a url".into(), "".into()],
             None,
+            None,
         ))
         .expect("invalid inputs should still succeed");
 
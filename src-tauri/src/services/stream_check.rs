@@ -12,6 +12,7 @@ use std::time::{Duration, Instant};
 use crate::app_config::AppType;
 use crate::error::AppError;
 use crate::provider::Provider;
+use crate::proxy::http_client::{self, ClientOptions};
 use crate::proxy::providers::{get_adapter, AuthInfo};
 
 /// 健康状态枚举
@@ -74,11 +75,21 @@ impl StreamCheckService {
         app_type: &AppType,
         provider: &Provider,
         config: &StreamCheckConfig,
+    ) -> Result<StreamCheckResult, AppError> {
+        Self::check_with_model(app_type, provider, config, None).await
+    }
+
+    /// 使用指定模型执行流式健康检查（带重试），`model` 为 None 时按供应商配置解析
+    pub async fn check_with_model(
+        app_type: &AppType,
+        provider: &Provider,
+        config: &StreamCheckConfig,
+        model: Option<&str>,
     ) -> Result<StreamCheckResult, AppError> {
         let mut last_result = None;
 
         for attempt in 0..=config.max_retries {
-            let result = Self::check_once(app_type, provider, config).await;
+            let result = Self::check_once(app_type, provider, config, model).await;
 
             match &result {
                 Ok(r) if r.success => {
@@ -124,6 +135,7 @@ impl StreamCheckService {
         app_type: &AppType,
         provider: &Provider,
         config: &StreamCheckConfig,
+        model: Option<&str>,
     ) -> Result<StreamCheckResult, AppError> {
         let start = Instant::now();
         let adapter = get_adapter(app_type);
@@ -136,13 +148,19 @@ impl StreamCheckService {
             .extract_auth(provider)
             .ok_or_else(|| AppError::Message("未找到 API Key".to_string()))?;
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(config.timeout_secs))
-            .user_agent("cc-switch/1.0")
-            .build()
-            .map_err(|e| AppError::Message(format!("创建客户端失败: {e}")))?;
-
-        let model_to_test = Self::resolve_test_model(app_type, provider, config);
+        let client = http_client::client_for(
+            ClientOptions {
+                timeout: Some(Duration::from_secs(config.timeout_secs)),
+                user_agent: Some("cc-switch/1.0"),
+                ..Default::default()
+            },
+            provider.outbound(),
+        )
+        .map_err(AppError::Message)?;
+
+        let model_to_test = model
+            .map(str::to_string)
+            .unwrap_or_else(|| Self::resolve_test_model(app_type, provider, config));
 
         let result = match app_type {
             AppType::Claude => {
@@ -4,7 +4,7 @@
 
 use crate::database::{lock_conn, Database};
 use crate::error::AppError;
-use chrono::{Duration, Utc};
+use chrono::{Datelike, Duration, Utc};
 use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -62,6 +62,17 @@ pub struct ModelStats {
     pub avg_cost_per_request: String,
 }
 
+/// API Key 统计（Key 已遮蔽）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyStats {
+    pub api_key: String,
+    pub request_count: u64,
+    pub total_tokens: u64,
+    pub total_cost: String,
+    pub success_rate: f32,
+}
+
 /// 请求日志过滤器
 #[derive(Debug, Clone, Default, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -110,6 +121,15 @@ pub struct RequestLogDetail {
     pub status_code: u16,
     pub error_message: Option<String>,
     pub created_at: i64,
+    /// 是否命中本地响应缓存
+    #[serde(default)]
+    pub cache_hit: bool,
+    /// 客户端会话 ID（用于在会话视图中查看同一会话的各轮请求）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// 流式中断后的续写次数
+    #[serde(default)]
+    pub stream_recoveries: u32,
 }
 
 impl Database {
@@ -354,6 +374,54 @@ impl Database {
         Ok(stats)
     }
 
+    /// 获取指定 Provider 按 API Key 归属的统计
+    ///
+    /// 仅统计记录了 Key 的请求（启用 Key 池后的请求）
+    pub fn get_api_key_stats(
+        &self,
+        provider_id: &str,
+        app_type: &str,
+    ) -> Result<Vec<ApiKeyStats>, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let sql = "SELECT
+                api_key,
+                COUNT(*) as request_count,
+                COALESCE(SUM(input_tokens + output_tokens), 0) as total_tokens,
+                COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0) as total_cost,
+                COALESCE(SUM(CASE WHEN status_code >= 200 AND status_code < 300 THEN 1 ELSE 0 END), 0) as success_count
+             FROM proxy_request_logs
+             WHERE provider_id = ?1 AND app_type = ?2 AND api_key IS NOT NULL
+             GROUP BY api_key
+             ORDER BY request_count DESC";
+
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map(params![provider_id, app_type], |row| {
+            let request_count: i64 = row.get(1)?;
+            let success_count: i64 = row.get(4)?;
+            let success_rate = if request_count > 0 {
+                (success_count as f32 / request_count as f32) * 100.0
+            } else {
+                0.0
+            };
+
+            Ok(ApiKeyStats {
+                api_key: row.get(0)?,
+                request_count: request_count as u64,
+                total_tokens: row.get::<_, i64>(2)? as u64,
+                total_cost: format!("{:.6}", row.get::<_, f64>(3)?),
+                success_rate,
+            })
+        })?;
+
+        let mut stats = Vec::new();
+        for row in rows {
+            stats.push(row?);
+        }
+
+        Ok(stats)
+    }
+
     /// 获取模型统计
     pub fn get_model_stats(&self) -> Result<Vec<ModelStats>, AppError> {
         let conn = lock_conn!(self.conn);
@@ -458,7 +526,8 @@ impl Database {
                     l.input_tokens, l.output_tokens, l.cache_read_tokens, l.cache_creation_tokens,
                     l.input_cost_usd, l.output_cost_usd, l.cache_read_cost_usd, l.cache_creation_cost_usd, l.total_cost_usd,
                     l.is_streaming, l.latency_ms, l.first_token_ms, l.duration_ms,
-                    l.status_code, l.error_message, l.created_at
+                    l.status_code, l.error_message, l.created_at, l.cache_hit, l.session_id,
+                    l.stream_recoveries
              FROM proxy_request_logs l
              LEFT JOIN providers p ON l.provider_id = p.id AND l.app_type = p.app_type
              {where_clause}
@@ -491,6 +560,9 @@ impl Database {
                 status_code: row.get::<_, i64>(18)? as u16,
                 error_message: row.get(19)?,
                 created_at: row.get(20)?,
+                cache_hit: row.get::<_, i64>(21)? != 0,
+                session_id: row.get(22)?,
+                stream_recoveries: row.get::<_, i64>(23)? as u32,
             })
         })?;
 
@@ -529,7 +601,8 @@ impl Database {
                     input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens,
                     input_cost_usd, output_cost_usd, cache_read_cost_usd, cache_creation_cost_usd, total_cost_usd,
                     is_streaming, latency_ms, first_token_ms, duration_ms,
-                    status_code, error_message, created_at
+                    status_code, error_message, created_at, cache_hit, session_id,
+                    stream_recoveries
              FROM proxy_request_logs l
              LEFT JOIN providers p ON l.provider_id = p.id AND l.app_type = p.app_type
              WHERE l.request_id = ?",
@@ -557,6 +630,9 @@ impl Database {
                     status_code: row.get::<_, i64>(18)? as u16,
                     error_message: row.get(19)?,
                     created_at: row.get(20)?,
+                    cache_hit: row.get::<_, i64>(21)? != 0,
+                    session_id: row.get(22)?,
+                    stream_recoveries: row.get::<_, i64>(23)? as u32,
                 })
             },
         );
@@ -611,29 +687,12 @@ impl Database {
             })
             .unwrap_or((None, None));
 
-        // 计算今日使用量
-        let daily_usage: f64 = conn
-            .query_row(
-                "SELECT COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0)
-             FROM proxy_request_logs
-             WHERE provider_id = ? AND app_type = ?
-               AND date(created_at, 'unixepoch') = date('now')",
-                params![provider_id, app_type],
-                |row| row.get(0),
-            )
-            .unwrap_or(0.0);
-
-        // 计算本月使用量
-        let monthly_usage: f64 = conn
-            .query_row(
-                "SELECT COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0)
-             FROM proxy_request_logs
-             WHERE provider_id = ? AND app_type = ?
-               AND strftime('%Y-%m', created_at, 'unixepoch') = strftime('%Y-%m', 'now')",
-                params![provider_id, app_type],
-                |row| row.get(0),
-            )
-            .unwrap_or(0.0);
+        // 计算今日与本月使用量
+        let SpendTotals {
+            daily: daily_usage,
+            monthly: monthly_usage,
+        } = query_spend_totals(&conn, Some(app_type), Some(provider_id))
+            .map_err(|e| AppError::Database(e.to_string()))?;
 
         let daily_exceeded = limit_daily
             .map(|limit| daily_usage >= limit)
@@ -654,6 +713,61 @@ impl Database {
     }
 }
 
+/// 今日 / 本月消费合计（USD）
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SpendTotals {
+    pub daily: f64,
+    pub monthly: f64,
+}
+
+impl Database {
+    /// 统计今日与本月消费，`app_type` / `provider_id` 为 None 时不按该维度过滤
+    pub fn get_spend_totals(
+        &self,
+        app_type: Option<&str>,
+        provider_id: Option<&str>,
+    ) -> Result<SpendTotals, AppError> {
+        let conn = lock_conn!(self.conn);
+        query_spend_totals(&conn, app_type, provider_id)
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+}
+
+/// 按 UTC 自然日 / 自然月统计消费
+///
+/// 以 `created_at` 范围过滤（可走 `idx_request_logs_created_at`），只扫描本月的日志
+fn query_spend_totals(
+    conn: &Connection,
+    app_type: Option<&str>,
+    provider_id: Option<&str>,
+) -> rusqlite::Result<SpendTotals> {
+    let today = Utc::now().date_naive();
+    let day_start = today.and_time(chrono::NaiveTime::MIN).and_utc().timestamp();
+    let month_start = today
+        .with_day(1)
+        .unwrap_or(today)
+        .and_time(chrono::NaiveTime::MIN)
+        .and_utc()
+        .timestamp();
+
+    conn.query_row(
+        "SELECT
+            COALESCE(SUM(CASE WHEN created_at >= ?4
+                THEN CAST(total_cost_usd AS REAL) ELSE 0 END), 0),
+            COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0)
+         FROM proxy_request_logs
+         WHERE created_at >= ?3
+           AND (?1 IS NULL OR app_type = ?1) AND (?2 IS NULL OR provider_id = ?2)",
+        params![app_type, provider_id, month_start, day_start],
+        |row| {
+            Ok(SpendTotals {
+                daily: row.get(0)?,
+                monthly: row.get(1)?,
+            })
+        },
+    )
+}
+
 /// Provider 限额状态
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -934,6 +1048,41 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_get_api_key_stats() -> Result<(), AppError> {
+        let db = Database::memory()?;
+
+        {
+            let conn = lock_conn!(db.conn);
+            for (id, key, status) in [
+                ("req1", Some("sk-a...1111"), 200),
+                ("req2", Some("sk-a...1111"), 401),
+                ("req3", Some("sk-b...2222"), 200),
+                ("req4", None, 200),
+            ] {
+                conn.execute(
+                    "INSERT INTO proxy_request_logs (
+                        request_id, provider_id, app_type, model,
+                        input_tokens, output_tokens, total_cost_usd,
+                        latency_ms, status_code, created_at, api_key
+                    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                    params![
+                        id, "p1", "claude", "claude-3", 100, 50, "0.01", 100, status, 1000, key
+                    ],
+                )?;
+            }
+        }
+
+        let stats = db.get_api_key_stats("p1", "claude")?;
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].api_key, "sk-a...1111");
+        assert_eq!(stats[0].request_count, 2);
+        assert_eq!(stats[0].success_rate, 50.0);
+        assert_eq!(stats[1].request_count, 1);
+
+        Ok(())
+    }
+
     #[test]
     fn test_get_model_stats() -> Result<(), AppError> {
         let db = Database::memory()?;
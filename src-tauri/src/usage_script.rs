@@ -1,4 +1,3 @@
-use reqwest::Client;
 use rquickjs::{Context, Function, Runtime};
 use serde_json::Value;
 use std::collections::HashMap;
@@ -6,6 +5,8 @@ use std::time::Duration;
 use url::{Host, Url};
 
 use crate::error::AppError;
+use crate::provider::OutboundConfig;
+use crate::proxy::http_client::{self, ClientOptions};
 
 /// 执行用量查询脚本
 pub async fn execute_usage_script(
@@ -15,6 +16,7 @@ pub async fn execute_usage_script(
     timeout_secs: u64,
     access_token: Option<&str>,
     user_id: Option<&str>,
+    outbound: Option<&OutboundConfig>,
 ) -> Result<Value, AppError> {
     // 1. 替换模板变量，避免泄露敏感信息
     let script_with_vars =
@@ -102,7 +104,7 @@ pub async fn execute_usage_script(
     validate_request_url(&request.url, base_url)?;
 
     // 6. 发送 HTTP 请求
-    let response_data = send_http_request(&request, timeout_secs).await?;
+    let response_data = send_http_request(&request, timeout_secs, outbound).await?;
 
     // 7. 在独立作用域中执行 extractor（确保 Runtime/Context 在函数结束前释放）
     let result: Value = {
@@ -214,19 +216,25 @@ struct RequestConfig {
 }
 
 /// 发送 HTTP 请求
-async fn send_http_request(config: &RequestConfig, timeout_secs: u64) -> Result<String, AppError> {
+async fn send_http_request(
+    config: &RequestConfig,
+    timeout_secs: u64,
+    outbound: Option<&OutboundConfig>,
+) -> Result<String, AppError> {
     // 约束超时范围，防止异常配置导致长时间阻塞
     let timeout = timeout_secs.clamp(2, 30);
-    let client = Client::builder()
-        .timeout(Duration::from_secs(timeout))
-        .build()
-        .map_err(|e| {
-            AppError::localized(
-                "usage_script.client_create_failed",
-                format!("创建客户端失败: {e}"),
-                format!("Failed to create client: {e}"),
-            )
-        })?;
+    // 与代理转发使用同一出站设置（上游代理、根证书）
+    let client = http_client::client_for(
+        ClientOptions::with_timeout(Duration::from_secs(timeout)),
+        outbound,
+    )
+    .map_err(|e| {
+        AppError::localized(
+            "usage_script.client_create_failed",
+            format!("创建客户端失败: {e}"),
+            format!("Failed to create client: {e}"),
+        )
+    })?;
 
     // 严格校验 HTTP 方法，非法值不回退为 GET
     let method: reqwest::Method = config.method.parse().map_err(|_| {
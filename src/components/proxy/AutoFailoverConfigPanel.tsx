@@ -67,6 +67,24 @@ export function AutoFailoverConfigPanel({
         circuitTimeoutSeconds: formData.circuitTimeoutSeconds,
         circuitErrorRateThreshold: formData.circuitErrorRateThreshold,
         circuitMinRequests: formData.circuitMinRequests,
+        loadBalanceStrategy: config.loadBalanceStrategy,
+        hedgeEnabled: config.hedgeEnabled,
+        hedgeDelayMs: config.hedgeDelayMs,
+        responseCacheEnabled: config.responseCacheEnabled,
+        responseCacheTtlSeconds: config.responseCacheTtlSeconds,
+        responseCacheMaxMb: config.responseCacheMaxMb,
+        payloadCaptureEnabled: config.payloadCaptureEnabled,
+        payloadRetentionDays: config.payloadRetentionDays,
+        payloadMaxBodyKb: config.payloadMaxBodyKb,
+        spendLimitDailyUsd: config.spendLimitDailyUsd,
+        spendLimitMonthlyUsd: config.spendLimitMonthlyUsd,
+        streamRecoveryEnabled: config.streamRecoveryEnabled,
+        streamRecoveryMaxAttempts: config.streamRecoveryMaxAttempts,
+        healthProbeEnabled: config.healthProbeEnabled,
+        healthProbeIntervalSecs: config.healthProbeIntervalSecs,
+        healthProbeDailyLimit: config.healthProbeDailyLimit,
+        healthProbeDailyCostUsd: config.healthProbeDailyCostUsd,
+        healthProbeModel: config.healthProbeModel,
       });
       toast.success(
         t("proxy.autoFailover.configSaved", "自动故障转移配置已保存"),
@@ -3,7 +3,15 @@ import type {
   ProviderHealth,
   CircuitBreakerConfig,
   CircuitBreakerStats,
+  CircuitTransitionRecord,
   FailoverQueueItem,
+  PoolKeyStatus,
+  ProviderIncompatibility,
+  ProviderSchedule,
+  RoutingRule,
+  SchedulePreview,
+  ResponseCacheEntry,
+  ResponseCacheStats,
 } from "@/types/proxy";
 
 export interface Provider {
@@ -59,6 +67,32 @@ export const failoverApi = {
     return invoke("get_circuit_breaker_stats", { providerId, appType });
   },
 
+  // 获取熔断器状态转换历史（按时间倒序）
+  async getCircuitBreakerTransitions(
+    appType?: string,
+    providerId?: string,
+    limit?: number,
+  ): Promise<CircuitTransitionRecord[]> {
+    return invoke("get_circuit_breaker_transitions", {
+      appType,
+      providerId,
+      limit,
+    });
+  },
+
+  // 获取供应商 Key 池状态
+  async getKeyPoolStatus(
+    providerId: string,
+    appType: string,
+  ): Promise<PoolKeyStatus[]> {
+    return invoke("get_key_pool_status", { providerId, appType });
+  },
+
+  // 重新启用 Key 池中被停用的 Key
+  async resetKeyPool(providerId: string, appType: string): Promise<void> {
+    return invoke("reset_key_pool", { providerId, appType });
+  },
+
   // ========== 故障转移队列 API（新） ==========
 
   // 获取故障转移队列
@@ -96,4 +130,90 @@ export const failoverApi = {
   ): Promise<void> {
     return invoke("set_auto_failover_enabled", { appType, enabled });
   },
+
+  // ========== 模型路由规则 API ==========
+
+  // 获取指定应用的路由规则
+  async getRoutingRules(appType: string): Promise<RoutingRule[]> {
+    return invoke("get_routing_rules", { appType });
+  },
+
+  // 新增或更新路由规则（id 为空时新增）
+  async saveRoutingRule(rule: RoutingRule): Promise<RoutingRule> {
+    return invoke("save_routing_rule", { rule });
+  },
+
+  // 删除路由规则
+  async deleteRoutingRule(id: string): Promise<void> {
+    return invoke("delete_routing_rule", { id });
+  },
+
+  // ========== 定时切换 API ==========
+
+  // 获取指定应用的定时规则
+  async getProviderSchedules(appType: string): Promise<ProviderSchedule[]> {
+    return invoke("get_provider_schedules", { appType });
+  },
+
+  // 新增或更新定时规则（id 为空时新增）
+  async saveProviderSchedule(
+    schedule: ProviderSchedule,
+  ): Promise<ProviderSchedule> {
+    return invoke("save_provider_schedule", { schedule });
+  },
+
+  // 删除定时规则
+  async deleteProviderSchedule(id: string): Promise<void> {
+    return invoke("delete_provider_schedule", { id });
+  },
+
+  // 预览某一时刻（Unix 秒，默认当前时间）生效的定时规则
+  async previewProviderSchedule(
+    appType: string,
+    at?: number,
+  ): Promise<SchedulePreview> {
+    return invoke("preview_provider_schedule", { appType, at });
+  },
+
+  // ========== 本地响应缓存 API ==========
+
+  // 获取响应缓存统计（appType 为空时统计全部应用）
+  async getResponseCacheStats(appType?: string): Promise<ResponseCacheStats> {
+    return invoke("get_response_cache_stats", { appType });
+  },
+
+  // 列出响应缓存条目
+  async listResponseCacheEntries(
+    appType?: string,
+    limit?: number,
+  ): Promise<ResponseCacheEntry[]> {
+    return invoke("list_response_cache_entries", { appType, limit });
+  },
+
+  // 清空响应缓存，返回删除的条目数
+  async clearResponseCache(appType?: string): Promise<number> {
+    return invoke("clear_response_cache", { appType });
+  },
+
+  // ========== 字段兼容性 API ==========
+
+  // 列出代理自动识别的上游不支持字段（appType 为空时列出全部应用）
+  async listProviderIncompatibilities(
+    appType?: string,
+  ): Promise<ProviderIncompatibility[]> {
+    return invoke("list_provider_incompatibilities", { appType });
+  },
+
+  // 重置字段不兼容记录（providerId / field 为空时重置全部），返回删除的条数
+  async resetProviderIncompatibilities(
+    appType: string,
+    providerId?: string,
+    field?: string,
+  ): Promise<number> {
+    return invoke("reset_provider_incompatibilities", {
+      appType,
+      providerId,
+      field,
+    });
+  },
 };
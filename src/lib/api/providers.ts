@@ -1,6 +1,7 @@
 import { invoke } from "@tauri-apps/api/core";
 import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 import type {
+  BodyPatch,
   Provider,
   UniversalProvider,
   UniversalProvidersMap,
@@ -12,6 +13,12 @@ export interface ProviderSortUpdate {
   sortIndex: number;
 }
 
+export interface BodyPatchPreview {
+  body: unknown;
+  // 实际生效的规则，如 "remove metadata"
+  applied: string[];
+}
+
 export interface ProviderSwitchEvent {
   appType: AppId;
   providerId: string;
@@ -57,6 +64,14 @@ export const providersApi = {
     return await invoke("update_providers_sort_order", { updates, app: appId });
   },
 
+  // 预览请求体改写规则对示例请求的效果，规则无效时抛出错误
+  async previewBodyPatches(
+    patches: BodyPatch[],
+    body: unknown,
+  ): Promise<BodyPatchPreview> {
+    return await invoke("preview_body_patches", { patches, body });
+  },
+
   async onSwitched(
     handler: (event: ProviderSwitchEvent) => void,
   ): Promise<UnlistenFn> {
@@ -83,6 +83,16 @@ export const proxyApi = {
     return invoke("update_global_proxy_config", { config });
   },
 
+  // 重新生成本地访问令牌（旧令牌立即失效）
+  async regenerateProxyAccessToken(): Promise<string> {
+    return invoke("regenerate_proxy_access_token");
+  },
+
+  // 获取自动生成的 HTTPS 根证书路径（未生成时为 null）
+  async getProxyTlsCaPath(): Promise<string | null> {
+    return invoke("get_proxy_tls_ca_path");
+  },
+
   // 获取指定应用的代理配置
   async getProxyConfigForApp(appType: string): Promise<AppProxyConfig> {
     return invoke("get_proxy_config_for_app", { appType });
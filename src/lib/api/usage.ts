@@ -4,11 +4,15 @@ import type {
   DailyStats,
   ProviderStats,
   ModelStats,
+  ApiKeyStats,
   RequestLog,
   LogFilters,
   ModelPricing,
   ProviderLimitStatus,
   PaginatedLogs,
+  RequestPayload,
+  SessionTurn,
+  ReplayResult,
 } from "@/types/usage";
 import type { UsageResult } from "@/types";
 import type { AppId } from "./types";
@@ -61,6 +65,13 @@ export const usageApi = {
     return invoke("get_model_stats");
   },
 
+  getApiKeyStats: async (
+    providerId: string,
+    appType: string,
+  ): Promise<ApiKeyStats[]> => {
+    return invoke("get_api_key_stats", { providerId, appType });
+  },
+
   getRequestLogs: async (
     filters: LogFilters,
     page: number = 0,
@@ -77,6 +88,23 @@ export const usageApi = {
     return invoke("get_request_detail", { requestId });
   },
 
+  getRequestPayload: async (
+    requestId: string,
+  ): Promise<RequestPayload | null> => {
+    return invoke("get_request_payload", { requestId });
+  },
+
+  listSessionTurns: async (sessionId: string): Promise<SessionTurn[]> => {
+    return invoke("list_session_turns", { sessionId });
+  },
+
+  replayRequest: async (
+    requestId: string,
+    providerId: string,
+  ): Promise<ReplayResult> => {
+    return invoke("replay_request", { requestId, providerId });
+  },
+
   getModelPricing: async (): Promise<ModelPricing[]> => {
     return invoke("get_model_pricing");
   },
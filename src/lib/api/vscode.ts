@@ -16,11 +16,14 @@ export const vscodeApi = {
 
   async testApiEndpoints(
     urls: string[],
-    options?: { timeoutSecs?: number },
+    options?: { timeoutSecs?: number; appId?: AppId; providerId?: string },
   ): Promise<EndpointLatencyResult[]> {
+    // 传入 appId + providerId 时后端使用该供应商的出站设置测速
     return await invoke("test_api_endpoints", {
       urls,
       timeoutSecs: options?.timeoutSecs,
+      app: options?.providerId ? options.appId : undefined,
+      providerId: options?.providerId,
     });
   },
 
@@ -96,6 +96,81 @@ export interface ProviderMeta {
   isPartner?: boolean;
   // 合作伙伴促销 key（用于后端识别 PackyCode 等）
   partnerPromotionKey?: string;
+  // 代理限流：每分钟请求数 / 每分钟输入 Token 数 / 最大并发数
+  rateLimitRpm?: number;
+  rateLimitTpm?: number;
+  maxConcurrency?: number;
+  // 限流等待队列长度与最长等待时间（毫秒），超时后溢出到下一个故障转移供应商
+  rateLimitQueueSize?: number;
+  rateLimitMaxWaitMs?: number;
+  // 消费限额（USD）；hard 模式超出后代理跳过该供应商，soft 模式仅告警
+  limitDailyUsd?: string;
+  limitMonthlyUsd?: string;
+  limitMode?: "hard" | "soft";
+  // 出站网络设置（上游代理、自定义根证书），缺省跟随系统代理
+  outbound?: ProviderOutboundConfig;
+  // 请求头改写规则，在设置认证头之后按顺序执行
+  headerRules?: HeaderRule[];
+  // 请求体改写规则，转发前对最终请求体按顺序执行
+  bodyPatches?: BodyPatch[];
+  // API Key 池：与供应商自身的 Key 一起轮换使用
+  keyPool?: KeyPool;
+  // 后台健康探测使用的模型，缺省按供应商自身配置的模型解析
+  healthProbeModel?: string;
+}
+
+// API Key 池
+export interface KeyPool {
+  strategy?: "round_robin" | "least_used" | "sticky";
+  keys: PoolKey[];
+}
+
+export interface PoolKey {
+  key: string;
+  label?: string;
+  enabled?: boolean;
+}
+
+// 请求体改写规则
+// path 为点分路径，数组用下标，* 匹配每个元素或键（如 messages.*.content.*.cache_control）
+export interface BodyPatch {
+  action: "remove" | "set" | "rename" | "clamp";
+  path: string;
+  // set：写入的值；clamp：允许的最大值
+  value?: unknown;
+  // rename：新的键名
+  to?: string;
+  // 仅对匹配的模型生效（通配符）
+  model?: string;
+  enabled?: boolean;
+}
+
+// 请求头改写规则
+// value 支持模板变量：{session_id}、{provider_id}、{provider_name}、{model}、{timestamp}
+export interface HeaderRule {
+  action: "set" | "append" | "remove" | "replace";
+  name: string;
+  // set / append 的值；replace 的替换文本（支持 $1 等捕获组）
+  value?: string;
+  // replace 的正则表达式
+  pattern?: string;
+  enabled?: boolean;
+}
+
+// 供应商出站网络设置
+export interface ProviderOutboundConfig {
+  // system：跟随系统代理；direct：直连；custom：使用 proxyUrl
+  proxyMode?: "system" | "direct" | "custom";
+  // http://、https://、socks5://、socks5h://
+  proxyUrl?: string;
+  proxyUsername?: string;
+  proxyPassword?: string;
+  // 不走代理的主机（域名、*.example.com、IP 或 CIDR）
+  noProxy?: string[];
+  // 额外信任的根证书（PEM 文件路径）
+  caCertPath?: string;
+  // 跳过证书校验（仅用于内网自签名网关）
+  acceptInvalidCerts?: boolean;
 }
 
 // 应用设置类型（用于设置对话框与 Tauri API）
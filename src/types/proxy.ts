@@ -9,8 +9,16 @@ export interface ProxyConfig {
   streaming_first_byte_timeout: number;
   streaming_idle_timeout: number;
   non_streaming_timeout: number;
+  // 监听方式（由全局代理配置维护）
+  listen_mode?: ProxyListenMode;
+  tls_cert_path?: string | null;
+  tls_key_path?: string | null;
+  unix_socket_path?: string | null;
 }
 
+// 代理监听方式：明文 HTTP / HTTPS / Unix 域套接字
+export type ProxyListenMode = "tcp" | "tls" | "unix";
+
 export interface ProxyStatus {
   running: boolean;
   address: string;
@@ -27,6 +35,7 @@ export interface ProxyStatus {
   last_error: string | null;
   failover_count: number;
   active_targets?: ActiveTarget[];
+  cooldowns?: ProviderCooldown[];
 }
 
 export interface ActiveTarget {
@@ -35,6 +44,15 @@ export interface ActiveTarget {
   provider_id: string;
 }
 
+// 因上游限流（429/529 + Retry-After）处于冷却中的供应商
+export interface ProviderCooldown {
+  app_type: string;
+  provider_id: string;
+  provider_name: string;
+  status_code: number;
+  remaining_secs: number;
+}
+
 export interface ProxyServerInfo {
   address: string;
   port: number;
@@ -73,8 +91,35 @@ export interface CircuitBreakerStats {
   state: CircuitState;
   consecutiveFailures: number;
   consecutiveSuccesses: number;
+  // 最近 60 秒滑动窗口内的请求数与失败数
   totalRequests: number;
   failedRequests: number;
+  cooldownRemainingSecs?: number;
+}
+
+// 熔断器状态转换记录（保留 30 天）
+export interface CircuitTransitionRecord {
+  id: number;
+  appType: string;
+  providerId: string;
+  providerName?: string | null;
+  fromState: CircuitState;
+  toState: CircuitState;
+  reason?: string | null;
+  createdAt: number;
+}
+
+// Key 池中 Key 的运行状态（Key 已遮蔽）
+export interface PoolKeyStatus {
+  maskedKey: string;
+  label?: string;
+  // 是否为供应商自身配置的 Key
+  primary: boolean;
+  enabled: boolean;
+  disabledReason?: string;
+  // 剩余停用时间（秒），为空且有停用原因时需手动重置
+  disabledForSecs?: number;
+  selectedCount: number;
 }
 
 // 供应商健康状态枚举
@@ -116,6 +161,19 @@ export interface GlobalProxyConfig {
   listenAddress: string;
   listenPort: number;
   enableLogging: boolean;
+  // 全局消费上限（USD，所有应用合计），达到后代理拒绝请求
+  spendLimitDailyUsd?: string | null;
+  spendLimitMonthlyUsd?: string | null;
+  // 入站认证：要求客户端携带本地访问令牌（Authorization / x-api-key / x-goog-api-key）
+  authEnabled?: boolean;
+  accessToken?: string | null;
+  // 允许访问的来源地址（CIDR 或 IP），为空不限；回环地址始终允许
+  allowedCidrs?: string[];
+  // 监听方式；tls 未配置证书时使用自动生成的自签名 CA，unix 未配置路径时使用 ~/.cc-switch/proxy.sock
+  listenMode?: ProxyListenMode;
+  tlsCertPath?: string | null;
+  tlsKeyPath?: string | null;
+  unixSocketPath?: string | null;
 }
 
 // 应用级代理配置（每个 app 独立）
@@ -132,4 +190,126 @@ export interface AppProxyConfig {
   circuitTimeoutSeconds: number;
   circuitErrorRateThreshold: number;
   circuitMinRequests: number;
+  loadBalanceStrategy?: LoadBalanceStrategy;
+  // 对冲请求（仅流式请求生效）：首个供应商超过 hedgeDelayMs 未返回首字节时并发请求下一个供应商
+  hedgeEnabled?: boolean;
+  hedgeDelayMs?: number;
+  // 本地响应缓存（仅缓存 temperature 为 0 的确定性请求）
+  responseCacheEnabled?: boolean;
+  responseCacheTtlSeconds?: number;
+  responseCacheMaxMb?: number;
+  // 请求/响应载荷捕获（调试用，请求头中的密钥会被遮蔽）
+  payloadCaptureEnabled?: boolean;
+  payloadRetentionDays?: number;
+  payloadMaxBodyKb?: number;
+  // 应用级消费上限（USD），达到后代理拒绝该应用的请求
+  spendLimitDailyUsd?: string | null;
+  spendLimitMonthlyUsd?: string | null;
+  // 流式中断恢复（仅 Claude 原生 SSE）：上游输出部分内容后中断时，带上已输出内容续写并拼接到同一个流
+  streamRecoveryEnabled?: boolean;
+  streamRecoveryMaxAttempts?: number;
+  // 后台健康探测（仅自动故障转移开启时探测队列中的供应商），结果计入熔断器
+  healthProbeEnabled?: boolean;
+  healthProbeIntervalSecs?: number;
+  // 每日最多探测次数，0 表示不限
+  healthProbeDailyLimit?: number;
+  // 每日探测费用上限（USD），按模型定价估算每次探测的费用累计，为空表示不限
+  healthProbeDailyCostUsd?: string | null;
+  // 为空时沿用流式健康检查的模型
+  healthProbeModel?: string | null;
+}
+
+// provider-limit-exceeded 事件负载
+export interface ProviderLimitExceededEvent {
+  scope: "provider" | "app" | "global";
+  appType?: string | null;
+  providerId?: string | null;
+  providerName?: string | null;
+  period: "daily" | "monthly";
+  usageUsd: number;
+  limitUsd: number;
+  // 供应商 soft 模式：仅告警，不影响路由
+  soft: boolean;
+}
+
+// 负载均衡策略（仅在自动故障转移开启时生效）
+export type LoadBalanceStrategy =
+  | "priority"
+  | "weighted_random"
+  | "round_robin"
+  | "least_latency"
+  | "least_in_flight";
+
+// 模型路由规则（按请求模型路由到指定供应商链）
+export interface RoutingRule {
+  id: string;
+  appType: string;
+  name: string;
+  modelPattern: string;
+  matchType: "glob" | "regex";
+  // 为空表示不限；true 仅匹配启用 thinking 的请求
+  thinking?: boolean | null;
+  providerIds: string[];
+  enabled: boolean;
+  sortIndex: number;
+  createdAt?: number;
+}
+
+// 定时切换规则（按 cron 在指定时刻切换供应商或重排故障转移队列，仅代理接管时生效）
+export interface ProviderSchedule {
+  id: string;
+  appType: string;
+  name: string;
+  // 分 时 日 月 周（本地时间），如 "0 9 * * 1-5"
+  cron: string;
+  // switch：切换到 providerIds[0]；reorder_queue：按顺序排到故障转移队列最前
+  action: "switch" | "reorder_queue";
+  providerIds: string[];
+  enabled: boolean;
+  sortIndex: number;
+  createdAt?: number;
+}
+
+// 某一时刻生效的定时规则预览（时间均为 Unix 秒）
+export interface SchedulePreview {
+  at: number;
+  active?: ProviderSchedule | null;
+  triggeredAt?: number | null;
+  nextChangeAt?: number | null;
+  nextScheduleId?: string | null;
+}
+
+// 本地响应缓存条目（不含响应体）
+export interface ResponseCacheEntry {
+  cacheKey: string;
+  appType: string;
+  providerId: string;
+  model: string;
+  isStreaming: boolean;
+  statusCode: number;
+  sizeBytes: number;
+  hitCount: number;
+  createdAt: number;
+  expiresAt: number;
+  lastHitAt?: number | null;
+}
+
+// 本地响应缓存统计
+export interface ResponseCacheStats {
+  entries: number;
+  totalBytes: number;
+  totalHits: number;
+}
+
+// 代理自动识别的上游不支持字段（之后发往该供应商的请求会自动去掉）
+export interface ProviderIncompatibility {
+  appType: string;
+  providerId: string;
+  providerName?: string | null;
+  // 字段路径，如 metadata、messages.*.content.*.cache_control
+  field: string;
+  errorMessage?: string | null;
+  hitCount: number;
+  learnedAt: number;
+  lastSeenAt: number;
 }
@@ -28,9 +28,81 @@ export interface RequestLog {
   durationMs?: number;
   statusCode: number;
   errorMessage?: string;
+  // 是否由本地响应缓存直接返回
+  cacheHit?: boolean;
+  // 客户端会话 ID（可在会话视图中查看同一会话的各轮请求）
+  sessionId?: string;
+  // 流式中断后的续写次数
+  streamRecoveries?: number;
   createdAt: number;
 }
 
+// 捕获的请求/响应载荷（请求头中的密钥已遮蔽）
+export interface RequestPayload {
+  requestId: string;
+  sessionId?: string | null;
+  appType: string;
+  providerId: string;
+  model: string;
+  endpoint: string;
+  requestHeaders: Record<string, string>;
+  requestBody: string;
+  statusCode: number;
+  responseHeaders?: Record<string, string> | null;
+  // 流式响应为收集到的 SSE 事件 JSON 数组
+  responseBody?: string | null;
+  isStreaming: boolean;
+  truncated: boolean;
+  createdAt: number;
+  // 实际发往上游的请求头（执行请求头改写规则之后）
+  upstreamHeaders?: Record<string, string> | null;
+  // 生效的请求头改写规则，如 "set x-title"
+  headerRewrites?: string[];
+}
+
+// 会话中的一轮请求（不含载荷正文）
+export interface SessionTurn {
+  requestId: string;
+  appType: string;
+  providerId: string;
+  providerName?: string | null;
+  model: string;
+  endpoint: string;
+  statusCode: number;
+  isStreaming: boolean;
+  requestBytes: number;
+  responseBytes: number;
+  inputTokens?: number | null;
+  outputTokens?: number | null;
+  totalCostUsd?: string | null;
+  latencyMs?: number | null;
+  createdAt: number;
+}
+
+export interface ReplayResponse {
+  providerId: string;
+  providerName?: string | null;
+  statusCode: number;
+  latencyMs?: number | null;
+  inputTokens: number;
+  outputTokens: number;
+  cacheReadTokens: number;
+  cacheCreationTokens: number;
+  totalCostUsd?: string | null;
+  body?: string | null;
+  errorMessage?: string | null;
+}
+
+export interface ReplayResult {
+  requestId: string;
+  appType: string;
+  endpoint: string;
+  model: string;
+  isStreaming: boolean;
+  original: ReplayResponse;
+  replay: ReplayResponse;
+}
+
 export interface PaginatedLogs {
   data: RequestLog[];
   total: number;
@@ -86,6 +158,14 @@ export interface ModelStats {
   avgCostPerRequest: string;
 }
 
+export interface ApiKeyStats {
+  apiKey: string;
+  requestCount: number;
+  totalTokens: number;
+  totalCost: string;
+  successRate: number;
+}
+
 export interface LogFilters {
   appType?: string;
   providerName?: string;